@@ -1,5 +1,40 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use rustyscript::{json_args, Module, Runtime, RuntimeOptions};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Counts heap allocations, so that the call benchmarks can report allocations as well as time
+struct CountingAllocator;
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Returns the average number of heap allocations made by one call of `f`
+fn allocations_per_call(mut f: impl FnMut()) -> f64 {
+    const CALLS: usize = 10_000;
+    f(); // Warm up any lazily allocated state
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..CALLS {
+        f();
+    }
+    let after = ALLOCATIONS.load(Ordering::Relaxed);
+    (after - before) as f64 / CALLS as f64
+}
 
 fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("init_runtime", |b| {
@@ -47,6 +82,25 @@ fn criterion_benchmark(c: &mut Criterion) {
         })
     });
 
+    let slot = runtime
+        .prepare_call(Some(&modref), "test")
+        .expect("Could not prepare call");
+    c.bench_function("call_slot", |b| {
+        b.iter(|| {
+            let _: usize = runtime
+                .call_slot(&slot, json_args!())
+                .expect("could not call function");
+        })
+    });
+
+    c.bench_function("call_slot_with_args", |b| {
+        b.iter(|| {
+            let _: usize = runtime
+                .call_slot(&slot, json_args!("test", 1, false))
+                .expect("could not call function");
+        })
+    });
+
     c.bench_function("call_function_with_args", |b| {
         b.iter(|| {
             let _: usize = runtime
@@ -54,6 +108,19 @@ fn criterion_benchmark(c: &mut Criterion) {
                 .expect("could not call function");
         })
     });
+
+    // Prepared slots skip the function lookup, and reuse the storage for decoded arguments
+    let by_name = allocations_per_call(|| {
+        let _: usize = runtime
+            .call_function(Some(&modref), "test", json_args!("test", 1, false))
+            .expect("could not call function");
+    });
+    let by_slot = allocations_per_call(|| {
+        let _: usize = runtime
+            .call_slot(&slot, json_args!("test", 1, false))
+            .expect("could not call function");
+    });
+    println!("allocations per call: call_function {by_name:.1}, call_slot {by_slot:.1}");
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -30,4 +30,8 @@ fn main() -> Result<(), Error> {
     //     startup_snapshot: Some(STARTUP_SNAPSHOT),
     //     ..Default::default()
     // };
+    //
+    // The module is already loaded in runtimes created from the snapshot:
+    // let mut runtime = Runtime::new(options)?;
+    // let module = runtime.get_module_handle("my_module.js")?;
 }
@@ -1,13 +1,145 @@
-use crate::Error;
-use std::rc::Rc;
+use crate::{
+    cpu_watchdog::CpuWatchdog,
+    ext::rustyscript::{ConsoleErrorLog, MicrotaskBudget},
+    inner_runtime::{InnerRuntime, ProgressCallback, RuntimeTrait, TimeoutHook},
+    progress_monitor::ProgressMonitor,
+    Error, RuntimeInfo, RuntimeOptions,
+};
+use deno_core::v8;
+use std::{rc::Rc, sync::mpsc::RecvTimeoutError};
+use tokio::{runtime::RuntimeFlavor, task::LocalSet};
 use tokio_util::sync::CancellationToken;
 
+/// The tokio runtime used to drive blocking calls
+#[derive(Clone)]
+enum Executor {
+    /// A runtime created by the bridge, or given to it with [`AsyncBridge::with_tokio_runtime`]
+    Owned(Rc<tokio::runtime::Runtime>),
+
+    /// A runtime owned by the caller - see [`RuntimeOptions::tokio_handle`]
+    Handle(tokio::runtime::Handle),
+}
+
+impl Executor {
+    /// Block the current thread until the future resolves
+    ///
+    /// Blocking from a worker thread of a multi-threaded runtime is allowed, by moving that thread's other tasks elsewhere
+    /// From inside a single-threaded runtime it is not possible, and an error is returned instead of panicking
+    fn block_on<F: std::future::Future>(
+        &self,
+        local_set: Option<&LocalSet>,
+        future: F,
+    ) -> Result<F::Output, Error> {
+        let in_runtime = match tokio::runtime::Handle::try_current() {
+            Ok(current) if current.runtime_flavor() == RuntimeFlavor::MultiThread => true,
+            Ok(_) => {
+                return Err(Error::Runtime(
+                    "Cannot block from within a single-threaded tokio runtime - use the `_async` variant of this method instead".to_string(),
+                ))
+            }
+            Err(_) => false,
+        };
+
+        let run = || {
+            let future = async move {
+                match local_set {
+                    Some(local_set) => local_set.run_until(future).await,
+                    None => future.await,
+                }
+            };
+
+            match self {
+                Self::Owned(rt) => rt.block_on(future),
+                Self::Handle(handle) => handle.block_on(future),
+            }
+        };
+
+        if in_runtime {
+            Ok(tokio::task::block_in_place(run))
+        } else {
+            Ok(run())
+        }
+    }
+
+    /// Returns true if tokio's timers progress while blocking on this runtime
+    ///
+    /// A single-threaded runtime only runs its timers while it is driven by [`tokio::runtime::Runtime::block_on`],
+    /// so blocking on it through a handle does not drive them
+    fn drives_timers(&self) -> bool {
+        match self {
+            Self::Owned(_) => true,
+            Self::Handle(handle) => handle.runtime_flavor() != RuntimeFlavor::CurrentThread,
+        }
+    }
+}
+
+/// Resolves once the timeout has elapsed
+///
+/// When tokio's timers are not being driven, the deadline is kept by a separate thread instead,
+/// which stops as soon as the future is dropped
+async fn deadline(timeout: std::time::Duration, drives_timers: bool) {
+    if drives_timers {
+        tokio::time::sleep(timeout).await;
+        return;
+    }
+
+    let (done, done_rx) = std::sync::mpsc::channel::<()>();
+    let (elapsed, elapsed_rx) = tokio::sync::oneshot::channel();
+    std::thread::spawn(move || {
+        if let Err(RecvTimeoutError::Timeout) = done_rx.recv_timeout(timeout) {
+            elapsed.send(()).ok();
+        }
+    });
+
+    elapsed_rx.await.ok();
+    drop(done);
+}
+
+/// The parts of the runtime options used by the bridge, taken before the options are consumed
+/// by [`InnerRuntime::new`], and applied with [`AsyncBridge::configure`] once the runtime exists
+pub struct BridgeSettings {
+    timeout: std::time::Duration,
+    cpu_time_limit: Option<std::time::Duration>,
+    on_timeout: Option<TimeoutHook>,
+    progress: Option<(std::time::Duration, ProgressCallback)>,
+}
+
+impl BridgeSettings {
+    /// Take the settings from a runtime's options
+    #[must_use]
+    pub fn new(options: &RuntimeOptions) -> Self {
+        let (timeout, cpu_time_limit) = options.time_limits();
+        Self {
+            timeout,
+            cpu_time_limit,
+            on_timeout: options.on_timeout.clone(),
+            progress: options
+                .progress_callback
+                .clone()
+                .map(|callback| (options.progress_interval, callback)),
+        }
+    }
+
+    /// The timeout for blocking calls
+    #[must_use]
+    pub fn timeout(&self) -> std::time::Duration {
+        self.timeout
+    }
+}
+
 /// A bridge to the tokio runtime that connects the Deno and Tokio runtimes
 /// Implements common patterns used throughout the codebase
 pub struct AsyncBridge {
-    tokio: Rc<tokio::runtime::Runtime>,
+    tokio: Option<Executor>,
+    local_set: Option<Rc<LocalSet>>,
     timeout: std::time::Duration,
     heap_exhausted_token: CancellationToken,
+    cpu_watchdog: Option<Rc<CpuWatchdog>>,
+    on_timeout: Option<TimeoutHook>,
+    info: Option<RuntimeInfo>,
+    progress_monitor: Option<Rc<ProgressMonitor>>,
+    console_errors: Option<ConsoleErrorLog>,
+    microtask_budget: Option<(v8::IsolateHandle, MicrotaskBudget)>,
 }
 
 impl AsyncBridge {
@@ -23,30 +155,113 @@ impl AsyncBridge {
         Ok(Self::with_tokio_runtime(timeout, tokio))
     }
 
+    /// Creates a new instance using the tokio handle and local set from the runtime options, if any
+    /// A new tokio runtime is only created if no handle was given
+    pub fn for_options(
+        timeout: std::time::Duration,
+        options: &RuntimeOptions,
+    ) -> Result<Self, Error> {
+        let bridge = match &options.tokio_handle {
+            Some(handle) => Self::with_executor(timeout, Executor::Handle(handle.clone())),
+            None => Self::new(timeout)?,
+        };
+
+        Ok(bridge.with_local_set(options.tokio_local_set.clone()))
+    }
+
     /// Creates a new instance with the provided options and a pre-configured tokio runtime.
     pub fn with_tokio_runtime(
         timeout: std::time::Duration,
         tokio: Rc<tokio::runtime::Runtime>,
     ) -> Self {
+        Self::with_executor(timeout, Executor::Owned(tokio))
+    }
+
+    fn with_executor(timeout: std::time::Duration, executor: Executor) -> Self {
         let heap_exhausted_token = CancellationToken::new();
         Self {
-            tokio,
+            tokio: Some(executor),
+            local_set: None,
             timeout,
             heap_exhausted_token,
+            cpu_watchdog: None,
+            on_timeout: None,
+            info: None,
+            progress_monitor: None,
+            console_errors: None,
+            microtask_budget: None,
         }
     }
 
+    /// Run blocking calls inside the given local set, so ops can use `tokio::task::spawn_local`
+    #[must_use]
+    pub fn with_local_set(mut self, local_set: Option<Rc<LocalSet>>) -> Self {
+        self.local_set = local_set;
+        self
+    }
+
+    /// Apply the settings taken from the options of a runtime, once it has been created
+    ///
+    /// - Limits the CPU time each blocking call may spend executing javascript in its isolate
+    /// - Invokes the progress callback every interval while a blocking call executes javascript
+    /// - Invokes the timeout hook, with the runtime's info, whenever a blocking call times out
+    /// - Fails each blocking call that records a message in the runtime's console error log
+    /// - Reports an overrun of the runtime's microtask budget by a blocking call, and clears its termination.  
+    ///   Async calls do the same through [`InnerRuntime::settle_microtask_budget`]
+    pub fn configure<RT: RuntimeTrait>(
+        &mut self,
+        settings: BridgeSettings,
+        inner: &mut InnerRuntime<RT>,
+    ) {
+        let isolate = inner.isolate_handle();
+        self.cpu_watchdog = settings
+            .cpu_time_limit
+            .map(|limit| Rc::new(CpuWatchdog::new(isolate.clone(), limit)));
+        self.progress_monitor = settings.progress.map(|(interval, callback)| {
+            Rc::new(ProgressMonitor::new(isolate.clone(), interval, callback))
+        });
+        self.on_timeout = settings.on_timeout;
+        self.info = Some(inner.info().clone());
+        self.console_errors = inner.console_errors();
+        self.microtask_budget = inner.microtask_budget().map(|budget| (isolate, budget));
+    }
+
+    fn executor(&self) -> &Executor {
+        self.tokio
+            .as_ref()
+            .expect("The tokio runtime is only released on drop")
+    }
+
     /// Access the underlying tokio runtime used for blocking operations
+    ///
+    /// # Errors
+    /// If the bridge was created with [`RuntimeOptions::tokio_handle`], since it does not own a runtime
+    pub fn try_tokio_runtime(&self) -> Result<Rc<tokio::runtime::Runtime>, Error> {
+        match self.executor() {
+            Executor::Owned(rt) => Ok(rt.clone()),
+            Executor::Handle(_) => Err(Error::Runtime(
+                "The runtime was created from a tokio handle - use `tokio_handle` instead"
+                    .to_string(),
+            )),
+        }
+    }
+
+    /// Returns a handle to the tokio runtime used for blocking operations
     #[must_use]
-    pub fn tokio_runtime(&self) -> std::rc::Rc<tokio::runtime::Runtime> {
-        self.tokio.clone()
+    pub fn tokio_handle(&self) -> tokio::runtime::Handle {
+        match self.executor() {
+            Executor::Owned(rt) => rt.handle().clone(),
+            Executor::Handle(handle) => handle.clone(),
+        }
     }
 
     /// Destroy instance, releasing all resources
     /// Then the internal tokio runtime will be returned
-    #[must_use]
-    pub fn into_tokio_runtime(self) -> Rc<tokio::runtime::Runtime> {
-        self.tokio
+    ///
+    /// # Errors
+    /// If the bridge was created with [`RuntimeOptions::tokio_handle`], since it does not own a runtime
+    pub fn try_into_tokio_runtime(self) -> Result<Rc<tokio::runtime::Runtime>, Error> {
+        self.try_tokio_runtime()
     }
 
     /// Returns the timeout for the runtime
@@ -63,6 +278,20 @@ impl AsyncBridge {
     }
 }
 
+impl Drop for AsyncBridge {
+    fn drop(&mut self) {
+        // Tokio panics if a runtime is dropped from within an async context
+        // So when the runtime is owned by an async task (such as with `evaluate_async`), it is shut down in the background
+        if let Some(Executor::Owned(tokio)) = self.tokio.take() {
+            if tokio::runtime::Handle::try_current().is_ok() {
+                if let Ok(tokio) = Rc::try_unwrap(tokio) {
+                    tokio.shutdown_background();
+                }
+            }
+        }
+    }
+}
+
 pub trait AsyncBridgeExt {
     fn bridge(&self) -> &AsyncBridge;
 
@@ -72,14 +301,67 @@ pub trait AsyncBridgeExt {
         F: FnOnce(&'a mut Self) -> Fut,
     {
         let timeout = self.bridge().timeout();
-        let rt = self.bridge().tokio_runtime();
+        let executor = self.bridge().executor().clone();
+        let drives_timers = executor.drives_timers();
+        let local_set = self.bridge().local_set.clone();
         let heap_exhausted_token = self.bridge().heap_exhausted_token();
+        let on_timeout = self.bridge().on_timeout.clone();
+        let info = self.bridge().info.clone();
+        let console_errors = self.bridge().console_errors.clone();
+        if let Some(log) = &console_errors {
+            // Only messages written during this call are reported
+            log.take();
+        }
+        let microtask_budget = self.bridge().microtask_budget.clone();
+        let watchdog = self.bridge().cpu_watchdog.clone();
+        let watched = watchdog.as_deref().map(CpuWatchdog::watch);
+        let monitor = self.bridge().progress_monitor.clone();
+        let monitored = monitor.as_deref().map(ProgressMonitor::watch);
 
-        rt.block_on(async move {
+        let result = executor.block_on(local_set.as_deref(), async move {
             tokio::select! {
-                result = tokio::time::timeout(timeout, f(self)) => result?,
+                biased;
+
+                result = f(self) => result,
+                () = deadline(timeout, drives_timers) => match info {
+                    Some(info) => {
+                        if let Some(hook) = on_timeout {
+                            hook(&info);
+                        }
+                        Err(Error::Timeout(format!("{info}: deadline has elapsed")))
+                    }
+                    None => Err(Error::Timeout("deadline has elapsed".to_string())),
+                },
                 () = heap_exhausted_token.cancelled() => Err(Error::HeapExhausted),
             }
-        })
+        });
+        let result = result.and_then(|result| result);
+
+        // A call terminated for using up its CPU time reports that instead of the termination error
+        let result = match watched {
+            Some(watched) => watched.finish(result),
+            None => result,
+        };
+
+        // As does a call aborted by the progress callback
+        let result = match monitored {
+            Some(monitored) => monitored.finish(result),
+            None => result,
+        };
+
+        // And one terminated for using up its microtask budget
+        let result = match microtask_budget {
+            Some((isolate, budget)) => budget.settle(&isolate, result),
+            None => result,
+        };
+
+        // A successful call that wrote to console.error or console.warn fails with the messages
+        if let Some(log) = console_errors {
+            let messages = log.take();
+            if result.is_ok() && !messages.is_empty() {
+                return Err(Error::ConsoleError(messages.join("\n")));
+            }
+        }
+        result
     }
 }
@@ -0,0 +1,102 @@
+use deno_core::v8;
+use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+};
+
+use crate::ModuleHandle;
+
+/// A pre-resolved function call, created with [`crate::Runtime::prepare_call`]
+///
+/// Calling a function by name has to look up the module namespace and the function on every call.
+/// A `CallSlot` performs that lookup once, and keeps the results around for repeated calls in hot loops.
+///
+/// Arguments are still serialized on each call, since v8 handles cannot outlive the scope they were created in,
+/// but the storage they are decoded into is kept by the slot and reused.
+///
+/// A slot is bound to the runtime that created it - calling it on any other runtime returns an error instead of panicking.  
+/// It is also invalidated when the runtime reloads its modules to recover from a terminated isolate (see [`crate::RuntimeOptions::auto_recover`]),
+/// since it would still call the old instance - prepare it again to call the new one.
+pub struct CallSlot {
+    name: String,
+    module_context: Option<ModuleHandle>,
+    function: v8::Global<v8::Function>,
+    namespace: Option<v8::Global<v8::Object>>,
+    args: RefCell<ArgBuffer>,
+    owner: Weak<()>,
+}
+
+impl CallSlot {
+    pub(crate) fn new(
+        name: &str,
+        module_context: Option<&ModuleHandle>,
+        function: v8::Global<v8::Function>,
+        namespace: Option<v8::Global<v8::Object>>,
+        owner: &Rc<()>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            module_context: module_context.cloned(),
+            function,
+            namespace,
+            args: RefCell::default(),
+            owner: Rc::downgrade(owner),
+        }
+    }
+
+    /// The name of the function this slot was prepared for
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The module this slot was prepared for, if any
+    #[must_use]
+    pub fn module_context(&self) -> Option<&ModuleHandle> {
+        self.module_context.as_ref()
+    }
+
+    /// Returns true if the slot was prepared by the given owner
+    pub(crate) fn is_valid_for(&self, owner: &Rc<()>) -> bool {
+        self.owner.upgrade().is_some_and(|o| Rc::ptr_eq(&o, owner))
+    }
+
+    pub(crate) fn function(&self) -> &v8::Global<v8::Function> {
+        &self.function
+    }
+
+    pub(crate) fn namespace(&self) -> Option<&v8::Global<v8::Object>> {
+        self.namespace.as_ref()
+    }
+
+    pub(crate) fn args(&self) -> &RefCell<ArgBuffer> {
+        &self.args
+    }
+}
+
+/// Storage for decoded call arguments, kept between calls so that its allocation is reused
+///
+/// Always empty between calls, since the handles it holds only live as long as the scope of a single call
+#[derive(Default)]
+pub(crate) struct ArgBuffer(Vec<v8::Local<'static, v8::Value>>);
+
+impl ArgBuffer {
+    /// Takes the storage out of the buffer, as an empty vector for handles in the current scope
+    pub(crate) fn take<'s>(&mut self) -> Vec<v8::Local<'s, v8::Value>> {
+        relabel(std::mem::take(&mut self.0))
+    }
+
+    /// Puts the storage back once the handles in it are no longer needed
+    pub(crate) fn restore(&mut self, storage: Vec<v8::Local<'_, v8::Value>>) {
+        self.0 = relabel(storage);
+    }
+}
+
+/// Empties a vector of handles, and reuses its allocation for handles of another scope
+fn relabel<'a, 'b>(storage: Vec<v8::Local<'a, v8::Value>>) -> Vec<v8::Local<'b, v8::Value>> {
+    let mut storage = std::mem::ManuallyDrop::new(storage);
+    storage.clear();
+
+    // Safety: the vector is empty, and only the lifetime of the element type changes - not its layout
+    unsafe { Vec::from_raw_parts(storage.as_mut_ptr().cast(), 0, storage.capacity()) }
+}
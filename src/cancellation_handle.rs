@@ -0,0 +1,29 @@
+use deno_core::v8;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// A handle that can cancel a runtime's running call from another thread, created with [`crate::Runtime::cancellation_handle`]
+///
+/// Cancelling terminates the javascript currently executing in the runtime, and the interrupted call
+/// returns [`crate::Error::Cancelled`]. If nothing is running, cancelling has no effect.
+///
+/// The handle can be cloned, sent between threads, and reused for any number of calls.
+#[derive(Clone)]
+pub struct CancellationHandle {
+    isolate: v8::IsolateHandle,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationHandle {
+    pub(crate) fn new(isolate: v8::IsolateHandle, cancelled: Arc<AtomicBool>) -> Self {
+        Self { isolate, cancelled }
+    }
+
+    /// Cancel the call currently running in the runtime
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.isolate.terminate_execution();
+    }
+}
@@ -0,0 +1,204 @@
+use crate::Error;
+use deno_core::v8;
+use std::{
+    ffi::c_void,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+/// How often the isolate is sampled
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(5);
+
+/// An interrupt serviced sooner than this after being requested means javascript was executing
+/// Interrupts requested while the isolate is idle are only serviced once javascript runs again
+const RUNNING_THRESHOLD: Duration = Duration::from_millis(1);
+
+/// State shared between the watchdog thread and the isolate's interrupt callback
+struct Sampler {
+    epoch: Instant,
+
+    /// Time of the last interrupt request, in nanoseconds since `epoch`
+    requested_at: AtomicU64,
+
+    /// True while an interrupt request has not been serviced
+    pending: AtomicBool,
+
+    /// Number of samples that found javascript executing during the current call
+    running: AtomicU64,
+}
+
+impl Sampler {
+    fn now(&self) -> u64 {
+        u64::try_from(self.epoch.elapsed().as_nanos()).unwrap_or(u64::MAX)
+    }
+}
+
+extern "C" fn on_interrupt(_: &mut v8::Isolate, data: *mut c_void) {
+    // Safety: `data` is an Arc leaked by the watchdog for this request
+    let sampler = unsafe { Arc::from_raw(data.cast::<Sampler>()) };
+    let delay = sampler
+        .now()
+        .saturating_sub(sampler.requested_at.load(Ordering::SeqCst));
+    if u128::from(delay) < RUNNING_THRESHOLD.as_nanos() {
+        sampler.running.fetch_add(1, Ordering::SeqCst);
+    }
+    sampler.pending.store(false, Ordering::SeqCst);
+}
+
+/// Whether a call is being watched, and whether it used up its budget
+///
+/// Behind a lock, so that the watchdog cannot terminate the isolate once a call has finished
+#[derive(Default)]
+struct Watch {
+    active: bool,
+    exceeded: bool,
+}
+
+/// State shared between the watchdog and its thread
+struct Shared {
+    sampler: Arc<Sampler>,
+    watch: Mutex<Watch>,
+    stop: AtomicBool,
+}
+
+impl Shared {
+    fn watch(&self) -> std::sync::MutexGuard<'_, Watch> {
+        self.watch.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Enforces [`crate::RuntimeOptions::cpu_time_limit`] for each call made through a runtime
+///
+/// A single background thread, living as long as the runtime, periodically interrupts the isolate while
+/// a call is being watched - each interrupt that is serviced immediately means javascript was executing,
+/// and counts as one interval of CPU time. Once a call's budget is used, the isolate's execution is terminated
+pub(crate) struct CpuWatchdog {
+    isolate: v8::IsolateHandle,
+    limit: Duration,
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl CpuWatchdog {
+    /// Start the watchdog thread for an isolate - it stays idle until a call is watched
+    pub fn new(isolate: v8::IsolateHandle, limit: Duration) -> Self {
+        let shared = Arc::new(Shared {
+            sampler: Arc::new(Sampler {
+                epoch: Instant::now(),
+                requested_at: AtomicU64::new(0),
+                pending: AtomicBool::new(false),
+                running: AtomicU64::new(0),
+            }),
+            watch: Mutex::new(Watch::default()),
+            stop: AtomicBool::new(false),
+        });
+
+        let thread = {
+            let isolate = isolate.clone();
+            let shared = shared.clone();
+            let budget = limit.as_nanos() / SAMPLE_INTERVAL.as_nanos();
+            std::thread::spawn(move || {
+                let sampler = &shared.sampler;
+                while !shared.stop.load(Ordering::SeqCst) {
+                    {
+                        let mut watch = shared.watch();
+                        if !watch.active || watch.exceeded {
+                            // Sleep until the next call is watched
+                            drop(watch);
+                            std::thread::park();
+                            continue;
+                        }
+
+                        if u128::from(sampler.running.load(Ordering::SeqCst)) > budget {
+                            watch.exceeded = true;
+                            isolate.terminate_execution();
+                            continue;
+                        }
+                    }
+
+                    // Only one request is in flight at a time
+                    if !sampler.pending.swap(true, Ordering::SeqCst) {
+                        sampler.requested_at.store(sampler.now(), Ordering::SeqCst);
+                        let data = Arc::into_raw(sampler.clone()).cast_mut().cast::<c_void>();
+                        if !isolate.request_interrupt(on_interrupt, data) {
+                            // The isolate is gone - reclaim the reference, and stop
+                            drop(unsafe { Arc::from_raw(data.cast::<Sampler>()) });
+                            break;
+                        }
+                    }
+
+                    std::thread::park_timeout(SAMPLE_INTERVAL);
+                }
+            })
+        };
+
+        Self {
+            isolate,
+            limit,
+            shared,
+            thread: Some(thread),
+        }
+    }
+
+    /// Start watching a call, with a fresh budget, until the returned guard is finished or dropped
+    pub fn watch(&self) -> WatchedCall<'_> {
+        self.shared.sampler.running.store(0, Ordering::SeqCst);
+        *self.shared.watch() = Watch {
+            active: true,
+            exceeded: false,
+        };
+        if let Some(thread) = &self.thread {
+            thread.thread().unpark();
+        }
+
+        WatchedCall(self)
+    }
+
+    /// Stop watching the current call - returns true if it used up its budget,
+    /// in which case the termination is cleared so the runtime can be used again
+    fn unwatch(&self) -> bool {
+        let mut watch = self.shared.watch();
+        watch.active = false;
+        let exceeded = std::mem::take(&mut watch.exceeded);
+        if exceeded {
+            self.isolate.cancel_terminate_execution();
+        }
+        exceeded
+    }
+}
+
+impl Drop for CpuWatchdog {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            thread.join().ok();
+        }
+    }
+}
+
+/// A call being watched by a [`CpuWatchdog`] - watching stops when it is dropped
+pub(crate) struct WatchedCall<'a>(&'a CpuWatchdog);
+
+impl WatchedCall<'_> {
+    /// Stop watching, and return the result of the call
+    ///
+    /// A call that failed after using up its CPU time reports [`Error::CpuTimeExceeded`] instead of the
+    /// termination error. One that succeeded anyway - the budget ran out as it returned - keeps its result
+    pub fn finish<T>(self, result: Result<T, Error>) -> Result<T, Error> {
+        match result {
+            Err(_) if self.0.unwatch() => Err(Error::CpuTimeExceeded(self.0.limit)),
+            result => result,
+        }
+    }
+}
+
+impl Drop for WatchedCall<'_> {
+    fn drop(&mut self) {
+        self.0.unwatch();
+    }
+}
@@ -0,0 +1,172 @@
+//! Static pre-flight checks of a module and its imports - see [`crate::Runtime::dry_run`]
+use crate::{module_loader::RustyLoader, traits::ToModuleSpecifier, transpiler, Error, Module};
+use deno_core::ModuleSpecifier;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+
+/// An import that could not be resolved, loaded or parsed during a [`crate::Runtime::dry_run`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnresolvedImport {
+    /// The specifier, as written in the importing module
+    pub specifier: String,
+
+    /// The fully resolved specifier of the importing module
+    pub referrer: ModuleSpecifier,
+
+    /// Why the import failed
+    pub reason: String,
+}
+
+impl std::fmt::Display for UnresolvedImport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} (imported by {}): {}",
+            self.specifier, self.referrer, self.reason
+        )
+    }
+}
+
+/// The result of a [`crate::Runtime::dry_run`]
+#[derive(Debug, Clone, Default)]
+pub struct DryRunReport {
+    /// Every module imported by the module, directly or not, that was resolved and loaded
+    pub modules: Vec<ModuleSpecifier>,
+
+    /// Imports that could not be resolved, loaded or parsed
+    pub unresolved_imports: Vec<UnresolvedImport>,
+
+    /// Names exported by the module, including those re-exported with `export * from`
+    pub exports: Vec<String>,
+}
+
+impl DryRunReport {
+    /// Returns true if every import in the graph was resolved and loaded
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.unresolved_imports.is_empty()
+    }
+
+    /// Returns true if the module has a default export, which is used as its entrypoint
+    ///
+    /// Entrypoints registered with `rustyscript.register_entrypoint` only exist once the module has run,
+    /// and are not detected
+    #[must_use]
+    pub fn has_entrypoint(&self) -> bool {
+        self.exports.iter().any(|name| name == "default")
+    }
+
+    /// Returns the names in `required` that the module does not export
+    #[must_use]
+    pub fn missing_exports<'a>(&self, required: &[&'a str]) -> Vec<&'a str> {
+        required
+            .iter()
+            .filter(|name| !self.exports.iter().any(|e| e == *name))
+            .copied()
+            .collect()
+    }
+}
+
+/// Modules provided by extensions are always available, and are not loaded
+fn is_builtin(url: &ModuleSpecifier) -> bool {
+    matches!(url.scheme(), "ext" | "node")
+}
+
+/// Walks the import graph of a module through the loader, without instantiating or running anything
+pub(crate) async fn dry_run(
+    loader: &RustyLoader,
+    cwd: &Path,
+    module: &Module,
+) -> Result<DryRunReport, Error> {
+    let root = loader.normalize_specifier(module.filename().to_module_specifier(cwd)?);
+
+    // Syntax errors in the module itself are returned, rather than reported
+    let imports = transpiler::find_imports(&root, module.contents(), module.language())?;
+    let (exports, star_sources) =
+        transpiler::find_exports(&root, module.contents(), module.language())?;
+
+    let mut report = DryRunReport {
+        exports,
+        ..DryRunReport::default()
+    };
+    let mut sources = HashMap::new();
+    let mut resolved = HashMap::new();
+    let mut seen = HashSet::from([root.clone()]);
+    let mut queue = VecDeque::from([(root.clone(), imports)]);
+    while let Some((referrer, imports)) = queue.pop_front() {
+        for specifier in imports {
+            let unresolved = |reason: String| UnresolvedImport {
+                specifier: specifier.clone(),
+                referrer: referrer.clone(),
+                reason,
+            };
+
+            let url = match loader.resolve_specifier(&specifier, referrer.as_str()) {
+                Ok(url) => url,
+                Err(e) => {
+                    report.unresolved_imports.push(unresolved(e.to_string()));
+                    continue;
+                }
+            };
+            resolved.insert((referrer.clone(), specifier.clone()), url.clone());
+            if is_builtin(&url) || !seen.insert(url.clone()) {
+                continue;
+            }
+
+            let code = match loader.load_source(&url).await {
+                Ok(code) => code,
+                Err(e) => {
+                    report.unresolved_imports.push(unresolved(e.to_string()));
+                    continue;
+                }
+            };
+
+            let imports = if url.path().ends_with(".json") {
+                vec![]
+            } else {
+                match transpiler::find_imports(&url, &code, None) {
+                    Ok(imports) => imports,
+                    Err(e) => {
+                        report.unresolved_imports.push(unresolved(e.to_string()));
+                        continue;
+                    }
+                }
+            };
+
+            report.modules.push(url.clone());
+            sources.insert(url.clone(), code);
+            queue.push_back((url, imports));
+        }
+    }
+
+    // Follow `export * from` re-exports through the modules loaded above
+    let mut stars: Vec<_> = star_sources
+        .into_iter()
+        .map(|s| (root.clone(), s))
+        .collect();
+    let mut visited = HashSet::new();
+    while let Some((referrer, specifier)) = stars.pop() {
+        let Some(url) = resolved.get(&(referrer, specifier)) else {
+            continue;
+        };
+        let Some(code) = sources.get(url) else {
+            continue;
+        };
+        if !visited.insert(url.clone()) {
+            continue;
+        }
+
+        let Ok((names, star_sources)) = transpiler::find_exports(url, code, None) else {
+            continue;
+        };
+        for name in names {
+            // `export *` never re-exports the default export
+            if name != "default" && !report.exports.contains(&name) {
+                report.exports.push(name);
+            }
+        }
+        stars.extend(star_sources.into_iter().map(|s| (url.clone(), s)));
+    }
+
+    Ok(report)
+}
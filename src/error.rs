@@ -28,6 +28,36 @@ impl Default for ErrorFormattingOptions {
     }
 }
 
+/// Structured information about an error thrown from javascript
+///
+/// Extracted from the thrown value, so `name` and `message` will be set for any `Error` subclass,
+/// and `cause` is populated from the error's `cause` property, if present
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct JsErrorDetails {
+    /// The error's class name, such as `TypeError` or `ValidationError`
+    pub name: Option<String>,
+
+    /// The error's message, without the class name
+    pub message: Option<String>,
+
+    /// The error's stack trace, if one was captured
+    pub stack: Option<String>,
+
+    /// The error this one was caused by, if any
+    pub cause: Option<Box<JsErrorDetails>>,
+}
+
+impl From<&deno_core::error::JsError> for JsErrorDetails {
+    fn from(e: &deno_core::error::JsError) -> Self {
+        Self {
+            name: e.name.clone(),
+            message: e.message.clone(),
+            stack: e.stack.clone(),
+            cause: e.cause.as_deref().map(|c| Box::new(Self::from(c))),
+        }
+    }
+}
+
 /// Represents the errors that can occur during execution of a module
 #[derive(Error, Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum Error {
@@ -35,6 +65,11 @@ pub enum Error {
     #[error("{0} has no entrypoint. Register one, or add a default to the runtime")]
     MissingEntrypoint(Module),
 
+    /// Triggers when the entrypoint module of a set cannot be determined
+    /// Contains the list of candidate modules
+    #[error("Could not determine the entrypoint module - specify one of: {0}")]
+    AmbiguousEntrypoint(String),
+
     /// Triggers when an attempt to find a value by name fails
     #[error("{0} could not be found in global, or module exports")]
     ValueNotFound(String),
@@ -43,6 +78,11 @@ pub enum Error {
     #[error("{0} is not a function")]
     ValueNotCallable(String),
 
+    /// Triggers when calling the default export of a module that is missing, or not a function  
+    /// The second value is the `typeof` the export that was found
+    #[error("The default export of {0} is not a function (found {1})")]
+    DefaultExportNotCallable(Module, String),
+
     /// Triggers when a string could not be encoded for v8
     #[error("{0} could not be encoded as a v8 value")]
     V8Encoding(String),
@@ -55,10 +95,21 @@ pub enum Error {
     #[error("{0}")]
     ModuleNotFound(String),
 
+    /// Triggers when a module imported over HTTP (`url_import` crate feature) was refused by the server
+    /// Such as a `401` or `403` from a registry that requires authentication
+    /// Contains the URL of the module and the HTTP status code
+    #[error("Could not import {0}: the server responded with HTTP {1}")]
+    ImportHttpStatus(String, u16),
+
     /// Triggers when attempting to use a worker that has already been shutdown
     #[error("This worker has been destroyed")]
     WorkerHasStopped,
 
+    /// Triggers when every worker in a pool already has the maximum number of pending queries
+    /// Contains the per-worker queue capacity
+    #[error("All workers are busy - each already has {0} pending queries")]
+    WorkerQueueFull(usize),
+
     /// Triggers on runtime issues during execution of a module
     #[error("{0}")]
     Runtime(String),
@@ -71,12 +122,115 @@ pub enum Error {
     #[error("Module timed out: {0}")]
     Timeout(String),
 
+    /// Triggers when a call spends more time executing javascript than [`crate::RuntimeOptions::cpu_time_limit`]
+    /// Contains the limit
+    #[error("CPU time limit of {0:?} exceeded")]
+    CpuTimeExceeded(std::time::Duration),
+
+    /// Triggers when a single turn of the event loop runs more promise reactions than [`crate::RuntimeOptions::microtask_limit`]
+    /// Contains the limit
+    #[error("Microtask limit of {0} exceeded in a single turn of the event loop")]
+    MicrotaskLimitExceeded(usize),
+
+    /// Triggers when a call writes to `console.error` or `console.warn`, and [`crate::RuntimeOptions::fail_on_console_error`] is set
+    /// Contains the messages written, one per line, each prefixed with the method that wrote it - such as `console.warn: ...`
+    #[error("Script wrote to the console:\n{0}")]
+    ConsoleError(String),
+
+    /// Triggers when calls into registered functions are nested deeper than [`crate::RuntimeOptions::max_call_depth`]
+    /// Contains the maximum depth
+    #[error(
+        "Maximum call depth exceeded - registered functions were nested more than {0} calls deep"
+    )]
+    MaxCallDepthExceeded(usize),
+
+    /// Triggers when a call is cancelled through a [`crate::CancellationHandle`]
+    #[error("Execution was cancelled")]
+    Cancelled,
+
     /// Triggers when the heap (via `max_heap_size`) is exhausted during execution
     #[error("Heap exhausted")]
     HeapExhausted,
+
+    /// Triggers when a string returned from javascript is larger than `max_string_bytes`
+    /// Contains the size of the string, and the limit, in UTF-8 bytes
+    #[error("String of {0} bytes exceeds the maximum of {1} bytes")]
+    StringTooLarge(usize, usize),
+
+    /// Triggers when a value returned from javascript is larger than `max_result_size`
+    /// Contains the size measured before the check stopped, and the limit, in bytes
+    #[error("Result of at least {0} bytes exceeds the maximum of {1} bytes")]
+    ResultTooLarge(usize, usize),
+
+    /// Triggers when a module's `teardown` export fails after the call it was cleaning up also failed
+    /// Contains the primary error, followed by the teardown error
+    #[error("{0} (teardown also failed: {1})")]
+    TeardownFailed(Box<Error>, Box<Error>),
+
+    /// Triggers when a module archive passed to `load_archive` is malformed or unsupported
+    #[error("Invalid module archive: {0}")]
+    InvalidArchive(String),
+
+    /// An error that a registered function throws into javascript as an instance of a custom class
+    /// Contains the class name, registered with `Runtime::register_error_class`, and the message - see [`Error::js_class`]
+    #[error("{1}")]
+    JsClass(String, String),
+
+    /// Triggers when a snapshot file cannot be loaded, such as one created by a different version of rustyscript or v8
+    #[error("Snapshot cannot be used: {0}")]
+    IncompatibleSnapshot(String),
 }
 
 impl Error {
+    /// Creates an error that a registered function can return, to throw an instance of a custom javascript class
+    ///
+    /// The class must be registered with [`crate::Runtime::register_error_class`] first -
+    /// otherwise a plain `Error` is thrown, with the class name prepended to the message
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Error, serde_json::Value };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.register_error_class("NetworkError")?;
+    /// runtime.register_function("fetch", |_| {
+    ///     Err::<Value, _>(Error::js_class("NetworkError", "connection refused"))
+    /// })?;
+    ///
+    /// let caught: bool = runtime.eval("
+    ///     try { rustyscript.functions.fetch(); false }
+    ///     catch (e) { e instanceof NetworkError }
+    /// ")?;
+    /// assert!(caught);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn js_class(class: impl ToString, message: impl ToString) -> Self {
+        Self::JsClass(class.to_string(), message.to_string())
+    }
+
+    /// Returns structured information about the thrown javascript error, if this is a [`Error::JsError`]
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Undefined };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let e = runtime.eval::<Undefined>("throw new TypeError('oops')").unwrap_err();
+    /// let details = e.js_error_details().unwrap();
+    /// assert_eq!(details.name.as_deref(), Some("TypeError"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn js_error_details(&self) -> Option<JsErrorDetails> {
+        match self {
+            Error::JsError(e) => Some(JsErrorDetails::from(e)),
+            _ => None,
+        }
+    }
+
     /// Formats an error for display in a terminal
     /// If the error is a `JsError`, it will attempt to highlight the source line
     /// in this format:
@@ -169,6 +323,82 @@ impl Error {
             self.to_string()
         }
     }
+
+    /// Formats an error as a diagnostic for terminals, in the style of deno:
+    /// ```text
+    /// error: Uncaught Error: oops
+    ///   --> file:///test.js:3:11
+    ///    |
+    ///  3 |     throw new Error('oops');
+    ///    |           ^
+    ///     at fail (file:///test.js:3:11)
+    /// ```
+    ///
+    /// The source line is taken from the module sources retained by the runtime's loader
+    /// If `use_color` is true, the output is colorized with ANSI escape codes
+    ///
+    /// Errors that did not come from javascript are displayed as `error: <message>`
+    #[must_use]
+    pub fn format_pretty(&self, use_color: bool) -> String {
+        let paint = |code: &str, s: &str| {
+            if use_color {
+                format!("\x1b[{code}m{s}\x1b[0m")
+            } else {
+                s.to_string()
+            }
+        };
+        let heading = paint("1;31", "error");
+
+        let Error::JsError(e) = self else {
+            return format!("{heading}: {self}");
+        };
+
+        let mut output = format!("{heading}: {}", e.exception_message);
+
+        // Position and source snippet
+        let frame = e.frames.first();
+        let filename = frame
+            .and_then(|f| f.file_name.as_deref())
+            .filter(|f| !f.is_empty());
+        let row = frame.and_then(|f| f.line_number).unwrap_or(1);
+        let col = frame.and_then(|f| f.column_number).unwrap_or(1);
+        let col = usize::try_from(col).unwrap_or(1).max(1);
+
+        let gutter = " ".repeat(row.to_string().len());
+        if let Some(filename) = filename {
+            output.push_str(&format!(
+                "\n{gutter}{} {filename}:{row}:{col}",
+                paint("36", "-->")
+            ));
+        }
+
+        if let Some(line) = &e.source_line {
+            let line = line.trim_end();
+
+            // Keep tabs, so the caret lines up with the source
+            let padding: String = line
+                .chars()
+                .take(col - 1)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+
+            let bar = paint("36", "|");
+            output.push_str(&format!(
+                "\n{gutter} {bar}\n{} {bar} {line}\n{gutter} {bar} {padding}{}",
+                paint("36", &row.to_string()),
+                paint("1;31", "^")
+            ));
+        }
+
+        // Stack frames, from the captured stack
+        if let Some(stack) = &e.stack {
+            for frame in stack.lines().filter(|l| l.trim_start().starts_with("at ")) {
+                output.push_str(&format!("\n    {}", paint("2", frame.trim())));
+            }
+        }
+
+        output
+    }
 }
 
 #[macro_use]
@@ -203,6 +433,12 @@ map_error!(deno_core::serde_v8::Error, |e| Error::JsonDecode(
 ));
 
 map_error!(deno_core::anyhow::Error, |e| {
+    // Errors raised by rustyscript itself (such as in the module loader) are passed through
+    let e = match e.downcast::<Error>() {
+        Ok(e) => return e,
+        Err(e) => e,
+    };
+
     // trydowncast to deno_core::error::JsError
     let s = e.to_string();
     match e.downcast::<deno_core::error::JsError>() {
@@ -228,7 +464,9 @@ map_error!(deno_broadcast_channel::BroadcastChannelError, |e| {
 
 #[cfg(test)]
 mod test {
-    use crate::{error::ErrorFormattingOptions, Module, Runtime, RuntimeOptions, Undefined};
+    use crate::{
+        error::ErrorFormattingOptions, json_args, Module, Runtime, RuntimeOptions, Undefined,
+    };
 
     #[test]
     #[rustfmt::skip]
@@ -253,4 +491,58 @@ mod test {
             "= Uncaught (in promise) ReferenceError: x is not defined"
         ));
     }
+
+    #[test]
+    fn test_format_pretty() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+
+        let module = Module::new("test.js", "1+1;\n1 + x");
+        let e = runtime.load_module(&module).unwrap_err();
+
+        let plain = e.format_pretty(false);
+        assert!(plain.starts_with("error: Uncaught (in promise) ReferenceError: x is not defined"));
+        assert!(plain.contains("test.js:2:5"), "{plain}");
+        assert!(plain.contains("2 | 1 + x\n  |     ^"), "{plain}");
+
+        let colored = e.format_pretty(true);
+        assert!(colored.starts_with("\x1b[1;31merror\x1b[0m: "), "{colored}");
+
+        let e = crate::Error::Runtime("boom".to_string());
+        assert_eq!(e.format_pretty(false), "error: boom");
+    }
+
+    #[test]
+    fn test_js_error_details() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+
+        let e = runtime
+            .eval::<Undefined>("throw new TypeError('oops', { cause: new Error('inner') })")
+            .unwrap_err();
+        let details = e.js_error_details().expect("No details for JsError");
+        assert_eq!(details.name.as_deref(), Some("TypeError"));
+        assert_eq!(details.message.as_deref(), Some("oops"));
+        let cause = details.cause.expect("Cause was not captured");
+        assert_eq!(cause.message.as_deref(), Some("inner"));
+
+        let module = Module::new(
+            "test.js",
+            "
+            class ValidationError extends Error {
+                constructor(msg) { super(msg); this.name = 'ValidationError'; }
+            }
+            export const f = () => { throw new ValidationError('bad input'); };
+        ",
+        );
+        let module = runtime.load_module(&module).unwrap();
+        let e = runtime
+            .call_function::<Undefined>(Some(&module), "f", json_args!())
+            .unwrap_err();
+        assert!(matches!(e, crate::Error::JsError(_)));
+        assert!(e
+            .to_string()
+            .starts_with("Uncaught ValidationError: bad input"));
+        let details = e.js_error_details().expect("No details for a thrown error");
+        assert_eq!(details.name.as_deref(), Some("ValidationError"));
+        assert_eq!(details.message.as_deref(), Some("bad input"));
+    }
 }
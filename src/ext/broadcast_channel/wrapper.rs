@@ -1,4 +1,4 @@
-use crate::{big_json_args, Error, Runtime};
+use crate::{async_bridge::AsyncBridgeExt, big_json_args, Error, Runtime};
 use deno_broadcast_channel::BroadcastChannel;
 use serde::{de::DeserializeOwned, Serialize};
 use std::time::Duration;
@@ -32,8 +32,7 @@ impl<Channel: BroadcastChannel> BroadcastChannelWrapper<Channel> {
     /// # Errors
     /// Will return an error if the message cannot be serialized or sent
     pub fn send_sync<T: Serialize>(&self, runtime: &mut Runtime, data: T) -> Result<(), Error> {
-        let tokio_rt = runtime.tokio_runtime();
-        tokio_rt.block_on(self.send(runtime, data))
+        runtime.block_on(|runtime| self.send(runtime, data))
     }
 
     /// Send a message to the channel
@@ -93,8 +92,7 @@ impl<Channel: BroadcastChannel> BroadcastChannelWrapper<Channel> {
         runtime: &mut Runtime,
         timeout: Option<Duration>,
     ) -> Result<Option<T>, Error> {
-        let tokio_rt = runtime.tokio_runtime();
-        tokio_rt.block_on(self.recv(runtime, timeout))
+        runtime.block_on(|runtime| self.recv(runtime, timeout))
     }
 }
 
@@ -0,0 +1,86 @@
+import { op_encoding_encode, op_encoding_encode_into, op_encoding_decode_utf8 } from "ext:core/ops";
+import { applyToGlobal, nonEnumerable } from 'ext:rustyscript/rustyscript.js';
+
+const UTF8_LABELS = ['unicode-1-1-utf-8', 'unicode11utf8', 'unicode20utf8', 'utf-8', 'utf8', 'x-unicode20utf8'];
+
+class TextEncoder {
+    get encoding() {
+        return 'utf-8';
+    }
+
+    /**
+     * @param {string} input
+     * @returns {Uint8Array}
+     */
+    encode(input = '') {
+        return op_encoding_encode(String(input));
+    }
+
+    /**
+     * @param {string} source
+     * @param {Uint8Array} destination
+     * @returns {{ read: number, written: number }}
+     */
+    encodeInto(source, destination) {
+        if (!(destination instanceof Uint8Array)) {
+            throw new TypeError("Failed to execute 'encodeInto' on 'TextEncoder': Argument 2 is not a Uint8Array");
+        }
+
+        const progress = new Uint32Array(2);
+        op_encoding_encode_into(String(source), destination, progress);
+        return { read: progress[0], written: progress[1] };
+    }
+}
+
+class TextDecoder {
+    #fatal;
+    #ignoreBOM;
+
+    /**
+     * @param {string} label
+     * @param {{ fatal?: boolean, ignoreBOM?: boolean }} options
+     */
+    constructor(label = 'utf-8', options = {}) {
+        if (!UTF8_LABELS.includes(String(label).trim().toLowerCase())) {
+            throw new RangeError(`The encoding label provided ('${label}') is invalid - only utf-8 is supported`);
+        }
+
+        this.#fatal = Boolean(options.fatal);
+        this.#ignoreBOM = Boolean(options.ignoreBOM);
+    }
+
+    get encoding() {
+        return 'utf-8';
+    }
+
+    get fatal() {
+        return this.#fatal;
+    }
+
+    get ignoreBOM() {
+        return this.#ignoreBOM;
+    }
+
+    /**
+     * @param {BufferSource} input
+     * @param {{ stream?: boolean }} options
+     * @returns {string}
+     */
+    decode(input = new Uint8Array(), options = {}) {
+        if (options.stream) {
+            throw new TypeError("Failed to execute 'decode' on 'TextDecoder': streaming is not supported");
+        }
+
+        const text = op_encoding_decode_utf8(input, this.#fatal, this.#ignoreBOM);
+        if (text === null) {
+            throw new TypeError("Failed to execute 'decode' on 'TextDecoder': The encoded data is not valid");
+        }
+
+        return text;
+    }
+}
+
+applyToGlobal({
+    TextEncoder: nonEnumerable(TextEncoder),
+    TextDecoder: nonEnumerable(TextDecoder),
+});
@@ -0,0 +1,106 @@
+//! A lightweight `TextEncoder` / `TextDecoder` implementation, used when the `web` feature is disabled
+//!
+//! Only UTF-8 is supported. It provides no access to the network or filesystem, so it preserves sandboxing
+use super::ExtensionTrait;
+use deno_core::{extension, op2, Extension};
+
+/// Encodes a string as UTF-8
+#[op2]
+#[buffer]
+fn op_encoding_encode(#[string] input: &str) -> Vec<u8> {
+    input.as_bytes().to_vec()
+}
+
+/// Encodes as much of a string as fits into the buffer, without splitting a character
+/// Writes the number of UTF-16 code units read, and bytes written, to `progress`
+#[op2(fast)]
+#[allow(clippy::cast_possible_truncation)]
+fn op_encoding_encode_into(
+    #[string] input: &str,
+    #[buffer] buffer: &mut [u8],
+    #[buffer] progress: &mut [u32],
+) {
+    let mut boundary = input.len().min(buffer.len());
+    while !input.is_char_boundary(boundary) {
+        boundary -= 1;
+    }
+
+    buffer[..boundary].copy_from_slice(&input.as_bytes()[..boundary]);
+    progress[0] = input[..boundary].encode_utf16().count() as u32;
+    progress[1] = boundary as u32;
+}
+
+/// Decodes UTF-8 bytes into a string
+/// Returns `None` if `fatal` is set and the input is not valid UTF-8
+#[op2]
+#[serde]
+fn op_encoding_decode_utf8(
+    #[anybuffer] input: &[u8],
+    fatal: bool,
+    ignore_bom: bool,
+) -> Option<String> {
+    let input = match input {
+        [0xEF, 0xBB, 0xBF, rest @ ..] if !ignore_bom => rest,
+        _ => input,
+    };
+
+    if fatal {
+        std::str::from_utf8(input).ok().map(str::to_string)
+    } else {
+        Some(String::from_utf8_lossy(input).into_owned())
+    }
+}
+
+extension!(
+    init_encoding,
+    deps = [rustyscript],
+    ops = [op_encoding_encode, op_encoding_encode_into, op_encoding_decode_utf8],
+    esm_entry_point = "ext:init_encoding/init_encoding.js",
+    esm = [ dir "src/ext/encoding", "init_encoding.js" ],
+);
+impl ExtensionTrait<()> for init_encoding {
+    fn init((): ()) -> Extension {
+        init_encoding::init_ops_and_esm()
+    }
+}
+
+pub fn extensions(is_snapshot: bool) -> Vec<Extension> {
+    vec![init_encoding::build((), is_snapshot)]
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Runtime, RuntimeOptions};
+
+    #[test]
+    fn test_text_encoding() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+
+        let bytes: Vec<u8> = runtime
+            .eval("Array.from(new TextEncoder().encode('hé'))")
+            .unwrap();
+        assert_eq!(bytes, vec![0x68, 0xC3, 0xA9]);
+
+        let text: String = runtime
+            .eval("new TextDecoder().decode(new Uint8Array([0xEF, 0xBB, 0xBF, 0x68, 0xC3, 0xA9]))")
+            .unwrap();
+        assert_eq!(text, "hé");
+
+        // encodeInto never splits a character
+        let progress: (usize, usize) = runtime
+            .eval(
+                "(() => {
+                    const { read, written } = new TextEncoder().encodeInto('aé', new Uint8Array(2));
+                    return [read, written];
+                })()",
+            )
+            .unwrap();
+        assert_eq!(progress, (1, 1));
+
+        runtime
+            .eval::<String>(
+                "new TextDecoder('utf-8', { fatal: true }).decode(new Uint8Array([0xFF]))",
+            )
+            .expect_err("Decoded invalid UTF-8 in fatal mode");
+    }
+}
@@ -1,5 +1,7 @@
 use super::ExtensionTrait;
 use deno_core::{extension, Extension};
+use deno_io::{Stdio, StdioPipe};
+use std::io::{Read, Write};
 
 #[cfg(windows)]
 mod tty_windows;
@@ -33,6 +35,147 @@ impl ExtensionTrait<()> for tty::deno_tty {
     }
 }
 
+/// Custom streams for the stdin/out/err pipes of the `deno_io` extension  
+/// Any stream left unset is inherited from the process
+///
+/// Convert to pipes with [`IoStreams::into_stdio`], and pass them in [`crate::ExtensionOptions::io_pipes`]
+///
+/// Output is forwarded to the sinks on background threads, one write at a time.
+/// Separate stdout and stderr sinks are forwarded independently - use [`IoStreams::with_combined_output`]
+/// to keep the order of writes across both streams
+///
+/// ```rust
+/// use rustyscript::{ IoStreams, RuntimeBuilder };
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let pipes = IoStreams::new()
+///     .with_stdin(std::io::Cursor::new(b"input".to_vec()))
+///     .with_stdout(std::io::sink())
+///     .into_stdio()?;
+/// let runtime = RuntimeBuilder::new().with_io_pipes(pipes).build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct IoStreams {
+    stdin: Option<Box<dyn Read + Send>>,
+    stdout: Option<Box<dyn Write + Send>>,
+    stderr: Option<Box<dyn Write + Send>>,
+    combined: bool,
+}
+
+impl IoStreams {
+    /// Creates a new set of streams, all inherited from the process
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read stdin from the given source, instead of the process' stdin
+    #[must_use]
+    pub fn with_stdin(mut self, stdin: impl Read + Send + 'static) -> Self {
+        self.stdin = Some(Box::new(stdin));
+        self
+    }
+
+    /// Write stdout to the given sink, instead of the process' stdout
+    #[must_use]
+    pub fn with_stdout(mut self, stdout: impl Write + Send + 'static) -> Self {
+        self.stdout = Some(Box::new(stdout));
+        self.combined = false;
+        self
+    }
+
+    /// Write stderr to the given sink, instead of the process' stderr
+    #[must_use]
+    pub fn with_stderr(mut self, stderr: impl Write + Send + 'static) -> Self {
+        self.stderr = Some(Box::new(stderr));
+        self.combined = false;
+        self
+    }
+
+    /// Write both stdout and stderr to the given sink, through a single pipe  
+    /// Writes arrive in the order they were made, across both streams
+    #[must_use]
+    pub fn with_combined_output(mut self, output: impl Write + Send + 'static) -> Self {
+        self.stdout = Some(Box::new(output));
+        self.stderr = None;
+        self.combined = true;
+        self
+    }
+
+    /// Creates the pipes for these streams, and starts the threads forwarding data to and from them
+    ///
+    /// # Errors
+    /// Can fail if the OS pipes cannot be created
+    pub fn into_stdio(self) -> std::io::Result<Stdio> {
+        let mut stdio = Stdio::default();
+
+        if let Some(mut source) = self.stdin {
+            let (reader, mut writer) = os_pipe::pipe()?;
+            spawn_forwarder("stdin", move || {
+                // Dropping the writer signals EOF to the runtime
+                let _ = std::io::copy(&mut source, &mut writer);
+            })?;
+            stdio.stdin = StdioPipe::file(into_file(reader));
+        }
+
+        if let Some(sink) = self.stdout {
+            let (reader, writer) = os_pipe::pipe()?;
+            if self.combined {
+                stdio.stderr = StdioPipe::file(into_file(writer.try_clone()?));
+            }
+            spawn_forwarder("stdout", move || forward_output(reader, sink))?;
+            stdio.stdout = StdioPipe::file(into_file(writer));
+        }
+
+        if let Some(sink) = self.stderr {
+            let (reader, writer) = os_pipe::pipe()?;
+            spawn_forwarder("stderr", move || forward_output(reader, sink))?;
+            stdio.stderr = StdioPipe::file(into_file(writer));
+        }
+
+        Ok(stdio)
+    }
+}
+
+/// Copies everything written to a pipe into a sink, flushing after each write  
+/// Ends once every copy of the pipe's write end has been closed
+fn forward_output(mut reader: os_pipe::PipeReader, mut sink: Box<dyn Write + Send>) {
+    let mut buf = [0; 8192];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                if sink
+                    .write_all(&buf[..n])
+                    .and_then(|()| sink.flush())
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn spawn_forwarder(name: &str, f: impl FnOnce() + Send + 'static) -> std::io::Result<()> {
+    std::thread::Builder::new()
+        .name(format!("rustyscript-{name}"))
+        .spawn(f)
+        .map(|_| ())
+}
+
+#[cfg(unix)]
+fn into_file(pipe: impl Into<std::os::fd::OwnedFd>) -> std::fs::File {
+    std::fs::File::from(pipe.into())
+}
+
+#[cfg(windows)]
+fn into_file(pipe: impl Into<std::os::windows::io::OwnedHandle>) -> std::fs::File {
+    std::fs::File::from(pipe.into())
+}
+
 pub fn extensions(pipes: Option<deno_io::Stdio>, is_snapshot: bool) -> Vec<Extension> {
     vec![
         deno_io::deno_io::build(pipes, is_snapshot),
@@ -40,3 +183,58 @@ pub fn extensions(pipes: Option<deno_io::Stdio>, is_snapshot: bool) -> Vec<Exten
         init_io::build((), is_snapshot),
     ]
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Module, RuntimeBuilder};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_io_streams() {
+        let output = SharedBuffer::default();
+        let pipes = IoStreams::new()
+            .with_stdin(std::io::Cursor::new(b"hello".to_vec()))
+            .with_combined_output(output.clone())
+            .into_stdio()
+            .unwrap();
+
+        let mut runtime = RuntimeBuilder::new().with_io_pipes(pipes).build().unwrap();
+        let module = Module::new(
+            "test.js",
+            "
+                const input = new Uint8Array(5);
+                await Deno.stdin.read(input);
+
+                const encoder = new TextEncoder();
+                Deno.stdout.writeSync(encoder.encode('out:'));
+                Deno.stderr.writeSync(encoder.encode('err:'));
+                Deno.stdout.writeSync(input);
+            ",
+        );
+        runtime.load_module(&module).unwrap();
+        drop(runtime);
+
+        // Output is forwarded on a background thread
+        for _ in 0..100 {
+            if output.0.lock().unwrap().len() >= 13 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(output.0.lock().unwrap().as_slice(), b"out:err:hello");
+    }
+}
@@ -51,12 +51,15 @@ pub mod http;
 #[cfg(feature = "url")]
 pub mod url;
 
-#[cfg(feature = "web")]
+#[cfg(any(feature = "web", feature = "net"))]
 pub mod web;
 
-#[cfg(all(not(feature = "web"), feature = "web_stub"))]
+#[cfg(all(not(any(feature = "web", feature = "net")), feature = "web_stub"))]
 pub mod web_stub;
 
+#[cfg(all(not(any(feature = "web", feature = "net")), feature = "encoding"))]
+pub mod encoding;
+
 #[cfg(feature = "io")]
 pub mod io;
 
@@ -89,9 +92,9 @@ pub mod runtime;
 pub struct ExtensionOptions {
     /// Options specific to the `deno_web`, `deno_fetch` and `deno_net` extensions
     ///
-    /// Requires the `web` feature to be enabled
-    #[cfg(feature = "web")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+    /// Requires the `web` or `net` feature to be enabled
+    #[cfg(any(feature = "web", feature = "net"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "web", feature = "net"))))]
     pub web: web::WebOptions,
 
     /// Optional seed for the `deno_crypto` extension
@@ -157,7 +160,7 @@ pub struct ExtensionOptions {
 impl Default for ExtensionOptions {
     fn default() -> Self {
         Self {
-            #[cfg(feature = "web")]
+            #[cfg(any(feature = "web", feature = "net"))]
             web: web::WebOptions::default(),
 
             #[cfg(feature = "crypto")]
@@ -204,7 +207,7 @@ pub(crate) fn all_extensions(
     #[cfg(feature = "url")]
     extensions.extend(url::extensions(is_snapshot));
 
-    #[cfg(feature = "web")]
+    #[cfg(any(feature = "web", feature = "net"))]
     extensions.extend(web::extensions(options.web.clone(), is_snapshot));
 
     #[cfg(feature = "broadcast_channel")]
@@ -216,9 +219,12 @@ pub(crate) fn all_extensions(
     #[cfg(feature = "cache")]
     extensions.extend(cache::extensions(options.cache.clone(), is_snapshot));
 
-    #[cfg(all(not(feature = "web"), feature = "web_stub"))]
+    #[cfg(all(not(any(feature = "web", feature = "net")), feature = "web_stub"))]
     extensions.extend(web_stub::extensions(is_snapshot));
 
+    #[cfg(all(not(any(feature = "web", feature = "net")), feature = "encoding"))]
+    extensions.extend(encoding::extensions(is_snapshot));
+
     #[cfg(feature = "crypto")]
     extensions.extend(crypto::extensions(options.crypto_seed, is_snapshot));
 
@@ -188,7 +188,7 @@ fn create_web_worker_callback(options: WebWorkerCallbackOptions) -> Arc<CreateWe
         let module_loader = Rc::new(RustyLoader::new(LoaderOptions {
             cache_provider: None,
             import_provider: None,
-            schema_whlist: HashSet::default(),
+            schema_whitelist: HashSet::default(),
             node_resolver: node_resolver.clone(),
             ..Default::default()
         }));
@@ -265,13 +265,22 @@ fn create_web_worker_callback(options: WebWorkerCallbackOptions) -> Arc<CreateWe
 
 pub fn get_error_class_name(e: &deno_core::error::AnyError) -> &'static str {
     deno_runtime::errors::get_error_class_name(e)
-        .or_else(|| {
-            e.downcast_ref::<deno_ast::ParseDiagnostic>()
-                .map(|_| "SyntaxError")
-        })
+        .or_else(|| is_parse_diagnostic(e).then_some("SyntaxError"))
         .or_else(|| {
             e.downcast_ref::<std::num::TryFromIntError>()
                 .map(|_| "TypeError")
         })
         .unwrap_or("Error")
 }
+
+/// Returns true for errors raised by the parser, which only exists with the `typescript` feature
+#[cfg(feature = "typescript")]
+fn is_parse_diagnostic(e: &deno_core::error::AnyError) -> bool {
+    e.downcast_ref::<deno_ast::ParseDiagnostic>().is_some()
+}
+
+/// Returns true for errors raised by the parser, which only exists with the `typescript` feature
+#[cfg(not(feature = "typescript"))]
+fn is_parse_diagnostic(_e: &deno_core::error::AnyError) -> bool {
+    false
+}
@@ -99,6 +99,7 @@ macro_rules! codegen_function {
                     scope: &mut v8::HandleScope,
                 ) -> Result<Self::Arguments, $crate::Error> {
                     let mut args = args.into_iter();
+                    let _active = $crate::js_value::ActiveRuntime::enter(scope);
                     $(
                         let next = args.next().ok_or($crate::Error::Runtime(format!("Missing argument {} for {}", stringify!($n), stringify!($name))))?;
                         let next = $crate::deno_core::v8::Local::new(scope, next);
@@ -0,0 +1,18 @@
+// Records everything written to console.error and console.warn
+// Used by the `fail_on_console_error` runtime option
+(() => {
+    const console = globalThis.console;
+    if (!console) return;
+
+    const format = (args) => args
+        .map((arg) => typeof arg === 'string' ? arg : (globalThis.Deno.inspect ?? String)(arg))
+        .join(' ');
+
+    for (const level of ['error', 'warn']) {
+        const original = console[level];
+        console[level] = (...args) => {
+            Deno.core.ops.op_record_console_error(level, format(args));
+            return original.apply(console, args);
+        };
+    }
+})();
@@ -0,0 +1,23 @@
+// Replaces Deno.env with a read-only view of the variables given to the runtime
+// Used by the `env_vars` runtime option - called with an object of the variables
+((vars) => {
+    const entries = new Map(Object.entries(vars));
+    const denied = () => {
+        throw new Error('Environment variables are read-only');
+    };
+
+    const env = Object.freeze({
+        get: (key) => entries.get(String(key)),
+        has: (key) => entries.has(String(key)),
+        toObject: () => Object.fromEntries(entries),
+        set: denied,
+        delete: denied,
+    });
+
+    Object.defineProperty(globalThis.Deno, 'env', {
+        value: env,
+        writable: false,
+        enumerable: false,
+        configurable: true,
+    });
+})
@@ -0,0 +1,24 @@
+// Defines a custom error class, and registers it so that errors from rust can be thrown as instances of it
+// Used by `Runtime::register_error_class` - called with the name of the class
+// An existing global constructor with the same name, such as `TypeError`, is registered instead of being replaced
+((name) => {
+    let errorClass = globalThis[name];
+    if (typeof errorClass !== 'function') {
+        errorClass = class extends Error {};
+        Object.defineProperty(errorClass, 'name', { value: name });
+        Object.defineProperty(errorClass.prototype, 'name', {
+            value: name,
+            writable: true,
+            enumerable: false,
+            configurable: true,
+        });
+        Object.defineProperty(globalThis, name, {
+            value: errorClass,
+            writable: true,
+            enumerable: false,
+            configurable: true,
+        });
+    }
+
+    Deno.core.registerErrorClass(name, errorClass);
+})
@@ -0,0 +1,73 @@
+// Replaces Date, performance.now and the timer functions with versions following a FakeClock
+// Used by the `fake_time` runtime option
+(() => {
+    const core = Deno.core;
+    const now = () => core.ops.op_fake_clock_now();
+    const start = now();
+
+    // Date, called with no arguments, uses the clock
+    const RealDate = globalThis.Date;
+    function Date(...args) {
+        if (!new.target) return new RealDate(now()).toString();
+        return Reflect.construct(RealDate, args.length ? args : [now()], new.target);
+    }
+    Object.setPrototypeOf(Date, RealDate);
+    Date.prototype = RealDate.prototype;
+    Date.now = now;
+    globalThis.Date = Date;
+
+    if (globalThis.performance) {
+        Object.defineProperty(globalThis.performance, 'now', {
+            value: () => now() - start,
+            writable: true,
+            configurable: true,
+        });
+    }
+
+    // Pending timers do not keep the event loop alive, since only advancing the clock can fire them
+    // Each is registered with the clock under a key, so that clearing it also removes it from the clock
+    // Each also counts against `max_timers`, under its id negated so as not to clash with the event loop's timers
+    const timers = new Map();
+    let nextId = 1;
+    const { op_timer_limit_track, op_timer_limit_release } = core.ops;
+
+    const schedule = (id, timer) => {
+        timer.key = core.ops.op_fake_clock_register(timer.deadline);
+        const promise = core.ops.op_fake_clock_wait(timer.key);
+        core.unrefOpPromise(promise);
+        promise.then((fired) => {
+            if (!fired || timers.get(id) !== timer) return;
+            if (timer.repeat) {
+                timer.deadline += timer.delay;
+                schedule(id, timer);
+            } else {
+                timers.delete(id);
+                op_timer_limit_release(-id);
+            }
+            timer.callback(...timer.args);
+        });
+    };
+
+    const addTimer = (callback, delay, args, repeat) => {
+        delay = Math.max(Number(delay) || 0, repeat ? 1 : 0);
+        const id = nextId++;
+        op_timer_limit_track(-id);
+        const timer = { callback, args, delay, repeat, deadline: now() + delay };
+        timers.set(id, timer);
+        schedule(id, timer);
+        return id;
+    };
+
+    const clearTimer = (id) => {
+        const timer = timers.get(id);
+        if (!timer) return;
+        timers.delete(id);
+        op_timer_limit_release(-id);
+        core.ops.op_fake_clock_clear(timer.key);
+    };
+
+    globalThis.setTimeout = (callback, delay, ...args) => addTimer(callback, delay, args, false);
+    globalThis.setInterval = (callback, delay, ...args) => addTimer(callback, delay, args, true);
+    globalThis.clearTimeout = clearTimer;
+    globalThis.clearInterval = clearTimer;
+})();
@@ -0,0 +1,33 @@
+// Recursively freezes the intrinsics and the global object
+// Used by the `freeze_globals` runtime option
+(() => {
+    const optOut = Symbol.for('rustyscript.unfrozen');
+    const seen = new WeakSet();
+
+    // Deno internals still mutate their own namespace after startup
+    const skipped = new Set(['Deno']);
+
+    const freeze = (value) => {
+        if (value === null || (typeof value !== 'object' && typeof value !== 'function')) return;
+        if (seen.has(value)) return;
+        seen.add(value);
+
+        // Objects carrying the opt-out property are left mutable
+        if (Object.prototype.hasOwnProperty.call(value, optOut)) return;
+
+        Object.freeze(value);
+        for (const key of Reflect.ownKeys(value)) {
+            const descriptor = Object.getOwnPropertyDescriptor(value, key);
+            if (descriptor && 'value' in descriptor) freeze(descriptor.value);
+        }
+        freeze(Object.getPrototypeOf(value));
+    };
+
+    for (const key of Reflect.ownKeys(globalThis)) {
+        if (skipped.has(key)) continue;
+        const descriptor = Object.getOwnPropertyDescriptor(globalThis, key);
+        if (descriptor && 'value' in descriptor) freeze(descriptor.value);
+    }
+
+    Object.freeze(globalThis);
+})();
@@ -0,0 +1,56 @@
+// Binds a registered function to a dotted path such as `myapi.db.query`, creating the objects along the way
+// Used by `Runtime::register_function` - called with the name of the function, the configured root path (or null
+// for the global object), and whether the function is async
+// Objects created here are marked as namespaces, so that a path can only be extended, never clobbered
+((name, root, isAsync) => {
+    const NAMESPACE = Symbol.for('rustyscript.namespace');
+    const FUNCTION = Symbol.for('rustyscript.function');
+
+    const segments = [...(root ? root.split('.') : []), ...name.split('.')];
+    const leaf = segments.pop();
+
+    let target = globalThis;
+    const path = [];
+    for (const segment of segments) {
+        path.push(segment);
+        const next = target[segment];
+        if (next === undefined) {
+            const namespace = {};
+            Object.defineProperty(namespace, NAMESPACE, { value: true });
+            Object.defineProperty(target, segment, {
+                value: namespace,
+                writable: true,
+                enumerable: target !== globalThis,
+                configurable: true,
+            });
+            target = namespace;
+        } else if (next?.[NAMESPACE]) {
+            target = next;
+        } else if (next?.[FUNCTION]) {
+            throw new Error(`Cannot register ${name}: ${path.join('.')} is already a registered function`);
+        } else {
+            throw new Error(`Cannot register ${name}: ${path.join('.')} already exists, and is not a namespace`);
+        }
+    }
+
+    path.push(leaf);
+    const existing = target[leaf];
+    if (existing?.[NAMESPACE]) {
+        throw new Error(`Cannot register ${name}: ${path.join('.')} is already a namespace`);
+    } else if (existing !== undefined && !existing?.[FUNCTION]) {
+        throw new Error(`Cannot register ${name}: ${path.join('.')} already exists`);
+    }
+
+    const call = isAsync
+        ? Deno.core.ops.call_registered_function_async
+        : Deno.core.ops.call_registered_function;
+    const f = (...args) => call(name, args);
+    Object.defineProperty(f, FUNCTION, { value: true });
+    Object.defineProperty(f, 'name', { value: leaf });
+    Object.defineProperty(target, leaf, {
+        value: f,
+        writable: true,
+        enumerable: target !== globalThis,
+        configurable: true,
+    });
+})
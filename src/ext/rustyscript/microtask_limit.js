@@ -0,0 +1,26 @@
+// Bounds the number of promise reactions that may run in a single turn of the event loop
+// Used by the `microtask_limit` runtime option - called with the limit
+// The count is reset by a zero-delay timer, which can only fire once the microtask queue has drained,
+// so a script that keeps the queue from ever draining uses up the budget, and is terminated
+((limit) => {
+    const core = Deno.core;
+    const { queueUserTimer } = core;
+    let count = 0;
+    let resetPending = false;
+    const reset = () => {
+        count = 0;
+        resetPending = false;
+    };
+
+    core.setPromiseHooks(null, () => {
+        if (!resetPending) {
+            resetPending = true;
+            core.unrefTimer(queueUserTimer(core.getTimerDepth() + 1, false, 0, reset));
+        }
+
+        if (++count > limit) {
+            reset();
+            core.ops.op_microtask_limit_exceeded();
+        }
+    }, null, null);
+})
@@ -1,13 +1,235 @@
 use super::ExtensionTrait;
-use crate::{error::Error, RsAsyncFunction, RsFunction};
-use deno_core::{anyhow::anyhow, extension, op2, serde_json, v8, Extension, OpState};
-use std::collections::HashMap;
+use crate::{
+    error::Error,
+    module_getter::{ModuleGetter, ModuleNamespaces},
+    module_loader::RustyLoader,
+    FakeClock, RsAsyncFunction, RsFunction, RsModuleFunction, RsStreamFunction, RsValueStream,
+};
+use deno_core::{
+    anyhow::anyhow, extension, futures::Stream, op2, serde_json, v8, Extension, OpState, Resource,
+    ResourceId,
+};
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    collections::{BTreeSet, HashMap, HashSet},
+    rc::Rc,
+    sync::Mutex,
+};
 
-type FnCache = HashMap<String, Box<dyn RsFunction>>;
+type FnCache = HashMap<String, Rc<dyn RsFunction>>;
 type AsyncFnCache = HashMap<String, Box<dyn RsAsyncFunction>>;
+type ModuleFnCache = HashMap<String, Rc<dyn RsModuleFunction>>;
+type StreamFnCache = HashMap<String, Rc<dyn RsStreamFunction>>;
+
+/// A stream returned by a registered stream function, pulled from as javascript iterates over it
+struct StreamResource(RefCell<RsValueStream>);
+impl Resource for StreamResource {
+    fn name(&self) -> Cow<str> {
+        "rustyscriptStream".into()
+    }
+}
+
+/// Tracks how deeply calls into registered functions are nested
+pub struct CallDepth {
+    current: usize,
+    max: Option<usize>,
+}
+impl CallDepth {
+    pub fn new(max: Option<usize>) -> Self {
+        Self { current: 0, max }
+    }
+}
+
+/// Counts a call into a registered function, for as long as it is alive
+struct CallDepthGuard(Rc<RefCell<OpState>>);
+impl CallDepthGuard {
+    fn enter(state: &Rc<RefCell<OpState>>) -> Result<Self, Error> {
+        if let Some(depth) = state.borrow_mut().try_borrow_mut::<CallDepth>() {
+            if let Some(max) = depth.max {
+                if depth.current >= max {
+                    return Err(Error::MaxCallDepthExceeded(max));
+                }
+            }
+            depth.current += 1;
+        }
+
+        Ok(Self(state.clone()))
+    }
+}
+impl Drop for CallDepthGuard {
+    fn drop(&mut self) {
+        if let Some(depth) = self.0.borrow_mut().try_borrow_mut::<CallDepth>() {
+            depth.current = depth.current.saturating_sub(1);
+        }
+    }
+}
+
+/// Names of the error classes registered with `Runtime::register_error_class`
+#[derive(Clone, Default)]
+pub struct ErrorClasses(Rc<RefCell<HashSet<String>>>);
+impl ErrorClasses {
+    pub fn insert(&self, name: &str) {
+        self.0.borrow_mut().insert(name.to_string());
+    }
+
+    /// Errors with a class that was never registered cannot be built in javascript,
+    /// so they are thrown as plain errors instead
+    fn resolve(classes: Option<&Self>, e: Error) -> Error {
+        match e {
+            Error::JsClass(class, message)
+                if !classes.is_some_and(|c| c.0.borrow().contains(&class)) =>
+            {
+                Error::Runtime(format!("{class}: {message}"))
+            }
+            e => e,
+        }
+    }
+
+    /// See [`ErrorClasses::resolve`]
+    fn resolve_in(state: &Rc<RefCell<OpState>>, e: Error) -> Error {
+        Self::resolve(state.borrow().try_borrow::<Self>(), e)
+    }
+}
+
+/// Returns the javascript class of an error returned by an op
+/// Only [`Error::JsClass`] errors have a class other than `Error`
+pub fn get_error_class_name(e: &deno_core::error::AnyError) -> &'static str {
+    match e.downcast_ref::<Error>() {
+        Some(Error::JsClass(class, _)) => intern_class_name(class),
+        _ => "Error",
+    }
+}
+
+/// Class names must be `'static` - each distinct name is leaked once, and reused after that
+fn intern_class_name(name: &str) -> &'static str {
+    static NAMES: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+    let mut names = NAMES
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    if let Some(name) = names.get(name) {
+        return name;
+    }
+
+    let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+    names.insert(name);
+    name
+}
 
 mod callbacks;
 
+/// Script run to freeze the intrinsics and global object, when `freeze_globals` is set
+pub const FREEZE_GLOBALS_JS: &str = include_str!("freeze_globals.js");
+
+/// Function replacing `Math.random` with a seeded generator, when `rng_seed` is set
+pub const SEEDED_RANDOM_JS: &str = include_str!("seeded_random.js");
+
+/// Function replacing `Deno.env` with a read-only view of the given variables, when `env_vars` is set
+pub const ENV_VARS_JS: &str = include_str!("env_vars.js");
+
+/// Function defining minimal `process`, `Buffer` and `global` globals, when `node_compat` is set
+pub const NODE_COMPAT_JS: &str = include_str!("node_compat.js");
+
+/// Script replacing `Date` and the timer functions with versions following a [`FakeClock`], when `fake_time` is set
+pub const FAKE_CLOCK_JS: &str = include_str!("fake_clock.js");
+
+/// Script counting the timers queued with the event loop against [`TimerLimit`], when `max_timers` is set
+pub const TIMER_LIMIT_JS: &str = include_str!("timer_limit.js");
+
+/// Function defining and registering a custom error class, for `Runtime::register_error_class`
+pub const ERROR_CLASS_JS: &str = include_str!("error_class.js");
+
+/// Function binding a registered function to a dotted path, for `Runtime::register_function`
+pub const FUNCTION_PATH_JS: &str = include_str!("function_path.js");
+
+/// Function bounding the number of promise reactions run per turn of the event loop, when `microtask_limit` is set
+pub const MICROTASK_LIMIT_JS: &str = include_str!("microtask_limit.js");
+
+/// Function defining a global `require` over the modules loaded into the runtime, when `require_shim` is set
+pub const REQUIRE_JS: &str = include_str!("require.js");
+
+/// Script recording `console.error` and `console.warn` output, when `fail_on_console_error` is set
+pub const CONSOLE_ERRORS_JS: &str = include_str!("console_errors.js");
+
+/// Messages written to `console.error` or `console.warn` during the current call
+///
+/// Shared between the op state and the runtime, which clears them as each call starts and reports them once it finishes
+#[derive(Clone, Default)]
+pub struct ConsoleErrorLog(Rc<RefCell<Vec<String>>>);
+impl ConsoleErrorLog {
+    /// Remove and return the recorded messages
+    pub fn take(&self) -> Vec<String> {
+        std::mem::take(&mut self.0.borrow_mut())
+    }
+}
+
+/// The budget of promise reactions per turn of the event loop, when `microtask_limit` is set
+///
+/// Shared between the op state and the runtime, which reports the overrun once the terminated call returns
+#[derive(Clone)]
+pub struct MicrotaskBudget {
+    limit: usize,
+    exceeded: Rc<Cell<bool>>,
+}
+impl MicrotaskBudget {
+    /// Create a new budget with the given limit
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            exceeded: Rc::default(),
+        }
+    }
+
+    /// The maximum number of promise reactions per turn
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Returns true if the budget was used up since the last check, clearing the flag
+    pub fn take_exceeded(&self) -> bool {
+        self.exceeded.take()
+    }
+
+    /// Returns the result of a call that just finished
+    ///
+    /// A call that used up the budget reports [`Error::MicrotaskLimitExceeded`] instead, and the termination
+    /// it caused is cleared so the runtime can be used again
+    pub fn settle<T>(
+        &self,
+        isolate: &v8::IsolateHandle,
+        result: Result<T, Error>,
+    ) -> Result<T, Error> {
+        if self.take_exceeded() {
+            isolate.cancel_terminate_execution();
+            return Err(Error::MicrotaskLimitExceeded(self.limit));
+        }
+        result
+    }
+}
+
+/// The number of timers that may be pending at once, when `max_timers` is set
+///
+/// Timers are tracked by id as they are queued, and released once they fire or are cleared.  
+/// Timers waiting on a [`FakeClock`] are tracked under their id negated, so they cannot clash with the event loop's
+pub struct TimerLimit {
+    limit: usize,
+    pending: HashSet<i64>,
+}
+impl TimerLimit {
+    /// Create a new limit, with no timers pending
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            pending: HashSet::new(),
+        }
+    }
+}
+
+/// Resolves the specifiers given to `require`, when `require_shim` is set
+///
+/// Specifiers are resolved the same way as the filenames of loaded modules - relative to the runtime's current directory
+pub struct RequireResolver(pub Rc<RustyLoader>);
+
 /// Registers a JS function with the runtime as being the entrypoint for the module
 ///
 /// # Arguments
@@ -22,15 +244,29 @@ fn op_register_entrypoint(state: &mut OpState, #[global] callback: v8::Global<v8
 #[serde]
 #[allow(clippy::needless_pass_by_value)]
 fn call_registered_function(
+    scope: &mut v8::HandleScope,
     #[string] name: &str,
     #[serde] args: Vec<serde_json::Value>,
-    state: &mut OpState,
+    state: Rc<RefCell<OpState>>,
 ) -> Result<serde_json::Value, Error> {
-    if state.has::<FnCache>() {
-        let table = state.borrow_mut::<FnCache>();
-        if let Some(callback) = table.get(name) {
-            return callback(&args);
-        }
+    let _guard = CallDepthGuard::enter(&state)?;
+
+    // Callbacks are cloned out, so that the state is not borrowed if they call back into JS
+    let callback = state
+        .borrow()
+        .try_borrow::<FnCache>()
+        .and_then(|table| table.get(name).cloned());
+    if let Some(callback) = callback {
+        return callback(&args).map_err(|e| ErrorClasses::resolve_in(&state, e));
+    }
+
+    let callback = state
+        .borrow()
+        .try_borrow::<ModuleFnCache>()
+        .and_then(|table| table.get(name).cloned());
+    if let Some(callback) = callback {
+        let mut getter = ModuleGetter::new(scope, &state);
+        return callback(&args, &mut getter).map_err(|e| ErrorClasses::resolve_in(&state, e));
     }
 
     Err(Error::ValueNotCallable(name.to_string()))
@@ -43,14 +279,202 @@ fn call_registered_function_async(
     #[serde] args: Vec<serde_json::Value>,
     state: &mut OpState,
 ) -> impl std::future::Future<Output = Result<serde_json::Value, Error>> {
-    if state.has::<AsyncFnCache>() {
-        let table = state.borrow_mut::<AsyncFnCache>();
-        if let Some(callback) = table.get(&name) {
-            return callback(args);
+    let classes = state.try_borrow::<ErrorClasses>().cloned();
+    let future = state
+        .try_borrow::<AsyncFnCache>()
+        .and_then(|table| table.get(&name))
+        .map(|callback| callback(args));
+
+    async move {
+        match future {
+            Some(future) => future
+                .await
+                .map_err(|e| ErrorClasses::resolve(classes.as_ref(), e)),
+            None => Err(Error::ValueNotCallable(name)),
         }
     }
+}
+
+/// Calls a registered stream function, returning the id of the resulting stream
+#[op2]
+#[smi]
+#[allow(clippy::needless_pass_by_value)]
+fn op_stream_open(
+    #[string] name: &str,
+    #[serde] args: Vec<serde_json::Value>,
+    state: Rc<RefCell<OpState>>,
+) -> Result<ResourceId, Error> {
+    let _guard = CallDepthGuard::enter(&state)?;
+
+    let callback = state
+        .borrow()
+        .try_borrow::<StreamFnCache>()
+        .and_then(|table| table.get(name).cloned());
+    let Some(callback) = callback else {
+        return Err(Error::ValueNotCallable(name.to_string()));
+    };
+
+    let stream = callback(&args).map_err(|e| ErrorClasses::resolve_in(&state, e))?;
+    Ok(state
+        .borrow_mut()
+        .resource_table
+        .add(StreamResource(RefCell::new(stream))))
+}
+
+/// Pulls the next value from a stream, as an iterator result
+/// The stream is dropped once it ends, or fails
+#[op2(async)]
+#[serde]
+async fn op_stream_next(
+    state: Rc<RefCell<OpState>>,
+    #[smi] rid: ResourceId,
+) -> Result<serde_json::Value, Error> {
+    let resource = state
+        .borrow()
+        .resource_table
+        .get::<StreamResource>(rid)
+        .map_err(|_| Error::Runtime("The stream has been closed".to_string()))?;
+
+    // Not borrowed across polls, so the stream can be closed while waiting
+    let next = std::future::poll_fn(|cx| resource.0.borrow_mut().as_mut().poll_next(cx)).await;
+    match next {
+        Some(Ok(value)) => Ok(serde_json::json!({ "done": false, "value": value })),
+        Some(Err(e)) => {
+            state.borrow_mut().resource_table.close(rid).ok();
+            Err(ErrorClasses::resolve_in(&state, e))
+        }
+        None => {
+            state.borrow_mut().resource_table.close(rid).ok();
+            Ok(serde_json::json!({ "done": true }))
+        }
+    }
+}
+
+/// Drops a stream before it has ended - called when javascript breaks out of the loop
+#[op2(fast)]
+fn op_stream_close(state: &mut OpState, #[smi] rid: ResourceId) {
+    state.resource_table.close(rid).ok();
+}
+
+/// Returns the time of the runtime's [`FakeClock`], in milliseconds since the unix epoch
+#[op2(fast)]
+fn op_fake_clock_now(state: &mut OpState) -> f64 {
+    state
+        .try_borrow::<FakeClock>()
+        .map_or(0.0, FakeClock::millis)
+}
+
+/// Registers a timer with the runtime's [`FakeClock`], with a deadline in milliseconds since the unix epoch
+/// Returns the key to wait on it with, or 0 if there is no clock
+#[op2(fast)]
+fn op_fake_clock_register(state: &mut OpState, deadline: f64) -> u32 {
+    state
+        .try_borrow::<FakeClock>()
+        .map_or(0, |clock| clock.register(deadline))
+}
 
-    Box::pin(std::future::ready(Err(Error::ValueNotCallable(name))))
+/// Resolves to true once the runtime's [`FakeClock`] reaches the deadline of a registered timer,
+/// or to false if the timer was cleared
+#[op2(async)]
+fn op_fake_clock_wait(state: &mut OpState, key: u32) -> impl std::future::Future<Output = bool> {
+    let wait = state.try_borrow::<FakeClock>().map(|clock| clock.wait(key));
+    async move {
+        match wait {
+            Some(wait) => wait.await,
+            None => true,
+        }
+    }
+}
+
+/// Removes a timer from the runtime's [`FakeClock`]
+#[op2(fast)]
+fn op_fake_clock_clear(state: &mut OpState, key: u32) {
+    if let Some(clock) = state.try_borrow::<FakeClock>() {
+        clock.clear(key);
+    }
+}
+
+/// Counts a newly queued timer against the runtime's [`TimerLimit`], if it has one
+/// Throws a `RangeError` once the limit is reached - the timer must then be cancelled
+#[op2(fast)]
+fn op_timer_limit_track(state: &mut OpState, #[number] id: i64) -> Result<(), Error> {
+    let Some(timers) = state.try_borrow_mut::<TimerLimit>() else {
+        return Ok(());
+    };
+
+    if timers.pending.len() >= timers.limit && !timers.pending.contains(&id) {
+        return Err(Error::js_class(
+            "RangeError",
+            format!("Timer limit of {} pending timers reached", timers.limit),
+        ));
+    }
+    timers.pending.insert(id);
+    Ok(())
+}
+
+/// Frees the slot of a timer that fired or was cleared
+#[op2(fast)]
+fn op_timer_limit_release(state: &mut OpState, #[number] id: i64) {
+    if let Some(timers) = state.try_borrow_mut::<TimerLimit>() {
+        timers.pending.remove(&id);
+    }
+}
+
+/// Records a message written to `console.error` or `console.warn`, along with the method that wrote it
+#[op2(fast)]
+fn op_record_console_error(
+    state: &mut OpState,
+    #[string] level: String,
+    #[string] message: String,
+) {
+    if let Some(log) = state.try_borrow::<ConsoleErrorLog>() {
+        log.0
+            .borrow_mut()
+            .push(format!("console.{level}: {message}"));
+    }
+}
+
+/// Terminates the running javascript once a turn of the event loop uses up its microtask budget
+#[op2]
+fn op_microtask_limit_exceeded(scope: &mut v8::HandleScope, state: &mut OpState) {
+    if let Some(budget) = state.try_borrow::<MicrotaskBudget>() {
+        budget.exceeded.set(true);
+        scope.terminate_execution();
+    }
+}
+
+/// Returns the namespace of an already-loaded module, for the `require` shim
+///
+/// The specifier can be the filename the module was loaded with, or a path or URL resolving to it  
+/// The `.js` and `.ts` extensions may be left out
+#[op2]
+fn op_require_module<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    state: &mut OpState,
+    #[string] specifier: String,
+) -> Result<v8::Local<'s, v8::Value>, deno_core::anyhow::Error> {
+    let namespace = state
+        .try_borrow::<ModuleNamespaces>()
+        .zip(state.try_borrow::<RequireResolver>())
+        .and_then(|(namespaces, resolver)| {
+            namespaces.get(&specifier).or_else(|| {
+                ["", ".js", ".ts"].iter().find_map(|ext| {
+                    let url = resolver
+                        .0
+                        .resolve_from_cwd(&format!("{specifier}{ext}"))
+                        .ok()?;
+                    namespaces.get_by_specifier(url.as_str())
+                })
+            })
+        })
+        .cloned();
+
+    match namespace {
+        Some(namespace) => Ok(v8::Local::new(scope, namespace).into()),
+        None => Err(anyhow!(
+            "Cannot require '{specifier}': only modules already loaded into the runtime can be required"
+        )),
+    }
 }
 
 #[op2(fast)]
@@ -60,7 +484,14 @@ fn op_panic2(#[string] msg: &str) -> Result<(), deno_core::anyhow::Error> {
 
 extension!(
     rustyscript,
-    ops = [op_register_entrypoint, call_registered_function, call_registered_function_async],
+    ops = [
+        op_register_entrypoint, call_registered_function, call_registered_function_async,
+        op_stream_open, op_stream_next, op_stream_close,
+        op_fake_clock_now, op_fake_clock_register, op_fake_clock_wait, op_fake_clock_clear,
+        op_timer_limit_track, op_timer_limit_release,
+        op_record_console_error, op_microtask_limit_exceeded,
+        op_require_module
+    ],
     esm_entry_point = "ext:rustyscript/rustyscript.js",
     esm = [ dir "src/ext/rustyscript", "rustyscript.js" ],
     middleware = |op| match op.name {
@@ -0,0 +1,139 @@
+// Minimal stand-ins for the node globals most scripts reach for - `global`, `process` and `Buffer`
+// Used by the `node_compat` runtime option - called with an object of the runtime's env_vars
+//
+// This is a compatibility layer, not node: only the members below exist, nothing reaches the host,
+// and globals that are already defined (such as by the node extension) are left alone
+((vars) => {
+    const define = (name, value) => {
+        if (name in globalThis) return;
+        Object.defineProperty(globalThis, name, {
+            value,
+            writable: true,
+            enumerable: false,
+            configurable: true,
+        });
+    };
+
+    define('global', globalThis);
+
+    define('process', {
+        env: { ...vars },
+        argv: [],
+        platform: 'rustyscript',
+        version: '',
+        versions: {},
+        cwd: () => '/',
+        exit: (code) => {
+            throw new Error(`process.exit(${code ?? 0}) is not supported`);
+        },
+        nextTick: (callback, ...args) => queueMicrotask(() => callback(...args)),
+    });
+
+    const normalize = (encoding) => {
+        const name = String(encoding ?? 'utf8').toLowerCase();
+        switch (name) {
+            case 'utf8': case 'utf-8': return 'utf8';
+            case 'latin1': case 'binary': case 'ascii': return 'latin1';
+            case 'hex': case 'base64': return name;
+            default: throw new TypeError(`Unknown encoding: ${encoding}`);
+        }
+    };
+
+    const toLatin1 = (bytes) => {
+        let s = '';
+        for (let i = 0; i < bytes.length; i += 0x8000) {
+            s += String.fromCharCode(...bytes.subarray(i, i + 0x8000));
+        }
+        return s;
+    };
+
+    class Buffer extends Uint8Array {
+        static from(value, encodingOrOffset, length) {
+            if (typeof value === 'string') {
+                return Buffer.#fromString(value, encodingOrOffset);
+            }
+
+            if (value instanceof ArrayBuffer) {
+                const offset = encodingOrOffset ?? 0;
+                return new Buffer(value, offset, length ?? value.byteLength - offset);
+            }
+
+            if (ArrayBuffer.isView(value)) {
+                const buffer = new Buffer(value.byteLength);
+                buffer.set(new Uint8Array(value.buffer, value.byteOffset, value.byteLength));
+                return buffer;
+            }
+
+            return new Buffer(Array.from(value));
+        }
+
+        static alloc(size, fill = 0) {
+            return new Buffer(size).fill(fill);
+        }
+
+        static isBuffer(value) {
+            return value instanceof Buffer;
+        }
+
+        static byteLength(value, encoding) {
+            return typeof value === 'string'
+                ? Buffer.#fromString(value, encoding).length
+                : value.byteLength;
+        }
+
+        static concat(list, totalLength) {
+            const length = totalLength ?? list.reduce((sum, b) => sum + b.length, 0);
+            const result = Buffer.alloc(length);
+            let offset = 0;
+            for (const b of list) {
+                if (offset >= length) break;
+                result.set(b.subarray(0, length - offset), offset);
+                offset += b.length;
+            }
+            return result;
+        }
+
+        static #fromString(value, encoding) {
+            switch (normalize(encoding)) {
+                case 'utf8': {
+                    const bytes = new TextEncoder().encode(value);
+                    return new Buffer(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+                }
+                case 'latin1':
+                    return new Buffer(Array.from(value, (c) => c.charCodeAt(0) & 0xff));
+                case 'base64':
+                    return Buffer.#fromString(atob(value), 'latin1');
+                case 'hex': {
+                    const bytes = [];
+                    for (let i = 0; i + 1 < value.length; i += 2) {
+                        const byte = parseInt(value.slice(i, i + 2), 16);
+                        if (Number.isNaN(byte)) break;
+                        bytes.push(byte);
+                    }
+                    return new Buffer(bytes);
+                }
+            }
+        }
+
+        toString(encoding, start = 0, end = this.length) {
+            const bytes = new Uint8Array(this.buffer, this.byteOffset, this.byteLength)
+                .subarray(start, end);
+            switch (normalize(encoding)) {
+                case 'utf8': return new TextDecoder().decode(bytes);
+                case 'latin1': return toLatin1(bytes);
+                case 'base64': return btoa(toLatin1(bytes));
+                case 'hex': return Array.from(bytes, (b) => b.toString(16).padStart(2, '0')).join('');
+            }
+        }
+
+        equals(other) {
+            return this.length === other.length && this.every((b, i) => b === other[i]);
+        }
+
+        toJSON() {
+            return { type: 'Buffer', data: Array.from(this) };
+        }
+    }
+
+    define('Buffer', Buffer);
+})
@@ -0,0 +1,17 @@
+// Synchronous `require` for CommonJS-style scripts - used by the `require_shim` runtime option
+//
+// Only modules already loaded into the runtime can be required: nothing is fetched or evaluated,
+// and the module's namespace object is returned - so a default export is found under `.default`
+(() => {
+    if ('require' in globalThis) return;
+
+    const { op_require_module } = Deno.core.ops;
+    const require = (specifier) => op_require_module(String(specifier));
+
+    Object.defineProperty(globalThis, 'require', {
+        value: require,
+        writable: true,
+        enumerable: false,
+        configurable: true,
+    });
+})
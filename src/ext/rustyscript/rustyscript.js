@@ -40,6 +40,23 @@ globalThis.rustyscript = {
         get: function(_target, name) {
             return (...args) => Deno.core.ops.call_registered_function_async(name, args);
         }
+    }),
+
+    // Values are pulled from rust one at a time, as the iterator is consumed
+    'stream_functions': new Proxy({}, {
+        get: function(_target, name) {
+            return (...args) => {
+                const rid = Deno.core.ops.op_stream_open(name, args);
+                return {
+                    [Symbol.asyncIterator]() { return this; },
+                    next: () => Deno.core.ops.op_stream_next(rid),
+                    return: (value) => {
+                        Deno.core.ops.op_stream_close(rid);
+                        return Promise.resolve({ done: true, value });
+                    }
+                };
+            };
+        }
     })
 };
 Object.freeze(globalThis.rustyscript);
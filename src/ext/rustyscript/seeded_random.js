@@ -0,0 +1,23 @@
+// Replaces Math.random with a deterministic xoshiro128** generator
+// Used by the `rng_seed` runtime option - called with the 4 words of initial state
+((a, b, c, d) => {
+    const next = () => {
+        const result = Math.imul(((Math.imul(b, 5) << 7) | (Math.imul(b, 5) >>> 25)), 9);
+        const t = b << 9;
+        c ^= a;
+        d ^= b;
+        b ^= c;
+        a ^= d;
+        c ^= t;
+        d = (d << 11) | (d >>> 21);
+        return result >>> 0;
+    };
+
+    Object.defineProperty(Math, 'random', {
+        value: function random() {
+            return next() / 4294967296;
+        },
+        writable: true,
+        configurable: true,
+    });
+})
@@ -0,0 +1,38 @@
+// Counts the timers queued with the event loop against the `max_timers` runtime option
+// The count is kept by the runtime - each queued timer is tracked by id, and fails to queue once the limit is reached
+// Both `Deno.core` and its raw ops are wrapped, so that scripts calling them directly are counted too
+(() => {
+    const core = Deno.core;
+    const { queueUserTimer, cancelTimer } = core;
+    const { op_timer_limit_track, op_timer_limit_release } = core.ops;
+    core.registerErrorClass('RangeError', RangeError);
+
+    // Scripts must not be able to free a slot without cancelling its timer
+    delete core.ops.op_timer_limit_track;
+    delete core.ops.op_timer_limit_release;
+
+    core.queueUserTimer = (depth, repeat, timeout, task) => {
+        // One-shot timers free their slot once they fire
+        const id = queueUserTimer(depth, repeat, timeout, repeat ? task : function (...args) {
+            op_timer_limit_release(id);
+            return Reflect.apply(task, this, args);
+        });
+
+        try {
+            op_timer_limit_track(id);
+        } catch (e) {
+            cancelTimer(id);
+            throw e;
+        }
+        return id;
+    };
+
+    core.cancelTimer = (id) => {
+        op_timer_limit_release(id);
+        return cancelTimer(id);
+    };
+
+    core.ops.op_timer_queue = (depth, repeat, timeout, task) =>
+        core.queueUserTimer(depth, repeat, timeout, task);
+    core.ops.op_timer_cancel = (id) => core.cancelTimer(id);
+})();
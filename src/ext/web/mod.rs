@@ -8,21 +8,24 @@ pub use options::WebOptions;
 mod permissions;
 pub(crate) use permissions::PermissionsContainer;
 pub use permissions::{
-    AllowlistWebPermissions, DefaultWebPermissions, PermissionDenied, SystemsPermissionKind,
-    WebPermissions,
+    AllowlistWebPermissions, DefaultWebPermissions, PermissionDenied, PermissionHandler,
+    PermissionRequest, PromptWebPermissions, SystemsPermissionKind, WebPermissions,
 };
 
+#[cfg(feature = "web")]
 extension!(
     init_fetch,
     deps = [rustyscript],
     esm_entry_point = "ext:init_fetch/init_fetch.js",
     esm = [ dir "src/ext/web", "init_fetch.js" ],
 );
+#[cfg(feature = "web")]
 impl ExtensionTrait<WebOptions> for init_fetch {
     fn init(options: WebOptions) -> Extension {
         init_fetch::init_ops_and_esm()
     }
 }
+#[cfg(feature = "web")]
 impl ExtensionTrait<WebOptions> for deno_fetch::deno_fetch {
     fn init(options: WebOptions) -> Extension {
         let options = deno_fetch::Options {
@@ -61,18 +64,21 @@ impl ExtensionTrait<WebOptions> for deno_net::deno_net {
     }
 }
 
+#[cfg(feature = "web")]
 extension!(
     init_telemetry,
     deps = [rustyscript],
     esm_entry_point = "ext:init_telemetry/init_telemetry.js",
     esm = [ dir "src/ext/web", "init_telemetry.js" ],
 );
+#[cfg(feature = "web")]
 impl ExtensionTrait<()> for init_telemetry {
     fn init((): ()) -> Extension {
         init_telemetry::init_ops_and_esm()
     }
 }
 
+#[cfg(feature = "web")]
 impl ExtensionTrait<()> for deno_telemetry::deno_telemetry {
     fn init((): ()) -> Extension {
         deno_telemetry::deno_telemetry::init_ops_and_esm()
@@ -91,7 +97,11 @@ extension!(
 );
 impl ExtensionTrait<WebOptions> for init_web {
     fn init(options: WebOptions) -> Extension {
-        init_web::init_ops_and_esm(options.permissions)
+        let permissions = match options.permission_handler {
+            Some(handler) => Arc::new(PromptWebPermissions::new(options.permissions, handler)),
+            None => options.permissions,
+        };
+        init_web::init_ops_and_esm(permissions)
     }
 }
 
@@ -110,16 +120,23 @@ impl ExtensionTrait<()> for deno_tls::deno_tls {
     }
 }
 
+/// Fetch and telemetry are only included with the `web` feature - `net` provides sockets alone
 pub fn extensions(options: WebOptions, is_snapshot: bool) -> Vec<Extension> {
-    vec![
+    let mut extensions = vec![
         deno_web::deno_web::build(options.clone(), is_snapshot),
-        deno_telemetry::deno_telemetry::build((), is_snapshot),
         deno_net::deno_net::build(options.clone(), is_snapshot),
-        deno_fetch::deno_fetch::build(options.clone(), is_snapshot),
         deno_tls::deno_tls::build((), is_snapshot),
         init_web::build(options.clone(), is_snapshot),
-        init_telemetry::build((), is_snapshot),
         init_net::build(options.clone(), is_snapshot),
+    ];
+
+    #[cfg(feature = "web")]
+    extensions.extend([
+        deno_telemetry::deno_telemetry::build((), is_snapshot),
+        deno_fetch::deno_fetch::build(options.clone(), is_snapshot),
+        init_telemetry::build((), is_snapshot),
         init_fetch::build(options, is_snapshot),
-    ]
+    ]);
+
+    extensions
 }
@@ -1,6 +1,9 @@
 use super::{DefaultWebPermissions, WebPermissions};
+#[cfg(feature = "web")]
 use deno_core::error::AnyError;
+#[cfg(feature = "web")]
 use deno_fetch::dns::Resolver;
+#[cfg(feature = "web")]
 use hyper_util::client::legacy::Builder;
 use std::sync::Arc;
 
@@ -20,6 +23,7 @@ pub struct WebOptions {
     pub proxy: Option<deno_tls::Proxy>,
 
     /// Request builder hook for fetch
+    #[cfg(feature = "web")]
     #[allow(clippy::type_complexity)]
     pub request_builder_hook:
         Option<fn(&mut http::Request<deno_fetch::ReqBody>) -> Result<(), AnyError>>,
@@ -33,23 +37,33 @@ pub struct WebOptions {
     pub client_cert_chain_and_key: deno_tls::TlsKeys,
 
     /// File fetch handler for fetch
+    #[cfg(feature = "web")]
     pub file_fetch_handler: std::rc::Rc<dyn deno_fetch::FetchHandler>,
 
     /// Permissions manager for sandbox-breaking extensions
     pub permissions: Arc<dyn WebPermissions>,
 
+    /// Callback asked the first time a script accesses each net, fs, env or sys resource
+    ///
+    /// Decisions are cached per resource, and allowed accesses are still checked against `permissions`
+    /// See [`super::PromptWebPermissions`]
+    pub permission_handler: Option<super::PermissionHandler>,
+
     /// Blob store for the web related extensions
     pub blob_store: Arc<deno_web::BlobStore>,
 
     ///A callback to customize HTTP client configuration.
     ///
     /// For more info on what can be configured, see [`hyper_util::client::legacy::Builder`]
+    #[cfg(feature = "web")]
     pub client_builder_hook: Option<fn(Builder) -> Builder>,
 
     /// Resolver for DNS resolution
+    #[cfg(feature = "web")]
     pub resolver: Resolver,
 
     /// OpenTelemetry configuration for the `deno_telemetry` extension
+    #[cfg(feature = "web")]
     pub telemetry_config: deno_telemetry::OtelConfig,
 }
 
@@ -60,14 +74,20 @@ impl Default for WebOptions {
             user_agent: String::new(),
             root_cert_store_provider: None,
             proxy: None,
+            #[cfg(feature = "web")]
             request_builder_hook: None,
             unsafely_ignore_certificate_errors: None,
             client_cert_chain_and_key: deno_tls::TlsKeys::Null,
+            #[cfg(feature = "web")]
             file_fetch_handler: std::rc::Rc::new(deno_fetch::DefaultFileFetchHandler),
             permissions: Arc::new(DefaultWebPermissions),
+            permission_handler: None,
             blob_store: Arc::new(deno_web::BlobStore::default()),
+            #[cfg(feature = "web")]
             client_builder_hook: None,
+            #[cfg(feature = "web")]
             resolver: Resolver::default(),
+            #[cfg(feature = "web")]
             telemetry_config: deno_telemetry::OtelConfig::default(),
         }
     }
@@ -1,7 +1,7 @@
 use deno_permissions::{PermissionCheckError, PermissionDeniedError};
 use std::{
     borrow::Cow,
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
     sync::{Arc, RwLock},
 };
@@ -433,6 +433,226 @@ impl WebPermissions for AllowlistWebPermissions {
     }
 }
 
+/// A resource access that a [`PermissionHandler`] is asked to allow or deny
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PermissionRequest {
+    /// A URL used by fetch or websocket
+    Url(String),
+
+    /// A host (and optional port) connected to by net
+    Host(String, Option<u16>),
+
+    /// A path read by fs, fetch or net
+    Read(PathBuf),
+
+    /// A path written to by fs or net
+    Write(PathBuf),
+
+    /// Read access to every path
+    ReadAll,
+
+    /// Write access to every path
+    WriteAll,
+
+    /// An environment variable
+    Env(String),
+
+    /// A system information call
+    Sys(SystemsPermissionKind),
+
+    /// FFI execution
+    Exec,
+}
+
+/// Callback deciding whether a [`PermissionRequest`] is allowed
+///
+/// Return `true` to allow the access, or `false` to deny it
+pub type PermissionHandler = Arc<dyn Fn(PermissionRequest) -> bool + Send + Sync>;
+
+/// Permissions manager that asks a [`PermissionHandler`] the first time each resource is accessed
+///
+/// Decisions are cached per resource, so the handler is only called once for each request
+/// Accesses the handler allows are then checked against the wrapped permissions as usual
+///
+/// Used automatically when [`crate::WebOptions::permission_handler`] is set
+#[derive(Clone)]
+pub struct PromptWebPermissions {
+    inner: Arc<dyn WebPermissions>,
+    handler: PermissionHandler,
+    decisions: Arc<RwLock<HashMap<PermissionRequest, bool>>>,
+}
+impl PromptWebPermissions {
+    /// Create a new instance, consulting `handler` before deferring to `inner`
+    #[must_use]
+    pub fn new(inner: Arc<dyn WebPermissions>, handler: PermissionHandler) -> Self {
+        Self {
+            inner,
+            handler,
+            decisions: Arc::default(),
+        }
+    }
+
+    /// Forget all cached decisions, so that the handler is asked again
+    pub fn clear(&self) {
+        self.decisions
+            .write()
+            .expect("Could not lock permissions")
+            .clear();
+    }
+
+    fn ask(&self, request: PermissionRequest) -> Result<(), PermissionDenied> {
+        let cached = self
+            .decisions
+            .read()
+            .expect("Could not lock permissions")
+            .get(&request)
+            .copied();
+
+        // The lock is not held while the handler runs, since it may block on the host
+        let allowed = match cached {
+            Some(allowed) => allowed,
+            None => {
+                let allowed = (self.handler)(request.clone());
+                self.decisions
+                    .write()
+                    .expect("Could not lock permissions")
+                    .insert(request.clone(), allowed);
+                allowed
+            }
+        };
+
+        if allowed {
+            Ok(())
+        } else {
+            PermissionDenied::oops(format!("{request:?}"))
+        }
+    }
+}
+
+impl std::fmt::Debug for PromptWebPermissions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PromptWebPermissions")
+            .field("inner", &self.inner)
+            .field("decisions", &self.decisions)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WebPermissions for PromptWebPermissions {
+    fn allow_hrtime(&self) -> bool {
+        self.inner.allow_hrtime()
+    }
+
+    fn check_url(&self, url: &deno_core::url::Url, api_name: &str) -> Result<(), PermissionDenied> {
+        self.ask(PermissionRequest::Url(url.to_string()))?;
+        self.inner.check_url(url, api_name)
+    }
+
+    fn check_open<'a>(
+        &self,
+        resolved: bool,
+        read: bool,
+        write: bool,
+        path: &'a Path,
+        api_name: &str,
+    ) -> Option<std::borrow::Cow<'a, Path>> {
+        if read {
+            self.ask(PermissionRequest::Read(path.to_path_buf())).ok()?;
+        }
+        if write {
+            self.ask(PermissionRequest::Write(path.to_path_buf()))
+                .ok()?;
+        }
+        self.inner.check_open(resolved, read, write, path, api_name)
+    }
+
+    fn check_read<'a>(
+        &self,
+        p: &'a Path,
+        api_name: Option<&str>,
+    ) -> Result<Cow<'a, Path>, PermissionDenied> {
+        self.ask(PermissionRequest::Read(p.to_path_buf()))?;
+        self.inner.check_read(p, api_name)
+    }
+
+    fn check_read_all(&self, api_name: Option<&str>) -> Result<(), PermissionDenied> {
+        self.ask(PermissionRequest::ReadAll)?;
+        self.inner.check_read_all(api_name)
+    }
+
+    fn check_read_blind(
+        &self,
+        p: &Path,
+        display: &str,
+        api_name: &str,
+    ) -> Result<(), PermissionDenied> {
+        self.ask(PermissionRequest::Read(p.to_path_buf()))?;
+        self.inner.check_read_blind(p, display, api_name)
+    }
+
+    fn check_write<'a>(
+        &self,
+        p: &'a Path,
+        api_name: Option<&str>,
+    ) -> Result<Cow<'a, Path>, PermissionDenied> {
+        self.ask(PermissionRequest::Write(p.to_path_buf()))?;
+        self.inner.check_write(p, api_name)
+    }
+
+    fn check_write_all(&self, api_name: &str) -> Result<(), PermissionDenied> {
+        self.ask(PermissionRequest::WriteAll)?;
+        self.inner.check_write_all(api_name)
+    }
+
+    fn check_write_blind(
+        &self,
+        p: &Path,
+        display: &str,
+        api_name: &str,
+    ) -> Result<(), PermissionDenied> {
+        self.ask(PermissionRequest::Write(p.to_path_buf()))?;
+        self.inner.check_write_blind(p, display, api_name)
+    }
+
+    fn check_write_partial(
+        &self,
+        path: &str,
+        api_name: &str,
+    ) -> Result<std::path::PathBuf, PermissionDenied> {
+        self.ask(PermissionRequest::Write(PathBuf::from(path)))?;
+        self.inner.check_write_partial(path, api_name)
+    }
+
+    fn check_host(
+        &self,
+        host: &str,
+        port: Option<u16>,
+        api_name: &str,
+    ) -> Result<(), PermissionDenied> {
+        self.ask(PermissionRequest::Host(host.to_string(), port))?;
+        self.inner.check_host(host, port, api_name)
+    }
+
+    fn check_sys(
+        &self,
+        kind: SystemsPermissionKind,
+        api_name: &str,
+    ) -> Result<(), PermissionDenied> {
+        self.ask(PermissionRequest::Sys(kind.clone()))?;
+        self.inner.check_sys(kind, api_name)
+    }
+
+    fn check_env(&self, var: &str) -> Result<(), PermissionDenied> {
+        self.ask(PermissionRequest::Env(var.to_string()))?;
+        self.inner.check_env(var)
+    }
+
+    fn check_exec(&self) -> Result<(), PermissionDenied> {
+        self.ask(PermissionRequest::Exec)?;
+        self.inner.check_exec()
+    }
+}
+
 /// Trait managing the permissions for the web related extensions
 ///
 /// See [`DefaultWebPermissions`] for a default implementation that allows-all
@@ -628,6 +848,7 @@ impl deno_web::TimersPermission for PermissionsContainer {
         self.0.allow_hrtime()
     }
 }
+#[cfg(feature = "web")]
 impl deno_fetch::FetchPermissions for PermissionsContainer {
     fn check_net_url(
         &mut self,
@@ -0,0 +1,26 @@
+// Copyright 2018-2024 the Deno authors. All rights reserved. MIT license.
+import * as webidl from "ext:deno_webidl/00_webidl.js";
+import { DOMException } from "ext:deno_web/01_dom_exception.js";
+import { core } from "ext:core/mod.js";
+
+/**
+ * Deep-copies a value using v8's serializer
+ * Supports the same types as `postMessage` - Map, Set, Date, RegExp, typed arrays, and so on
+ * Transfer lists are not supported, since there are no workers to transfer to
+ * 
+ * @param {any} value
+ * @returns {any}
+ */
+function structuredClone(value) {
+    const prefix = "Failed to execute 'structuredClone'";
+    webidl.requiredArguments(arguments.length, 1, prefix);
+    try {
+        return core.deserialize(core.serialize(value));
+    } catch (e) {
+        throw new DOMException(e.message, "DataCloneError");
+    }
+}
+
+export {
+    structuredClone
+}
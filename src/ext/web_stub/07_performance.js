@@ -0,0 +1,208 @@
+// A minimal, sandbox-safe implementation of the `performance` API, for use without the `web` feature
+// Provides `now`, `timeOrigin`, marks and measures, backed by a monotonic clock
+// Observers, resource timing and event timing are not implemented
+
+import { DOMException } from "ext:deno_web/01_dom_exception.js";
+import { op_performance_now } from "ext:core/ops";
+
+const entries = [];
+
+class PerformanceEntry {
+  #name;
+  #entryType;
+  #startTime;
+  #duration;
+
+  constructor(name, entryType, startTime, duration) {
+    this.#name = name;
+    this.#entryType = entryType;
+    this.#startTime = startTime;
+    this.#duration = duration;
+  }
+
+  get name() {
+    return this.#name;
+  }
+
+  get entryType() {
+    return this.#entryType;
+  }
+
+  get startTime() {
+    return this.#startTime;
+  }
+
+  get duration() {
+    return this.#duration;
+  }
+
+  toJSON() {
+    return {
+      name: this.name,
+      entryType: this.entryType,
+      startTime: this.startTime,
+      duration: this.duration,
+    };
+  }
+}
+
+class PerformanceMark extends PerformanceEntry {
+  #detail;
+
+  constructor(name, options = {}) {
+    const startTime = options?.startTime ?? performance.now();
+    if (startTime < 0) {
+      throw new TypeError("startTime cannot be negative");
+    }
+
+    super(String(name), "mark", startTime, 0);
+    this.#detail = options?.detail ?? null;
+  }
+
+  get detail() {
+    return this.#detail;
+  }
+
+  toJSON() {
+    return { ...super.toJSON(), detail: this.detail };
+  }
+}
+
+class PerformanceMeasure extends PerformanceEntry {
+  #detail;
+
+  constructor(name, startTime, duration, detail) {
+    super(name, "measure", startTime, duration);
+    this.#detail = detail;
+  }
+
+  get detail() {
+    return this.#detail;
+  }
+
+  toJSON() {
+    return { ...super.toJSON(), detail: this.detail };
+  }
+}
+
+/**
+ * Finds the time of the latest mark with the given name - numbers are used as-is
+ */
+function markTime(mark) {
+  if (typeof mark === "number") {
+    return mark;
+  }
+
+  const name = String(mark);
+  const entry = entries.findLast((e) =>
+    e.entryType === "mark" && e.name === name
+  );
+  if (!entry) {
+    throw new DOMException(`The mark '${name}' does not exist.`, "SyntaxError");
+  }
+  return entry.startTime;
+}
+
+function removeEntries(entryType, name) {
+  for (let i = entries.length - 1; i >= 0; i--) {
+    const entry = entries[i];
+    if (entry.entryType === entryType && (name === undefined || entry.name === name)) {
+      entries.splice(i, 1);
+    }
+  }
+}
+
+class Performance {
+  #timeOrigin;
+
+  /**
+   * Milliseconds since the runtime started, with sub-millisecond precision
+   */
+  now() {
+    return op_performance_now();
+  }
+
+  get timeOrigin() {
+    this.#timeOrigin ??= Date.now() - this.now();
+    return this.#timeOrigin;
+  }
+
+  mark(name, options) {
+    const mark = new PerformanceMark(name, options);
+    entries.push(mark);
+    return mark;
+  }
+
+  measure(name, startOrOptions, endMark) {
+    let start, end, duration, detail;
+    if (typeof startOrOptions === "object" && startOrOptions !== null) {
+      ({ start, end, duration, detail } = startOrOptions);
+    } else {
+      start = startOrOptions;
+      end = endMark;
+    }
+
+    let endTime;
+    if (end !== undefined) {
+      endTime = markTime(end);
+    } else if (start !== undefined && duration !== undefined) {
+      endTime = markTime(start) + duration;
+    } else {
+      endTime = this.now();
+    }
+
+    let startTime;
+    if (start !== undefined) {
+      startTime = markTime(start);
+    } else if (duration !== undefined && end !== undefined) {
+      startTime = endTime - duration;
+    } else {
+      startTime = 0;
+    }
+
+    const measure = new PerformanceMeasure(
+      String(name),
+      startTime,
+      endTime - startTime,
+      detail ?? null,
+    );
+    entries.push(measure);
+    return measure;
+  }
+
+  getEntries() {
+    return entries.slice();
+  }
+
+  getEntriesByName(name, type) {
+    return entries.filter((e) =>
+      e.name === String(name) && (type === undefined || e.entryType === type)
+    );
+  }
+
+  getEntriesByType(type) {
+    return entries.filter((e) => e.entryType === type);
+  }
+
+  clearMarks(name) {
+    removeEntries("mark", name === undefined ? undefined : String(name));
+  }
+
+  clearMeasures(name) {
+    removeEntries("measure", name === undefined ? undefined : String(name));
+  }
+
+  toJSON() {
+    return { timeOrigin: this.timeOrigin };
+  }
+}
+
+const performance = new Performance();
+
+export {
+  Performance,
+  performance,
+  PerformanceEntry,
+  PerformanceMark,
+  PerformanceMeasure,
+};
@@ -1,6 +1,8 @@
 import * as DOMException from 'ext:deno_web/01_dom_exception.js';
 import * as timers from 'ext:deno_web/02_timers.js';
 import * as base64 from 'ext:deno_web/05_base64.js';
+import * as structuredClone from 'ext:deno_web/06_structured_clone.js';
+import * as performance from 'ext:deno_web/07_performance.js';
 
 import { applyToGlobal, nonEnumerable, writeable } from 'ext:rustyscript/rustyscript.js';
 applyToGlobal({
@@ -16,5 +18,13 @@ applyToGlobal({
 
     atob: writeable(base64.atob),
     btoa: writeable(base64.btoa),
+
+    structuredClone: writeable(structuredClone.structuredClone),
+
+    Performance: nonEnumerable(performance.Performance),
+    PerformanceEntry: nonEnumerable(performance.PerformanceEntry),
+    PerformanceMark: nonEnumerable(performance.PerformanceMark),
+    PerformanceMeasure: nonEnumerable(performance.PerformanceMeasure),
+    performance: writeable(performance.performance),
 });
 
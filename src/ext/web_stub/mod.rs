@@ -11,11 +11,12 @@ mod timers;
 extension!(
     deno_web,
     ops = [
-        timers::op_now, timers::op_defer,
+        timers::op_now, timers::op_defer, timers::op_performance_now,
         encoding::op_base64_decode, encoding::op_base64_atob, encoding::op_base64_encode, encoding::op_base64_btoa,
     ],
     esm_entry_point = "ext:deno_web/init_stub.js",
-    esm = [ dir "src/ext/web_stub", "init_stub.js", "01_dom_exception.js", "02_timers.js", "05_base64.js" ],
+    esm = [ dir "src/ext/web_stub", "init_stub.js", "01_dom_exception.js", "02_timers.js", "05_base64.js", "06_structured_clone.js", "07_performance.js" ],
+    state = |state| state.put(timers::StartTime::now()),
 );
 impl ExtensionTrait<()> for deno_web {
     fn init((): ()) -> Extension {
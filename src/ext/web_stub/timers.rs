@@ -30,6 +30,14 @@ pub fn op_now(state: &mut OpState, #[buffer] buf: &mut [u8]) {
     buf[1] = subsec_nanos;
 }
 
+/// Returns the milliseconds since the start time of the runtime, for `performance.now`
+/// Unlike `op_now`, the result keeps sub-millisecond precision, rounded down to the microsecond
+#[op2(fast)]
+pub fn op_performance_now(state: &mut OpState) -> f64 {
+    let elapsed = state.borrow::<StartTime>().elapsed();
+    (elapsed.as_secs_f64() * 1_000_000.0).floor() / 1000.0
+}
+
 #[allow(clippy::unused_async)]
 #[op2(async(lazy), fast)]
 pub async fn op_defer() {}
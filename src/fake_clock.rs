@@ -0,0 +1,202 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// A timer waiting on the clock
+struct Waiter {
+    /// Deadline, in milliseconds since the unix epoch
+    deadline: f64,
+    waker: Option<Waker>,
+}
+
+struct ClockState {
+    now: SystemTime,
+
+    /// Timers waiting on the clock, by key - removed once they fire, are cleared, or are dropped
+    waiters: HashMap<u32, Waiter>,
+    next_key: u32,
+}
+
+/// A manually controlled clock, replacing the real time seen by javascript - see [`crate::RuntimeOptions::fake_time`]
+///
+/// `Date`, `performance.now`, `setTimeout` and `setInterval` all follow this clock instead of real time.
+/// Timers fire once the clock is advanced past their deadline, the next time the event loop runs.
+///
+/// The clock can be cloned, and shared between threads - all clones control the same time
+///
+/// ```rust
+/// use rustyscript::{ FakeClock, Runtime, RuntimeOptions, Undefined };
+/// use std::time::{ Duration, UNIX_EPOCH };
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let clock = FakeClock::new(UNIX_EPOCH + Duration::from_secs(60));
+/// let mut runtime = Runtime::new(RuntimeOptions {
+///     fake_time: Some(clock.clone()),
+///     ..Default::default()
+/// })?;
+///
+/// let now: f64 = runtime.eval("Date.now()")?;
+/// assert_eq!(now, 60_000.0);
+///
+/// runtime.eval::<Undefined>("setTimeout(() => globalThis.done = true, 5000)")?;
+/// clock.advance(Duration::from_secs(5));
+/// runtime.block_on_event_loop(Default::default(), None)?;
+///
+/// let done: bool = runtime.eval("globalThis.done")?;
+/// assert!(done);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct FakeClock(Arc<Mutex<ClockState>>);
+
+impl FakeClock {
+    /// Create a new clock, starting at the given time
+    #[must_use]
+    pub fn new(now: SystemTime) -> Self {
+        Self(Arc::new(Mutex::new(ClockState {
+            now,
+            waiters: HashMap::new(),
+            next_key: 1,
+        })))
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<ClockState> {
+        self.0.lock().expect("Could not lock the clock")
+    }
+
+    /// Returns the current time of the clock
+    #[must_use]
+    pub fn now(&self) -> SystemTime {
+        self.state().now
+    }
+
+    /// Set the current time of the clock
+    ///
+    /// Timers whose deadline has passed will fire the next time the event loop runs
+    pub fn set(&self, now: SystemTime) {
+        let wakers: Vec<_> = {
+            let mut state = self.state();
+            state.now = now;
+            let now = to_millis(now);
+            state
+                .waiters
+                .values_mut()
+                .filter(|waiter| waiter.deadline <= now)
+                .filter_map(|waiter| waiter.waker.take())
+                .collect()
+        };
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Move the clock forward by the given duration
+    ///
+    /// Timers whose deadline has passed will fire the next time the event loop runs
+    pub fn advance(&self, duration: Duration) {
+        self.set(self.now() + duration);
+    }
+
+    /// Returns the current time, in milliseconds since the unix epoch
+    pub(crate) fn millis(&self) -> f64 {
+        to_millis(self.now())
+    }
+
+    /// Registers a timer with a deadline, in milliseconds since the unix epoch - returns its key
+    ///
+    /// The timer is waited on with [`FakeClock::wait`], and can be cancelled with [`FakeClock::clear`]
+    pub(crate) fn register(&self, deadline: f64) -> u32 {
+        let mut state = self.state();
+        let key = state.next_key;
+        state.next_key = state.next_key.wrapping_add(1).max(1);
+        state.waiters.insert(
+            key,
+            Waiter {
+                deadline,
+                waker: None,
+            },
+        );
+        key
+    }
+
+    /// Resolves to true once the clock reaches the deadline of a registered timer,
+    /// or to false if the timer was cleared
+    pub(crate) fn wait(&self, key: u32) -> WaitUntil {
+        WaitUntil {
+            clock: self.clone(),
+            key,
+        }
+    }
+
+    /// Cancels a registered timer, removing it from the clock
+    pub(crate) fn clear(&self, key: u32) {
+        let waiter = self.state().waiters.remove(&key);
+        if let Some(waker) = waiter.and_then(|waiter| waiter.waker) {
+            waker.wake();
+        }
+    }
+
+    /// Returns the number of timers waiting on the clock
+    #[cfg(test)]
+    pub(crate) fn pending(&self) -> usize {
+        self.state().waiters.len()
+    }
+}
+
+impl Default for FakeClock {
+    /// A clock starting at the unix epoch
+    fn default() -> Self {
+        Self::new(UNIX_EPOCH)
+    }
+}
+
+impl std::fmt::Debug for FakeClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("FakeClock").field(&self.now()).finish()
+    }
+}
+
+fn to_millis(time: SystemTime) -> f64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs_f64() * 1000.0,
+        Err(e) => -e.duration().as_secs_f64() * 1000.0,
+    }
+}
+
+/// Future returned by [`FakeClock::wait`] - the timer is removed from the clock if it is dropped early
+pub(crate) struct WaitUntil {
+    clock: FakeClock,
+    key: u32,
+}
+
+impl Future for WaitUntil {
+    type Output = bool;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<bool> {
+        let mut state = self.clock.state();
+        let now = to_millis(state.now);
+        let Some(waiter) = state.waiters.get_mut(&self.key) else {
+            return Poll::Ready(false);
+        };
+        if now < waiter.deadline {
+            waiter.waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        state.waiters.remove(&self.key);
+        Poll::Ready(true)
+    }
+}
+
+impl Drop for WaitUntil {
+    fn drop(&mut self) {
+        self.clock.state().waiters.remove(&self.key);
+    }
+}
@@ -1,25 +1,67 @@
 use crate::{
+    call_slot::{ArgBuffer, CallSlot},
     ext,
+    module_getter::ModuleNamespaces,
     module_loader::{LoaderOptions, RustyLoader},
     traits::{ToDefinedValue, ToModuleSpecifier, ToV8String},
-    transpiler::transpile,
-    utilities, Error, ExtensionOptions, Module, ModuleHandle,
+    transpiler::{transpile_if, TranspileOptions},
+    utilities, CancellationHandle, Error, ExtensionOptions, Module, ModuleHandle, RsModuleFunction,
 };
 use deno_core::{
     futures::FutureExt, serde_json, serde_v8::from_v8, v8, FeatureChecker, JsRuntime,
-    JsRuntimeForSnapshot, PollEventLoopOptions,
+    JsRuntimeForSnapshot, ModuleSpecifier, PollEventLoopOptions,
 };
 use serde::de::DeserializeOwned;
 use std::{
+    cell::RefCell,
     collections::{HashMap, HashSet},
     path::{Path, PathBuf},
     pin::Pin,
     rc::Rc,
-    task::Poll,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    task::{Context, Poll, Wake, Waker},
     time::Duration,
 };
 use tokio_util::sync::CancellationToken;
 
+/// Applies [`RuntimeOptions::v8_flags`]
+///
+/// v8 flags are process-global, and only take effect before v8 is initialized by the first runtime.
+/// The first runtime's flags are kept, and any later runtime asking for different flags is refused
+fn apply_v8_flags(flags: &[String]) -> Result<(), Error> {
+    static APPLIED: Mutex<Option<Vec<String>>> = Mutex::new(None);
+    let mut applied = APPLIED.lock().unwrap_or_else(PoisonError::into_inner);
+    match applied.as_deref() {
+        None => {
+            if !flags.is_empty() {
+                // The first argument is the program name, and is never parsed as a flag
+                let args = std::iter::once(String::new()).chain(flags.iter().cloned());
+                let unrecognized = deno_core::v8_set_flags(args.collect());
+                if unrecognized.len() > 1 {
+                    return Err(Error::Runtime(format!(
+                        "Unrecognized v8 flags: {}",
+                        unrecognized[1..].join(" ")
+                    )));
+                }
+            }
+            *applied = Some(flags.to_vec());
+        }
+
+        Some(current) if flags.is_empty() || flags == current => {}
+        Some(current) => {
+            return Err(Error::Runtime(format!(
+                "v8 flags are process-global, and were already set to [{}] when the first runtime was created",
+                current.join(" ")
+            )));
+        }
+    }
+
+    Ok(())
+}
+
 /// Wrapper trait to make the `InnerRuntime` generic over the runtime types
 pub trait RuntimeTrait {
     fn try_new(options: deno_core::RuntimeOptions) -> Result<Self, Error>
@@ -78,37 +120,405 @@ impl<F> RsAsyncFunction for F where
 {
 }
 
+/// A callback invoked when a call exceeds [`RuntimeOptions::timeout`] - see [`RuntimeOptions::on_timeout`]
+pub type TimeoutHook = std::sync::Arc<dyn Fn(&RuntimeInfo)>;
+
+/// A callback invoked periodically while a call executes javascript - see [`RuntimeOptions::progress_callback`]
+///
+/// Receives the time elapsed since the call began, and decides if the call may continue
+pub type ProgressCallback = std::sync::Arc<dyn Fn(Duration) -> ProgressSignal + Send + Sync>;
+
+/// Returned by a [`ProgressCallback`] to let the running call continue, or to abort it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressSignal {
+    /// Let the call keep running
+    Continue,
+
+    /// Terminate the running javascript - the call fails with [`Error::Cancelled`]
+    Abort,
+}
+
+/// A stream of values produced by rust, pulled from by javascript one value at a time - see [`RsStreamFunction`]
+pub type RsValueStream =
+    Pin<Box<dyn deno_core::futures::Stream<Item = Result<serde_json::Value, Error>>>>;
+
+/// Represents a rust function returning a stream, exposed to javascript as an async iterable
+pub trait RsStreamFunction:
+    Fn(&[serde_json::Value]) -> Result<RsValueStream, Error> + 'static
+{
+}
+impl<F> RsStreamFunction for F where
+    F: Fn(&[serde_json::Value]) -> Result<RsValueStream, Error> + 'static
+{
+}
+
 /// Decodes a set of arguments into a vector of v8 values
 /// This is used to pass arguments to a javascript function
 /// And is faster and more flexible than using `json_args!`
+///
+/// The values are appended to `result`, so that callers can reuse its allocation
 fn decode_args<'a>(
     args: &impl serde::ser::Serialize,
     scope: &mut v8::HandleScope<'a>,
+    mut result: Vec<v8::Local<'a, v8::Value>>,
 ) -> Result<Vec<v8::Local<'a, v8::Value>>, Error> {
+    let active = crate::js_value::ActiveRuntime::enter(scope);
     let args = deno_core::serde_v8::to_v8(scope, args)?;
+    drop(active);
     match v8::Local::<v8::Array>::try_from(args) {
         Ok(args) => {
             let len = args.length();
-            let mut result = Vec::with_capacity(len as usize);
+            result.reserve(len as usize);
             for i in 0..len {
-                let index = v8::Integer::new(
-                    scope,
-                    i.try_into().map_err(|_| {
-                        Error::Runtime(format!(
-                            "Could not decode {len} arguments - use `big_json_args`"
-                        ))
-                    })?,
-                );
                 let arg = args
-                    .get(scope, index.into())
+                    .get_index(scope, i)
                     .ok_or_else(|| Error::Runtime(format!("Invalid argument at index {i}")))?;
                 result.push(arg);
             }
-            Ok(result)
         }
-        Err(_) if args.is_undefined() || args.is_null() => Ok(vec![]),
-        Err(_) => Ok(vec![args]),
+        Err(_) if args.is_undefined() || args.is_null() => {}
+        Err(_) => result.push(args),
+    }
+    Ok(result)
+}
+
+/// Copies a value for deserialization, replacing each `Map` and `Set` - including those nested in arrays and
+/// objects - with a plain object or array, so they deserialize into rust maps and sets instead of empty objects
+///
+/// - Every object is copied, so each property is read exactly once - getters do not run again when the copy is deserialized
+/// - Shared references and cycles are copied once, and are shared or cycled the same way in the copy
+/// - Only the elements an array actually has are copied, so sparse arrays stay sparse
+/// - Map keys become property keys, so they must be strings, numbers, bigints or booleans,
+///   and keys that convert to the same property - such as `1` and `"1"` - are rejected
+///
+/// Functions, buffers and typed arrays are not copied  
+/// `copies` holds the copies made so far, by the object they were copied from - each is recorded before its contents are copied
+///
+/// # Errors
+/// Fails if a property cannot be read, or a map key cannot become a property key of its own
+pub(crate) fn expand_collections<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    value: v8::Local<'s, v8::Value>,
+    depth: usize,
+    copies: &mut IdentityMap<'s, v8::Local<'s, v8::Object>>,
+) -> Result<v8::Local<'s, v8::Value>, Error> {
+    // Deeper values are left for the deserializer to reject
+    const MAX_DEPTH: usize = 64;
+    if depth >= MAX_DEPTH
+        || !value.is_object()
+        || value.is_function()
+        || value.is_array_buffer()
+        || value.is_array_buffer_view()
+    {
+        return Ok(value);
+    }
+    let Some(object) = value.to_object(scope) else {
+        return Ok(value);
+    };
+    if let Some(copy) = copies.get(object) {
+        return Ok((*copy).into());
+    }
+
+    if let Ok(map) = v8::Local::<v8::Map>::try_from(value) {
+        // Flattened as [key, value, key, value, ...]
+        let entries = map.as_array(scope);
+        let copy = v8::Object::new(scope);
+        copies.insert(object, copy);
+        for i in (0..entries.length()).step_by(2) {
+            let (Some(key), Some(value)) =
+                (entries.get_index(scope, i), entries.get_index(scope, i + 1))
+            else {
+                continue;
+            };
+
+            if !(key.is_string() || key.is_number() || key.is_big_int() || key.is_boolean()) {
+                let kind = key.type_of(scope).to_rust_string_lossy(scope);
+                return Err(Error::Runtime(format!(
+                    "Map key of type {kind} cannot be used as a property key"
+                )));
+            }
+            let Some(name) = key.to_string(scope) else {
+                continue;
+            };
+            if copy.has_own_property(scope, name.into()) == Some(true) {
+                let name = name.to_rust_string_lossy(scope);
+                return Err(Error::Runtime(format!(
+                    "Map has more than one key that converts to the property '{name}'"
+                )));
+            }
+
+            let value = expand_collections(scope, value, depth + 1, copies)?;
+            copy.set(scope, name.into(), value);
+        }
+        return Ok(copy.into());
+    }
+
+    if let Ok(set) = v8::Local::<v8::Set>::try_from(value) {
+        let values = set.as_array(scope);
+        let copy = v8::Array::new(scope, 0);
+        copies.insert(object, copy.into());
+        for i in 0..values.length() {
+            if let Some(value) = values.get_index(scope, i) {
+                let value = expand_collections(scope, value, depth + 1, copies)?;
+                copy.set_index(scope, i, value);
+            }
+        }
+        return Ok(copy.into());
+    }
+
+    // Arrays keep their length, but only the elements they have are copied
+    let copy: v8::Local<v8::Object> = match v8::Local::<v8::Array>::try_from(value) {
+        Ok(array) => {
+            let copy = v8::Array::new(scope, 0);
+            let key = v8::String::new(scope, "length")
+                .ok_or_else(|| Error::Runtime("Could not copy an array".to_string()))?;
+            let length = v8::Integer::new_from_unsigned(scope, array.length());
+            copy.set(scope, key.into(), length.into());
+            copy.into()
+        }
+        Err(_) => v8::Object::new(scope),
+    };
+    copies.insert(object, copy);
+
+    let keys = object
+        .get_own_property_names(scope, v8::GetPropertyNamesArgs::default())
+        .ok_or_else(|| Error::Runtime("Could not read the properties of an object".to_string()))?;
+    for i in 0..keys.length() {
+        let Some(key) = keys.get_index(scope, i) else {
+            continue;
+        };
+        let Some(value) = object.get(scope, key) else {
+            let key = key.to_rust_string_lossy(scope);
+            return Err(Error::Runtime(format!(
+                "Could not read the property '{key}'"
+            )));
+        };
+        let value = expand_collections(scope, value, depth + 1, copies)?;
+        copy.set(scope, key, value);
+    }
+    Ok(copy.into())
+}
+
+/// Deepest nesting the size checks will follow - a deeper value fails the check instead of passing unchecked
+const MAX_CHECK_DEPTH: usize = 64;
+
+/// A map keyed by the identity of javascript objects, so that walks over a value can handle cycles and shared references
+pub(crate) struct IdentityMap<'s, V>(HashMap<i32, Vec<(v8::Local<'s, v8::Object>, V)>>);
+
+impl<V> Default for IdentityMap<'_, V> {
+    fn default() -> Self {
+        Self(HashMap::new())
+    }
+}
+
+impl<'s, V> IdentityMap<'s, V> {
+    /// Returns the value recorded for an object, if any
+    pub(crate) fn get(&self, object: v8::Local<'s, v8::Object>) -> Option<&V> {
+        self.0
+            .get(&object.get_identity_hash().get())?
+            .iter()
+            .find(|(o, _)| o.strict_equals(object.into()))
+            .map(|(_, value)| value)
+    }
+
+    /// Returns the value recorded for an object, if any, for modification
+    pub(crate) fn get_mut(&mut self, object: v8::Local<'s, v8::Object>) -> Option<&mut V> {
+        self.0
+            .get_mut(&object.get_identity_hash().get())?
+            .iter_mut()
+            .find(|(o, _)| o.strict_equals(object.into()))
+            .map(|(_, value)| value)
+    }
+
+    /// Records a value for an object, returning the one it replaced, if any
+    pub(crate) fn insert(&mut self, object: v8::Local<'s, v8::Object>, value: V) -> Option<V> {
+        // Identity hashes can collide, so each bucket is compared by identity
+        let bucket = self.0.entry(object.get_identity_hash().get()).or_default();
+        match bucket
+            .iter_mut()
+            .find(|(o, _)| o.strict_equals(object.into()))
+        {
+            Some((_, existing)) => Some(std::mem::replace(existing, value)),
+            None => {
+                bucket.push((object, value));
+                None
+            }
+        }
+    }
+}
+
+/// Returns the children of a value that a size check must walk - the keys and values of its own enumerable
+/// properties, or the entries of a `Map` or `Set`
+///
+/// Only data properties are read, so no javascript runs: getters and proxies fail the check instead,
+/// since their contents cannot be known without running them
+fn size_check_children<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    object: v8::Local<'s, v8::Object>,
+) -> Result<Vec<v8::Local<'s, v8::Value>>, Error> {
+    if object.is_proxy() {
+        return Err(Error::Runtime(
+            "Cannot check the size of a proxy without running its traps".to_string(),
+        ));
     }
+
+    let value: v8::Local<v8::Value> = object.into();
+    let entries = if let Ok(map) = v8::Local::<v8::Map>::try_from(value) {
+        Some(map.as_array(scope))
+    } else if let Ok(set) = v8::Local::<v8::Set>::try_from(value) {
+        Some(set.as_array(scope))
+    } else {
+        None
+    };
+    if let Some(entries) = entries {
+        return Ok((0..entries.length())
+            .filter_map(|i| entries.get_index(scope, i))
+            .collect());
+    }
+
+    let args = v8::GetPropertyNamesArgs {
+        key_conversion: v8::KeyConversionMode::ConvertToString,
+        ..Default::default()
+    };
+    let Some(keys) = object.get_own_property_names(scope, args) else {
+        return Ok(vec![]);
+    };
+
+    let value_key = "value".to_v8_string(scope)?;
+    let get_key = "get".to_v8_string(scope)?;
+    let mut children = Vec::with_capacity(keys.length() as usize * 2);
+    for i in 0..keys.length() {
+        let Some(key) = keys
+            .get_index(scope, i)
+            .and_then(|key| v8::Local::<v8::String>::try_from(key).ok())
+        else {
+            continue;
+        };
+        let Some(descriptor) = object
+            .get_own_property_descriptor(scope, key.into())
+            .and_then(|descriptor| descriptor.to_object(scope))
+        else {
+            continue;
+        };
+
+        if descriptor.has_own_property(scope, get_key.into()) == Some(true) {
+            let key = key.to_rust_string_lossy(scope);
+            return Err(Error::Runtime(format!(
+                "Cannot check the size of the accessor property `{key}` without running it"
+            )));
+        }
+
+        children.push(key.into());
+        if let Some(value) = descriptor.get(scope, value_key.into()) {
+            children.push(value);
+        }
+    }
+    Ok(children)
+}
+
+/// Checks that no string in a value - or nested in its arrays, objects, maps and sets - is longer than `max` UTF-8 bytes
+/// Called before deserializing, so that an oversized string is never copied into rust
+///
+/// `visited` holds the objects already walked, so that cycles and shared references are only walked once
+fn check_string_sizes<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    value: v8::Local<'s, v8::Value>,
+    max: usize,
+    depth: usize,
+    visited: &mut IdentityMap<'s, ()>,
+) -> Result<(), Error> {
+    if let Ok(string) = v8::Local::<v8::String>::try_from(value) {
+        let len = string.utf8_length(scope);
+        if len > max {
+            return Err(Error::StringTooLarge(len, max));
+        }
+    } else if value.is_object() && !value.is_function() {
+        if depth >= MAX_CHECK_DEPTH {
+            return Err(Error::Runtime(format!(
+                "Value is nested more than {MAX_CHECK_DEPTH} levels deep, and its strings cannot be checked"
+            )));
+        }
+
+        let Some(object) = value.to_object(scope) else {
+            return Ok(());
+        };
+        if visited.insert(object, ()).is_some() {
+            return Ok(());
+        }
+
+        for child in size_check_children(scope, object)? {
+            check_string_sizes(scope, child, max, depth + 1, visited)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds `n` bytes to the measured size of a result, failing once it is over `max`
+fn add_result_size(size: &mut usize, n: usize, max: usize) -> Result<(), Error> {
+    *size = size.saturating_add(n);
+    if *size > max {
+        return Err(Error::ResultTooLarge(*size, max));
+    }
+    Ok(())
+}
+
+/// Measures the approximate serialized size of a value, failing once it is over `max`
+///
+/// Shared objects count every time they appear, as they would once deserialized - but are only walked once.  
+/// A reference back to an object containing it counts as a primitive
+///
+/// `measured` holds the objects already met, with the size of everything nested in them -
+/// an object still being measured has no size yet, so meeting it again means the value contains a cycle
+fn check_result_size<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    value: v8::Local<'s, v8::Value>,
+    max: usize,
+    size: &mut usize,
+    depth: usize,
+    measured: &mut IdentityMap<'s, Option<usize>>,
+) -> Result<(), Error> {
+    // Approximate size of a value once serialized - 8 bytes for numbers and other primitives
+    if let Ok(string) = v8::Local::<v8::String>::try_from(value) {
+        return add_result_size(size, string.utf8_length(scope), max);
+    } else if let Ok(view) = v8::Local::<v8::ArrayBufferView>::try_from(value) {
+        return add_result_size(size, view.byte_length(), max);
+    } else if let Ok(buffer) = v8::Local::<v8::ArrayBuffer>::try_from(value) {
+        return add_result_size(size, buffer.byte_length(), max);
+    } else if let Ok(buffer) = v8::Local::<v8::SharedArrayBuffer>::try_from(value) {
+        return add_result_size(size, buffer.byte_length(), max);
+    }
+
+    add_result_size(size, 8, max)?;
+    if !value.is_object() || value.is_function() {
+        return Ok(());
+    }
+
+    let Some(object) = value.to_object(scope) else {
+        return Ok(());
+    };
+    match measured.get(object) {
+        Some(Some(contents)) => return add_result_size(size, *contents, max),
+        Some(None) => return Ok(()),
+        None => {}
+    }
+
+    if depth >= MAX_CHECK_DEPTH {
+        return Err(Error::Runtime(format!(
+            "Value is nested more than {MAX_CHECK_DEPTH} levels deep, and its size cannot be checked"
+        )));
+    }
+
+    measured.insert(object, None);
+    let before = *size;
+    for child in size_check_children(scope, object)? {
+        check_result_size(scope, child, max, size, depth + 1, measured)?;
+    }
+    if let Some(entry) = measured.get_mut(object) {
+        *entry = Some(*size - before);
+    }
+
+    Ok(())
 }
 
 /// Represents the set of options accepted by the runtime constructor
@@ -116,6 +526,32 @@ pub struct RuntimeOptions {
     /// A set of `deno_core` extensions to add to the runtime
     pub extensions: Vec<deno_core::Extension>,
 
+    /// Individual `#[op2]` functions to add to the runtime, without writing an `extension!`
+    ///
+    /// They are wrapped into a single extension, and called from javascript with `Deno.core.ops.<name>(...)`  
+    /// Use [`RuntimeOptions::extensions`] for ops that need state, javascript modules, or other setup
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, RuntimeOptions, deno_core::op2 };
+    ///
+    /// #[op2(fast)]
+    /// fn op_double(x: u32) -> u32 {
+    ///     x * 2
+    /// }
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(RuntimeOptions {
+    ///     ops: vec![op_double()],
+    ///     ..Default::default()
+    /// })?;
+    ///
+    /// let value: u32 = runtime.eval("Deno.core.ops.op_double(21)")?;
+    /// assert_eq!(value, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub ops: Vec<deno_core::OpDecl>,
+
     /// Additional options for the built-in extensions
     pub extension_options: ext::ExtensionOptions,
 
@@ -125,9 +561,107 @@ pub struct RuntimeOptions {
     /// Amount of time to run for before killing the thread
     pub timeout: Duration,
 
+    /// Optional limit on the CPU time a blocking call may spend executing javascript
+    ///
+    /// Unlike [`RuntimeOptions::timeout`], time spent waiting on timers or I/O does not count.  
+    /// The isolate is sampled every few milliseconds, so the limit is approximate.  
+    /// When exceeded, the running javascript is terminated and the call returns [`Error::CpuTimeExceeded`];
+    /// the runtime can then be used again. Whichever of the two limits trips first decides the error
+    pub cpu_time_limit: Option<Duration>,
+
+    /// Optional callback invoked when a call exceeds [`RuntimeOptions::timeout`], before the call is abandoned
+    ///
+    /// Receives the [`RuntimeInfo`] of the runtime that timed out, and is purely observational -
+    /// the call still fails with [`Error::Timeout`] once the callback returns.  
+    /// The callback runs on the thread that made the call, so it should return quickly
+    pub on_timeout: Option<TimeoutHook>,
+
+    /// Optional callback invoked every [`RuntimeOptions::progress_interval`] while a blocking call executes javascript
+    ///
+    /// Runs from a v8 interrupt, so scripts do not need to cooperate - even a tight loop is interrupted.  
+    /// Only time spent executing javascript triggers the callback; time spent waiting on timers or I/O does not.  
+    /// Returning [`ProgressSignal::Abort`] terminates the running javascript, and the call returns [`Error::Cancelled`];
+    /// the runtime can then be used again. A callback that panics also aborts the call.
+    ///
+    /// The callback runs on the runtime's thread, in the middle of javascript execution - it must not
+    /// use the runtime, and should return quickly. The `_async` methods are not monitored
+    pub progress_callback: Option<ProgressCallback>,
+
+    /// How often [`RuntimeOptions::progress_callback`] is invoked - defaults to 100ms
+    pub progress_interval: Duration,
+
     /// Optional maximum heap size for the runtime
     pub max_heap_size: Option<usize>,
 
+    /// Optional maximum size, in bytes, of the stack used by javascript - v8 defaults to just under 1MiB
+    ///
+    /// Exceeding it throws a catchable `RangeError` in javascript. Raise it for legitimately deep recursion.
+    ///
+    /// This is a limit v8 enforces on itself, and does not grow the stack of the thread running the runtime.  
+    /// The limit is measured from the point on the stack where the runtime is created, and must fit within
+    /// that thread's stack (along with whatever rust code sits below it) - otherwise deep recursion crashes the
+    /// process with a stack overflow, instead of throwing. The main thread usually has 8MiB on linux and 1MiB on
+    /// windows, and threads spawned by rust default to 2MiB. To go beyond that, create and use the runtime on a
+    /// thread spawned with a larger stack, using [`std::thread::Builder::stack_size`]
+    pub max_stack_size: Option<usize>,
+
+    /// Optional maximum size, in UTF-8 bytes, of any string returned from javascript
+    ///
+    /// Checked before deserialization, including strings nested in arrays, objects, maps and sets,
+    /// so that a runaway string cannot exhaust memory on the rust side.  
+    /// Oversized strings result in [`Error::StringTooLarge`]
+    ///
+    /// The check never runs javascript, so values it cannot inspect fail it - proxies, accessor properties,
+    /// and anything nested more than 64 levels deep
+    pub max_string_bytes: Option<usize>,
+
+    /// Optional maximum size, in bytes, of any value returned from javascript
+    ///
+    /// Applies to the results of function calls, evaluations, and exported values.  
+    /// The size is an estimate of the serialized value - strings count their UTF-8 length, array buffers and
+    /// typed arrays their byte length, and every other value 8 bytes, including the entries of maps and sets.
+    /// An object referenced several times counts each time. It is checked before deserialization, so that an
+    /// untrusted script cannot exhaust memory on the rust side, independently of [`RuntimeOptions::max_heap_size`].  
+    /// Oversized values result in [`Error::ResultTooLarge`]
+    ///
+    /// Like [`RuntimeOptions::max_string_bytes`], the check never runs javascript - proxies, accessor properties,
+    /// and anything nested more than 64 levels deep fail it
+    pub max_result_size: Option<usize>,
+
+    /// Optional maximum number of module sources kept by the module loader
+    ///
+    /// The loader keeps the source and source map of every module loaded, to produce error messages -
+    /// in long-running runtimes that load many short-lived modules, this grows without bound.  
+    /// With a limit, the least recently used sources are evicted first.
+    ///
+    /// Evicting a source does not unload the module, which stays instantiated and usable.
+    /// Errors thrown from an evicted module lose their source line, and source-mapped positions
+    pub source_cache_limit: Option<usize>,
+
+    /// Optional maximum number of pending `setTimeout` and `setInterval` timers
+    ///
+    /// Scheduling a timer beyond the limit throws a `RangeError` in javascript, so that a script cannot exhaust
+    /// memory with timers, or keep the event loop from ever draining.  
+    /// A timeout frees its slot once it fires, and any timer once it is cleared - intervals hold theirs until cleared.  
+    /// Timers are counted as they are queued with the event loop, so queueing one through `Deno.core` directly counts too.  
+    /// `setImmediate` is not counted
+    pub max_timers: Option<usize>,
+
+    /// Optional maximum number of promise reactions (`then` callbacks and `await` continuations) run in a single turn of the event loop
+    ///
+    /// A script that endlessly chains promises never yields back to the event loop, so timers never fire
+    /// and the call cannot end cleanly. Once the budget is used up, the running javascript is terminated
+    /// and the call returns [`Error::MicrotaskLimitExceeded`]; the runtime can then be used again.  
+    /// The count is reset each time the event loop gets a chance to run timers
+    pub microtask_limit: Option<usize>,
+
+    /// Optional dotted path under which functions registered with a dotted name, such as `myapi.db.query`, are placed
+    ///
+    /// By default such functions are placed on the global object, so `myapi.db.query` is callable as written.  
+    /// With a root of `host`, the same function is callable as `host.myapi.db.query` instead.  
+    /// Functions without a dot in their name are only reachable through `rustyscript.functions`, as before
+    pub function_namespace_root: Option<String>,
+
     /// Optional cache provider for the module loader
     #[allow(deprecated)]
     pub module_cache: Option<Box<dyn crate::module_loader::ModuleCacheProvider>>,
@@ -150,6 +684,17 @@ pub struct RuntimeOptions {
     /// See the `rusty_v8` documentation for more information
     pub isolate_params: Option<v8::CreateParams>,
 
+    /// Flags passed to v8, such as `--harmony-temporal` or `--no-opt`
+    ///
+    /// **v8 flags are process-global** - they affect every isolate in the process, not only this runtime.  
+    /// They can only be set before v8 is initialized, which happens when the first runtime is created,
+    /// so the flags of the first runtime in the process apply to every runtime after it.  
+    /// Creating a later runtime with different flags fails, rather than silently running without them -
+    /// later runtimes should use the same flags, or none. Runtimes created with `deno_core` directly are not tracked.
+    ///
+    /// Flags v8 does not recognize are reported as an error
+    pub v8_flags: Vec<String>,
+
     /// Optional shared array buffer store to use for the runtime.
     ///
     /// Allows data-sharing between runtimes across threads
@@ -159,27 +704,717 @@ pub struct RuntimeOptions {
     ///
     /// By default only `http`/`https` (`url_import` crate feature), and `file` (`fs_import` crate feature) are allowed
     pub schema_whlist: HashSet<String>,
+
+    /// Import aliases, equivalent to the `paths` option of a `tsconfig.json`
+    ///
+    /// - `"@app/*" -> "src/*"` maps `@app/utils` to `src/utils`
+    /// - `"@config" -> "src/config.ts"` maps only the exact specifier
+    ///
+    /// Relative targets are resolved against the runtime's current directory - which serves as `baseUrl`.  
+    /// Extensionless targets will resolve to a matching `.ts`/`.js` file, or directory index, if one exists
+    pub path_aliases: HashMap<String, PathBuf>,
+
+    /// If true, file paths that differ only by case refer to the same module - defaults to false
+    ///
+    /// Paths are resolved to the casing of the file on disk, so imports of `./foo.js` and `./Foo.js` resolve to the
+    /// same module instance, instead of loading it twice - or failing on a case-sensitive filesystem.  
+    /// Modules that are not on disk, such as static modules, keep the first casing that loaded.  
+    /// On windows, backslashes in file paths are always treated as separators, regardless of this option
+    pub case_insensitive_module_paths: bool,
+
+    /// Optional Deno-style import map, consulted before any other module resolution - including path aliases
+    ///
+    /// Supports both `imports` and `scopes` - see [`crate::module_loader::parse_import_map`] to create one from JSON.  
+    /// Bare specifiers the map does not cover fall back to the usual resolution, but an import the map
+    /// rejects - such as one blocked by a `null` entry - fails
+    pub import_map: Option<crate::module_loader::ImportMap>,
+
+    /// Optional callback fired for every module loaded, including modules served by the module cache,
+    /// and modules given to the runtime directly from rust
+    ///
+    /// Receives the specifier, load time and size of each module - see [`crate::module_loader::ModuleLoadEvent`].  
+    /// Does not affect how modules are loaded
+    pub module_load_observer: Option<crate::module_loader::ModuleLoadObserver>,
+
+    /// Optional callback observing, and allowing or denying, each dynamic `import()` made by a running script
+    ///
+    /// Receives the resolved specifier and the referrer - see [`crate::module_loader::DynamicImportEvent`].  
+    /// Denied imports reject the promise returned by `import()`. Static imports are not affected
+    pub dynamic_import_hook: Option<crate::module_loader::DynamicImportHook>,
+
+    /// Optional callback rewriting the source of every module loaded, before it is transpiled
+    ///
+    /// Useful for instrumentation, such as coverage counters, or macro expansion.  
+    /// See [`crate::module_loader::SourceTransform`]
+    pub source_transform: Option<crate::module_loader::SourceTransform>,
+
+    /// Headers sent with every module imported over HTTP (`url_import` crate feature), such as `Authorization`
+    ///
+    /// On a redirect to a different host, sensitive headers like `Authorization` are dropped.  
+    /// A response with an error status, such as `401` or `403`, results in [`Error::ImportHttpStatus`]
+    #[cfg(feature = "url_import")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "url_import")))]
+    pub url_import_headers: HashMap<String, String>,
+
+    /// Optional user agent sent with every module imported over HTTP (`url_import` crate feature)
+    #[cfg(feature = "url_import")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "url_import")))]
+    pub url_import_user_agent: Option<String>,
+
+    /// Optional root certificate store used to verify modules imported over HTTPS (`url_import` crate feature)
+    ///
+    /// Use this to trust a custom CA, such as a corporate one. Defaults to the bundled webpki roots.  
+    /// `fetch` and websockets are configured separately, through `extension_options.web`
+    #[cfg(feature = "url_import")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "url_import")))]
+    pub url_import_root_cert_store_provider:
+        Option<std::sync::Arc<dyn deno_tls::RootCertStoreProvider>>,
+
+    /// List of domain names or IP addresses for which TLS errors are ignored when importing modules over HTTPS (`url_import` crate feature)
+    ///
+    /// This is useful for testing with self-signed certificates.  
+    /// An empty list ignores certificate errors for every host - `Some(vec![])` turns off verification entirely
+    #[cfg(feature = "url_import")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "url_import")))]
+    pub url_import_unsafely_ignore_certificate_errors: Option<Vec<String>>,
+
+    /// If false, modules that need transpiling are rejected instead of transpiled - defaults to true
+    ///
+    /// Plain javascript is loaded unchanged either way - only modules that need transpiling (`.ts`, `.tsx`, `.jsx`,
+    /// `.mts`, `.cts`, or a module created with [`crate::Module::new_typescript`]) go through the transpiler.  
+    /// When disabled, loading or importing one of those fails with an error, so a runtime can be limited to javascript.  
+    /// Extensions, and modules compiled ahead of time with [`crate::Module::compile`], are not affected
+    pub transpile: bool,
+
+    /// Options for the transpiler, used for modules that need transpiling
+    ///
+    /// Controls decorator handling, and how JSX in `.jsx` and `.tsx` modules is transformed - see [`crate::JsxMode`]
+    /// for the supported modes. Defaults to leaving decorators as-is, and classic `React.createElement` JSX
+    pub transpile_options: TranspileOptions,
+
+    /// If true, the intrinsics (`Object`, `Array.prototype`, etc.) and `globalThis` will be recursively frozen
+    /// once the first set of modules has been loaded, preventing scripts from monkeypatching built-ins
+    ///
+    /// If other javascript runs first - such as through `eval`, or by calling a function - the globals are frozen
+    /// before it runs instead, so no script ever sees them writable. Setup done from rust, like registering
+    /// functions or error classes, should happen before either.
+    ///
+    /// - Every global value, and every object reachable from it (properties and prototypes), is passed to `Object.freeze`
+    /// - `globalThis` itself is then frozen, so new globals cannot be added
+    /// - The `Deno` namespace is left untouched, since the runtime's internals still modify it
+    /// - Registered rust functions remain callable through `rustyscript.functions` and `rustyscript.async_functions`
+    ///
+    /// Scripts that need to add their own globals should do so in the first module loaded, before the freeze.  
+    /// An object with an own property of `Symbol.for('rustyscript.unfrozen')` opts out, and is left mutable.
+    ///
+    /// Note that assigning to a property inherited from a frozen prototype (such as `this.name` in an `Error` subclass)
+    /// will throw in strict mode - use `Object.defineProperty` instead
+    pub freeze_globals: bool,
+
+    /// Optional limit on how deeply calls into registered rust functions may be nested - defaults to 256
+    ///
+    /// A registered function that runs javascript (such as through a [`crate::ModuleGetter`]) which calls
+    /// back into a registered function nests one level deeper. Exceeding the limit makes the innermost call
+    /// fail with [`Error::MaxCallDepthExceeded`], instead of recursing until the native stack overflows.  
+    /// `None` disables the limit
+    pub max_call_depth: Option<usize>,
+
+    /// If true, a call that fails because the isolate's execution was terminated
+    /// (for example through [`v8::IsolateHandle::terminate_execution`]) will be retried once,
+    /// after clearing the termination state and reloading the module the call was made against
+    ///
+    /// The reloaded module is a fresh instance, with its top-level state reset, and replaces the old one
+    /// for every later call made through the same [`crate::ModuleHandle`]. The modules it imports are not reloaded.
+    ///
+    /// Errors thrown by the javascript itself are never retried, and neither are the deliberate terminations
+    /// of a limit - such as [`Error::Timeout`], [`Error::CpuTimeExceeded`], [`Error::MicrotaskLimitExceeded`],
+    /// [`Error::HeapExhausted`] or [`Error::Cancelled`] - which are returned as they are.
+    ///
+    /// Applies to the blocking `call_function`, `call_stored_function`, and `call_entrypoint` methods of [`crate::Runtime`]
+    pub auto_recover: bool,
+
+    /// If true, a call that writes to `console.error` or `console.warn` fails with [`Error::ConsoleError`],
+    /// containing every message it wrote, each prefixed with the method that wrote it - defaults to false
+    ///
+    /// Messages are still printed as usual. Only blocking calls are checked, once they finish successfully -
+    /// messages written outside of one, such as by the `_async` methods, are discarded when the next blocking call
+    /// starts, so they never fail an unrelated call.
+    ///
+    /// A module whose loading writes a message still fails to load with the error, but stays loaded:
+    /// its handle can be recovered with [`crate::Runtime::get_module_handle`]
+    pub fail_on_console_error: bool,
+
+    /// Optional seed making `Math.random` and `crypto.getRandomValues` deterministic
+    ///
+    /// **This destroys the security of the crypto extension - use it for testing only.**  
+    /// `Math.random` is replaced with a seeded generator, and the seed overrides
+    /// `extension_options.crypto_seed` when the `crypto` feature is enabled.  
+    /// Two runtimes created with the same seed produce the same random values, which makes
+    /// golden-file testing of scripts feasible
+    pub rng_seed: Option<u64>,
+
+    /// Optional clock replacing real time, for testing time-dependent scripts without real sleeps
+    ///
+    /// `Date`, `performance.now`, `setTimeout` and `setInterval` follow the clock instead of real time.  
+    /// Timers fire once the clock is advanced past their deadline, the next time the event loop runs -
+    /// pending timers do not keep the event loop alive on their own.  
+    /// See [`crate::FakeClock`] for an example
+    pub fake_time: Option<crate::FakeClock>,
+
+    /// Environment variables exposed to javascript through `Deno.env`
+    ///
+    /// Only these entries are visible - `Deno.env.get` returns `undefined` for any other key,
+    /// and the host's real environment is never read. `Deno.env.set` and `Deno.env.delete` throw.  
+    /// When empty, `Deno.env` is left as-is
+    pub env_vars: HashMap<String, String>,
+
+    /// If true, defines minimal versions of the node globals `process`, `Buffer` and `global` - defaults to false
+    ///
+    /// **This is a compatibility layer for porting simple scripts, not node.**  
+    /// - `process.env` is a copy of [`RuntimeOptions::env_vars`] - the host's environment is never read
+    /// - `process` also has `argv` (empty), `cwd()` (always `/`), `nextTick`, and an `exit` that throws
+    /// - `Buffer` is a `Uint8Array` supporting `from`, `alloc`, `concat`, `byteLength`, `isBuffer`,
+    ///   and `toString` - with the `utf8`, `latin1`, `hex` and `base64` encodings
+    ///
+    /// Globals that already exist, such as those from the `node_experimental` feature, are left as-is
+    pub node_compat: bool,
+
+    /// If true, defines a global `require` function for CommonJS-style scripts - defaults to false
+    ///
+    /// **Only modules already loaded into the runtime can be required.**  
+    /// `require` is synchronous, so it cannot fetch or evaluate anything - load the modules a script
+    /// requires ahead of time, with [`crate::Runtime::load_module`] or as side modules, before running it.
+    ///
+    /// - The specifier is either the filename a module was loaded with, or a path or URL resolving to it,
+    ///   relative to the runtime's current directory - not to the calling script
+    /// - The `.js` and `.ts` extensions can be left out
+    /// - The module's namespace object is returned, so a default export is found under `.default`
+    ///
+    /// An existing `require`, such as the one from the `node_experimental` feature, is left as-is
+    pub require_shim: bool,
+
+    /// Optional name for the runtime, used to tell runtimes apart in logs
+    ///
+    /// Available to extensions and ops through [`RuntimeInfo`] in the op state, alongside a unique id.  
+    /// Both are included in [`Error::Timeout`] messages, and passed to [`RuntimeOptions::on_timeout`]
+    pub name: Option<String>,
+
+    /// Optional inspector server, allowing a debugger such as Chrome DevTools to attach to the runtime
+    ///
+    /// See [`crate::InspectorOptions`]  
+    /// Breakpoints are only serviced while javascript or the event loop is running
+    #[cfg(feature = "inspector")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "inspector")))]
+    pub inspector: Option<crate::InspectorOptions>,
+
+    /// Optional handle to an existing tokio runtime, used instead of creating a new one
+    ///
+    /// Blocking calls such as [`crate::Runtime::eval`] are driven on this runtime.  
+    /// They can be made from a worker thread of a multi-threaded runtime, but not from inside a
+    /// single-threaded one - use the `_async` methods there instead.  
+    /// On a single-threaded runtime, timers and IO only progress while that runtime is being driven
+    /// elsewhere, so a multi-threaded runtime is recommended - the timeout is enforced either way  
+    /// Such a runtime owns no tokio runtime, so use [`crate::Runtime::tokio_handle`] or [`crate::Runtime::try_tokio_runtime`]
+    /// instead of [`crate::Runtime::tokio_runtime`], which panics
+    pub tokio_handle: Option<tokio::runtime::Handle>,
+
+    /// Optional local set that blocking calls are run inside of
+    ///
+    /// Allows ops and extensions to use `tokio::task::spawn_local`
+    pub tokio_local_set: Option<std::rc::Rc<tokio::task::LocalSet>>,
 }
 
 impl Default for RuntimeOptions {
     fn default() -> Self {
         Self {
             extensions: Vec::default(),
+            ops: Vec::default(),
             default_entrypoint: None,
             timeout: Duration::MAX,
+            cpu_time_limit: None,
+            on_timeout: None,
+            progress_callback: None,
+            progress_interval: Duration::from_millis(100),
             max_heap_size: None,
+            max_stack_size: None,
+            max_string_bytes: None,
+            max_result_size: None,
+            source_cache_limit: None,
+            max_timers: None,
+            microtask_limit: None,
+            function_namespace_root: None,
             module_cache: None,
             import_provider: None,
             startup_snapshot: None,
             isolate_params: None,
+            v8_flags: Vec::default(),
             shared_array_buffer_store: None,
             schema_whlist: HashSet::default(),
+            path_aliases: HashMap::default(),
+            case_insensitive_module_paths: false,
+            import_map: None,
+            module_load_observer: None,
+            dynamic_import_hook: None,
+            source_transform: None,
+            #[cfg(feature = "url_import")]
+            url_import_headers: HashMap::default(),
+            #[cfg(feature = "url_import")]
+            url_import_user_agent: None,
+            #[cfg(feature = "url_import")]
+            url_import_root_cert_store_provider: None,
+            #[cfg(feature = "url_import")]
+            url_import_unsafely_ignore_certificate_errors: None,
+            max_call_depth: Some(256),
+            transpile: true,
+            transpile_options: TranspileOptions::default(),
+            freeze_globals: false,
+            auto_recover: false,
+            fail_on_console_error: false,
+            rng_seed: None,
+            fake_time: None,
+            env_vars: HashMap::default(),
+            node_compat: false,
+            require_shim: false,
+            name: None,
+            #[cfg(feature = "inspector")]
+            inspector: None,
+            tokio_handle: None,
+            tokio_local_set: None,
 
             extension_options: ExtensionOptions::default(),
         }
     }
 }
 
+impl RuntimeOptions {
+    /// Returns the `timeout` and `cpu_time_limit` to enforce, which are lifted while debugging
+    pub(crate) fn time_limits(&self) -> (Duration, Option<Duration>) {
+        #[cfg(feature = "inspector")]
+        if self.inspector.as_ref().is_some_and(|i| i.disable_timeouts) {
+            return (Duration::MAX, None);
+        }
+
+        (self.timeout, self.cpu_time_limit)
+    }
+
+    /// Returns a copy of the plain-data options, which can be cloned and serialized
+    ///
+    /// See [`RuntimeProfile`] for the fields included
+    #[must_use]
+    pub fn profile(&self) -> RuntimeProfile {
+        RuntimeProfile {
+            default_entrypoint: self.default_entrypoint.clone(),
+            timeout: self.timeout,
+            cpu_time_limit: self.cpu_time_limit,
+            progress_interval: self.progress_interval,
+            max_heap_size: self.max_heap_size,
+            max_stack_size: self.max_stack_size,
+            max_string_bytes: self.max_string_bytes,
+            max_result_size: self.max_result_size,
+            source_cache_limit: self.source_cache_limit,
+            max_timers: self.max_timers,
+            microtask_limit: self.microtask_limit,
+            function_namespace_root: self.function_namespace_root.clone(),
+            v8_flags: self.v8_flags.clone(),
+            schema_whitelist: self.schema_whlist.clone(),
+            path_aliases: self.path_aliases.clone(),
+            case_insensitive_module_paths: self.case_insensitive_module_paths,
+            #[cfg(feature = "url_import")]
+            url_import_headers: self.url_import_headers.clone(),
+            #[cfg(feature = "url_import")]
+            url_import_user_agent: self.url_import_user_agent.clone(),
+            #[cfg(feature = "url_import")]
+            url_import_unsafely_ignore_certificate_errors: self
+                .url_import_unsafely_ignore_certificate_errors
+                .clone(),
+            max_call_depth: self.max_call_depth,
+            transpile: self.transpile,
+            transpile_options: self.transpile_options.clone(),
+            freeze_globals: self.freeze_globals,
+            auto_recover: self.auto_recover,
+            fail_on_console_error: self.fail_on_console_error,
+            rng_seed: self.rng_seed,
+            env_vars: self.env_vars.clone(),
+            node_compat: self.node_compat,
+            require_shim: self.require_shim,
+            name: self.name.clone(),
+        }
+    }
+
+    /// Overwrite the plain-data options with the values from a profile
+    /// Options not covered by [`RuntimeProfile`] are left unchanged
+    pub fn apply_profile(&mut self, profile: RuntimeProfile) {
+        self.default_entrypoint = profile.default_entrypoint;
+        self.timeout = profile.timeout;
+        self.cpu_time_limit = profile.cpu_time_limit;
+        self.progress_interval = profile.progress_interval;
+        self.max_heap_size = profile.max_heap_size;
+        self.max_stack_size = profile.max_stack_size;
+        self.max_string_bytes = profile.max_string_bytes;
+        self.max_result_size = profile.max_result_size;
+        self.source_cache_limit = profile.source_cache_limit;
+        self.max_timers = profile.max_timers;
+        self.microtask_limit = profile.microtask_limit;
+        self.function_namespace_root = profile.function_namespace_root;
+        self.v8_flags = profile.v8_flags;
+        self.schema_whlist = profile.schema_whitelist;
+        self.path_aliases = profile.path_aliases;
+        self.case_insensitive_module_paths = profile.case_insensitive_module_paths;
+        #[cfg(feature = "url_import")]
+        {
+            self.url_import_headers = profile.url_import_headers;
+            self.url_import_user_agent = profile.url_import_user_agent;
+            self.url_import_unsafely_ignore_certificate_errors =
+                profile.url_import_unsafely_ignore_certificate_errors;
+        }
+        self.max_call_depth = profile.max_call_depth;
+        self.transpile = profile.transpile;
+        self.transpile_options = profile.transpile_options;
+        self.freeze_globals = profile.freeze_globals;
+        self.auto_recover = profile.auto_recover;
+        self.fail_on_console_error = profile.fail_on_console_error;
+        self.rng_seed = profile.rng_seed;
+        self.env_vars = profile.env_vars;
+        self.node_compat = profile.node_compat;
+        self.require_shim = profile.require_shim;
+        self.name = profile.name;
+    }
+}
+
+impl From<RuntimeProfile> for RuntimeOptions {
+    fn from(profile: RuntimeProfile) -> Self {
+        let mut options = Self::default();
+        options.apply_profile(profile);
+        options
+    }
+}
+
+/// The plain-data subset of [`RuntimeOptions`], which can be cloned, and serialized to persist a configuration
+///
+/// `RuntimeOptions` itself holds extensions, callbacks, and other values that cannot be cloned or serialized.  
+/// Those are not part of a profile - a `RuntimeOptions` created from one (see [`RuntimeOptions::from`])
+/// uses their defaults, so callbacks, extensions, module caches, and the like must be set again afterwards.
+///
+/// Missing fields are filled in with their defaults when deserializing
+///
+/// ```rust
+/// use rustyscript::{ RuntimeOptions, RuntimeProfile };
+/// use std::time::Duration;
+///
+/// let base = RuntimeProfile {
+///     timeout: Duration::from_secs(5),
+///     ..Default::default()
+/// };
+///
+/// let mut strict = base.clone();
+/// strict.freeze_globals = true;
+///
+/// let options = RuntimeOptions::from(strict);
+/// assert_eq!(options.timeout, Duration::from_secs(5));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct RuntimeProfile {
+    /// See [`RuntimeOptions::default_entrypoint`]
+    pub default_entrypoint: Option<String>,
+
+    /// See [`RuntimeOptions::timeout`]
+    pub timeout: Duration,
+
+    /// See [`RuntimeOptions::cpu_time_limit`]
+    pub cpu_time_limit: Option<Duration>,
+
+    /// See [`RuntimeOptions::progress_interval`]
+    pub progress_interval: Duration,
+
+    /// See [`RuntimeOptions::max_heap_size`]
+    pub max_heap_size: Option<usize>,
+
+    /// See [`RuntimeOptions::max_stack_size`]
+    pub max_stack_size: Option<usize>,
+
+    /// See [`RuntimeOptions::max_string_bytes`]
+    pub max_string_bytes: Option<usize>,
+
+    /// See [`RuntimeOptions::max_result_size`]
+    pub max_result_size: Option<usize>,
+
+    /// See [`RuntimeOptions::source_cache_limit`]
+    pub source_cache_limit: Option<usize>,
+
+    /// See [`RuntimeOptions::max_timers`]
+    pub max_timers: Option<usize>,
+
+    /// See [`RuntimeOptions::microtask_limit`]
+    pub microtask_limit: Option<usize>,
+
+    /// See [`RuntimeOptions::function_namespace_root`]
+    pub function_namespace_root: Option<String>,
+
+    /// See [`RuntimeOptions::v8_flags`]
+    pub v8_flags: Vec<String>,
+
+    /// See [`RuntimeOptions::schema_whlist`]
+    pub schema_whitelist: HashSet<String>,
+
+    /// See [`RuntimeOptions::path_aliases`]
+    pub path_aliases: HashMap<String, PathBuf>,
+
+    /// See [`RuntimeOptions::case_insensitive_module_paths`]
+    pub case_insensitive_module_paths: bool,
+
+    /// See [`RuntimeOptions::url_import_headers`]
+    #[cfg(feature = "url_import")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "url_import")))]
+    pub url_import_headers: HashMap<String, String>,
+
+    /// See [`RuntimeOptions::url_import_user_agent`]
+    #[cfg(feature = "url_import")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "url_import")))]
+    pub url_import_user_agent: Option<String>,
+
+    /// See [`RuntimeOptions::url_import_unsafely_ignore_certificate_errors`]
+    #[cfg(feature = "url_import")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "url_import")))]
+    pub url_import_unsafely_ignore_certificate_errors: Option<Vec<String>>,
+
+    /// See [`RuntimeOptions::max_call_depth`]
+    pub max_call_depth: Option<usize>,
+
+    /// See [`RuntimeOptions::transpile`]
+    pub transpile: bool,
+
+    /// See [`RuntimeOptions::transpile_options`]
+    pub transpile_options: TranspileOptions,
+
+    /// See [`RuntimeOptions::freeze_globals`]
+    pub freeze_globals: bool,
+
+    /// See [`RuntimeOptions::auto_recover`]
+    pub auto_recover: bool,
+
+    /// See [`RuntimeOptions::fail_on_console_error`]
+    pub fail_on_console_error: bool,
+
+    /// See [`RuntimeOptions::rng_seed`]
+    pub rng_seed: Option<u64>,
+
+    /// See [`RuntimeOptions::env_vars`]
+    pub env_vars: HashMap<String, String>,
+
+    /// See [`RuntimeOptions::node_compat`]
+    pub node_compat: bool,
+
+    /// See [`RuntimeOptions::require_shim`]
+    pub require_shim: bool,
+
+    /// See [`RuntimeOptions::name`]
+    pub name: Option<String>,
+}
+
+impl Default for RuntimeProfile {
+    fn default() -> Self {
+        RuntimeOptions::default().profile()
+    }
+}
+
+/// Identifies a runtime in logs - see [`RuntimeOptions::name`]
+///
+/// Each runtime is given an id unique within the process.  
+/// Stored in the op state, so extensions and ops can tag their output with it:
+/// `state.borrow::<RuntimeInfo>()`
+///
+/// Displays as `runtime 3`, or `runtime 3 (name)` for a named runtime
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeInfo {
+    /// Unique id of the runtime, assigned in order of creation
+    pub id: u64,
+
+    /// Name given to the runtime, if any
+    pub name: Option<String>,
+}
+
+impl RuntimeInfo {
+    fn new(name: Option<String>) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            name,
+        }
+    }
+}
+
+impl std::fmt::Display for RuntimeInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "runtime {} ({name})", self.id),
+            None => write!(f, "runtime {}", self.id),
+        }
+    }
+}
+
+/// Waker recording whether it was woken - see [`InnerRuntime::run_event_loop_until_idle`]
+#[derive(Default)]
+struct WakeFlag(AtomicBool);
+impl Wake for WakeFlag {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Serializer for exported module state - host objects are not supported
+struct StateSerializer;
+impl v8::ValueSerializerImpl for StateSerializer {
+    fn throw_data_clone_error<'s>(
+        &self,
+        scope: &mut v8::HandleScope<'s>,
+        message: v8::Local<'s, v8::String>,
+    ) {
+        let error = v8::Exception::type_error(scope, message);
+        scope.throw_exception(error);
+    }
+}
+
+/// Deserializer for exported module state
+struct StateDeserializer;
+impl v8::ValueDeserializerImpl for StateDeserializer {}
+
+/// The contents of an object restored by [`InnerRuntime::restore_export`]
+///
+/// Maps and sets are restored through their entries, other objects through their own properties
+enum ExportContents<'s> {
+    Properties(Vec<(v8::Local<'s, v8::Value>, v8::Local<'s, v8::Value>)>),
+    Map(v8::Local<'s, v8::Array>),
+    Set(v8::Local<'s, v8::Array>),
+}
+
+impl<'s> ExportContents<'s> {
+    /// Reads the contents of an object - returns None if an exception was thrown
+    fn read(scope: &mut v8::HandleScope<'s>, object: v8::Local<'s, v8::Object>) -> Option<Self> {
+        let value: v8::Local<v8::Value> = object.into();
+        if let Ok(map) = v8::Local::<v8::Map>::try_from(value) {
+            return Some(Self::Map(map.as_array(scope)));
+        }
+        if let Ok(set) = v8::Local::<v8::Set>::try_from(value) {
+            return Some(Self::Set(set.as_array(scope)));
+        }
+
+        let keys = object.get_own_property_names(scope, v8::GetPropertyNamesArgs::default())?;
+        let mut properties = Vec::with_capacity(keys.length() as usize);
+        for i in 0..keys.length() {
+            let key = keys.get_index(scope, i)?;
+            let value = object.get(scope, key)?;
+            properties.push((key, value));
+        }
+        Some(Self::Properties(properties))
+    }
+
+    /// True if both contents can be written to the same kind of object
+    fn same_kind(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    /// Replaces the contents of `target` - returns None if an exception was thrown
+    fn write(
+        &self,
+        scope: &mut v8::HandleScope<'s>,
+        target: v8::Local<'s, v8::Object>,
+    ) -> Option<()> {
+        match self {
+            Self::Properties(properties) => {
+                let keys =
+                    target.get_own_property_names(scope, v8::GetPropertyNamesArgs::default())?;
+                for i in 0..keys.length() {
+                    let key = keys.get_index(scope, i)?;
+                    if !target.delete(scope, key)? {
+                        return None;
+                    }
+                }
+                for (key, value) in properties {
+                    if !target.set(scope, *key, *value)? {
+                        return None;
+                    }
+                }
+            }
+
+            Self::Map(entries) => {
+                let map =
+                    v8::Local::<v8::Map>::try_from(v8::Local::<v8::Value>::from(target)).ok()?;
+                map.clear();
+                for i in (0..entries.length()).step_by(2) {
+                    let key = entries.get_index(scope, i)?;
+                    let value = entries.get_index(scope, i + 1)?;
+                    map.set(scope, key, value)?;
+                }
+            }
+
+            Self::Set(values) => {
+                invoke_method(scope, target, "clear", &[])?;
+                for i in 0..values.length() {
+                    let value = values.get_index(scope, i)?;
+                    invoke_method(scope, target, "add", &[value])?;
+                }
+            }
+        }
+
+        Some(())
+    }
+
+    /// Puts back the original contents of `target` after a failed write, as far as it is possible
+    fn rollback(&self, scope: &mut v8::HandleScope<'s>, target: v8::Local<'s, v8::Object>) {
+        let Self::Properties(properties) = self else {
+            self.write(scope, target);
+            return;
+        };
+
+        // Unlike a write, keep going past properties that cannot be changed
+        if let Some(keys) =
+            target.get_own_property_names(scope, v8::GetPropertyNamesArgs::default())
+        {
+            for i in 0..keys.length() {
+                if let Some(key) = keys.get_index(scope, i) {
+                    target.delete(scope, key);
+                }
+            }
+        }
+        for (key, value) in properties {
+            target.set(scope, *key, *value);
+        }
+    }
+}
+
+/// Calls a method of `object` by name - returns None if it is missing or threw
+fn invoke_method<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    object: v8::Local<'s, v8::Object>,
+    name: &str,
+    args: &[v8::Local<'s, v8::Value>],
+) -> Option<v8::Local<'s, v8::Value>> {
+    let key = v8::String::new(scope, name)?;
+    let method = object.get(scope, key.into())?;
+    let method = v8::Local::<v8::Function>::try_from(method).ok()?;
+    method.call(scope, object.into(), args)
+}
+
+/// Converts an exception caught while serializing or restoring `name` into an error
+fn caught_error(scope: &mut v8::TryCatch<v8::HandleScope>, name: &str) -> Error {
+    let msg = scope
+        .message()
+        .map(|m| m.get(scope).to_rust_string_lossy(scope))
+        .unwrap_or_else(|| "the object cannot be modified".to_string());
+    Error::Runtime(format!("Could not transfer the state of {name}: {msg}"))
+}
+
+/// Returns true if `name` can be used as a javascript identifier without quoting
+///
+/// Only ASCII identifiers are accepted - anything else is treated as needing quotes
+fn is_js_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().enumerate().all(|(i, c)| {
+            c == '_' || c == '$' || c.is_ascii_alphabetic() || (i > 0 && c.is_ascii_digit())
+        })
+}
+
 /// Deno `JsRuntime` wrapper providing helper functions needed
 /// by the public-facing Runtime API
 ///
@@ -192,17 +1427,92 @@ pub struct InnerRuntime<RT: RuntimeTrait> {
 
     pub cwd: PathBuf,
     pub default_entrypoint: Option<String>,
+
+    /// Id and name of the runtime
+    info: RuntimeInfo,
+
+    /// Owner token for prepared call slots, so that slots from other runtimes are refused
+    /// Replaced whenever modules are reloaded, so that slots still pointing at the old instances are refused too
+    call_slot_owner: Rc<()>,
+
+    /// True if the globals should be frozen after the next module load
+    freeze_globals_pending: bool,
+
+    /// Retry calls once after the isolate's execution was terminated
+    pub auto_recover: bool,
+
+    /// Set when a call was interrupted by a termination of the isolate
+    terminated: bool,
+
+    /// Modules reloaded by `auto_recover`, by the id of the instance they replace
+    recovered_modules: HashMap<deno_core::ModuleId, ModuleHandle>,
+
+    /// Number of modules reloaded by `auto_recover`, to give each reload its own specifier
+    recoveries: usize,
+
+    /// The value thrown by the most recent failed call, until it is taken
+    last_exception: Option<v8::Global<v8::Value>>,
+
+    /// Messages written to `console.error` or `console.warn`, when `fail_on_console_error` is set
+    console_errors: Option<ext::rustyscript::ConsoleErrorLog>,
+
+    /// Budget of promise reactions per turn of the event loop, if `microtask_limit` is set
+    microtask_budget: Option<ext::rustyscript::MicrotaskBudget>,
+
+    /// Set by cancellation handles when they terminate the isolate
+    cancelled: Arc<AtomicBool>,
+
+    /// Maximum size of strings returned to rust
+    max_string_bytes: Option<usize>,
+
+    /// Maximum size of values returned to rust
+    max_result_size: Option<usize>,
+
+    /// Transpile modules before loading them
+    transpile: bool,
+    transpile_options: TranspileOptions,
+
+    /// Typescript signatures for registered functions, used by `generate_host_dts`
+    function_type_hints: HashMap<String, String>,
+
+    /// Path under which functions with a dotted name are placed - `None` for the global object
+    function_namespace_root: Option<String>,
+
+    /// Inspector server debuggers attach through - kept alive for as long as the runtime
+    #[cfg(feature = "inspector")]
+    #[allow(dead_code)]
+    inspector_server: Option<deno_runtime::inspector_server::InspectorServer>,
 }
 impl<RT: RuntimeTrait> InnerRuntime<RT> {
     pub fn new(
         options: RuntimeOptions,
         heap_exhausted_token: CancellationToken,
     ) -> Result<Self, Error> {
+        apply_v8_flags(&options.v8_flags)?;
+
         let cwd = std::env::current_dir()?;
         let module_loader = Rc::new(RustyLoader::new(LoaderOptions {
             cache_provider: options.module_cache,
             import_provider: options.import_provider,
-            schema_whlist: options.schema_whlist,
+            schema_whitelist: options.schema_whlist,
+            path_aliases: options.path_aliases,
+            case_insensitive_paths: options.case_insensitive_module_paths,
+            import_map: options.import_map,
+            load_observer: options.module_load_observer,
+            dynamic_import_hook: options.dynamic_import_hook,
+            source_transform: options.source_transform,
+            #[cfg(feature = "url_import")]
+            url_import_headers: options.url_import_headers,
+            #[cfg(feature = "url_import")]
+            url_import_user_agent: options.url_import_user_agent,
+            #[cfg(feature = "url_import")]
+            url_import_root_cert_store_provider: options.url_import_root_cert_store_provider,
+            #[cfg(feature = "url_import")]
+            url_import_unsafely_ignore_certificate_errors: options
+                .url_import_unsafely_ignore_certificate_errors,
+            disable_transpile: !options.transpile,
+            transpile_options: options.transpile_options.clone(),
+            source_cache_limit: options.source_cache_limit,
             cwd: cwd.clone(),
 
             #[cfg(feature = "node_experimental")]
@@ -218,11 +1528,28 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
             deno_telemetry::init(otel_conf)?;
         }
 
+        // A runtime-wide seed takes over the crypto extension's entropy source
+        let mut extension_options = options.extension_options;
+        #[cfg(feature = "crypto")]
+        if let Some(seed) = options.rng_seed {
+            extension_options.crypto_seed = Some(seed);
+        }
+
+        // Loose ops are wrapped into an extension of their own
+        let mut user_extensions = options.extensions;
+        if !options.ops.is_empty() {
+            user_extensions.push(deno_core::Extension {
+                name: "rustyscript_ops",
+                ops: std::borrow::Cow::Owned(options.ops),
+                ..Default::default()
+            });
+        }
+
         // If a snapshot is provided, do not reload ESM for extensions
         let is_snapshot = options.startup_snapshot.is_some();
         let extensions = ext::all_extensions(
-            options.extensions,
-            options.extension_options,
+            user_extensions,
+            extension_options,
             options.shared_array_buffer_store.clone(),
             is_snapshot,
         );
@@ -261,9 +1588,178 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
             startup_snapshot: options.startup_snapshot,
             extensions,
 
+            get_error_class_fn: Some(&ext::rustyscript::get_error_class_name),
+
+            #[cfg(feature = "inspector")]
+            inspector: options.inspector.is_some(),
+            #[cfg(feature = "inspector")]
+            is_main: true,
+
             ..Default::default()
         })?;
 
+        // Start the inspector server, and optionally wait for a debugger to attach
+        #[cfg(feature = "inspector")]
+        let inspector_server = match options.inspector {
+            Some(inspector) => {
+                let server = deno_runtime::inspector_server::InspectorServer::new(
+                    inspector.address,
+                    "rustyscript",
+                )?;
+                server.register_inspector(
+                    "rustyscript:main".to_string(),
+                    deno_runtime.rt_mut(),
+                    inspector.wait_for_debugger,
+                );
+                if inspector.wait_for_debugger {
+                    deno_runtime
+                        .rt_mut()
+                        .inspector()
+                        .borrow_mut()
+                        .wait_for_session_and_break_on_next_statement();
+                }
+                Some(server)
+            }
+            None => None,
+        };
+
+        // v8's stack limit is an address - the lowest the stack may grow to, measured from the current position
+        if let Some(max_stack_size) = options.max_stack_size {
+            let stack_position = std::ptr::addr_of!(max_stack_size) as usize;
+            deno_runtime
+                .rt_mut()
+                .v8_isolate()
+                .set_stack_limit(stack_position.saturating_sub(max_stack_size));
+        }
+
+        // Replace Math.random with a generator seeded from splitmix64
+        if let Some(mut seed) = options.rng_seed {
+            let mut state = [0u32; 4];
+            for pair in state.chunks_mut(2) {
+                seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                let mut z = seed;
+                z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+                z ^= z >> 31;
+
+                #[allow(clippy::cast_possible_truncation)]
+                let (high, low) = ((z >> 32) as u32, z as u32);
+                pair[0] = high;
+                pair[1] = low;
+            }
+
+            let [a, b, c, d] = state;
+            deno_runtime.rt_mut().execute_script(
+                "ext:rustyscript/seeded_random.js",
+                format!(
+                    "({})({a}, {b}, {c}, {d});",
+                    ext::rustyscript::SEEDED_RANDOM_JS
+                ),
+            )?;
+        }
+
+        // Expose only the given environment variables
+        if !options.env_vars.is_empty() {
+            let vars = deno_core::serde_json::to_string(&options.env_vars)?;
+            deno_runtime.rt_mut().execute_script(
+                "ext:rustyscript/env_vars.js",
+                format!("({})({vars});", ext::rustyscript::ENV_VARS_JS),
+            )?;
+        }
+
+        // Define minimal node globals, for scripts ported from node
+        if options.node_compat {
+            let vars = deno_core::serde_json::to_string(&options.env_vars)?;
+            deno_runtime.rt_mut().execute_script(
+                "ext:rustyscript/node_compat.js",
+                format!("({})({vars});", ext::rustyscript::NODE_COMPAT_JS),
+            )?;
+        }
+
+        // Define a synchronous `require` over the modules loaded into the runtime
+        if options.require_shim {
+            deno_runtime
+                .rt_mut()
+                .op_state()
+                .borrow_mut()
+                .put(ext::rustyscript::RequireResolver(module_loader.clone()));
+            deno_runtime.rt_mut().execute_script(
+                "ext:rustyscript/require.js",
+                format!("({})();", ext::rustyscript::REQUIRE_JS),
+            )?;
+        }
+
+        // Replace real time with the fake clock
+        if let Some(clock) = options.fake_time {
+            deno_runtime.rt_mut().op_state().borrow_mut().put(clock);
+            deno_runtime.rt_mut().execute_script(
+                "ext:rustyscript/fake_clock.js",
+                ext::rustyscript::FAKE_CLOCK_JS,
+            )?;
+        }
+
+        // Bound the promise reactions run per turn of the event loop
+        let microtask_budget = if let Some(limit) = options.microtask_limit {
+            let budget = ext::rustyscript::MicrotaskBudget::new(limit);
+            deno_runtime
+                .rt_mut()
+                .op_state()
+                .borrow_mut()
+                .put(budget.clone());
+            deno_runtime.rt_mut().execute_script(
+                "ext:rustyscript/microtask_limit.js",
+                format!("({})({limit});", ext::rustyscript::MICROTASK_LIMIT_JS),
+            )?;
+            Some(budget)
+        } else {
+            None
+        };
+
+        // Bound the number of pending timers - after the microtask budget, so that its own reset timer is not counted
+        if let Some(limit) = options.max_timers {
+            deno_runtime
+                .rt_mut()
+                .op_state()
+                .borrow_mut()
+                .put(ext::rustyscript::TimerLimit::new(limit));
+            deno_runtime.rt_mut().execute_script(
+                "ext:rustyscript/timer_limit.js",
+                ext::rustyscript::TIMER_LIMIT_JS,
+            )?;
+        }
+
+        // Record console errors and warnings, to fail the call that wrote them
+        let console_errors = if options.fail_on_console_error {
+            let log = ext::rustyscript::ConsoleErrorLog::default();
+            deno_runtime
+                .rt_mut()
+                .op_state()
+                .borrow_mut()
+                .put(log.clone());
+            deno_runtime.rt_mut().execute_script(
+                "ext:rustyscript/console_errors.js",
+                ext::rustyscript::CONSOLE_ERRORS_JS,
+            )?;
+            Some(log)
+        } else {
+            None
+        };
+
+        // Make the runtime's identity available to ops
+        let info = RuntimeInfo::new(options.name);
+        deno_runtime
+            .rt_mut()
+            .op_state()
+            .borrow_mut()
+            .put(info.clone());
+
+        // Track how deeply registered functions are nested
+        deno_runtime
+            .rt_mut()
+            .op_state()
+            .borrow_mut()
+            .put(ext::rustyscript::CallDepth::new(options.max_call_depth));
+
         // Add a callback to terminate the runtime if the max_heap_size limit is approached
         if options.max_heap_size.is_some() {
             let isolate_handle = deno_runtime.rt_mut().v8_isolate().thread_safe_handle();
@@ -283,11 +1779,37 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         }
 
         let default_entrypoint = options.default_entrypoint;
+        let freeze_globals_pending = options.freeze_globals;
+        let auto_recover = options.auto_recover;
+        let max_string_bytes = options.max_string_bytes;
+        let max_result_size = options.max_result_size;
+        let function_namespace_root = options.function_namespace_root.clone();
+        let transpile = options.transpile;
+        let transpile_options = options.transpile_options;
         Ok(Self {
             module_loader,
             deno_runtime,
             cwd,
             default_entrypoint,
+            info,
+            call_slot_owner: Rc::new(()),
+            freeze_globals_pending,
+            auto_recover,
+            terminated: false,
+            recovered_modules: HashMap::new(),
+            recoveries: 0,
+            last_exception: None,
+            console_errors,
+            microtask_budget,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            max_string_bytes,
+            max_result_size,
+            transpile,
+            transpile_options,
+            function_type_hints: HashMap::new(),
+            function_namespace_root,
+            #[cfg(feature = "inspector")]
+            inspector_server,
         })
     }
 
@@ -315,10 +1837,50 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         Ok(&self.cwd)
     }
 
+    /// Load a module transpiled ahead of time
+    /// Its source map is registered first, so errors thrown while it is evaluated point at the original source
+    pub async fn load_compiled(
+        &mut self,
+        compiled: &crate::CompiledModule,
+    ) -> Result<ModuleHandle, Error> {
+        let module = compiled.module();
+        if let Some(source_map) = compiled.source_map() {
+            let specifier = self.module_specifier(module.filename())?;
+            self.module_loader.insert_source_map(
+                specifier.as_str(),
+                module.contents().to_string(),
+                Some(source_map.to_vec()),
+            );
+        }
+
+        self.load_modules(None, vec![module]).await
+    }
+
+    /// Make modules available to imports, without loading them
+    pub fn register_static_modules(&mut self, modules: &[Module]) -> Result<(), Error> {
+        for module in modules {
+            let specifier = self.module_specifier(module.filename())?;
+            self.module_loader
+                .register_static_module(specifier, module.clone());
+        }
+        Ok(())
+    }
+
+    /// Converts a module's filename into the specifier it is loaded under
+    fn module_specifier(&self, filename: impl AsRef<Path>) -> Result<ModuleSpecifier, Error> {
+        let specifier = filename.to_module_specifier(&self.cwd)?;
+        Ok(self.module_loader.normalize_specifier(specifier))
+    }
+
     pub fn current_dir(&self) -> &Path {
         &self.cwd
     }
 
+    /// Returns the options used to transpile modules
+    pub fn transpile_options(&self) -> &TranspileOptions {
+        &self.transpile_options
+    }
+
     /// Remove and return a value from the state
     pub fn take<T>(&mut self) -> Option<T>
     where
@@ -354,6 +1916,8 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
     where
         F: RsAsyncFunction,
     {
+        self.bind_function_path(name, true)?;
+
         let state = self.deno_runtime().op_state();
         let mut state = state.try_borrow_mut()?;
 
@@ -376,17 +1940,209 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
     where
         F: RsFunction,
     {
+        self.bind_function_path(name, false)?;
+
         let state = self.deno_runtime().op_state();
         let mut state = state.try_borrow_mut()?;
 
-        if !state.has::<HashMap<String, Box<dyn RsFunction>>>() {
-            state.put(HashMap::<String, Box<dyn RsFunction>>::new());
+        if !state.has::<HashMap<String, Rc<dyn RsFunction>>>() {
+            state.put(HashMap::<String, Rc<dyn RsFunction>>::new());
         }
 
         // Insert the callback into the state
         state
-            .borrow_mut::<HashMap<String, Box<dyn RsFunction>>>()
-            .insert(name.to_string(), Box::new(callback));
+            .borrow_mut::<HashMap<String, Rc<dyn RsFunction>>>()
+            .insert(name.to_string(), Rc::new(callback));
+
+        Ok(())
+    }
+
+    /// Place a function with a dotted name, such as `myapi.db.query`, at that path
+    /// under the configured root, creating the objects along the way
+    ///
+    /// Names without a dot are left alone, and stay reachable only through `rustyscript.functions`
+    fn bind_function_path(&mut self, name: &str, is_async: bool) -> Result<(), Error> {
+        if !name.contains('.') {
+            return Ok(());
+        }
+
+        let root = self.function_namespace_root.clone();
+        for path in std::iter::once(name).chain(root.as_deref()) {
+            if path.split('.').any(str::is_empty) {
+                return Err(Error::Runtime(format!(
+                    "{path} is not a valid function path"
+                )));
+            }
+        }
+
+        let name = serde_json::to_string(name)?;
+        let root = serde_json::to_string(&root)?;
+        self.deno_runtime().execute_script(
+            "ext:rustyscript/function_path.js",
+            format!(
+                "({})({name}, {root}, {is_async});",
+                ext::rustyscript::FUNCTION_PATH_JS
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Register a rust function returning a stream of values
+    /// The function must return a `RsValueStream`
+    /// and accept a slice of `serde_json::Value` as arguments
+    pub fn register_stream_function<F>(&mut self, name: &str, callback: F) -> Result<(), Error>
+    where
+        F: RsStreamFunction,
+    {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+
+        if !state.has::<HashMap<String, Rc<dyn RsStreamFunction>>>() {
+            state.put(HashMap::<String, Rc<dyn RsStreamFunction>>::new());
+        }
+
+        // Insert the callback into the state
+        state
+            .borrow_mut::<HashMap<String, Rc<dyn RsStreamFunction>>>()
+            .insert(name.to_string(), Rc::new(callback));
+
+        Ok(())
+    }
+
+    /// Register a rust function with access to the exports of loaded modules
+    /// The function must return a `serde_json::Value`
+    /// and accept a slice of `serde_json::Value` as arguments, as well as a `ModuleGetter`
+    pub fn register_module_function<F>(&mut self, name: &str, callback: F) -> Result<(), Error>
+    where
+        F: RsModuleFunction,
+    {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+
+        if !state.has::<HashMap<String, Rc<dyn RsModuleFunction>>>() {
+            state.put(HashMap::<String, Rc<dyn RsModuleFunction>>::new());
+        }
+
+        // Insert the callback into the state
+        state
+            .borrow_mut::<HashMap<String, Rc<dyn RsModuleFunction>>>()
+            .insert(name.to_string(), Rc::new(callback));
+
+        Ok(())
+    }
+
+    /// Define a custom error class in javascript, which registered functions can throw with [`Error::js_class`]
+    pub fn register_error_class(&mut self, name: &str) -> Result<(), Error> {
+        if !is_js_identifier(name) {
+            return Err(Error::Runtime(format!(
+                "{name} is not a valid error class name"
+            )));
+        }
+
+        self.deno_runtime().execute_script(
+            "ext:rustyscript/error_class.js",
+            format!("({})({name:?});", ext::rustyscript::ERROR_CLASS_JS),
+        )?;
+
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+        if !state.has::<ext::rustyscript::ErrorClasses>() {
+            state.put(ext::rustyscript::ErrorClasses::default());
+        }
+        state
+            .borrow::<ext::rustyscript::ErrorClasses>()
+            .insert(name);
+        Ok(())
+    }
+
+    /// Set the typescript signature of a registered function, such as `(a: number, b: number): number`
+    /// Used when generating declarations with `generate_host_dts`
+    pub fn set_function_type_hint(&mut self, name: &str, signature: &str) {
+        self.function_type_hints
+            .insert(name.to_string(), signature.to_string());
+    }
+
+    /// Generate a typescript declaration file for the `rustyscript` global,
+    /// describing every registered function
+    ///
+    /// Functions without a type hint are declared as taking and returning `any`
+    pub fn generate_host_dts(&mut self) -> String {
+        let state = self.deno_runtime().op_state();
+        let state = state.borrow();
+
+        let mut sync_names: Vec<&String> = Vec::new();
+        if let Some(table) = state.try_borrow::<HashMap<String, Rc<dyn RsFunction>>>() {
+            sync_names.extend(table.keys());
+        }
+        if let Some(table) = state.try_borrow::<HashMap<String, Rc<dyn RsModuleFunction>>>() {
+            sync_names.extend(table.keys());
+        }
+        sync_names.sort();
+        sync_names.dedup();
+
+        let mut async_names: Vec<&String> = Vec::new();
+        if let Some(table) = state.try_borrow::<HashMap<String, Box<dyn RsAsyncFunction>>>() {
+            async_names.extend(table.keys());
+        }
+        async_names.sort();
+
+        let member = |name: &String, default: &str| {
+            let signature = self
+                .function_type_hints
+                .get(name)
+                .map_or(default, String::as_str);
+
+            let name = if is_js_identifier(name) {
+                name.clone()
+            } else {
+                serde_json::Value::from(name.as_str()).to_string()
+            };
+
+            format!("        {name}{signature};\n")
+        };
+
+        let mut dts = String::new();
+        dts.push_str("// Host functions registered with the rustyscript runtime\n");
+        dts.push_str("declare namespace rustyscript {\n");
+        dts.push_str("    function register_entrypoint(f: (...args: any[]) => any): void;\n");
+        dts.push_str("    function bail(msg: string): never;\n");
+
+        dts.push_str("    const functions: {\n");
+        for name in sync_names {
+            dts.push_str(&member(name, "(...args: any[]): any"));
+        }
+        dts.push_str("    };\n");
+
+        dts.push_str("    const async_functions: {\n");
+        for name in async_names {
+            dts.push_str(&member(name, "(...args: any[]): Promise<any>"));
+        }
+        dts.push_str("    };\n");
+
+        dts.push_str("}\n");
+        dts
+    }
+
+    /// Record a loaded module's namespace, so that registered functions can reach its exports
+    fn store_module_namespace(
+        &mut self,
+        module: &Module,
+        id: deno_core::ModuleId,
+    ) -> Result<(), Error> {
+        let specifier = self.module_specifier(module.filename())?;
+        let namespace = self.deno_runtime().get_module_namespace(id)?;
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+
+        if !state.has::<ModuleNamespaces>() {
+            state.put(ModuleNamespaces::default());
+        }
+
+        state.borrow_mut::<ModuleNamespaces>().insert(
+            module.filename().to_string_lossy().to_string(),
+            specifier.to_string(),
+            namespace,
+        );
 
         Ok(())
     }
@@ -407,6 +2163,25 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         }
     }
 
+    /// Runs the JS event loop until it is idle  
+    /// Fails with [`Error::Timeout`] if there is still pending work once the deadline passes
+    pub async fn drain(&mut self, deadline: Duration) -> Result<(), Error> {
+        let result = tokio::time::timeout(
+            deadline,
+            self.deno_runtime()
+                .run_event_loop(PollEventLoopOptions::default()),
+        )
+        .await;
+
+        match result {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(Error::Timeout(format!(
+                "{}: event loop still had pending work after {deadline:?}",
+                self.info
+            ))),
+        }
+    }
+
     /// Advances the JS event loop by one tick
     /// Return true if the event loop is pending
     pub async fn advance_event_loop(
@@ -424,6 +2199,47 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         Ok(result)
     }
 
+    /// Runs the JS event loop until it is idle - completed, or waiting on a timer or async operation
+    /// Returns true if the event loop is pending
+    ///
+    /// Yields to the async runtime between ticks, so that timeouts and other tasks can run
+    pub async fn run_event_loop_until_idle(
+        &mut self,
+        options: PollEventLoopOptions,
+    ) -> Result<bool, Error> {
+        loop {
+            // The event loop wakes itself when it has more work ready, so it is polled again
+            // until a poll no longer does so
+            let flag = Arc::new(WakeFlag::default());
+            let waker = Waker::from(flag.clone());
+            let mut cx = Context::from_waker(&waker);
+            match self.deno_runtime().poll_event_loop(&mut cx, options) {
+                Poll::Ready(t) => {
+                    t?;
+                    return Ok(false);
+                }
+                Poll::Pending if flag.0.load(Ordering::SeqCst) => tokio::task::yield_now().await,
+                Poll::Pending => return Ok(true),
+            }
+        }
+    }
+
+    /// Write a v8 heap snapshot, in the `.heapsnapshot` JSON format, to the given writer
+    pub fn write_heap_snapshot(&mut self, writer: &mut impl std::io::Write) -> Result<(), Error> {
+        let mut result = Ok(());
+        self.deno_runtime()
+            .v8_isolate()
+            .take_heap_snapshot(|chunk| match writer.write_all(chunk) {
+                Ok(()) => true,
+                Err(e) => {
+                    result = Err(e);
+                    false
+                }
+            });
+
+        Ok(result?)
+    }
+
     /// Evaluate a piece of non-ECMAScript-module JavaScript code
     /// The expression is evaluated in the global context, so changes persist
     ///
@@ -438,8 +2254,39 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
     /// result cannot be deserialized.
     #[allow(clippy::unused_async, reason = "Prevent panic on sleep calls")]
     pub async fn eval(&mut self, expr: impl ToString) -> Result<v8::Global<v8::Value>, Error> {
-        let result = self.deno_runtime().execute_script("", expr.to_string())?;
-        Ok(result)
+        self.freeze_globals_if_pending()?;
+        self.last_exception = None;
+
+        // Run directly instead of through `execute_script`, so the thrown value can be kept
+        let mut exception = None;
+        let result = {
+            let mut scope = self.deno_runtime().handle_scope();
+            let mut scope = v8::TryCatch::new(&mut scope);
+            let source = expr.to_string().as_str().to_v8_string(&mut scope)?;
+            let value = v8::Script::compile(&mut scope, source, None)
+                .and_then(|script| script.run(&mut scope));
+
+            match value {
+                Some(value) => Ok(v8::Global::new(&mut scope, value)),
+                None if scope.has_terminated() => {
+                    Err(Error::Runtime("Execution terminated".to_string()))
+                }
+                None => match scope.exception() {
+                    Some(thrown) => {
+                        exception = Some(v8::Global::new(&mut scope, thrown));
+                        Err(Error::JsError(
+                            deno_core::error::JsError::from_v8_exception(&mut scope, thrown),
+                        ))
+                    }
+                    None => Err(Error::Runtime(
+                        "Unknown error during evaluation".to_string(),
+                    )),
+                },
+            }
+        };
+
+        self.last_exception = exception;
+        result
     }
 
     /// Attempt to get a value out of the global context (globalThis.name)
@@ -492,24 +2339,354 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         }
     }
 
+    /// Check if a module exports the given name, without reading the export
+    ///
+    /// # Arguments
+    /// * `module` - A handle to a loaded module
+    /// * `name` - Name of the export
+    pub fn has_export(&mut self, module_context: &ModuleHandle, name: &str) -> bool {
+        let Ok(module_namespace) = self
+            .deno_runtime()
+            .get_module_namespace(module_context.id())
+        else {
+            return false;
+        };
+        let mut scope = self.deno_runtime().handle_scope();
+        let module_namespace = module_namespace.open(&mut scope);
+
+        let Ok(key) = name.to_v8_string(&mut scope) else {
+            return false;
+        };
+        module_namespace
+            .has_own_property(&mut scope, key.into())
+            .unwrap_or(false)
+    }
+
+    /// Serialize an object exported by a module, using v8's structured clone format
+    ///
+    /// # Arguments
+    /// * `module` - A handle to a loaded module
+    /// * `name` - Name of the exported object
+    ///
+    /// # Returns
+    /// A `Result` containing the serialized object, or an error (`Error`)
+    pub fn serialize_export(
+        &mut self,
+        module_context: &ModuleHandle,
+        name: &str,
+    ) -> Result<Vec<u8>, Error> {
+        let value = self.get_module_export_value(module_context, name)?;
+        let mut scope = self.deno_runtime().handle_scope();
+        let mut scope = v8::TryCatch::new(&mut scope);
+
+        let value = v8::Local::new(&mut scope, value);
+        if !value.is_object() || value.is_function() {
+            return Err(Error::Runtime(format!("{name} is not an object")));
+        }
+
+        let mut serializer = v8::ValueSerializer::new(&mut scope, Box::new(StateSerializer));
+        serializer.write_header();
+        let context = scope.get_current_context();
+        match serializer.write_value(context, value) {
+            Some(true) => Ok(serializer.release()),
+            _ => Err(caught_error(&mut scope, name)),
+        }
+    }
+
+    /// Restore an object exported by a module from the output of [`InnerRuntime::serialize_export`]
+    ///
+    /// The object's properties (or entries, for a `Map` or `Set`) are replaced in place, so existing references
+    /// to it see the restored state. If the replacement fails part way, the original contents are put back
+    ///
+    /// # Arguments
+    /// * `module` - A handle to a loaded module
+    /// * `name` - Name of the exported object
+    /// * `state` - The serialized object
+    pub fn restore_export(
+        &mut self,
+        module_context: &ModuleHandle,
+        name: &str,
+        state: &[u8],
+    ) -> Result<(), Error> {
+        let target = self.get_module_export_value(module_context, name)?;
+        let mut scope = self.deno_runtime().handle_scope();
+        let mut scope = v8::TryCatch::new(&mut scope);
+
+        let target = v8::Local::new(&mut scope, target);
+        let target = match v8::Local::<v8::Object>::try_from(target) {
+            Ok(target) if !target.is_function() => target,
+            _ => return Err(Error::Runtime(format!("{name} is not an object"))),
+        };
+
+        let context = scope.get_current_context();
+        let mut deserializer =
+            v8::ValueDeserializer::new(&mut scope, Box::new(StateDeserializer), state);
+        let restored = match deserializer.read_header(context) {
+            Some(true) => deserializer.read_value(context),
+            _ => None,
+        };
+        drop(deserializer);
+
+        // The state is restored into a temporary first, and only then swapped into the target
+        let invalid = || Error::Runtime(format!("Invalid state for {name}"));
+        let restored = restored
+            .and_then(|v| v8::Local::<v8::Object>::try_from(v).ok())
+            .ok_or_else(invalid)?;
+        let Some(contents) = ExportContents::read(&mut scope, restored) else {
+            return Err(caught_error(&mut scope, name));
+        };
+        let Some(backup) = ExportContents::read(&mut scope, target) else {
+            return Err(caught_error(&mut scope, name));
+        };
+        if !backup.same_kind(&contents) {
+            return Err(invalid());
+        }
+
+        // If the swap fails part way, put the original contents back
+        if contents.write(&mut scope, target).is_none() {
+            let error = caught_error(&mut scope, name);
+            scope.reset();
+            backup.rollback(&mut scope, target);
+            scope.reset();
+            return Err(error);
+        }
+
+        Ok(())
+    }
+
+    /// Serialize a value using v8's structured clone format
+    pub fn serialize_value(&mut self, value: &v8::Global<v8::Value>) -> Result<Vec<u8>, Error> {
+        let mut scope = self.deno_runtime().handle_scope();
+        let mut scope = v8::TryCatch::new(&mut scope);
+
+        let value = v8::Local::new(&mut scope, value);
+        let mut serializer = v8::ValueSerializer::new(&mut scope, Box::new(StateSerializer));
+        serializer.write_header();
+        let context = scope.get_current_context();
+        match serializer.write_value(context, value) {
+            Some(true) => Ok(serializer.release()),
+            _ => {
+                let msg = scope
+                    .message()
+                    .map(|m| m.get(&mut scope).to_rust_string_lossy(&mut scope))
+                    .unwrap_or_default();
+                Err(Error::Runtime(format!(
+                    "Could not serialize the value: {msg}"
+                )))
+            }
+        }
+    }
+
+    /// Deserialize a value from the output of [`InnerRuntime::serialize_value`]
+    pub fn deserialize_value(&mut self, bytes: &[u8]) -> Result<v8::Global<v8::Value>, Error> {
+        let mut scope = self.deno_runtime().handle_scope();
+        let mut scope = v8::TryCatch::new(&mut scope);
+
+        let context = scope.get_current_context();
+        let mut deserializer =
+            v8::ValueDeserializer::new(&mut scope, Box::new(StateDeserializer), bytes);
+        let value = match deserializer.read_header(context) {
+            Some(true) => deserializer.read_value(context),
+            _ => None,
+        };
+        drop(deserializer);
+
+        match value {
+            Some(value) => Ok(v8::Global::new(&mut scope, value)),
+            None => Err(Error::Runtime("Invalid serialized value".to_string())),
+        }
+    }
+
+    /// Calls a method of an object with no arguments, without resolving the result
+    pub fn call_method(
+        &mut self,
+        object: &v8::Global<v8::Value>,
+        name: &str,
+    ) -> Result<v8::Global<v8::Value>, Error> {
+        self.freeze_globals_if_pending()?;
+        self.last_exception = None;
+        let mut exception = None;
+        let result = {
+            let mut scope = self.deno_runtime().handle_scope();
+            let mut scope = v8::TryCatch::new(&mut scope);
+
+            let object = v8::Local::new(&mut scope, object);
+            let key = name.to_v8_string(&mut scope)?;
+            let method = object
+                .to_object(&mut scope)
+                .and_then(|o| o.get(&mut scope, key.into()))
+                .and_then(|f| v8::Local::<v8::Function>::try_from(f).ok())
+                .ok_or_else(|| Error::ValueNotCallable(name.to_string()))?;
+
+            match method.call(&mut scope, object, &[]) {
+                Some(value) => Ok(v8::Global::new(&mut scope, value)),
+                None => match scope.exception() {
+                    Some(thrown) => {
+                        exception = Some(v8::Global::new(&mut scope, thrown));
+                        Err(Error::JsError(
+                            deno_core::error::JsError::from_v8_exception(&mut scope, thrown),
+                        ))
+                    }
+                    None => Err(Error::Runtime("Execution terminated".to_string())),
+                },
+            }
+        };
+
+        self.last_exception = exception;
+        result
+    }
+
+    /// Reads an iterator result object - `{ done, value }`
+    /// Returns `None` if the iterator is done
+    pub fn iterator_result(
+        &mut self,
+        result: v8::Global<v8::Value>,
+    ) -> Result<Option<v8::Global<v8::Value>>, Error> {
+        let mut scope = self.deno_runtime().handle_scope();
+        let result = v8::Local::new(&mut scope, result);
+        let result = result
+            .to_object(&mut scope)
+            .ok_or_else(|| Error::Runtime("Iterator result is not an object".to_string()))?;
+
+        let done = "done".to_v8_string(&mut scope)?;
+        let done = result
+            .get(&mut scope, done.into())
+            .is_some_and(|done| done.boolean_value(&mut scope));
+        if done {
+            return Ok(None);
+        }
+
+        let value = "value".to_v8_string(&mut scope)?;
+        let value = result
+            .get(&mut scope, value.into())
+            .unwrap_or_else(|| v8::undefined(&mut scope).into());
+        Ok(Some(v8::Global::new(&mut scope, value)))
+    }
+
     pub async fn resolve_with_event_loop(
         &mut self,
         value: v8::Global<v8::Value>,
     ) -> Result<v8::Global<v8::Value>, Error> {
-        let future = self.deno_runtime().resolve(value);
+        let future = self.deno_runtime().resolve(value.clone());
         let result = self
             .deno_runtime()
             .with_event_loop_future(future, PollEventLoopOptions::default())
-            .await?;
-        Ok(result)
+            .await;
+        if result.is_err() {
+            self.record_rejection(&value);
+        }
+
+        Ok(result?)
+    }
+
+    /// Records the reason a promise was rejected as the last exception
+    fn record_rejection(&mut self, value: &v8::Global<v8::Value>) {
+        let mut scope = self.deno_runtime().handle_scope();
+        let value = v8::Local::new(&mut scope, value);
+        let Ok(promise) = v8::Local::<v8::Promise>::try_from(value) else {
+            return;
+        };
+
+        if promise.state() == v8::PromiseState::Rejected {
+            let reason = promise.result(&mut scope);
+            let reason = v8::Global::new(&mut scope, reason);
+            drop(scope);
+            self.last_exception = Some(reason);
+        }
+    }
+
+    /// Records the value a module threw while it was evaluated as the last exception
+    ///
+    /// Importing a module that failed to evaluate rejects with the same value, so it is recovered that way.  
+    /// This is best-effort - nothing is recorded if the import cannot run, such as after a termination
+    async fn record_module_exception(&mut self, specifier: &ModuleSpecifier) {
+        let Ok(specifier) = serde_json::to_string(specifier.as_str()) else {
+            return;
+        };
+        let Ok(promise) = self.deno_runtime().execute_script(
+            "ext:rustyscript/module_exception.js",
+            format!("import({specifier}).then(() => undefined, (e) => e)"),
+        ) else {
+            return;
+        };
+
+        let future = self.deno_runtime().resolve(promise);
+        if let Ok(reason) = self
+            .deno_runtime()
+            .with_event_loop_future(future, PollEventLoopOptions::default())
+            .await
+        {
+            self.last_exception = Some(reason);
+        }
+    }
+
+    /// Takes the value thrown by the most recent failed call, if it was recorded
+    ///
+    /// Cleared as each call starts. Set by exceptions thrown by evaluated code, called functions and
+    /// loaded modules, and by rejected promises they return
+    pub fn take_last_exception(&mut self) -> Option<v8::Global<v8::Value>> {
+        self.last_exception.take()
+    }
+
+    /// Resolves a set of values together, running the event loop only once
+    /// Values that are already errors are passed through untouched
+    pub async fn resolve_all_with_event_loop(
+        &mut self,
+        values: Vec<Result<v8::Global<v8::Value>, Error>>,
+    ) -> Vec<Result<v8::Global<v8::Value>, Error>> {
+        let futures: Vec<_> = values
+            .into_iter()
+            .map(|value| {
+                let future = value.map(|value| self.deno_runtime().resolve(value));
+                async move {
+                    match future {
+                        Ok(future) => future.await.map_err(Error::from),
+                        Err(e) => Err(e),
+                    }
+                }
+            })
+            .collect();
+
+        let n_values = futures.len();
+        let future = Box::pin(async move {
+            Ok::<_, deno_core::error::AnyError>(deno_core::futures::future::join_all(futures).await)
+        });
+        match self
+            .deno_runtime()
+            .with_event_loop_future(future, PollEventLoopOptions::default())
+            .await
+        {
+            Ok(results) => results,
+            Err(e) => {
+                let e = Error::from(e);
+                vec![Err(e); n_values]
+            }
+        }
     }
 
     pub fn decode_value<T>(&mut self, value: v8::Global<v8::Value>) -> Result<T, Error>
     where
         T: DeserializeOwned,
     {
+        let max_string_bytes = self.max_string_bytes;
+        let max_result_size = self.max_result_size;
         let mut scope = self.deno_runtime().handle_scope();
         let result = v8::Local::<v8::Value>::new(&mut scope, value);
+        if let Some(max) = max_string_bytes {
+            check_string_sizes(&mut scope, result, max, 0, &mut IdentityMap::default())?;
+        }
+        if let Some(max) = max_result_size {
+            check_result_size(
+                &mut scope,
+                result,
+                max,
+                &mut 0,
+                0,
+                &mut IdentityMap::default(),
+            )?;
+        }
+
+        let _active = crate::js_value::ActiveRuntime::enter(&mut scope);
         Ok(from_v8(&mut scope, result)?)
     }
 
@@ -560,6 +2737,40 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         Ok(v8::Global::<v8::Function>::new(&mut scope, f))
     }
 
+    /// Retrieves the default export of a module as a function
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle to a loaded module
+    ///
+    /// # Returns
+    /// A `Result` containing a `v8::Global<v8::Function>` if the default export
+    /// is callable, or [`Error::DefaultExportNotCallable`] if it is missing or not a function
+    pub fn get_default_function(
+        &mut self,
+        module_context: &ModuleHandle,
+    ) -> Result<v8::Global<v8::Function>, Error> {
+        let value = self.get_module_export_value(module_context, "default").ok();
+
+        let mut scope = self.deno_runtime().handle_scope();
+        let Some(value) = value else {
+            return Err(Error::DefaultExportNotCallable(
+                module_context.module().clone(),
+                "undefined".to_string(),
+            ));
+        };
+
+        let local_value = v8::Local::<v8::Value>::new(&mut scope, value);
+        match v8::Local::<v8::Function>::try_from(local_value) {
+            Ok(f) => Ok(v8::Global::<v8::Function>::new(&mut scope, f)),
+            Err(_) => Err(Error::DefaultExportNotCallable(
+                module_context.module().clone(),
+                local_value
+                    .type_of(&mut scope)
+                    .to_rust_string_lossy(&mut scope),
+            )),
+        }
+    }
+
     pub fn call_function_by_ref(
         &mut self,
         module_context: Option<&ModuleHandle>,
@@ -576,57 +2787,224 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
             None
         };
 
-        let mut scope = self.deno_runtime().handle_scope();
-        let mut scope = v8::TryCatch::new(&mut scope);
+        self.call_function_with_namespace(module_namespace.as_ref(), function, args, None)
+    }
+
+    /// Calls a function using an already-resolved module namespace as the receiver
+    fn call_function_with_namespace(
+        &mut self,
+        module_namespace: Option<&v8::Global<v8::Object>>,
+        function: &v8::Global<v8::Function>,
+        args: &impl serde::ser::Serialize,
+        arg_buffer: Option<&RefCell<ArgBuffer>>,
+    ) -> Result<v8::Global<v8::Value>, Error> {
+        self.freeze_globals_if_pending()?;
+        self.last_exception = None;
+        let mut terminated = false;
+        let mut exception = None;
+        let result = {
+            let mut scope = self.deno_runtime().handle_scope();
+            let mut scope = v8::TryCatch::new(&mut scope);
+
+            // Get the namespace
+            // Module-level if supplied, none otherwise
+            let namespace: v8::Local<v8::Value> = if let Some(namespace) = module_namespace {
+                v8::Local::<v8::Object>::new(&mut scope, namespace).into()
+            } else {
+                // Create a new object to use as the namespace if none is provided
+                //let obj: v8::Local<v8::Value> = v8::Object::new(&mut scope).into();
+                let obj: v8::Local<v8::Value> = v8::undefined(&mut scope).into();
+                obj
+            };
+
+            let function_instance = function.open(&mut scope);
+
+            // Prep arguments, reusing the storage of a previous call if there is one
+            // The buffer is not held during the call, in case the function calls back into the same slot
+            let storage = arg_buffer
+                .and_then(|buffer| buffer.try_borrow_mut().ok())
+                .map(|mut buffer| buffer.take())
+                .unwrap_or_default();
+            let args = decode_args(args, &mut scope, storage)?;
+
+            // Call the function
+            let result = function_instance.call(&mut scope, namespace, &args);
+            if let Some(mut buffer) = arg_buffer.and_then(|buffer| buffer.try_borrow_mut().ok()) {
+                buffer.restore(args);
+            }
+            match result {
+                Some(value) => {
+                    let value = v8::Global::new(&mut scope, value);
+                    Ok(value)
+                }
+                None if scope.has_terminated() => {
+                    terminated = true;
+                    Err(Error::Runtime("Execution terminated".to_string()))
+                }
+                None => match scope.exception() {
+                    Some(thrown) => {
+                        exception = Some(v8::Global::new(&mut scope, thrown));
+                        Err(Error::JsError(
+                            deno_core::error::JsError::from_v8_exception(&mut scope, thrown),
+                        ))
+                    }
+                    None => Err(Error::Runtime(
+                        "Unknown error during function execution".to_string(),
+                    )),
+                },
+            }
+        };
+
+        self.terminated |= terminated;
+        self.last_exception = exception;
+        result
+    }
 
-        // Get the namespace
-        // Module-level if supplied, none otherwise
-        let namespace: v8::Local<v8::Value> = if let Some(namespace) = module_namespace {
-            v8::Local::<v8::Object>::new(&mut scope, namespace).into()
+    /// Resolves a function by name once, returning a slot that can be called repeatedly
+    /// without repeating the lookup
+    pub fn prepare_call(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+    ) -> Result<CallSlot, Error> {
+        let module_context = module_context.map(|m| self.recovered_module(m).clone());
+        let module_context = module_context.as_ref();
+        let function = self.get_function_by_name(module_context, name)?;
+        let namespace = if let Some(module_context) = module_context {
+            Some(
+                self.deno_runtime()
+                    .get_module_namespace(module_context.id())?,
+            )
         } else {
-            // Create a new object to use as the namespace if none is provided
-            //let obj: v8::Local<v8::Value> = v8::Object::new(&mut scope).into();
-            let obj: v8::Local<v8::Value> = v8::undefined(&mut scope).into();
-            obj
+            None
         };
 
-        let function_instance = function.open(&mut scope);
+        Ok(CallSlot::new(
+            name,
+            module_context,
+            function,
+            namespace,
+            &self.call_slot_owner,
+        ))
+    }
 
-        // Prep arguments
-        let args = decode_args(args, &mut scope)?;
+    /// Calls a function previously resolved with [`InnerRuntime::prepare_call`]
+    pub fn call_slot(
+        &mut self,
+        slot: &CallSlot,
+        args: &impl serde::ser::Serialize,
+    ) -> Result<v8::Global<v8::Value>, Error> {
+        if !slot.is_valid_for(&self.call_slot_owner) {
+            return Err(Error::Runtime(format!(
+                "Call slot for `{}` is no longer valid for this runtime",
+                slot.name()
+            )));
+        }
 
-        // Call the function
-        let result = function_instance.call(&mut scope, namespace, &args);
-        match result {
-            Some(value) => {
-                let value = v8::Global::new(&mut scope, value);
-                Ok(value)
-            }
-            None if scope.has_caught() => {
-                let e = scope
-                    .message()
-                    .ok_or_else(|| Error::Runtime("Unknown error".to_string()))?;
-
-                let filename = e.get_script_resource_name(&mut scope);
-                let linenumber = e.get_line_number(&mut scope).unwrap_or_default();
-                let filename = if let Some(v) = filename {
-                    let filename = v.to_rust_string_lossy(&mut scope);
-                    format!("{filename}:{linenumber}: ")
-                } else if let Some(module_context) = module_context {
-                    let filename = module_context.module().filename().to_string_lossy();
-                    format!("{filename}:{linenumber}: ")
-                } else {
-                    String::new()
-                };
+        self.call_function_with_namespace(
+            slot.namespace(),
+            slot.function(),
+            args,
+            Some(slot.args()),
+        )
+    }
+
+    /// Clears a termination of the isolate's execution, if one occurred
+    /// Returns true if the isolate was terminated, and can now be used again
+    pub fn recover_isolate(&mut self) -> bool {
+        let terminated = std::mem::take(&mut self.terminated);
+        let isolate = self.deno_runtime().v8_isolate();
+        if terminated || isolate.is_execution_terminating() {
+            isolate.cancel_terminate_execution();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the module that replaced `handle` after a recovery, or `handle` itself if it was never reloaded
+    pub fn recovered_module<'a>(&'a self, handle: &'a ModuleHandle) -> &'a ModuleHandle {
+        self.recovered_modules.get(&handle.id()).unwrap_or(handle)
+    }
+
+    /// Loads a fresh instance of a module after a recovery, which replaces the one originally loaded as `original`
+    ///
+    /// The new instance is loaded under its own specifier, so that `deno_core` does not reuse the old one  
+    /// Call slots prepared before the reload are invalidated, since they still refer to the old instance  
+    /// Modules without source, such as those from a snapshot, cannot be reloaded and are kept as they are
+    pub async fn reload_module(&mut self, original: &ModuleHandle) -> Result<ModuleHandle, Error> {
+        let current = self.recovered_module(original).clone();
+        if current.module().contents().is_empty() {
+            return Ok(current);
+        }
+
+        self.recoveries += 1;
+        self.call_slot_owner = Rc::new(());
+        let mut specifier = self.module_specifier(current.module().filename())?;
+        specifier.set_query(Some(&format!("rustyscript-recovery={}", self.recoveries)));
+
+        let (handle, _) = self
+            .load_side_module_at(current.module(), specifier, false)
+            .await?;
+        let handle = self.finish_load(handle)?;
+        self.recovered_modules.insert(original.id(), handle.clone());
+        Ok(handle)
+    }
+
+    /// Returns the id and name of the runtime
+    pub fn info(&self) -> &RuntimeInfo {
+        &self.info
+    }
 
-                let msg = e.get(&mut scope).to_rust_string_lossy(&mut scope);
+    /// Returns the log of console errors and warnings, if `fail_on_console_error` is set
+    pub fn console_errors(&self) -> Option<ext::rustyscript::ConsoleErrorLog> {
+        self.console_errors.clone()
+    }
+
+    /// Returns the budget of promise reactions per turn of the event loop, if `microtask_limit` is set
+    pub fn microtask_budget(&self) -> Option<ext::rustyscript::MicrotaskBudget> {
+        self.microtask_budget.clone()
+    }
 
-                let s = format!("{filename}{msg}");
-                Err(Error::Runtime(s))
+    /// Returns the result of an async call that just finished
+    ///
+    /// If the call used up the microtask budget, [`Error::MicrotaskLimitExceeded`] is returned instead,
+    /// and the termination is cleared - so that the next call, blocking or not, is not affected by it
+    pub fn settle_microtask_budget<T>(&mut self, result: Result<T, Error>) -> Result<T, Error> {
+        match self.microtask_budget.clone() {
+            Some(budget) => {
+                let isolate = self.isolate_handle();
+                budget.settle(&isolate, result)
             }
-            None => Err(Error::Runtime(
-                "Unknown error during function execution".to_string(),
-            )),
+            None => result,
+        }
+    }
+
+    /// Returns a thread-safe handle to the isolate
+    pub fn isolate_handle(&mut self) -> v8::IsolateHandle {
+        self.deno_runtime().v8_isolate().thread_safe_handle()
+    }
+
+    /// Returns a handle that can cancel the running call from another thread
+    pub fn cancellation_handle(&mut self) -> CancellationHandle {
+        let isolate = self.deno_runtime().v8_isolate().thread_safe_handle();
+        CancellationHandle::new(isolate, self.cancelled.clone())
+    }
+
+    /// Clears a cancellation made through a [`CancellationHandle`], if there was one
+    /// Returns true if there was a cancellation, in which case the isolate can now be used again
+    ///
+    /// A cancellation made while nothing was running leaves a pending termination request on the isolate,
+    /// which `is_execution_terminating` does not report - so it is always cancelled here
+    pub fn take_cancellation(&mut self) -> bool {
+        if self.cancelled.swap(false, Ordering::SeqCst) {
+            self.terminated = false;
+            self.deno_runtime()
+                .v8_isolate()
+                .cancel_terminate_execution();
+            true
+        } else {
+            false
         }
     }
 
@@ -664,14 +3042,8 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
                 return Poll::Ready(Err(e.into()));
             }
 
-            if self
-                .deno_runtime()
-                .poll_event_loop(cx, poll_options)
-                .is_ready()
-            {
-                // Event loop resolved - continue
-                println!("Event loop resolved");
-            }
+            // Event loop resolved or not - keep polling the future either way
+            let _ = self.deno_runtime().poll_event_loop(cx, poll_options);
 
             Poll::Pending
         })
@@ -714,6 +3086,16 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
         Ok(None)
     }
 
+    /// Returns the name of a function, or `None` if it is anonymous
+    fn function_name(&mut self, function: &v8::Global<v8::Function>) -> Option<String> {
+        let mut scope = self.deno_runtime().handle_scope();
+        let function = v8::Local::new(&mut scope, function);
+        let name = function
+            .get_name(&mut scope)
+            .to_rust_string_lossy(&mut scope);
+        (!name.is_empty()).then_some(name)
+    }
+
     /// Load one or more modules
     /// Returns a future that resolves to a handle to the main module, or the last
     /// side-module
@@ -722,53 +3104,37 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
     /// side-module
     pub async fn load_modules(
         &mut self,
-        main_module: Option<&Module>,
-        side_modules: Vec<&Module>,
-    ) -> Result<ModuleHandle, Error> {
-        if main_module.is_none() && side_modules.is_empty() {
-            return Err(Error::Runtime(
-                "Internal error: attempt to load no modules".to_string(),
-            ));
-        }
-
-        let mut module_handle_stub = ModuleHandle::default();
-
-        // Get additional modules first
-        for side_module in side_modules {
-            let module_specifier = side_module.filename().to_module_specifier(&self.cwd)?;
-            let (code, sourcemap) = transpile(&module_specifier, side_module.contents())?;
-
-            // Now CJS translation, for node
-            #[cfg(feature = "node_experimental")]
-            let code = self
-                .module_loader
-                .translate_cjs(&module_specifier, &code)
-                .await?;
-
-            let fast_code = deno_core::FastString::from(code.clone());
-
-            let s_modid = self
-                .deno_runtime()
-                .load_side_es_module_from_code(&module_specifier, fast_code)
-                .await?;
+        main_module: Option<&Module>,
+        side_modules: Vec<&Module>,
+    ) -> Result<ModuleHandle, Error> {
+        if main_module.is_none() && side_modules.is_empty() {
+            return Err(Error::Runtime(
+                "Internal error: attempt to load no modules".to_string(),
+            ));
+        }
+        self.last_exception = None;
 
-            // Update source map cache
-            self.module_loader.insert_source_map(
-                module_specifier.as_str(),
-                code,
-                sourcemap.map(|s| s.to_vec()),
-            );
+        let mut module_handle_stub = ModuleHandle::default();
 
-            let mod_load = self.deno_runtime().mod_evaluate(s_modid);
-            self.with_event_loop_future(mod_load, PollEventLoopOptions::default())
-                .await?;
-            module_handle_stub = ModuleHandle::new(side_module, s_modid, None);
+        // Get additional modules first
+        for side_module in side_modules {
+            (module_handle_stub, _) = self.load_side_module(side_module, false).await?;
         }
 
         // Load main module
         if let Some(module) = main_module {
-            let module_specifier = module.filename().to_module_specifier(&self.cwd)?;
-            let (code, sourcemap) = transpile(&module_specifier, module.contents())?;
+            let start = std::time::Instant::now();
+            let module_specifier = self.module_specifier(module.filename())?;
+            let contents = self
+                .module_loader
+                .transform_source(&module_specifier, module.contents());
+            let (code, sourcemap) = transpile_if(
+                &module_specifier,
+                &contents,
+                module.language(),
+                self.transpile,
+                &self.transpile_options,
+            )?;
 
             // Now CJS translation, for node
             #[cfg(feature = "node_experimental")]
@@ -783,6 +3149,8 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
                 .deno_runtime()
                 .load_main_es_module_from_code(&module_specifier, fast_code)
                 .await?;
+            self.module_loader
+                .report_load(&module_specifier, start, code.len());
 
             // Update source map cache
             self.module_loader.insert_source_map(
@@ -793,19 +3161,205 @@ impl<RT: RuntimeTrait> InnerRuntime<RT> {
 
             // Finish execution
             let mod_load = self.deno_runtime().mod_evaluate(module_id);
-            self.with_event_loop_future(mod_load, PollEventLoopOptions::default())
-                .await?;
-            module_handle_stub = ModuleHandle::new(module, module_id, None);
+            let result = self
+                .with_event_loop_future(mod_load, PollEventLoopOptions::default())
+                .await;
+            if let Err(e) = result {
+                self.record_module_exception(&module_specifier).await;
+                return Err(e);
+            }
+            self.store_module_namespace(module, module_id)?;
+            module_handle_stub =
+                ModuleHandle::new(module, module_id, None).with_specifier(module_specifier);
+        }
+
+        self.finish_load(module_handle_stub)
+    }
+
+    /// Load a side module, returning a handle to it and the completion value of its evaluation
+    ///
+    /// A module without any `import` or `export` statements is evaluated as a classic script in strict mode, whose
+    /// completion value is its last expression - its declarations stay local to it, instead of becoming globals.
+    /// Any other module completes with `undefined`
+    pub async fn load_module_with_completion(
+        &mut self,
+        module: &Module,
+    ) -> Result<(ModuleHandle, v8::Global<v8::Value>), Error> {
+        self.last_exception = None;
+        let (handle, completion) = self.load_side_module(module, true).await?;
+        let handle = self.finish_load(handle)?;
+
+        let completion = completion.unwrap_or_else(|| {
+            let mut scope = self.deno_runtime().handle_scope();
+            let undefined: v8::Local<v8::Value> = v8::undefined(&mut scope).into();
+            v8::Global::new(&mut scope, undefined)
+        });
+        Ok((handle, completion))
+    }
+
+    /// Load and evaluate a single side module, returning a stub handle without an entrypoint
+    ///
+    /// If `capture_completion` is set and the module is a classic script, it is run through a strict indirect `eval`
+    /// instead, and its completion value is returned alongside the handle - an empty module stands in for it
+    async fn load_side_module(
+        &mut self,
+        module: &Module,
+        capture_completion: bool,
+    ) -> Result<(ModuleHandle, Option<v8::Global<v8::Value>>), Error> {
+        let module_specifier = self.module_specifier(module.filename())?;
+        self.load_side_module_at(module, module_specifier, capture_completion)
+            .await
+    }
+
+    /// Load and evaluate a single side module under the given specifier - see [`InnerRuntime::load_side_module`]
+    async fn load_side_module_at(
+        &mut self,
+        module: &Module,
+        module_specifier: ModuleSpecifier,
+        capture_completion: bool,
+    ) -> Result<(ModuleHandle, Option<v8::Global<v8::Value>>), Error> {
+        let start = std::time::Instant::now();
+        let contents = self
+            .module_loader
+            .transform_source(&module_specifier, module.contents());
+        let (code, sourcemap) = transpile_if(
+            &module_specifier,
+            &contents,
+            module.language(),
+            self.transpile,
+            &self.transpile_options,
+        )?;
+
+        // Now CJS translation, for node
+        #[cfg(feature = "node_experimental")]
+        let code = self
+            .module_loader
+            .translate_cjs(&module_specifier, &code)
+            .await?;
+
+        // Strict mode keeps the script's declarations local to its `eval`, instead of leaking them into the globals
+        // It keeps its own name and line numbers in stack traces, so the source map cache still applies
+        let script = capture_completion
+            .then(|| format!("'use strict';{code}\n//# sourceURL={module_specifier}"))
+            .filter(|script| self.is_classic_script(script));
+        let completion = match script {
+            Some(script) => {
+                let script = serde_json::to_string(&script)?;
+                Some(self.eval(format!("(0, eval)({script})")).await?)
+            }
+            None => None,
+        };
+        let module_code = if completion.is_some() {
+            String::new()
+        } else {
+            code.clone()
+        };
+
+        let bytes = code.len();
+        let s_modid = self
+            .deno_runtime()
+            .load_side_es_module_from_code(&module_specifier, module_code.into())
+            .await?;
+        self.module_loader
+            .report_load(&module_specifier, start, bytes);
+
+        // Update source map cache
+        self.module_loader.insert_source_map(
+            module_specifier.as_str(),
+            code,
+            sourcemap.map(|s| s.to_vec()),
+        );
+
+        let mod_load = self.deno_runtime().mod_evaluate(s_modid);
+        let result = self
+            .with_event_loop_future(mod_load, PollEventLoopOptions::default())
+            .await;
+        if let Err(e) = result {
+            self.record_module_exception(&module_specifier).await;
+            return Err(e);
+        }
+        self.store_module_namespace(module, s_modid)?;
+        let handle = ModuleHandle::new(module, s_modid, None).with_specifier(module_specifier);
+        Ok((handle, completion))
+    }
+
+    /// Returns true if the code compiles as a classic script - that is, it has no `import` or `export` statements
+    ///
+    /// Code with a syntax error, including one only invalid in strict mode, is not a script either,
+    /// so that the error is reported when it is loaded as a module
+    fn is_classic_script(&mut self, code: &str) -> bool {
+        let mut scope = self.deno_runtime().handle_scope();
+        let mut scope = v8::TryCatch::new(&mut scope);
+        let Some(source) = v8::String::new(&mut scope, code) else {
+            return false;
+        };
+        v8::Script::compile(&mut scope, source, None).is_some()
+    }
+
+    /// Freezes the globals, if `freeze_globals` is set and they are not frozen yet
+    ///
+    /// Called once the first modules are in, and before any other javascript runs - whichever comes first
+    fn freeze_globals_if_pending(&mut self) -> Result<(), Error> {
+        if self.freeze_globals_pending {
+            self.freeze_globals_pending = false;
+            self.deno_runtime().execute_script(
+                "ext:rustyscript/freeze_globals.js",
+                ext::rustyscript::FREEZE_GLOBALS_JS,
+            )?;
         }
+        Ok(())
+    }
+
+    /// Freeze the globals if needed, and look up the entrypoint of a freshly loaded module
+    fn finish_load(&mut self, mut module_handle_stub: ModuleHandle) -> Result<ModuleHandle, Error> {
+        self.freeze_globals_if_pending()?;
 
         // Try to get the default entrypoint
         let entrypoint = self.get_module_entrypoint(&mut module_handle_stub)?;
+        let entrypoint_name = entrypoint.as_ref().and_then(|f| self.function_name(f));
 
         Ok(ModuleHandle::new(
             module_handle_stub.module(),
             module_handle_stub.id(),
             entrypoint,
-        ))
+        )
+        .with_specifier(module_handle_stub.specifier())
+        .with_entrypoint_name(entrypoint_name))
+    }
+
+    /// Get a handle to a module that is already loaded - such as one included in the startup snapshot
+    ///
+    /// Modules already in the runtime are not executed again, and any other module is not loaded -
+    /// [`Error::ModuleNotFound`] is returned instead
+    ///
+    /// The module's source is not retained, so the handle's [`Module`] will have empty contents
+    pub async fn get_module_handle(&mut self, filename: &Path) -> Result<ModuleHandle, Error> {
+        let module_specifier = self.module_specifier(filename)?;
+
+        self.module_loader
+            .begin_loaded_only(module_specifier.clone());
+        let module_id = self
+            .deno_runtime()
+            .load_side_es_module(&module_specifier)
+            .await;
+        if self.module_loader.end_loaded_only() {
+            return Err(Error::ModuleNotFound(module_specifier.to_string()));
+        }
+        let module_id = module_id?;
+
+        let mod_load = self.deno_runtime().mod_evaluate(module_id);
+        self.with_event_loop_future(mod_load, PollEventLoopOptions::default())
+            .await?;
+
+        let module = Module::new(filename, "");
+        self.store_module_namespace(&module, module_id)?;
+
+        let mut module_handle = ModuleHandle::new(&module, module_id, None);
+        let entrypoint = self.get_module_entrypoint(&mut module_handle)?;
+        let entrypoint_name = entrypoint.as_ref().and_then(|f| self.function_name(f));
+        Ok(ModuleHandle::new(&module, module_id, entrypoint)
+            .with_specifier(module_specifier)
+            .with_entrypoint_name(entrypoint_name))
     }
 }
 
@@ -847,6 +3401,35 @@ mod test_inner_runtime {
         };
     }
 
+    #[test]
+    fn test_runtime_profile() {
+        let mut options = RuntimeOptions {
+            timeout: Duration::from_secs(5),
+            freeze_globals: true,
+            module_load_observer: Some(Box::new(|_: &crate::module_loader::ModuleLoadEvent| {})),
+            ..Default::default()
+        };
+        options
+            .path_aliases
+            .insert("@app/*".to_string(), PathBuf::from("src/*"));
+
+        let json = serde_json::to_string(&options.profile()).expect("Could not serialize");
+        let profile: RuntimeProfile = serde_json::from_str(&json).expect("Could not deserialize");
+        assert_eq!(profile, options.profile());
+
+        // Callbacks are not carried over
+        let restored = RuntimeOptions::from(profile);
+        assert_eq!(restored.timeout, Duration::from_secs(5));
+        assert!(restored.freeze_globals);
+        assert!(restored.module_load_observer.is_none());
+
+        // Missing fields take their defaults
+        let profile: RuntimeProfile =
+            serde_json::from_str(r#"{ "auto_recover": true }"#).expect("Could not deserialize");
+        assert!(profile.auto_recover);
+        assert_eq!(profile.timeout, Duration::MAX);
+    }
+
     #[test]
     fn test_decode_args() {
         let mut runtime =
@@ -855,29 +3438,34 @@ mod test_inner_runtime {
         let mut scope = runtime.deno_runtime.handle_scope();
 
         // empty
-        let args = decode_args(&json_args!(), &mut scope).expect("Could not decode args");
+        let args =
+            decode_args(&json_args!(), &mut scope, Vec::new()).expect("Could not decode args");
         assert_eq!(args.len(), 0);
 
         // single
-        let args = decode_args(&json_args!(2), &mut scope).expect("Could not decode args");
+        let args =
+            decode_args(&json_args!(2), &mut scope, Vec::new()).expect("Could not decode args");
         assert_eq!(args.len(), 1);
 
         // single raw
-        let args = decode_args(&2, &mut scope).expect("Could not decode args");
+        let args = decode_args(&2, &mut scope, Vec::new()).expect("Could not decode args");
         assert_eq!(args.len(), 1);
 
         // multiple heterogeneous
-        let args = decode_args(&json_args!(2, "test"), &mut scope).expect("Could not decode args");
+        let args = decode_args(&json_args!(2, "test"), &mut scope, Vec::new())
+            .expect("Could not decode args");
         assert_eq!(args.len(), 2);
 
         // multiple homogeneous
-        let args = decode_args(&json_args!(2, 3), &mut scope).expect("Could not decode args");
+        let args =
+            decode_args(&json_args!(2, 3), &mut scope, Vec::new()).expect("Could not decode args");
         assert_eq!(args.len(), 2);
 
         // 16 args
         let args = decode_args(
             &(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15),
             &mut scope,
+            Vec::new(),
         )
         .expect("Could not decode args");
         assert_eq!(args.len(), 16);
@@ -889,9 +3477,19 @@ mod test_inner_runtime {
                 10, 11, 12, 13, 14, 15
             ),
             &mut scope,
+            Vec::new(),
         )
         .expect("Could not decode args");
         assert_eq!(args.len(), 32);
+
+        // storage is reused, and cleared by the caller
+        let mut storage = args;
+        storage.clear();
+        let capacity = storage.capacity();
+        let args =
+            decode_args(&json_args!(1, 2), &mut scope, storage).expect("Could not decode args");
+        assert_eq!(args.len(), 2);
+        assert_eq!(args.capacity(), capacity);
     }
 
     #[test]
@@ -955,6 +3553,88 @@ mod test_inner_runtime {
         });
     }
 
+    #[test]
+    fn test_registered_function_errors() {
+        let mut runtime =
+            InnerRuntime::<JsRuntime>::new(RuntimeOptions::default(), CancellationToken::new())
+                .expect("Could not load runtime");
+        runtime
+            .register_function("fail", |_| Err(Error::Runtime("sync failure".to_string())))
+            .expect("Could not register function");
+        runtime
+            .register_async_function("fail", |_| {
+                Box::pin(async { Err(Error::Runtime("async failure".to_string())) })
+            })
+            .expect("Could not register function");
+
+        let module = Module::new(
+            "test.js",
+            "
+            export let sync_error;
+            try {
+                rustyscript.functions.fail();
+            } catch (e) {
+                sync_error = (e instanceof Error) ? e.message : 'not an error';
+            }
+
+            export let async_error;
+            try {
+                await rustyscript.async_functions.fail();
+            } catch (e) {
+                async_error = (e instanceof Error) ? e.message : 'not an error';
+            }
+            ",
+        );
+
+        let rt = &mut runtime;
+        let module = run_async_task(|| async move { rt.load_modules(Some(&module), vec![]).await });
+
+        let result = runtime
+            .get_value_ref(Some(&module), "sync_error")
+            .expect("Could not find value");
+        assert_v8!(result, "sync failure", String, runtime);
+
+        let result = runtime
+            .get_value_ref(Some(&module), "async_error")
+            .expect("Could not find value");
+        assert_v8!(result, "async failure", String, runtime);
+    }
+
+    #[test]
+    fn test_register_module_function() {
+        let mut runtime =
+            InnerRuntime::<JsRuntime>::new(RuntimeOptions::default(), CancellationToken::new())
+                .expect("Could not load runtime");
+        runtime
+            .register_module_function("test", |_, modules| {
+                assert!(!modules.has_module("missing.js"));
+                modules
+                    .get::<i64>("missing.js", "a")
+                    .expect_err("Found a module that was never loaded");
+                modules
+                    .get::<i64>("a.js", "missing")
+                    .expect_err("Found an export that does not exist");
+
+                let a: i64 = modules.get("a.js", "a")?;
+                let b: i64 = modules.get("b.js", "b")?;
+                Ok(serde_json::Value::from(a + b))
+            })
+            .expect("Could not register function");
+
+        let a = Module::new("a.js", "export const a = 2;");
+        let b = Module::new("b.js", "export const b = 3;");
+        let module = Module::new("test.js", "export const v = rustyscript.functions.test();");
+
+        let rt = &mut runtime;
+        let module =
+            run_async_task(|| async move { rt.load_modules(Some(&module), vec![&a, &b]).await });
+
+        let result = runtime
+            .get_value_ref(Some(&module), "v")
+            .expect("Could not find value");
+        assert_v8!(result, 5, usize, runtime);
+    }
+
     #[cfg(any(feature = "web", feature = "web_stub"))]
     #[test]
     fn test_eval() {
@@ -1006,6 +3686,46 @@ mod test_inner_runtime {
         });
     }
 
+    #[cfg(any(feature = "web_stub", feature = "web"))]
+    #[test]
+    fn test_structured_clone() {
+        let mut runtime =
+            InnerRuntime::<JsRuntime>::new(RuntimeOptions::default(), CancellationToken::new())
+                .expect("Could not load runtime");
+
+        run_async_task(|| async move {
+            let result = runtime
+                .eval(
+                    "
+                    const original = {
+                        map: new Map([['a', 1]]),
+                        set: new Set([1, 2]),
+                        date: new Date(0),
+                        bytes: new Uint8Array([1, 2, 3]),
+                    };
+                    const copy = structuredClone(original);
+                    copy !== original
+                        && copy.map.get('a') === 1
+                        && copy.set.has(2)
+                        && copy.date instanceof Date && copy.date.getTime() === 0
+                        && copy.bytes instanceof Uint8Array && copy.bytes[2] === 3
+                        && copy.bytes.buffer !== original.bytes.buffer
+                ",
+                )
+                .await
+                .expect("failed to eval");
+            assert_v8!(result, true, bool, runtime);
+
+            let result = runtime
+                .eval("try { structuredClone(() => 1); false } catch (e) { e.name === 'DataCloneError' }")
+                .await
+                .expect("failed to eval");
+            assert_v8!(result, true, bool, runtime);
+
+            Ok(())
+        });
+    }
+
     #[test]
     fn test_get_value_ref() {
         let module = Module::new(
@@ -1132,6 +3852,241 @@ mod test_inner_runtime {
         });
     }
 
+    #[test]
+    fn test_call_slot() {
+        let module = Module::new(
+            "test.js",
+            "
+            export function add(a, b) { return a + b; }
+            export const will_err = () => { throw new Error('msg'); };
+        ",
+        );
+
+        run_async_task(|| async move {
+            let mut runtime =
+                InnerRuntime::<JsRuntime>::new(RuntimeOptions::default(), CancellationToken::new())
+                    .expect("Could not load runtime");
+            let handle = runtime.load_modules(Some(&module), vec![]).await?;
+
+            let slot = runtime.prepare_call(Some(&handle), "add")?;
+            for i in 0..10 {
+                let result = runtime
+                    .call_slot(&slot, json_args!(i, 1))
+                    .expect("Could not call slot");
+                assert_v8!(result, i + 1, usize, runtime);
+            }
+
+            let slot = runtime.prepare_call(Some(&handle), "will_err")?;
+            runtime
+                .call_slot(&slot, json_args!())
+                .expect_err("Did not catch error");
+
+            runtime
+                .prepare_call(Some(&handle), "missing")
+                .expect_err("Did not detect missing function");
+
+            // Slots cannot be used by another runtime
+            let slot = runtime.prepare_call(Some(&handle), "add")?;
+            let mut other =
+                InnerRuntime::<JsRuntime>::new(RuntimeOptions::default(), CancellationToken::new())
+                    .expect("Could not load runtime");
+            other
+                .call_slot(&slot, json_args!(1, 2))
+                .expect_err("Did not detect a slot from another runtime");
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_max_result_size() {
+        let mut runtime = InnerRuntime::<JsRuntime>::new(
+            RuntimeOptions {
+                max_result_size: Some(64),
+                ..Default::default()
+            },
+            CancellationToken::new(),
+        )
+        .expect("Could not load runtime");
+
+        run_async_task(|| async move {
+            let v = runtime.eval("[1, 2, 3]").await?;
+            let v: Vec<usize> = runtime.decode_value(v)?;
+            assert_eq!(v, vec![1, 2, 3]);
+
+            let v = runtime.eval("'a'.repeat(65)").await?;
+            let e = runtime.decode_value::<String>(v).unwrap_err();
+            assert!(matches!(e, Error::ResultTooLarge(65, 64)));
+
+            let v = runtime.eval("Array(100).fill(1)").await?;
+            let e = runtime.decode_value::<Vec<usize>>(v).unwrap_err();
+            assert!(matches!(e, Error::ResultTooLarge(_, 64)));
+
+            let v = runtime.eval("new Uint8Array(100)").await?;
+            let e = runtime.decode_value::<serde_json::Value>(v).unwrap_err();
+            assert!(matches!(e, Error::ResultTooLarge(_, 64)));
+
+            // Buffers, maps and sets are counted too
+            let v = runtime.eval("({ buffer: new ArrayBuffer(100) })").await?;
+            let e = runtime.decode_value::<serde_json::Value>(v).unwrap_err();
+            assert!(matches!(e, Error::ResultTooLarge(_, 64)));
+
+            let v = runtime.eval("new Map([['a', 'b'.repeat(100)]])").await?;
+            let e = runtime.decode_value::<serde_json::Value>(v).unwrap_err();
+            assert!(matches!(e, Error::ResultTooLarge(_, 64)));
+
+            // A shared object counts every time it appears
+            let v = runtime
+                .eval("const shared = { s: 'a'.repeat(30) }; [shared, shared]")
+                .await?;
+            let e = runtime.decode_value::<serde_json::Value>(v).unwrap_err();
+            assert!(matches!(e, Error::ResultTooLarge(_, 64)));
+
+            // Cycles are measured once, and getters are never run
+            let v = runtime
+                .eval("const cycle = {}; cycle.self = cycle; cycle")
+                .await?;
+            runtime.decode_value::<crate::js_value::Value>(v)?;
+
+            let v = runtime
+                .eval("({ get x() { globalThis.ran = true; return 1; } })")
+                .await?;
+            runtime
+                .decode_value::<serde_json::Value>(v)
+                .expect_err("Accessors cannot be measured");
+            let ran = runtime.eval("globalThis.ran === undefined").await?;
+            assert!(runtime.decode_value::<bool>(ran)?);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_max_string_bytes() {
+        let mut runtime = InnerRuntime::<JsRuntime>::new(
+            RuntimeOptions {
+                max_string_bytes: Some(8),
+                ..Default::default()
+            },
+            CancellationToken::new(),
+        )
+        .expect("Could not load runtime");
+
+        run_async_task(|| async move {
+            let v = runtime.eval("'a'.repeat(8)").await?;
+            let v: String = runtime.decode_value(v)?;
+            assert_eq!(v, "aaaaaaaa");
+
+            // 5 UTF-16 code units, but 10 UTF-8 bytes
+            let v = runtime.eval("'é'.repeat(5)").await?;
+            let e = runtime.decode_value::<String>(v).unwrap_err();
+            assert!(matches!(e, Error::StringTooLarge(10, 8)));
+
+            let v = runtime.eval("({ a: [1, 'b'.repeat(9)] })").await?;
+            let e = runtime.decode_value::<serde_json::Value>(v).unwrap_err();
+            assert!(matches!(e, Error::StringTooLarge(9, 8)));
+
+            let v = runtime.eval("new Map([['a', 'b'.repeat(9)]])").await?;
+            let e = runtime.decode_value::<serde_json::Value>(v).unwrap_err();
+            assert!(matches!(e, Error::StringTooLarge(9, 8)));
+
+            // Shared objects are only walked once
+            let v = runtime.eval("const o = { a: 'b' }; [o, o]").await?;
+            let v: serde_json::Value = runtime.decode_value(v)?;
+            assert_eq!(v, serde_json::json!([{ "a": "b" }, { "a": "b" }]));
+
+            // Getters are not run, and values too deep to check are refused
+            let v = runtime
+                .eval("({ get a() { globalThis.ran = true; return 'b'.repeat(9); } })")
+                .await?;
+            runtime.decode_value::<serde_json::Value>(v).unwrap_err();
+            let v = runtime.eval("globalThis.ran ?? false").await?;
+            assert!(!runtime.decode_value::<bool>(v)?);
+
+            let v = runtime
+                .eval("let d = []; for (let i = 0; i < 100; i++) d = [d]; d")
+                .await?;
+            runtime.decode_value::<serde_json::Value>(v).unwrap_err();
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_freeze_globals() {
+        let module = Module::new(
+            "test.js",
+            "
+            globalThis.state = { [Symbol.for('rustyscript.unfrozen')]: true, count: 0 };
+            export const patch = () => { Array.prototype.evil = 1; };
+            export const add_global = () => { globalThis.foo = 1; };
+            export const bump = () => ++state.count;
+            export const call = () => rustyscript.functions.test(2, 3);
+        ",
+        );
+
+        let mut runtime = InnerRuntime::<JsRuntime>::new(
+            RuntimeOptions {
+                freeze_globals: true,
+                ..Default::default()
+            },
+            CancellationToken::new(),
+        )
+        .expect("Could not load runtime");
+        runtime
+            .register_function(
+                "test",
+                sync_callback!(|a: i64, b: i64| { Ok::<i64, Error>(a + b) }),
+            )
+            .expect("Could not register function");
+
+        run_async_task(|| async move {
+            let handle = runtime.load_modules(Some(&module), vec![]).await?;
+
+            let f = runtime.get_function_by_name(Some(&handle), "patch")?;
+            runtime
+                .call_function_by_ref(Some(&handle), &f, json_args!())
+                .expect_err("Could modify a frozen prototype");
+
+            let f = runtime.get_function_by_name(Some(&handle), "add_global")?;
+            runtime
+                .call_function_by_ref(Some(&handle), &f, json_args!())
+                .expect_err("Could add to a frozen global object");
+
+            let f = runtime.get_function_by_name(Some(&handle), "bump")?;
+            let v = runtime.call_function_by_ref(Some(&handle), &f, json_args!())?;
+            assert_v8!(v, 1, usize, runtime);
+
+            let f = runtime.get_function_by_name(Some(&handle), "call")?;
+            let v = runtime.call_function_by_ref(Some(&handle), &f, json_args!())?;
+            assert_v8!(v, 5, usize, runtime);
+
+            Ok(())
+        });
+
+        // Without any modules, the globals are frozen before the first script runs
+        let mut runtime = InnerRuntime::<JsRuntime>::new(
+            RuntimeOptions {
+                freeze_globals: true,
+                ..Default::default()
+            },
+            CancellationToken::new(),
+        )
+        .expect("Could not load runtime");
+        run_async_task(|| async move {
+            let v = runtime
+                .eval("'use strict'; try { globalThis.foo = 1; false } catch { true }")
+                .await?;
+            assert_v8!(v, true, bool, runtime);
+
+            let v = runtime.eval("Object.isFrozen(Array.prototype)").await?;
+            assert_v8!(v, true, bool, runtime);
+
+            Ok(())
+        });
+    }
+
+    #[cfg(feature = "typescript")]
     #[test]
     fn test_ts_loader() {
         let module = Module::new(
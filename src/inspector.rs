@@ -0,0 +1,51 @@
+use std::net::{Ipv4Addr, SocketAddr};
+
+/// Options for attaching a debugger, such as Chrome DevTools, to a runtime - see [`crate::RuntimeOptions::inspector`]
+///
+/// Once the runtime is created, the debugger can be attached by opening `chrome://inspect`
+/// and adding the address to the list of targets
+///
+/// ```rust,no_run
+/// use rustyscript::{ InspectorOptions, Runtime, RuntimeOptions, Undefined };
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let mut runtime = Runtime::new(RuntimeOptions {
+///     inspector: Some(InspectorOptions {
+///         wait_for_debugger: true,
+///         ..Default::default()
+///     }),
+///     ..Default::default()
+/// })?;
+///
+/// // Only runs once a debugger is attached
+/// runtime.eval::<Undefined>("debugger; console.log('attached')")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct InspectorOptions {
+    /// Address the inspector server listens on  
+    /// Defaults to `127.0.0.1:9229`, the default port used by DevTools
+    pub address: SocketAddr,
+
+    /// Block the creation of the runtime until a debugger is attached,
+    /// and pause on the first statement executed afterwards
+    pub wait_for_debugger: bool,
+
+    /// Ignore the runtime's `timeout` and `cpu_time_limit` while the inspector is enabled
+    ///
+    /// Time spent stopped at a breakpoint cannot be told apart from time spent executing,
+    /// so a script being debugged would otherwise be cancelled while paused.  
+    /// Defaults to true
+    pub disable_timeouts: bool,
+}
+
+impl Default for InspectorOptions {
+    fn default() -> Self {
+        Self {
+            address: SocketAddr::from((Ipv4Addr::LOCALHOST, 9229)),
+            wait_for_debugger: false,
+            disable_timeouts: true,
+        }
+    }
+}
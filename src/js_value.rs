@@ -6,6 +6,40 @@
 use deno_core::serde_v8::GlobalValue;
 use deno_core::v8::{self, HandleScope};
 use serde::Deserialize;
+use std::cell::Cell;
+
+thread_local! {
+    /// Id of the runtime converting values to or from javascript on this thread, if any
+    static ACTIVE_RUNTIME: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Marks the runtime owning a scope as the one converting values on this thread, until dropped
+///
+/// Values decoded meanwhile remember that runtime, and values can only be encoded back into
+/// javascript - as arguments - while the runtime they came from is active
+pub(crate) struct ActiveRuntime(Option<u64>);
+impl ActiveRuntime {
+    pub(crate) fn enter(scope: &mut v8::HandleScope) -> Self {
+        let id = runtime_id(scope).unwrap_or_default();
+        Self(ACTIVE_RUNTIME.with(|active| active.replace(Some(id))))
+    }
+}
+impl Drop for ActiveRuntime {
+    fn drop(&mut self) {
+        ACTIVE_RUNTIME.with(|active| active.set(self.0));
+    }
+}
+
+/// Error for a value created without knowing which runtime it belongs to
+const UNKNOWN_OWNER: &str =
+    "javascript value is not tied to a runtime - create it with `try_from_v8`, or decode it from the runtime";
+
+/// Returns the id of the runtime owning a scope
+fn runtime_id(scope: &mut v8::HandleScope) -> Option<u64> {
+    let state = deno_core::JsRuntime::op_state_from(scope);
+    let state = state.borrow();
+    state.try_borrow::<crate::RuntimeInfo>().map(|info| info.id)
+}
 
 /// A macro to implement the common functions for [Function], [Promise], and [Value]
 macro_rules! impl_v8 {
@@ -32,6 +66,12 @@ macro_rules! impl_v8 {
                 &self.0 .0
             }
 
+            /// Fails unless the value came from the given runtime
+            #[allow(dead_code)]
+            pub(crate) fn check_runtime(&self, runtime: &crate::RuntimeInfo) -> Result<(), crate::Error> {
+                self.0.check_runtime(runtime)
+            }
+
             /// Creates a new instance of this struct from a global value
             ///
             /// # Errors
@@ -45,20 +85,25 @@ macro_rules! impl_v8 {
                 v8::Local<'a, v8::Value>: From<v8::Local<'a, H>>,
             {
                 let local: v8::Local<v8::Value> = v8::Local::new(scope, value).into();
-                v8::Global::new(scope, local).try_into()
+                let mut value: Self = v8::Global::new(scope, local).try_into()?;
+                value.0 .2 = $crate::js_value::runtime_id(scope);
+                Ok(value)
             }
 
             /// Creates a new instance of this struct from a global value
             /// Makes no attempt to check the type of the value
             /// This can result in a panic if the value is not of the correct type
             ///
+            /// Without a scope, the value is only tied to a runtime if one is converting values on this thread -
+            /// otherwise it cannot be passed back to javascript. Use [`Self::try_from_v8`] to tie it to its runtime
+            ///
             /// # Safety
             /// This function is unsafe because it does not check the type of the value
             /// If the value is not of the correct type, a panic will occur
             /// It is recommended to use [`Self::try_from_v8`] instead
             #[must_use]
             pub unsafe fn from_v8_unchecked(value: v8::Global<v8::Value>) -> Self {
-                let inner = V8Value::<$checker>(value, std::marker::PhantomData);
+                let inner = V8Value::<$checker>::new(value);
                 Self(inner $(, std::marker::PhantomData::<$generic>)?)
             }
         }
@@ -74,6 +119,17 @@ macro_rules! impl_v8 {
             }
         }
 
+        // Passed back to javascript as-is, so values can be used as arguments without a round-trip through rust
+        // Only possible while encoding arguments for the runtime the value came from - any other serializer is refused
+        impl $(<$generic>)? serde::Serialize for $name $(<$generic>)? $(where $generic: serde::de::DeserializeOwned)? {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                self.0.serialize(serializer)
+            }
+        }
+
         #[allow(clippy::from_over_into)]
         impl $(<$generic>)? Into<v8::Global<v8::Value>> for $name $(<$generic>)? $(where $generic: serde::de::DeserializeOwned)? {
             fn into(self) -> v8::Global<v8::Value> {
@@ -81,11 +137,12 @@ macro_rules! impl_v8 {
             }
         }
 
+        // Without a scope, only tied to a runtime if one is converting values on this thread - see `try_from_v8`
         impl $(<$generic>)? TryFrom<v8::Global<v8::Value>> for $name $(<$generic>)? $(where $generic: serde::de::DeserializeOwned)? {
             type Error = crate::Error;
             fn try_from(value: v8::Global<v8::Value>) -> Result<Self, Self::Error> {
                 <$checker as $crate::js_value::V8TypeChecker>::validate(value.clone())?;
-                let inner = V8Value::<$checker>(value, std::marker::PhantomData);
+                let inner = V8Value::<$checker>::new(value);
                 Ok(Self(inner $(, std::marker::PhantomData::<$generic>)?))
             }
         }
@@ -151,13 +208,66 @@ impl_checker!(DefaultTypeChecker, Value);
 ///
 /// A Deserializable javascript object, that can be stored and used later
 /// Must live as long as the runtime it was birthed from
+///
+/// Also holds the id of that runtime, if known
 #[derive(Eq, Hash, PartialEq, Debug, Clone)]
 pub(crate) struct V8Value<V8TypeChecker>(
     v8::Global<v8::Value>,
     std::marker::PhantomData<V8TypeChecker>,
+    Option<u64>,
 );
 
 impl<T: V8TypeChecker> V8Value<T> {
+    /// Wraps a global, owned by the runtime currently active on this thread, if any
+    pub(crate) fn new(value: v8::Global<v8::Value>) -> Self {
+        Self(
+            value,
+            std::marker::PhantomData,
+            ACTIVE_RUNTIME.with(Cell::get),
+        )
+    }
+
+    /// Wraps a global, owned by the runtime of the given scope
+    pub(crate) fn in_scope(scope: &mut HandleScope, value: v8::Global<v8::Value>) -> Self {
+        Self(value, std::marker::PhantomData, runtime_id(scope))
+    }
+
+    /// Fails unless the value came from the given runtime
+    ///
+    /// Handles belong to a single isolate - using one in any other is undefined behaviour,
+    /// so a value whose runtime is not known is refused as well
+    pub(crate) fn check_runtime(&self, runtime: &crate::RuntimeInfo) -> Result<(), crate::Error> {
+        match self.2 {
+            Some(owner) if owner == runtime.id => Ok(()),
+            Some(_) => Err(crate::Error::Runtime(format!(
+                "javascript value belongs to a different runtime than {runtime}"
+            ))),
+            None => Err(crate::Error::Runtime(UNKNOWN_OWNER.to_string())),
+        }
+    }
+
+    /// Encodes the original value, if the runtime it came from is encoding arguments on this thread
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match (ACTIVE_RUNTIME.with(Cell::get), self.2) {
+            (None, _) => Err(serde::ser::Error::custom(
+                "javascript values can only be serialized as arguments to the runtime they came from",
+            )),
+            (_, None) => Err(serde::ser::Error::custom(UNKNOWN_OWNER)),
+            (Some(active), Some(owner)) if active != owner => Err(serde::ser::Error::custom(
+                "javascript value belongs to a different runtime",
+            )),
+            _ => {
+                let value = GlobalValue {
+                    v8_value: self.0.clone(),
+                };
+                serde::Serialize::serialize(&value, serializer)
+            }
+        }
+    }
+
     /// Returns the underlying global as a local in the type configured by the type checker
     pub(crate) fn as_local<'a>(&self, scope: &mut HandleScope<'a>) -> v8::Local<'a, T::Output>
     where
@@ -186,7 +296,7 @@ impl<'de, T: V8TypeChecker> serde::Deserialize<'de> for V8Value<T> {
     {
         let value = GlobalValue::deserialize(deserializer)?;
         T::validate(value.v8_value.clone()).map_err(serde::de::Error::custom)?;
-        Ok(Self(value.v8_value, std::marker::PhantomData))
+        Ok(Self::new(value.v8_value))
     }
 }
 
@@ -194,6 +304,27 @@ impl<'de, T: V8TypeChecker> serde::Deserialize<'de> for V8Value<T> {
 /// Can only be used on the same runtime it was created on
 ///
 /// This mimics the auto-decoding that happens when providing a type parameter to Runtime functions
+///
+/// # `null` and `undefined`
+/// When decoding directly into a rust type, `null` and `undefined` are not distinguished:
+///
+/// | Rust type                 | `undefined`    | `null`         |
+/// |---------------------------|----------------|----------------|
+/// | `Option<T>`               | `None`         | `None`         |
+/// | `Option<Option<T>>`       | `None`         | `None`         |
+/// | `()`                      | `()`           | `()`           |
+/// | `serde_json::Value`       | `Value::Null`  | `Value::Null`  |
+/// | [`crate::Undefined`]      | `Value`        | `Value`        |
+///
+/// Decoding straight into `Option<Option<T>>` does not tell them apart either - serde has no way to.  
+/// Instead, decode into a `Value` first and use [`Value::is_undefined`], [`Value::is_null`],
+/// or [`Value::try_into_option`], which maps `undefined` to `None` and `null` to `Some(None)`
+///
+/// # Passing values back to javascript
+/// `Value` - like the other types in this module - can be used as a function argument, and arrives as the
+/// original javascript value, without being deserialized and serialized again.  
+/// This only works with the `Runtime` methods, and only on the runtime the value came from:
+/// passing it to another runtime, or serializing it any other way - such as with `serde_json` - fails with an error
 #[derive(Eq, Hash, PartialEq, Debug, Clone)]
 pub struct Value(V8Value<DefaultTypeChecker>);
 impl_v8!(Value, DefaultTypeChecker);
@@ -210,14 +341,199 @@ impl Value {
         T: serde::de::DeserializeOwned,
     {
         let mut scope = runtime.deno_runtime().handle_scope();
+        let _active = ActiveRuntime::enter(&mut scope);
         let local = self.0.as_local(&mut scope);
         Ok(deno_core::serde_v8::from_v8(&mut scope, local)?)
     }
 
+    /// Converts the value to an arbitrary rust type, like [`Value::try_into`], but also decodes
+    /// javascript `Map` and `Set` - including nested ones - into rust maps and sets
+    ///
+    /// The value is copied first, with each `Map` replaced by a plain object and each `Set` by an array:
+    /// - Each property is read once, so getters run once
+    /// - Map keys become property keys, so they must be strings, numbers, bigints or booleans,
+    ///   and no two keys may convert to the same property - such as `1` and `"1"`
+    /// - Values decoded as handles, such as a nested [`Value`], refer to the copy rather than the original
+    ///
+    /// ```rust
+    /// use rustyscript::{ js_value::Value, Runtime };
+    /// use std::collections::{ BTreeSet, HashMap };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let value: Value = runtime.eval("new Map([['a', new Set([2, 1])]])")?;
+    /// let map: HashMap<String, BTreeSet<u32>> = value.try_into_collections(&mut runtime)?;
+    /// assert_eq!(map["a"], BTreeSet::from([1, 2]));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// Will return an error if the value belongs to a different runtime, or cannot be copied or deserialized into the given type
+    pub fn try_into_collections<T>(self, runtime: &mut crate::Runtime) -> Result<T, crate::Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.0.check_runtime(runtime.info())?;
+        let mut scope = runtime.deno_runtime().handle_scope();
+        let local = self.0.as_local(&mut scope);
+        let mut scope = v8::TryCatch::new(&mut scope);
+        let copy = crate::inner_runtime::expand_collections(
+            &mut scope,
+            local,
+            0,
+            &mut crate::inner_runtime::IdentityMap::default(),
+        );
+        if let Some(exception) = scope.exception() {
+            let message = exception.to_rust_string_lossy(&mut scope);
+            return Err(crate::Error::Runtime(message));
+        }
+
+        let copy = copy?;
+        let _active = ActiveRuntime::enter(&mut scope);
+        Ok(deno_core::serde_v8::from_v8(&mut scope, copy)?)
+    }
+
+    /// Converts the value to an `Option<Option<T>>`, keeping `undefined` and `null` distinct
+    ///
+    /// - `undefined` becomes `None`
+    /// - `null` becomes `Some(None)`
+    /// - Any other value is decoded as `Some(Some(T))`
+    ///
+    /// # Errors
+    /// Will return an error if the value belongs to a different runtime, or cannot be deserialized into the given type
+    pub fn try_into_option<T>(
+        self,
+        runtime: &mut crate::Runtime,
+    ) -> Result<Option<Option<T>>, crate::Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        if self.is_undefined(runtime)? {
+            Ok(None)
+        } else if self.is_null(runtime)? {
+            Ok(Some(None))
+        } else {
+            self.try_into(runtime).map(|v| Some(Some(v)))
+        }
+    }
+
+    /// Returns true if the value is `undefined`
+    ///
+    /// # Errors
+    /// Will return an error if the value belongs to a different runtime
+    pub fn is_undefined(&self, runtime: &mut crate::Runtime) -> Result<bool, crate::Error> {
+        self.0.check_runtime(runtime.info())?;
+        let mut scope = runtime.deno_runtime().handle_scope();
+        Ok(self.0.as_local(&mut scope).is_undefined())
+    }
+
+    /// Returns true if the value is `null`
+    ///
+    /// # Errors
+    /// Will return an error if the value belongs to a different runtime
+    pub fn is_null(&self, runtime: &mut crate::Runtime) -> Result<bool, crate::Error> {
+        self.0.check_runtime(runtime.info())?;
+        let mut scope = runtime.deno_runtime().handle_scope();
+        Ok(self.0.as_local(&mut scope).is_null())
+    }
+
+    /// Returns the javascript type of the value, without deserializing it
+    ///
+    /// Mirrors `typeof`, but distinguishes `null` and arrays from other objects  
+    /// The runtime must be the one the value came from
+    ///
+    /// # Errors
+    /// Will return an error if the value belongs to a different runtime
+    pub fn type_of(&self, runtime: &mut crate::Runtime) -> Result<ValueType, crate::Error> {
+        self.0.check_runtime(runtime.info())?;
+        let mut scope = runtime.deno_runtime().handle_scope();
+        let raw = self.0.as_local(&mut scope);
+        Ok(if raw.is_undefined() {
+            ValueType::Undefined
+        } else if raw.is_null() {
+            ValueType::Null
+        } else if raw.is_boolean() {
+            ValueType::Boolean
+        } else if raw.is_number() {
+            ValueType::Number
+        } else if raw.is_big_int() {
+            ValueType::BigInt
+        } else if raw.is_string() {
+            ValueType::String
+        } else if raw.is_symbol() {
+            ValueType::Symbol
+        } else if raw.is_function() {
+            ValueType::Function
+        } else if raw.is_array() {
+            ValueType::Array
+        } else {
+            ValueType::Object
+        })
+    }
+
     /// Contructs a new Value from a `v8::Value` global
+    ///
+    /// Without a scope, the value is only tied to a runtime if one is converting values on this thread -
+    /// otherwise it cannot be passed back to javascript. Use [`Value::try_from_v8`] to tie it to its runtime
     #[must_use]
     pub fn from_v8(value: v8::Global<v8::Value>) -> Self {
-        Self(V8Value(value, std::marker::PhantomData))
+        Self(V8Value::new(value))
+    }
+}
+
+/// The javascript type of a [`Value`], as returned by [`Value::type_of`]
+///
+/// Useful for picking a rust type to deserialize into - `BigInt` in particular
+/// will not survive conversion to a rust number, and should be decoded as a [`BigInt`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    /// `undefined`
+    Undefined,
+
+    /// `null`
+    Null,
+
+    /// `true` or `false`
+    Boolean,
+
+    /// Any non-`BigInt` number, including `NaN` and `Infinity`
+    Number,
+
+    /// A `BigInt`, such as `10n`
+    BigInt,
+
+    /// A string
+    String,
+
+    /// A `Symbol`
+    Symbol,
+
+    /// A function, including classes and async functions
+    Function,
+
+    /// An array
+    Array,
+
+    /// Any other object
+    Object,
+}
+
+impl std::fmt::Display for ValueType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Undefined => "undefined",
+            Self::Null => "null",
+            Self::Boolean => "boolean",
+            Self::Number => "number",
+            Self::BigInt => "bigint",
+            Self::String => "string",
+            Self::Symbol => "symbol",
+            Self::Function => "function",
+            Self::Array => "array",
+            Self::Object => "object",
+        };
+        write!(f, "{name}")
     }
 }
 
@@ -230,9 +546,15 @@ pub use promise::*;
 mod string;
 pub use string::*;
 
+mod bigint;
+pub use bigint::*;
+
 mod map;
 pub use map::*;
 
+mod serialized;
+pub use serialized::*;
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -263,5 +585,94 @@ mod test {
         let _f = f
             .into_inner()
             .as_local(&mut runtime.deno_runtime().handle_scope());
+
+        // Values built without a scope do not know their runtime, so they cannot be passed back
+        let module = Module::new("echo.js", "export const echo = (value) => value;");
+        let handle = runtime.load_module(&module).unwrap();
+        let value: Value = runtime.eval("({ a: 1 })").unwrap();
+        let detached = Value::from_v8(value.clone().into_v8());
+        runtime
+            .call_function::<Value>(Some(&handle), "echo", crate::json_args!(detached))
+            .unwrap_err();
+        let attached =
+            Value::try_from_v8(&mut runtime.deno_runtime().handle_scope(), value.into_v8())
+                .unwrap();
+        runtime
+            .call_function::<Value>(Some(&handle), "echo", crate::json_args!(attached))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_undefined_vs_null() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const u = undefined;
+            export const n = null;
+            export const v = 5;
+            export function f() {}
+        ",
+        );
+
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let handle = runtime.load_module(&module).unwrap();
+
+        let u: Value = runtime.get_value(Some(&handle), "u").unwrap();
+        assert!(u.is_undefined(&mut runtime).unwrap() && !u.is_null(&mut runtime).unwrap());
+        assert_eq!(u.try_into_option::<usize>(&mut runtime).unwrap(), None);
+
+        let n: Value = runtime.get_value(Some(&handle), "n").unwrap();
+        assert!(n.is_null(&mut runtime).unwrap() && !n.is_undefined(&mut runtime).unwrap());
+        assert_eq!(
+            n.try_into_option::<usize>(&mut runtime).unwrap(),
+            Some(None)
+        );
+
+        let v: Value = runtime.get_value(Some(&handle), "v").unwrap();
+        assert_eq!(
+            v.try_into_option::<usize>(&mut runtime).unwrap(),
+            Some(Some(5))
+        );
+
+        // Functions without a return value still decode as `Undefined`
+        let r: crate::Undefined = runtime
+            .call_function(Some(&handle), "f", crate::json_args!())
+            .unwrap();
+        assert!(r.is_undefined(&mut runtime).unwrap());
+
+        // Values from another runtime are refused instead of being opened in this one
+        let mut other = Runtime::new(RuntimeOptions::default()).unwrap();
+        u.is_undefined(&mut other).unwrap_err();
+        n.is_null(&mut other).unwrap_err();
+        n.try_into_option::<usize>(&mut other).unwrap_err();
+    }
+
+    #[test]
+    fn test_type_of() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let cases = [
+            ("undefined", ValueType::Undefined),
+            ("null", ValueType::Null),
+            ("true", ValueType::Boolean),
+            ("1.5", ValueType::Number),
+            ("2n ** 70n", ValueType::BigInt),
+            ("'s'", ValueType::String),
+            ("Symbol('s')", ValueType::Symbol),
+            ("(() => 1)", ValueType::Function),
+            ("[1, 2]", ValueType::Array),
+            ("({ a: 1 })", ValueType::Object),
+            ("new Date()", ValueType::Object),
+        ];
+
+        for (expr, expected) in cases {
+            let value: Value = runtime.eval(expr).unwrap();
+            assert_eq!(value.type_of(&mut runtime).unwrap(), expected, "{expr}");
+        }
+
+        // A value can only be inspected by the runtime it came from
+        let value: Value = runtime.eval("[1, 2]").unwrap();
+        let mut other = Runtime::new(RuntimeOptions::default()).unwrap();
+        value.type_of(&mut other).unwrap_err();
+        assert_eq!(value.type_of(&mut runtime).unwrap(), ValueType::Array);
     }
 }
@@ -0,0 +1,157 @@
+use super::V8Value;
+use deno_core::v8::{self, HandleScope};
+use serde::Deserialize;
+
+/// A Deserializable javascript `BigInt`, that can be stored and used later
+/// Must live as long as the runtime it was birthed from
+///
+/// Decoding a `BigInt` straight into a rust number loses precision - decode into this type instead,
+/// then use [`BigInt::to_i128`] or [`BigInt::to_u128`] for an exact conversion.  
+/// It can also be passed back to javascript as an argument, arriving as a `BigInt`
+#[derive(Eq, Hash, PartialEq, Debug, Clone)]
+pub struct BigInt(V8Value<BigIntTypeChecker>);
+impl_v8!(BigInt, BigIntTypeChecker);
+impl_checker!(BigIntTypeChecker, BigInt, is_big_int, |e| {
+    crate::Error::JsonDecode(format!("Expected a BigInt, found `{e}`"))
+});
+
+impl BigInt {
+    /// Creates a new javascript `BigInt` from an `i128`
+    ///
+    /// # Errors
+    /// Will return an error if v8 could not allocate the value
+    pub fn from_i128(runtime: &mut crate::Runtime, value: i128) -> Result<Self, crate::Error> {
+        let mut scope = runtime.deno_runtime().handle_scope();
+        Self::from_words(&mut scope, value < 0, value.unsigned_abs())
+    }
+
+    /// Creates a new javascript `BigInt` from a `u128`
+    ///
+    /// # Errors
+    /// Will return an error if v8 could not allocate the value
+    pub fn from_u128(runtime: &mut crate::Runtime, value: u128) -> Result<Self, crate::Error> {
+        let mut scope = runtime.deno_runtime().handle_scope();
+        Self::from_words(&mut scope, false, value)
+    }
+
+    /// Converts the value to an `i128`
+    ///
+    /// # Errors
+    /// Will return an error if the value does not fit in an `i128`
+    pub fn to_i128(&self, runtime: &mut crate::Runtime) -> Result<i128, crate::Error> {
+        let mut scope = runtime.deno_runtime().handle_scope();
+        let (negative, magnitude) = self.to_magnitude(&mut scope, "i128")?;
+        let value = if negative {
+            0i128.checked_sub_unsigned(magnitude)
+        } else {
+            i128::try_from(magnitude).ok()
+        };
+
+        value.ok_or_else(|| self.out_of_range(&mut scope, "i128"))
+    }
+
+    /// Converts the value to a `u128`
+    ///
+    /// # Errors
+    /// Will return an error if the value is negative, or does not fit in a `u128`
+    pub fn to_u128(&self, runtime: &mut crate::Runtime) -> Result<u128, crate::Error> {
+        let mut scope = runtime.deno_runtime().handle_scope();
+        match self.to_magnitude(&mut scope, "u128")? {
+            (true, magnitude) if magnitude != 0 => Err(self.out_of_range(&mut scope, "u128")),
+            (_, magnitude) => Ok(magnitude),
+        }
+    }
+
+    fn from_words(
+        scope: &mut HandleScope<'_>,
+        negative: bool,
+        magnitude: u128,
+    ) -> Result<Self, crate::Error> {
+        #[allow(clippy::cast_possible_truncation)]
+        let words = [magnitude as u64, (magnitude >> 64) as u64];
+        let local = v8::BigInt::new_from_words(scope, negative, &words)
+            .ok_or_else(|| crate::Error::Runtime("Could not create a BigInt".to_string()))?;
+        let local: v8::Local<v8::Value> = local.into();
+        let global = v8::Global::new(scope, local);
+        Ok(Self(V8Value::in_scope(scope, global)))
+    }
+
+    /// Returns the sign and magnitude of the value, if the magnitude fits in 128 bits
+    /// `target` names the type being converted to, for the error
+    fn to_magnitude(
+        &self,
+        scope: &mut HandleScope<'_>,
+        target: &str,
+    ) -> Result<(bool, u128), crate::Error> {
+        let local = self.0.as_local(scope);
+        let mut words = vec![0; local.word_count()];
+        let (negative, words) = local.to_words_array(&mut words);
+        if words.len() > 2 {
+            return Err(self.out_of_range(scope, target));
+        }
+
+        let magnitude = words
+            .iter()
+            .rev()
+            .fold(0u128, |acc, word| (acc << 64) | u128::from(*word));
+        Ok((negative, magnitude))
+    }
+
+    fn out_of_range(&self, scope: &mut HandleScope<'_>, target: &str) -> crate::Error {
+        let local = self.0.as_local(scope);
+        let value = local.to_rust_string_lossy(scope);
+        crate::Error::JsonDecode(format!("BigInt {value}n does not fit in {target}"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{json_args, Module, Runtime, RuntimeOptions};
+
+    #[test]
+    fn test_bigint() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const big = 2n ** 100n + 7n;
+            export const negative = -(2n ** 127n);
+            export const huge = 2n ** 128n;
+            export const double = (n) => typeof n === 'bigint' ? n * 2n : null;
+        ",
+        );
+
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let handle = runtime.load_module(&module).unwrap();
+
+        let big: BigInt = runtime.get_value(Some(&handle), "big").unwrap();
+        assert_eq!(big.to_i128(&mut runtime).unwrap(), (1i128 << 100) + 7);
+        assert_eq!(big.to_u128(&mut runtime).unwrap(), (1u128 << 100) + 7);
+
+        let negative: BigInt = runtime.get_value(Some(&handle), "negative").unwrap();
+        assert_eq!(negative.to_i128(&mut runtime).unwrap(), i128::MIN);
+        negative
+            .to_u128(&mut runtime)
+            .expect_err("Negative values do not fit in a u128");
+
+        let huge: BigInt = runtime.get_value(Some(&handle), "huge").unwrap();
+        let e = huge.to_u128(&mut runtime).unwrap_err();
+        assert!(e.to_string().contains("does not fit in u128"));
+        let e = huge.to_i128(&mut runtime).unwrap_err();
+        assert!(e.to_string().contains("does not fit in i128"), "{e}");
+
+        runtime
+            .get_value::<BigInt>(Some(&handle), "double")
+            .expect_err("Functions are not BigInts");
+
+        // Round-trip through javascript
+        let value = BigInt::from_i128(&mut runtime, i128::MAX / 4).unwrap();
+        let doubled: BigInt = runtime
+            .call_function(Some(&handle), "double", json_args!(value))
+            .unwrap();
+        assert_eq!(doubled.to_i128(&mut runtime).unwrap(), i128::MAX / 4 * 2);
+
+        let value = BigInt::from_u128(&mut runtime, u128::MAX).unwrap();
+        assert_eq!(value.to_u128(&mut runtime).unwrap(), u128::MAX);
+    }
+}
@@ -33,6 +33,7 @@ where
             .with_event_loop_future(future, PollEventLoopOptions::default())
             .await?;
         let mut scope = runtime.handle_scope();
+        let _active = super::ActiveRuntime::enter(&mut scope);
         let local = v8::Local::new(&mut scope, &result);
         Ok(deno_core::serde_v8::from_v8(&mut scope, local)?)
     }
@@ -77,6 +78,7 @@ where
                 std::task::Poll::Ready(Err(error.into()))
             }
             PromiseState::Fulfilled => {
+                let _active = super::ActiveRuntime::enter(&mut scope);
                 let result = value.result(&mut scope);
                 match deno_core::serde_v8::from_v8::<T>(&mut scope, result) {
                     Ok(value) => std::task::Poll::Ready(Ok(value)),
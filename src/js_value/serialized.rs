@@ -0,0 +1,34 @@
+/// A javascript value serialized with v8's structured clone format
+///
+/// Unlike JSON, this preserves types such as `Date`, `Map`, `Set`, `RegExp`, typed arrays and cyclic references.  
+/// Unlike the other types in this module, it does not belong to a runtime, and can be
+/// passed to a different runtime with [`crate::Runtime::import_value`]
+///
+/// Functions, symbols and host objects cannot be serialized
+#[derive(Eq, Hash, PartialEq, Debug, Clone)]
+pub struct SerializedValue(Vec<u8>);
+impl SerializedValue {
+    /// Wraps bytes previously produced by [`SerializedValue::into_bytes`]
+    #[must_use]
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+
+    /// Returns the serialized bytes
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Consumes the value, returning the serialized bytes
+    #[must_use]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl AsRef<[u8]> for SerializedValue {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
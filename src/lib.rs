@@ -198,6 +198,40 @@
 //!
 //! ----
 //!
+//! WebAssembly is available through the standard JS API - `WebAssembly.compile`, `WebAssembly.instantiate`, and friends.  
+//! Since the runtime has no filesystem or network access by default, the bytes must be provided to the script, for example as a function argument:
+//! ```rust
+//! use rustyscript::{ Runtime, Module, json_args };
+//!
+//! # fn main() -> Result<(), rustyscript::Error> {
+//! // A wasm module exporting `add(a, b)`
+//! let wasm: Vec<u8> = vec![
+//!     0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x07, 0x01, 0x60, 0x02, 0x7f, 0x7f, 0x01,
+//!     0x7f, 0x03, 0x02, 0x01, 0x00, 0x07, 0x07, 0x01, 0x03, 0x61, 0x64, 0x64, 0x00, 0x00, 0x0a, 0x09,
+//!     0x01, 0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6a, 0x0b,
+//! ];
+//!
+//! let module = Module::new("test.js", "
+//!     export async function add(bytes, a, b) {
+//!         const { instance } = await WebAssembly.instantiate(new Uint8Array(bytes));
+//!         return instance.exports.add(a, b);
+//!     }
+//! ");
+//!
+//! let mut runtime = Runtime::new(Default::default())?;
+//! let handle = runtime.load_module(&module)?;
+//! let result: i32 = runtime.call_function(Some(&handle), "add", json_args!(wasm, 2, 3))?;
+//! assert_eq!(result, 5);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Importing `.wasm` files directly (`import x from './mod.wasm'`) is not supported.  
+//! Compiling and running wasm counts towards the runtime's timeout, like any other JS. Prefer the async `WebAssembly.compile`
+//! over `new WebAssembly.Module(bytes)` for large modules - synchronous compilation blocks the thread, and cannot be interrupted until it completes.
+//!
+//! ----
+//!
 //! A threaded worker can be used to run code in a separate thread, or to allow multiple concurrent runtimes.
 //!
 //! the [`worker`] module provides a simple interface to create and interact with workers.
@@ -226,6 +260,7 @@
 //! These functions provide simple one-liner access to common features of this crate:
 //! - `evaluate`; Evaluate a single JS expression and return the resulting value
 //! - `import`; Get a handle to a JS module from which you can get exported values and functions
+//! - `evaluate_async` and `import_async`; Non-blocking versions of the above, for use on a `tokio::task::LocalSet`
 //! - `resolve_path`; Resolve a relative path to the current working dir
 //! - `validate`; Validate the syntax of a JS expression
 //! - `init_platform`; Initialize the V8 platform for multi-threaded applications
@@ -258,6 +293,7 @@
 //! |`fs`               |Provides ops for interacting with the file system.                                                         |**NO**            |`deno_fs`, `web`,  `io`                                                                        |
 //! |`http`             |Implements the fetch standard                                                                              |**NO**            |`deno_http`, `web`, `websocket`                                                                |
 //! |`kv`               |Implements the Deno KV Connect protocol                                                                    |**NO**            |`deno_kv`, `web`, `console`                                                                    |
+//! |`net`              |Provides raw TCP and TLS sockets (`Deno.connect`, `Deno.listen`) without fetch                             |**NO**            |`deno_net`, `deno_tls`, `deno_web`, `deno_webidl`, `deno_url`, `deno_console`                  |
 //! |`url`              |Provides the `URL`, and `URLPattern` APIs from within JS                                                   |yes               |`deno_webidl`, `deno_url`                                                                      |
 //! |`io`               |Provides IO primitives such as stdio streams and abstraction over File System files.                       |**NO**            |`deno_io`, `rustyline`, `winapi`, `nix`, `libc`, `once_cell`                                   |
 //! |`web`              |Provides the `Event`, `TextEncoder`, `TextDecoder`, `File`, Web Cryptography, and fetch APIs from within JS|**NO**            |`deno_webidl`, `deno_web`, `deno_crypto`, `deno_fetch`, `deno_url`, `deno_net`                 |
@@ -271,13 +307,17 @@
 //! |`all`              |Provides all available functionality                                                                       |**NO**            |`deno_console`, `deno_webidl`, `deno_web`, `deno_net`, `deno_crypto`, `deno_fetch`, `deno_url` |
 //! |                   |                                                                                                           |                  |                                                                                               |
 //! |`fs_import`        |Enables importing arbitrary code from the filesystem through JS                                            |**NO**            |None                                                                                           |
-//! |`url_import`       |Enables importing arbitrary code from network locations through JS                                         |**NO**            |`reqwest`                                                                                      |
+//! |`url_import`       |Enables importing arbitrary code from network locations through JS                                         |**NO**            |`reqwest`, `deno_tls`                                                                          |
 //! |                   |                                                                                                           |                  |                                                                                               |
 //! |`node_experimental`|HIGHLY EXPERIMENTAL nodeJS support that enables all available Deno extensions                              |**NO**            |For complete list, see Cargo.toml                                                              |
 //! |                   |                                                                                                           |                  |                                                                                               |
 //! |`worker`           |Enables access to the threaded worker API [`worker`]                                                       |yes               |None                                                                                           |
 //! |`snapshot_builder` |Enables access to [`SnapshotBuilder`], a runtime for creating snapshots that can improve start-times       |yes               |None                                                                                           |
+//! |`inspector`        |Enables attaching a debugger over the V8 inspector protocol - see [`RuntimeOptions::inspector`]            |**NO**            |`deno_runtime`                                                                                 |
 //! |`web_stub`         |Enables a subset of `web` features that do not break sandboxing                                            |yes               |`deno_webidl`                                                                                  |
+//! |`encoding`         |Provides UTF-8 `TextEncoder` and `TextDecoder` without the `web` feature                                   |yes               |None                                                                                           |
+//! |`archive`          |Enables loading modules from an in-memory zip or tar archive - see [`Runtime::load_archive`]               |yes               |`flate2`                                                                                       |
+//! |`typescript`       |Transpiles TypeScript, JSX and TSX modules - without it, only javascript can be loaded                     |yes               |`deno_ast`                                                                                     |
 //!
 //! ----
 //!
@@ -306,12 +346,20 @@ pub mod module_loader;
 pub mod static_runtime;
 
 mod async_bridge;
+mod call_slot;
+mod cancellation_handle;
+mod cpu_watchdog;
+mod dry_run;
 mod ext;
+mod fake_clock;
 mod inner_runtime;
 mod module;
+mod module_getter;
 mod module_handle;
 mod module_wrapper;
+mod progress_monitor;
 mod runtime;
+mod snapshot_file;
 mod traits;
 mod transpiler;
 mod utilities;
@@ -320,6 +368,13 @@ mod utilities;
 #[cfg_attr(docsrs, doc(cfg(feature = "worker")))]
 pub mod worker;
 
+#[cfg(feature = "inspector")]
+mod inspector;
+
+#[cfg(feature = "inspector")]
+#[cfg_attr(docsrs, doc(cfg(feature = "inspector")))]
+pub use inspector::InspectorOptions;
+
 // Expose a few dependencies that could be useful
 pub use deno_core;
 pub use deno_core::serde_json;
@@ -383,8 +438,8 @@ pub mod extensions {
     #[cfg_attr(docsrs, doc(cfg(feature = "webstorage")))]
     pub use deno_webstorage;
 
-    #[cfg(feature = "web")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "webstorage")))]
+    #[cfg(any(feature = "web", feature = "net"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "web", feature = "net"))))]
     pub use deno_tls;
 }
 
@@ -392,6 +447,10 @@ pub mod extensions {
 #[cfg_attr(docsrs, doc(cfg(feature = "kv")))]
 pub use ext::kv::{KvConfig, KvStore};
 
+#[cfg(feature = "io")]
+#[cfg_attr(docsrs, doc(cfg(feature = "io")))]
+pub use ext::io::IoStreams;
+
 #[cfg(feature = "cache")]
 #[cfg_attr(docsrs, doc(cfg(feature = "cache")))]
 pub use ext::cache::CacheBackend;
@@ -400,22 +459,36 @@ pub use ext::cache::CacheBackend;
 #[cfg_attr(docsrs, doc(cfg(feature = "node_experimental")))]
 pub use ext::node::RustyResolver;
 
-#[cfg(feature = "web")]
-#[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+#[cfg(any(feature = "web", feature = "net"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "web", feature = "net"))))]
 pub use ext::web::{
-    AllowlistWebPermissions, DefaultWebPermissions, PermissionDenied, SystemsPermissionKind,
-    WebOptions, WebPermissions,
+    AllowlistWebPermissions, DefaultWebPermissions, PermissionDenied, PermissionHandler,
+    PermissionRequest, PromptWebPermissions, SystemsPermissionKind, WebOptions, WebPermissions,
 };
 pub use ext::ExtensionOptions;
 
 // Expose some important stuff from us
+pub use call_slot::CallSlot;
+pub use cancellation_handle::CancellationHandle;
+pub use dry_run::{DryRunReport, UnresolvedImport};
 pub use error::Error;
-pub use inner_runtime::{RsAsyncFunction, RsFunction};
-pub use module::Module;
+pub use fake_clock::FakeClock;
+pub use inner_runtime::{
+    ProgressCallback, ProgressSignal, RsAsyncFunction, RsFunction, RsStreamFunction, RsValueStream,
+    TimeoutHook,
+};
+pub use module::{CompiledModule, Module, ModuleLanguage};
+pub use module_getter::{ModuleGetter, RsModuleFunction};
 pub use module_handle::ModuleHandle;
 pub use module_wrapper::ModuleWrapper;
-pub use runtime::{Runtime, RuntimeOptions, Undefined};
-pub use utilities::{evaluate, import, init_platform, resolve_path, validate};
+pub use runtime::{
+    GeneratorIter, HeapStats, Runtime, RuntimeInfo, RuntimeOptions, RuntimeProfile, Undefined,
+};
+pub use transpiler::{JsxMode, TranspileOptions};
+pub use utilities::{
+    evaluate, evaluate_async, import, import_async, init_platform, resolve_path, validate,
+    validate_all, Diagnostic, DiagnosticSeverity,
+};
 
 #[cfg(feature = "broadcast_channel")]
 #[cfg_attr(docsrs, doc(cfg(feature = "broadcast_channel")))]
@@ -442,7 +515,7 @@ mod test {
     }
 
     #[test]
-    #[cfg(not(feature = "web"))]
+    #[cfg(not(any(feature = "web", feature = "net")))]
     fn check_op_whitelist() {
         let inner = || -> Result<(), Error> {
             let mut runtime = Runtime::new(RuntimeOptions::default())?;
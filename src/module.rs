@@ -6,6 +6,8 @@ use std::fmt::Display;
 use std::fs::{read_dir, read_to_string};
 use std::path::{Path, PathBuf};
 
+use crate::traits::ToModuleSpecifier;
+
 /// Creates a static module
 ///
 /// This is just a macro around [`Module::new_static`]
@@ -51,6 +53,61 @@ macro_rules! include_module {
     };
 }
 
+/// Creates a slice of static modules, for use with [`crate::Runtime::load_static_modules`]
+///
+/// Each entry is a `(filename, contents)` pair - see [`Module::new_static`]
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{ static_modules, Module };
+///
+/// const LIBRARY: &[Module] = static_modules![
+///     ("lib/a.js", "export const a = 1;"),
+///     ("lib/b.ts", "export const b: number = 2;"),
+/// ];
+/// ```
+#[macro_export]
+macro_rules! static_modules {
+    ($(($filename:expr, $contents:expr)),* $(,)?) => {
+        &[$($crate::Module::new_static($filename, $contents)),*]
+    };
+}
+
+/// The source language of a [`Module`], used to decide whether it needs to be transpiled
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum ModuleLanguage {
+    /// Plain javascript, loaded as-is
+    JavaScript,
+
+    /// Typescript, transpiled to javascript before loading
+    TypeScript,
+}
+
+/// A module transpiled ahead of time, which can be loaded into many runtimes without being transpiled again
+///
+/// Created with [`Module::compile`], and loaded with [`crate::Runtime::load_compiled`]  
+/// Holds the transpiled javascript, and a source map pointing back at the original source
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CompiledModule {
+    module: Module,
+    source_map: Option<Vec<u8>>,
+}
+
+impl CompiledModule {
+    /// Returns the transpiled module, under the original filename
+    #[must_use]
+    pub fn module(&self) -> &Module {
+        &self.module
+    }
+
+    /// Returns the source map from the transpiled code to the original source, if it was transpiled
+    #[must_use]
+    pub fn source_map(&self) -> Option<&[u8]> {
+        self.source_map.as_deref()
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Default)]
 /// Represents a piece of javascript for execution.
 ///
@@ -60,6 +117,7 @@ macro_rules! include_module {
 pub struct Module {
     filename: MaybePathBuf<'static>,
     contents: Cow<'static, str>,
+    language: Option<ModuleLanguage>,
 }
 
 impl<'de> Deserialize<'de> for Module {
@@ -71,10 +129,19 @@ impl<'de> Deserialize<'de> for Module {
         struct OwnedModule {
             filename: PathBuf,
             contents: String,
+            #[serde(default)]
+            language: Option<ModuleLanguage>,
         }
 
-        let OwnedModule { filename, contents } = OwnedModule::deserialize(deserializer)?;
-        Ok(Module::new(filename, contents))
+        let OwnedModule {
+            filename,
+            contents,
+            language,
+        } = OwnedModule::deserialize(deserializer)?;
+        Ok(Module {
+            language,
+            ..Module::new(filename, contents)
+        })
     }
 }
 
@@ -108,7 +175,65 @@ impl Module {
         let filename = MaybePathBuf::Owned(filename.as_ref().to_path_buf());
         let contents = Cow::Owned(contents.to_string());
 
-        Self { filename, contents }
+        Self {
+            filename,
+            contents,
+            language: None,
+        }
+    }
+
+    /// Creates a new `Module` instance that is always treated as typescript, whatever its filename.
+    ///
+    /// Useful for generated modules, whose filenames do not end in `.ts`  
+    /// [`Module::new`] instead infers the language from the file extension
+    ///
+    /// # Arguments
+    /// * `filename` - A string representing the filename of the module.
+    /// * `contents` - A string containing the contents of the module.
+    ///
+    /// # Returns
+    /// A new `Module` instance.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::Module;
+    ///
+    /// let module = Module::new_typescript("generated:module", "export const x: number = 5;");
+    /// ```
+    #[must_use]
+    pub fn new_typescript(filename: impl AsRef<Path>, contents: impl ToString) -> Self {
+        Self {
+            language: Some(ModuleLanguage::TypeScript),
+            ..Self::new(filename, contents)
+        }
+    }
+
+    /// Creates a new `Module` instance that is always treated as javascript, whatever its filename.
+    ///
+    /// The module will not be transpiled, even if its filename ends in `.ts`  
+    /// [`Module::new`] instead infers the language from the file extension
+    ///
+    /// # Arguments
+    /// * `filename` - A string representing the filename of the module.
+    /// * `contents` - A string containing the contents of the module.
+    ///
+    /// # Returns
+    /// A new `Module` instance.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::Module;
+    ///
+    /// let module = Module::new_javascript("generated:module", "export const x = 5;");
+    /// ```
+    #[must_use]
+    pub fn new_javascript(filename: impl AsRef<Path>, contents: impl ToString) -> Self {
+        Self {
+            language: Some(ModuleLanguage::JavaScript),
+            ..Self::new(filename, contents)
+        }
     }
 
     /// Creates a new `Module` instance with the given filename and contents.  
@@ -135,6 +260,7 @@ impl Module {
         Self {
             filename: MaybePathBuf::new_str(filename),
             contents: Cow::Borrowed(contents),
+            language: None,
         }
     }
 
@@ -245,6 +371,101 @@ impl Module {
     pub fn contents(&self) -> &str {
         &self.contents
     }
+
+    /// Parses the module, and returns the specifiers it imports - without running it.
+    ///
+    /// Includes static `import` statements, re-exports (`export ... from`),
+    /// and dynamic `import()` calls with a string literal argument. Dynamic imports of
+    /// computed values cannot be discovered statically, and are not included.
+    ///
+    /// Specifiers are returned as written in the source, in order of appearance, without duplicates.  
+    /// Typescript is supported, and type-only imports are skipped since they are erased by transpilation
+    ///
+    /// # Errors
+    /// Will return an error if the module cannot be parsed
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::Module;
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// # #[cfg(feature = "typescript")] {
+    /// let module = Module::new("module.ts", "import { a } from './a.ts'; export * from 'b';");
+    /// assert_eq!(module.imports()?, vec!["./a.ts", "b"]);
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn imports(&self) -> Result<Vec<String>, crate::Error> {
+        let specifier = self
+            .filename()
+            .to_module_specifier(&std::env::current_dir()?)?;
+        Ok(crate::transpiler::find_imports(
+            &specifier,
+            self.contents(),
+            self.language,
+        )?)
+    }
+
+    /// Transpiles the module once, so that it can be loaded into many runtimes without being transpiled again
+    ///
+    /// The module is transpiled the way `runtime` would transpile it - with its
+    /// [`crate::RuntimeOptions::transpile_options`], and resolving the filename against its working directory.  
+    /// Runtimes loading the result should share those settings.
+    ///
+    /// Javascript modules are kept as-is. See [`crate::Runtime::load_compiled`]
+    ///
+    /// # Errors
+    /// Will return an error if the module cannot be parsed or transpiled
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Module, Runtime };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// # #[cfg(feature = "typescript")] {
+    /// let module = Module::new("module.ts", "export const x: number = 5;");
+    /// let compiled = module.compile(&Runtime::new(Default::default())?)?;
+    ///
+    /// for _ in 0..3 {
+    ///     let mut runtime = Runtime::new(Default::default())?;
+    ///     let handle = runtime.load_compiled(&compiled)?;
+    ///     let x: usize = runtime.get_value(Some(&handle), "x")?;
+    ///     assert_eq!(x, 5);
+    /// }
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn compile(&self, runtime: &crate::Runtime) -> Result<CompiledModule, crate::Error> {
+        let specifier = self.filename().to_module_specifier(runtime.current_dir())?;
+        let (code, source_map) = crate::transpiler::transpile_as(
+            &specifier,
+            self.contents(),
+            self.language,
+            runtime.transpile_options(),
+        )?;
+
+        Ok(CompiledModule {
+            module: Module {
+                filename: self.filename.clone(),
+                contents: Cow::Owned(code),
+                language: Some(ModuleLanguage::JavaScript),
+            },
+            source_map: source_map.map(|s| s.to_vec()),
+        })
+    }
+
+    /// Returns the explicit language of the module, if one was set with
+    /// [`Module::new_typescript`] or [`Module::new_javascript`]
+    ///
+    /// Modules without one are treated according to their file extension
+    #[must_use]
+    pub fn language(&self) -> Option<ModuleLanguage> {
+        self.language
+    }
 }
 
 #[cfg(test)]
@@ -258,6 +479,67 @@ mod test_module {
         assert_eq!(module.contents(), "console.log('Hello, World!');");
     }
 
+    #[cfg(feature = "typescript")]
+    #[test]
+    fn test_module_language() {
+        let module = Module::new_typescript("generated", "export const x: number = 5;");
+        assert_eq!(module.language(), Some(ModuleLanguage::TypeScript));
+        assert_eq!(Module::new("module.ts", "").language(), None);
+
+        let mut runtime = crate::Runtime::new(Default::default()).unwrap();
+        let handle = runtime
+            .load_module(&module)
+            .expect("Module was not transpiled");
+        let x: usize = runtime.get_value(Some(&handle), "x").unwrap();
+        assert_eq!(x, 5);
+
+        // Explicit javascript skips transpilation, even with a .ts extension
+        let module = Module::new_javascript("plain.ts", "export const y: number = 5;");
+        runtime
+            .load_module(&module)
+            .expect_err("Module was transpiled");
+    }
+
+    #[cfg(feature = "typescript")]
+    #[test]
+    fn test_imports() {
+        let module = Module::new(
+            "module.ts",
+            "
+            import { a } from './a.ts';
+            import type { T } from './types.ts';
+            import './side_effect.js';
+            export { b } from './b.js';
+            export * from './c.js';
+            export type { U } from './types2.ts';
+            import { a as again } from './a.ts';
+
+            const x: number = 5;
+            export async function load(name: string) {
+                await import('./dynamic.js');
+                await import(`./template.js`);
+                await import(name);
+            }
+        ",
+        );
+
+        assert_eq!(
+            module.imports().expect("Could not parse module"),
+            vec![
+                "./a.ts",
+                "./side_effect.js",
+                "./b.js",
+                "./c.js",
+                "./dynamic.js",
+                "./template.js"
+            ]
+        );
+
+        Module::new("bad.js", "import {")
+            .imports()
+            .expect_err("Parsed an invalid module");
+    }
+
     #[test]
     fn test_load_module() {
         let module =
@@ -0,0 +1,129 @@
+use deno_core::{serde_json, serde_v8::from_v8, v8, OpState};
+use serde::de::DeserializeOwned;
+use std::{cell::RefCell, collections::HashMap, sync::Arc};
+
+use crate::{traits::ToV8String, Error};
+
+/// Namespaces of every module loaded into a runtime, keyed by module filename, and by specifier
+///
+/// Kept in the runtime's op state, so that registered functions can reach them
+#[derive(Default)]
+pub(crate) struct ModuleNamespaces {
+    by_filename: HashMap<String, v8::Global<v8::Object>>,
+    by_specifier: HashMap<String, v8::Global<v8::Object>>,
+}
+impl ModuleNamespaces {
+    pub fn insert(
+        &mut self,
+        filename: String,
+        specifier: String,
+        namespace: v8::Global<v8::Object>,
+    ) {
+        self.by_filename.insert(filename, namespace.clone());
+        self.by_specifier.insert(specifier, namespace);
+    }
+
+    /// Find a module by the filename it was loaded with
+    pub fn get(&self, filename: &str) -> Option<&v8::Global<v8::Object>> {
+        self.by_filename.get(filename)
+    }
+
+    /// Find a module by its resolved specifier
+    pub fn get_by_specifier(&self, specifier: &str) -> Option<&v8::Global<v8::Object>> {
+        self.by_specifier.get(specifier)
+    }
+}
+
+/// Synchronous access to the exports of already-loaded modules, from inside a registered function
+///
+/// Passed to functions registered with [`crate::Runtime::register_module_function`]
+/// The getter borrows the runtime's scope for the duration of the call, so it cannot be sent
+/// to another thread, or kept after the function returns
+///
+/// Values are read from the module namespace without running the event loop
+/// Exports that run JS while being deserialized (such as getters) may call back into registered functions,
+/// up to the runtime's [`crate::RuntimeOptions::max_call_depth`]
+///
+/// The getter also provides the runtime's shared context - see [`crate::Runtime::set_shared_context`]
+pub struct ModuleGetter<'a, 's> {
+    scope: &'a mut v8::HandleScope<'s>,
+    state: &'a RefCell<OpState>,
+}
+
+impl<'a, 's> ModuleGetter<'a, 's> {
+    pub(crate) fn new(scope: &'a mut v8::HandleScope<'s>, state: &'a RefCell<OpState>) -> Self {
+        Self { scope, state }
+    }
+
+    /// Returns true if a module with the given filename has been loaded into the runtime
+    #[must_use]
+    pub fn has_module(&self, module: &str) -> bool {
+        self.state
+            .borrow()
+            .try_borrow::<ModuleNamespaces>()
+            .is_some_and(|namespaces| namespaces.get(module).is_some())
+    }
+
+    /// Get the shared context of the given type, set with [`crate::Runtime::set_shared_context`]
+    /// Returns `None` if no context of that type has been set
+    ///
+    /// Only the `Arc` is cloned - the context itself is never copied or serialized
+    #[must_use]
+    pub fn shared_context<T>(&self) -> Option<Arc<T>>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.state.borrow().try_borrow::<Arc<T>>().cloned()
+    }
+
+    /// Get an export from an already-loaded module, and deserialize it
+    ///
+    /// # Arguments
+    /// * `module` - The filename the module was loaded with, such as `"test.js"`
+    /// * `name` - The name of the export
+    ///
+    /// # Errors
+    /// Will return an error if the module is not loaded, the export does not exist,
+    /// or the value cannot be deserialized into the requested type
+    pub fn get<T>(&mut self, module: &str, name: &str) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        // The state is released before running any JS, since it may call back into registered functions
+        let namespace = self
+            .state
+            .borrow()
+            .try_borrow::<ModuleNamespaces>()
+            .and_then(|namespaces| namespaces.get(module).cloned())
+            .ok_or_else(|| Error::ModuleNotFound(format!("{module} has not been loaded")))?;
+
+        let mut scope = v8::TryCatch::new(&mut *self.scope);
+        let namespace = v8::Local::new(&mut scope, namespace);
+        let key = name.to_v8_string(&mut scope)?;
+        let value = match namespace.get(&mut scope, key.into()) {
+            Some(value) if !value.is_undefined() => value,
+            _ if scope.has_caught() => {
+                let msg = scope
+                    .message()
+                    .map(|m| m.get(&mut scope).to_rust_string_lossy(&mut scope))
+                    .unwrap_or_default();
+                return Err(Error::Runtime(msg));
+            }
+            _ => return Err(Error::ValueNotFound(format!("{module}::{name}"))),
+        };
+
+        let _active = crate::js_value::ActiveRuntime::enter(&mut scope);
+        Ok(from_v8(&mut scope, value)?)
+    }
+}
+
+/// Represents a function that can be registered with the runtime, with access to loaded modules
+pub trait RsModuleFunction:
+    Fn(&[serde_json::Value], &mut ModuleGetter<'_, '_>) -> Result<serde_json::Value, Error> + 'static
+{
+}
+impl<F> RsModuleFunction for F where
+    F: Fn(&[serde_json::Value], &mut ModuleGetter<'_, '_>) -> Result<serde_json::Value, Error>
+        + 'static
+{
+}
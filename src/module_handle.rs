@@ -1,7 +1,7 @@
 use deno_core::v8;
 use deno_core::ModuleId;
 
-use crate::Module;
+use crate::{traits::ToModuleSpecifier, Module};
 
 /// Represents a loaded instance of a module within a runtime
 #[derive(Clone, Debug, Eq, PartialEq, Default)]
@@ -9,6 +9,8 @@ pub struct ModuleHandle {
     entrypoint: Option<v8::Global<v8::Function>>,
     module_id: ModuleId,
     module: Module,
+    specifier: String,
+    entrypoint_name: Option<String>,
 }
 
 impl ModuleHandle {
@@ -22,22 +24,43 @@ impl ModuleHandle {
             module_id,
             entrypoint,
             module: module.clone(),
+            specifier: String::new(),
+            entrypoint_name: None,
         }
     }
 
+    /// Set the specifier the module was resolved to
+    pub(crate) fn with_specifier(mut self, specifier: impl ToString) -> Self {
+        self.specifier = specifier.to_string();
+        self
+    }
+
+    /// Set the name of the module's entrypoint function
+    pub(crate) fn with_entrypoint_name(mut self, name: Option<String>) -> Self {
+        self.entrypoint_name = name;
+        self
+    }
+
     /// Create a new module handle from raw parts
     ///
     /// # Safety
     /// This function is unsafe because it allows using potentially invalid `ModuleIds`.
     ///
     /// Use of an unloaded module ID will result in a panic.
+    ///
+    /// The specifier is resolved from the module's filename, relative to the process's current directory
     #[must_use]
     pub unsafe fn from_raw(
         module: &Module,
         module_id: ModuleId,
         entrypoint: Option<v8::Global<v8::Function>>,
     ) -> Self {
-        Self::new(module, module_id, entrypoint)
+        let specifier = std::env::current_dir()
+            .ok()
+            .and_then(|cwd| module.filename().to_module_specifier(&cwd).ok())
+            .map(|specifier| specifier.to_string())
+            .unwrap_or_default();
+        Self::new(module, module_id, entrypoint).with_specifier(specifier)
     }
 
     /// Return this module's contents
@@ -57,4 +80,19 @@ impl ModuleHandle {
     pub fn entrypoint(&self) -> &Option<v8::Global<v8::Function>> {
         &self.entrypoint
     }
+
+    /// Return the absolute specifier this module was resolved to, such as `file:///app/src/test.js`
+    ///
+    /// This is the name under which other modules can import it
+    #[must_use]
+    pub fn specifier(&self) -> &str {
+        &self.specifier
+    }
+
+    /// Return the name of this module's entrypoint function, if it has one and the function is named  
+    /// A default export, or the function passed to `rustyscript.register_entrypoint`, for example
+    #[must_use]
+    pub fn entrypoint_name(&self) -> Option<&str> {
+        self.entrypoint_name.as_deref()
+    }
 }
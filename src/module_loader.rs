@@ -2,38 +2,260 @@
 //! This module provides tools for caching module data, resolving module specifiers, and loading modules
 #![allow(deprecated)]
 use deno_core::{anyhow::Error, ModuleLoader, ModuleSpecifier};
-use std::{cell::RefCell, path::PathBuf, rc::Rc};
+use std::{borrow::Cow, cell::RefCell, path::PathBuf, rc::Rc, sync::Arc};
 
 mod cache_provider;
 mod import_provider;
 mod inner_loader;
 
+#[cfg(feature = "archive")]
+pub(crate) mod archive;
+
 use inner_loader::InnerRustyLoader;
-pub(crate) use inner_loader::LoaderOptions;
+pub use inner_loader::LoaderOptions;
 
 // Public exports
 pub use cache_provider::{ClonableSource, ModuleCacheProvider};
+pub use import_map::ImportMap;
 pub use import_provider::ImportProvider;
 
 use crate::transpiler::ExtensionTranspiler;
+use crate::Module;
+
+/// A custom module resolver, used to provide module source for specifiers on demand
+///
+/// Receives the fully resolved specifier (such as `db:module/foo`, or `file:///path/to/module.js`),  
+/// and returns the module to load for it, or `None` to fall back to the default loaders
+///
+/// Only the module's contents and language hint are used - without a hint (see [`Module::new_typescript`]),
+/// the file extension of the specifier determines if it is transpiled
+pub type ModuleResolver = Box<dyn Fn(&str) -> Option<Module>>;
+
+/// Information about a single module load, passed to a [`ModuleLoadObserver`]
+#[derive(Debug, Clone)]
+pub struct ModuleLoadEvent {
+    /// The fully resolved specifier of the module
+    pub specifier: ModuleSpecifier,
+
+    /// Time taken to fetch, transpile and cache the module - or to retrieve it from the cache
+    pub duration: std::time::Duration,
+
+    /// Size of the module's source code as given to the runtime, in bytes
+    pub bytes: usize,
+
+    /// True if the module was served by the module cache provider
+    pub cache_hit: bool,
+}
+
+/// A callback fired once for each module successfully loaded by the runtime
+///
+/// Purely observational - useful for progress reporting or profiling.  
+/// Failed loads do not trigger the callback
+pub type ModuleLoadObserver = Box<dyn Fn(&ModuleLoadEvent)>;
+
+/// A callback rewriting the source of each module as it is loaded, before it is transpiled
+///
+/// Receives the fully resolved specifier and the original source - typescript is seen as typescript.  
+/// Runs for every module loaded, including imports and modules loaded from rust, but not for modules
+/// served by the module cache provider, which are stored after transformation.
+///
+/// Source maps describe the transformed source, so transforms that keep each line in place,
+/// such as inserting counters at the end of lines, keep error locations meaningful
+pub type SourceTransform = Arc<dyn Fn(&str, &str) -> String>;
+
+/// Information about a dynamic `import()` made by a running script, passed to a [`DynamicImportHook`]
+#[derive(Debug, Clone)]
+pub struct DynamicImportEvent {
+    /// The fully resolved specifier of the imported module
+    pub specifier: ModuleSpecifier,
+
+    /// The specifier of the module making the import
+    pub referrer: String,
+}
+
+/// A callback fired for each dynamic `import()` made by a running script, before the module is loaded
+///
+/// Return `false` to deny the import - the promise returned by `import()` is then rejected.  
+/// Static imports, and modules loaded from rust, do not trigger the callback.  
+/// Fires for every `import()` call, including imports of modules that are already loaded
+pub type DynamicImportHook = Box<dyn Fn(&DynamicImportEvent) -> bool>;
+
+/// Parses a Deno-style import map from JSON, supporting both `imports` and `scopes`
+///
+/// Relative addresses in the map are resolved against `base_url` - usually the location of the import map file
+///
+/// # Errors
+/// Will return an error if the JSON is not a valid import map
+///
+/// ```rust
+/// use rustyscript::module_loader::parse_import_map;
+/// use rustyscript::deno_core::ModuleSpecifier;
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let base = ModuleSpecifier::parse("file:///app/import_map.json").unwrap();
+/// let import_map = parse_import_map(r#"{ "imports": { "utils": "./src/utils.js" } }"#, &base)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn parse_import_map(json: &str, base_url: &ModuleSpecifier) -> Result<ImportMap, crate::Error> {
+    import_map::parse_from_json(base_url.clone(), json)
+        .map(|result| result.import_map)
+        .map_err(|e| crate::Error::Runtime(format!("Invalid import map: {e}")))
+}
 
 /// The primary module loader implementation for rustyscript
 /// This structure manages fetching module code, transpilation, and caching
-pub(crate) struct RustyLoader {
+///
+/// Runtimes create their own loader, but one can also be built directly from [`LoaderOptions`]
+/// to test import resolution without starting a runtime:
+///
+/// ```rust
+/// use rustyscript::module_loader::{LoaderOptions, RustyLoader};
+/// use std::path::PathBuf;
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let mut options = LoaderOptions::default();
+/// options.cwd = PathBuf::from("/app");
+/// options.path_aliases = [("@app/*".to_string(), PathBuf::from("src/*"))].into();
+/// let loader = RustyLoader::new(options);
+///
+/// let specifier = loader.resolve_specifier("@app/utils.js", ".")?;
+/// assert_eq!(specifier.as_str(), "file:///app/src/utils.js");
+///
+/// // Unknown schemes are rejected
+/// assert!(loader.resolve_specifier("ftp://example.com/mod.js", ".").is_err());
+/// # Ok(())
+/// # }
+/// ```
+pub struct RustyLoader {
     inner: Rc<RefCell<InnerRustyLoader>>,
 }
 impl RustyLoader {
     /// Creates a new instance of `RustyLoader`
     /// An optional cache provider can be provided to manage module code caching, as well as an import provider to manage module resolution.
+    #[must_use]
     pub fn new(options: LoaderOptions) -> Self {
         let inner = Rc::new(RefCell::new(InnerRustyLoader::new(options)));
         Self { inner }
     }
 
-    pub fn set_current_dir(&self, current_dir: PathBuf) {
+    /// Resolves a specifier the way a static `import` in `referrer` would, without loading the module
+    ///
+    /// Applies path aliases and the import map, and checks that the import is allowed.  
+    /// A referrer of `"."` marks the import as coming from rust, which allows it to be loaded from the filesystem
+    ///
+    /// # Errors
+    /// Will return an error if the specifier cannot be resolved, or the import is not allowed
+    pub fn resolve_specifier(
+        &self,
+        specifier: &str,
+        referrer: &str,
+    ) -> Result<ModuleSpecifier, crate::Error> {
+        let url =
+            ModuleLoader::resolve(self, specifier, referrer, deno_core::ResolutionKind::Import)?;
+        Ok(url)
+    }
+
+    /// Resolves and loads a module, returning its specifier and the source that would be given to the runtime
+    ///
+    /// Nothing is executed - but the module is fetched, transformed and transpiled as normal,
+    /// so the cache provider and load observer, if any, see it as a regular load
+    ///
+    /// # Errors
+    /// Will return an error if the specifier cannot be resolved, the import is not allowed,
+    /// or the module cannot be loaded
+    pub async fn dry_load(
+        &self,
+        specifier: &str,
+        referrer: &str,
+    ) -> Result<(ModuleSpecifier, String), crate::Error> {
+        let url = self.resolve_specifier(specifier, referrer)?;
+        let code = self.load_source(&url).await?;
+        Ok((url, code))
+    }
+
+    /// Loads an already resolved module, returning the source that would be given to the runtime
+    pub(crate) async fn load_source(&self, url: &ModuleSpecifier) -> Result<String, crate::Error> {
+        let response =
+            ModuleLoader::load(self, url, None, false, deno_core::RequestedModuleType::None);
+        let source = match response {
+            deno_core::ModuleLoadResponse::Sync(result) => result?,
+            deno_core::ModuleLoadResponse::Async(future) => future.await?,
+        };
+
+        Ok(match source.code {
+            deno_core::ModuleSourceCode::String(code) => code.as_str().to_string(),
+            deno_core::ModuleSourceCode::Bytes(code) => {
+                String::from_utf8_lossy(code.as_bytes()).into_owned()
+            }
+        })
+    }
+
+    pub(crate) fn set_current_dir(&self, current_dir: PathBuf) {
         self.inner_mut().set_current_dir(current_dir);
     }
 
+    /// Sets a custom module resolver, consulted before the filesystem and URL loaders
+    pub(crate) fn set_module_resolver(&self, resolver: ModuleResolver) {
+        self.inner_mut().set_module_resolver(resolver);
+    }
+
+    /// Applies the source transform, if any, to a module's source
+    pub(crate) fn transform_source<'a>(
+        &self,
+        specifier: &ModuleSpecifier,
+        code: &'a str,
+    ) -> Cow<'a, str> {
+        self.inner().transform_source(specifier, code)
+    }
+
+    /// Normalizes a file specifier - see [`LoaderOptions::case_insensitive_paths`]
+    pub(crate) fn normalize_specifier(&self, specifier: ModuleSpecifier) -> ModuleSpecifier {
+        self.inner_mut().normalize_specifier(specifier)
+    }
+
+    /// Resolves a path or URL the way a module's filename is, relative to the current working directory
+    pub(crate) fn resolve_from_cwd(&self, specifier: &str) -> Result<ModuleSpecifier, Error> {
+        self.inner_mut().resolve_from_cwd(specifier)
+    }
+
+    /// Reports a module given to the runtime directly from rust to the load observer, if there is one
+    pub(crate) fn report_load(
+        &self,
+        specifier: &ModuleSpecifier,
+        start: std::time::Instant,
+        bytes: usize,
+    ) {
+        self.inner().report_load(specifier, start, bytes, false);
+    }
+
+    /// Makes loads of `specifier` fail, so that it is only found if it is already in the runtime
+    pub(crate) fn begin_loaded_only(&self, specifier: ModuleSpecifier) {
+        self.inner_mut().begin_loaded_only(specifier);
+    }
+
+    /// Ends [`RustyLoader::begin_loaded_only`], returning true if a load was refused
+    pub(crate) fn end_loaded_only(&self) -> bool {
+        self.inner_mut().end_loaded_only()
+    }
+
+    /// Makes a module available to imports under the given specifier, without loading it
+    pub(crate) fn register_static_module(&self, specifier: ModuleSpecifier, module: Module) {
+        self.inner_mut().register_static_module(specifier, module);
+    }
+
+    /// Confines imports made by modules under `root` to that directory
+    #[cfg(feature = "archive")]
+    pub(crate) fn add_archive_root(&self, root: ModuleSpecifier) {
+        self.inner_mut().add_archive_root(root);
+    }
+
+    /// Forgets the static modules registered under `root` - modules already loaded are unaffected
+    #[cfg(feature = "archive")]
+    pub(crate) fn remove_static_modules_under(&self, root: &ModuleSpecifier) {
+        self.inner_mut().remove_static_modules_under(root);
+    }
+
     fn inner(&self) -> std::cell::Ref<InnerRustyLoader> {
         self.inner.borrow()
     }
@@ -45,19 +267,24 @@ impl RustyLoader {
     /// Inserts a source map into the source map cache
     /// This is used to provide source maps for loaded modules
     /// for error message generation
-    pub fn insert_source_map(&self, file_name: &str, code: String, source_map: Option<Vec<u8>>) {
+    pub(crate) fn insert_source_map(
+        &self,
+        file_name: &str,
+        code: String,
+        source_map: Option<Vec<u8>>,
+    ) {
         self.inner_mut().add_source_map(file_name, code, source_map);
     }
 
     /// Get an extension transpiler that can be injected into a `deno_core::JsRuntime`
-    pub fn as_extension_transpiler(self: &Rc<Self>) -> ExtensionTranspiler {
+    pub(crate) fn as_extension_transpiler(self: &Rc<Self>) -> ExtensionTranspiler {
         let loader = self.clone();
         Rc::new(move |specifier, code| loader.inner().transpile_extension(&specifier, &code))
     }
 
     /// Transpile a module from CJS to ESM
     #[allow(dead_code)]
-    pub async fn translate_cjs(
+    pub(crate) async fn translate_cjs(
         &self,
         specifier: &ModuleSpecifier,
         source: &str,
@@ -82,7 +309,11 @@ impl ModuleLoader for RustyLoader {
         referrer: &str,
         kind: deno_core::ResolutionKind,
     ) -> Result<ModuleSpecifier, Error> {
-        self.inner_mut().resolve(specifier, referrer, kind)
+        let url = self.inner_mut().resolve(specifier, referrer, kind)?;
+        if matches!(kind, deno_core::ResolutionKind::DynamicImport) {
+            self.inner().check_dynamic_import(&url, referrer)?;
+        }
+        Ok(url)
     }
 
     /// Load a module by it's name
@@ -262,4 +493,386 @@ mod test {
             }
         }
     }
+
+    #[tokio::test]
+    async fn test_standalone_loader() {
+        let cwd = std::env::current_dir().unwrap();
+        let loader = RustyLoader::new(LoaderOptions {
+            path_aliases: [("@js/*".to_string(), PathBuf::from("examples/javascript/*"))]
+                .into_iter()
+                .collect(),
+            cwd: cwd.clone(),
+            ..LoaderOptions::default()
+        });
+
+        let expected = "examples/javascript/example_module.js"
+            .to_module_specifier(&cwd)
+            .unwrap();
+        let specifier = loader
+            .resolve_specifier("@js/example_module.js", ".")
+            .unwrap();
+        assert_eq!(specifier, expected);
+
+        let (specifier, source) = loader.dry_load("@js/example_module.js", ".").await.unwrap();
+        assert_eq!(specifier, expected);
+        assert!(source.contains("export"));
+
+        // Unknown schemes are denied
+        loader
+            .resolve_specifier("denied:module", ".")
+            .expect_err("Unrecognized schemes should not resolve");
+        loader
+            .dry_load("denied:module", ".")
+            .await
+            .expect_err("Unrecognized schemes should not load");
+    }
+
+    #[test]
+    fn test_source_cache_limit() {
+        let loader = RustyLoader::new(LoaderOptions {
+            source_cache_limit: Some(2),
+            ..LoaderOptions::default()
+        });
+
+        loader.insert_source_map("a.js", "a".to_string(), None);
+        loader.insert_source_map("b.js", "b".to_string(), None);
+
+        // Reading `a` makes `b` the least recently used
+        assert!(loader.get_source_mapped_source_line("a.js", 0).is_some());
+        loader.insert_source_map("c.js", "c".to_string(), None);
+
+        assert!(loader.get_source_mapped_source_line("a.js", 0).is_some());
+        assert!(loader.get_source_mapped_source_line("b.js", 0).is_none());
+        assert!(loader.get_source_mapped_source_line("c.js", 0).is_some());
+    }
+
+    #[test]
+    fn test_path_aliases() {
+        let cwd = std::env::current_dir().unwrap();
+        let loader = RustyLoader::new(LoaderOptions {
+            path_aliases: [
+                ("@js/*".to_string(), PathBuf::from("examples/javascript/*")),
+                (
+                    "@example".to_string(),
+                    PathBuf::from("examples/javascript/example_module.js"),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+            cwd: cwd.clone(),
+            ..LoaderOptions::default()
+        });
+
+        let expected = "examples/javascript/example_module.js"
+            .to_module_specifier(&cwd)
+            .unwrap();
+
+        // Wildcard alias, with the extension probed
+        let specifier = loader
+            .resolve("@js/example_module", ".", ResolutionKind::Import)
+            .unwrap();
+        assert_eq!(specifier, expected);
+
+        // Exact alias
+        let specifier = loader
+            .resolve("@example", ".", ResolutionKind::Import)
+            .unwrap();
+        assert_eq!(specifier, expected);
+
+        // Prefixes must match exactly - this stays an unresolvable bare specifier
+        loader
+            .resolve("@jsx/example_module", ".", ResolutionKind::Import)
+            .expect_err("Alias applied to the wrong prefix");
+    }
+
+    #[test]
+    fn test_case_insensitive_paths() {
+        let dir =
+            std::env::temp_dir().join(format!("rustyscript_test_casing_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("Lib")).unwrap();
+        std::fs::write(dir.join("Lib").join("Foo.js"), "export const foo = 1;").unwrap();
+        let resolve = |loader: &RustyLoader, specifier: &str| {
+            let specifier = specifier.to_module_specifier(&dir).unwrap();
+            loader
+                .resolve(specifier.as_str(), ".", ResolutionKind::Import)
+                .unwrap()
+        };
+
+        // Paths are case-sensitive by default
+        let loader = RustyLoader::new(LoaderOptions::default());
+        assert_ne!(
+            resolve(&loader, "Lib/Foo.js"),
+            resolve(&loader, "lib/foo.js")
+        );
+
+        // Otherwise the casing on disk is used for every spelling of the path - even if the first one is wrong
+        let loader = RustyLoader::new(LoaderOptions {
+            case_insensitive_paths: true,
+            ..LoaderOptions::default()
+        });
+        let first = resolve(&loader, "lib/foo.js");
+        assert!(first.as_str().ends_with("/Lib/Foo.js"), "{first}");
+        assert_eq!(first, resolve(&loader, "Lib/Foo.js"));
+        assert_eq!(first, resolve(&loader, "LIB/FOO.JS"));
+
+        // Relative imports from a module are normalized the same way
+        let url = loader
+            .resolve("./FOO.js", first.as_str(), ResolutionKind::Import)
+            .unwrap();
+        assert_eq!(first, url);
+
+        // Paths that are not on disk are left as they are, until one of them loads
+        let missing = resolve(&loader, "lib/Missing.js");
+        assert!(missing.as_str().ends_with("/lib/Missing.js"), "{missing}");
+        assert_ne!(missing, resolve(&loader, "lib/missing.js"));
+
+        let specifier = dir.join("Static.js").to_module_specifier(&dir).unwrap();
+        loader.register_static_module(specifier.clone(), crate::Module::new("Static.js", ""));
+        assert_eq!(specifier, resolve(&loader, "static.js"));
+
+        // Backslashes are only path separators on windows
+        if cfg!(windows) {
+            assert_eq!(first, resolve(&loader, "lib\\foo.js"));
+        } else {
+            assert_ne!(first, resolve(&loader, "lib\\foo.js"));
+        }
+
+        // Non-file specifiers are left alone
+        let url = ModuleSpecifier::parse("https://example.com/Foo.js").unwrap();
+        assert_eq!(url, loader.normalize_specifier(url.clone()));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_import_map() {
+        let base = ModuleSpecifier::parse("file:///app/import_map.json").unwrap();
+        let import_map = parse_import_map(
+            r#"{
+                "imports": {
+                    "utils": "db:/app/src/utils.js",
+                    "lib/": "db:/lib/"
+                },
+                "scopes": {
+                    "/app/legacy/": { "utils": "db:/app/src/legacy_utils.js" }
+                }
+            }"#,
+            &base,
+        )
+        .expect("Could not parse import map");
+
+        let loader = RustyLoader::new(LoaderOptions {
+            import_map: Some(import_map),
+            schema_whitelist: ["db:".to_string()].into_iter().collect(),
+            ..LoaderOptions::default()
+        });
+
+        let resolve = |specifier: &str, referrer: &str| {
+            loader
+                .resolve(specifier, referrer, ResolutionKind::Import)
+                .map(|url| url.to_string())
+        };
+
+        assert_eq!(
+            resolve("utils", "file:///app/main.js").unwrap(),
+            "db:/app/src/utils.js"
+        );
+        assert_eq!(
+            resolve("utils", "file:///app/legacy/main.js").unwrap(),
+            "db:/app/src/legacy_utils.js"
+        );
+        assert_eq!(
+            resolve("lib/a.js", "file:///app/main.js").unwrap(),
+            "db:/lib/a.js"
+        );
+
+        // Relative imports still resolve normally
+        assert_eq!(
+            resolve("./other.js", "db:/app/main.js").unwrap(),
+            "db:/app/other.js"
+        );
+
+        // Unmapped bare specifiers fail as usual
+        resolve("missing", "file:///app/main.js").expect_err("Resolved an unmapped specifier");
+
+        // The import map applies before path aliases, and its failures are reported
+        let import_map = parse_import_map(
+            r#"{ "imports": { "@app/utils": "db:/mapped.js", "blocked": null } }"#,
+            &base,
+        )
+        .expect("Could not parse import map");
+        let loader = RustyLoader::new(LoaderOptions {
+            import_map: Some(import_map),
+            path_aliases: [("@app/*".to_string(), "/app/src/*".into())]
+                .into_iter()
+                .collect(),
+            schema_whitelist: ["db:".to_string()].into_iter().collect(),
+            ..LoaderOptions::default()
+        });
+        let resolve = |specifier: &str| {
+            loader
+                .resolve(specifier, "file:///app/main.js", ResolutionKind::Import)
+                .map(|url| url.to_string())
+        };
+        assert_eq!(resolve("@app/utils").unwrap(), "db:/mapped.js");
+        resolve("blocked").expect_err("Resolved an import blocked by the import map");
+    }
+
+    #[tokio::test]
+    async fn test_load_observer() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let observer_events = events.clone();
+        let loader = RustyLoader::new(LoaderOptions {
+            cache_provider: Some(Box::new(MemoryModuleCacheProvider::default())),
+            load_observer: Some(Box::new(move |event: &ModuleLoadEvent| {
+                observer_events.borrow_mut().push(event.clone());
+            })),
+            ..LoaderOptions::default()
+        });
+
+        let specifier = "examples/javascript/example_module.js"
+            .to_module_specifier(&std::env::current_dir().unwrap())
+            .unwrap();
+
+        // First load is fresh, second is served by the cache
+        for _ in 0..2 {
+            match loader.load(
+                &specifier,
+                None,
+                false,
+                deno_core::RequestedModuleType::None,
+            ) {
+                ModuleLoadResponse::Async(future) => {
+                    future.await.expect("Expected to get source");
+                }
+                ModuleLoadResponse::Sync(result) => {
+                    result.expect("Expected to get source");
+                }
+            }
+        }
+
+        let events = events.borrow();
+        assert_eq!(events.len(), 2);
+        assert!(events
+            .iter()
+            .all(|e| e.specifier == specifier && e.bytes > 0));
+        assert!(!events[0].cache_hit);
+        assert!(events[1].cache_hit);
+    }
+
+    #[cfg(feature = "url_import")]
+    #[tokio::test]
+    async fn test_url_import_headers() {
+        use std::io::{Read, Write};
+
+        // Minimal server that records the request, and refuses it
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![0; 4096];
+            let len = stream.read(&mut request).unwrap();
+            stream
+                .write_all(
+                    b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .unwrap();
+            String::from_utf8_lossy(&request[..len]).to_lowercase()
+        });
+
+        let loader = RustyLoader::new(LoaderOptions {
+            url_import_headers: [("Authorization".to_string(), "Bearer token".to_string())]
+                .into_iter()
+                .collect(),
+            url_import_user_agent: Some("rustyscript-test".to_string()),
+            ..LoaderOptions::default()
+        });
+
+        let specifier = ModuleSpecifier::parse(&format!("http://127.0.0.1:{port}/mod.js")).unwrap();
+        let ModuleLoadResponse::Async(future) = loader.load(
+            &specifier,
+            None,
+            false,
+            deno_core::RequestedModuleType::None,
+        ) else {
+            panic!("Unexpected response");
+        };
+        let error: crate::Error = future.await.expect_err("Expected a 401").into();
+        assert!(matches!(error, crate::Error::ImportHttpStatus(_, 401)));
+
+        let request = server.join().unwrap();
+        assert!(request.contains("authorization: bearer token"));
+        assert!(request.contains("user-agent: rustyscript-test"));
+    }
+
+    #[cfg(feature = "url_import")]
+    #[tokio::test]
+    async fn test_url_import_tls_options() {
+        use std::{
+            io::{Read, Write},
+            sync::{
+                atomic::{AtomicUsize, Ordering},
+                Arc,
+            },
+        };
+
+        /// Root certificate store that counts how many times it is requested
+        struct CountingStore(deno_tls::rustls::RootCertStore, AtomicUsize);
+        impl deno_tls::RootCertStoreProvider for CountingStore {
+            fn get_or_try_init(
+                &self,
+            ) -> Result<&deno_tls::rustls::RootCertStore, deno_core::error::AnyError> {
+                self.1.fetch_add(1, Ordering::SeqCst);
+                Ok(&self.0)
+            }
+        }
+
+        // Minimal server that refuses every request
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().unwrap();
+                let _request = stream.read(&mut [0; 4096]).unwrap();
+                stream
+                    .write_all(
+                        b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    )
+                    .unwrap();
+            }
+        });
+
+        // An empty list ignores certificate errors for every host - the client can still be built with it
+        let store = Arc::new(CountingStore(
+            deno_tls::rustls::RootCertStore::empty(),
+            AtomicUsize::new(0),
+        ));
+        let loader = RustyLoader::new(LoaderOptions {
+            url_import_root_cert_store_provider: Some(store.clone()),
+            url_import_unsafely_ignore_certificate_errors: Some(vec![]),
+            ..LoaderOptions::default()
+        });
+
+        for name in ["a.js", "b.js"] {
+            let specifier =
+                ModuleSpecifier::parse(&format!("http://127.0.0.1:{port}/{name}")).unwrap();
+            let ModuleLoadResponse::Async(future) = loader.load(
+                &specifier,
+                None,
+                false,
+                deno_core::RequestedModuleType::None,
+            ) else {
+                panic!("Unexpected response");
+            };
+            let error: crate::Error = future.await.expect_err("Expected a 404").into();
+            assert!(
+                matches!(error, crate::Error::ImportHttpStatus(_, 404)),
+                "{error}"
+            );
+        }
+        server.join().unwrap();
+
+        // The client, and its TLS config, is only built once
+        assert_eq!(store.1.load(Ordering::SeqCst), 1);
+    }
 }
@@ -0,0 +1,435 @@
+//! Reads modules out of an in-memory zip, tar or gzipped tar archive
+//! Only the entries are extracted - nothing is ever written to disk
+use crate::Error;
+use std::io::Read;
+
+/// File extensions of entries that are extracted as modules - anything else in the archive is ignored
+const MODULE_EXTENSIONS: &[&str] = &["js", "mjs", "cjs", "jsx", "ts", "mts", "cts", "tsx", "json"];
+
+/// Most bytes that will be decompressed out of an archive, across all of its entries
+/// Guards against archives that expand to far more than their own size
+const MAX_EXTRACTED_SIZE: usize = 256 * 1024 * 1024;
+
+/// Extracts every module in the archive, as `(path, source)` pairs
+///
+/// The format is detected from the leading bytes - zip, gzip-compressed tar, or plain tar  
+/// Paths are relative to the root of the archive, using `/` as a separator
+pub(crate) fn read_archive(bytes: &[u8]) -> Result<Vec<(String, String)>, Error> {
+    let entries = if bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06") {
+        read_zip(bytes)?
+    } else if bytes.starts_with(&[0x1f, 0x8b]) {
+        let tar = decompress(
+            flate2::read::GzDecoder::new(bytes),
+            MAX_EXTRACTED_SIZE,
+            "archive",
+        )?;
+        read_tar(&tar)?
+    } else {
+        read_tar(bytes)?
+    };
+
+    let mut modules = vec![];
+    for (name, data) in entries {
+        let Some(path) = normalize_path(&name)? else {
+            continue;
+        };
+
+        let is_module = path
+            .rsplit_once('.')
+            .is_some_and(|(_, ext)| MODULE_EXTENSIONS.contains(&ext));
+        if !is_module {
+            continue;
+        }
+
+        let source =
+            String::from_utf8(data).map_err(|_| invalid(format!("{path} is not valid UTF-8")))?;
+        modules.push((path, source));
+    }
+
+    Ok(modules)
+}
+
+/// Normalizes an entry path to be relative to the archive root
+/// Returns `None` for directory entries, and an error for paths that escape the root
+pub(crate) fn normalize_path(name: &str) -> Result<Option<String>, Error> {
+    if name.ends_with('/') || name.ends_with('\\') {
+        return Ok(None);
+    }
+
+    let mut parts = vec![];
+    for part in name.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                if parts.pop().is_none() {
+                    return Err(invalid(format!("{name} points outside of the archive")));
+                }
+            }
+            part => parts.push(part),
+        }
+    }
+
+    if parts.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(parts.join("/")))
+    }
+}
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::InvalidArchive(message.into())
+}
+
+/// Decompresses a stream, failing if it expands to more than `limit` bytes
+fn decompress(reader: impl Read, limit: usize, name: &str) -> Result<Vec<u8>, Error> {
+    let mut data = vec![];
+    reader
+        .take(u64::try_from(limit).unwrap_or(u64::MAX).saturating_add(1))
+        .read_to_end(&mut data)
+        .map_err(|e| invalid(format!("could not decompress {name}: {e}")))?;
+    if data.len() > limit {
+        return Err(invalid(format!(
+            "{name} is larger than {limit} bytes once decompressed"
+        )));
+    }
+    Ok(data)
+}
+
+/// Adds to an offset, failing instead of overflowing
+fn offset(start: usize, by: usize) -> Result<usize, Error> {
+    start
+        .checked_add(by)
+        .ok_or_else(|| invalid("unexpected end of archive"))
+}
+
+fn slice(bytes: &[u8], start: usize, len: usize) -> Result<&[u8], Error> {
+    bytes
+        .get(start..offset(start, len)?)
+        .ok_or_else(|| invalid("unexpected end of archive"))
+}
+
+/// Reads a little-endian u16 from `field` bytes past `start`
+fn u16_at(bytes: &[u8], start: usize, field: usize) -> Result<usize, Error> {
+    let b = slice(bytes, offset(start, field)?, 2)?;
+    Ok(usize::from(u16::from_le_bytes([b[0], b[1]])))
+}
+
+/// Reads a little-endian u32 from `field` bytes past `start`
+fn u32_at(bytes: &[u8], start: usize, field: usize) -> Result<usize, Error> {
+    let b = slice(bytes, offset(start, field)?, 4)?;
+    usize::try_from(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .map_err(|_| invalid("zip entry is too large"))
+}
+
+/// Reads a NUL-terminated string out of a fixed-size tar header field
+fn header_str(field: &[u8]) -> String {
+    let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+fn read_zip(bytes: &[u8]) -> Result<Vec<(String, Vec<u8>)>, Error> {
+    const EOCD_SIGNATURE: &[u8] = b"PK\x05\x06";
+    const CENTRAL_SIGNATURE: &[u8] = b"PK\x01\x02";
+    const LOCAL_SIGNATURE: &[u8] = b"PK\x03\x04";
+
+    // The end of central directory record is at least 22 bytes, followed by a comment of up to 64k
+    let eocd = (0..=bytes.len().saturating_sub(22))
+        .rev()
+        .take(0xFFFF + 1)
+        .find(|i| bytes[*i..].starts_with(EOCD_SIGNATURE))
+        .ok_or_else(|| invalid("zip end of central directory not found"))?;
+
+    let count = u16_at(bytes, eocd, 10)?;
+    let mut position = u32_at(bytes, eocd, 16)?;
+    if count == 0xFFFF || position == 0xFFFF_FFFF {
+        return Err(invalid("zip64 archives are not supported"));
+    }
+
+    // Bytes that may still be decompressed, out of MAX_EXTRACTED_SIZE
+    let mut budget = MAX_EXTRACTED_SIZE;
+
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        if slice(bytes, position, 4)? != CENTRAL_SIGNATURE {
+            return Err(invalid("corrupt zip central directory"));
+        }
+
+        let flags = u16_at(bytes, position, 8)?;
+        let method = u16_at(bytes, position, 10)?;
+        let compressed_size = u32_at(bytes, position, 20)?;
+        let size = u32_at(bytes, position, 24)?;
+        let name_len = u16_at(bytes, position, 28)?;
+        let extra_len = u16_at(bytes, position, 30)?;
+        let comment_len = u16_at(bytes, position, 32)?;
+        let local_offset = u32_at(bytes, position, 42)?;
+        let name =
+            String::from_utf8_lossy(slice(bytes, offset(position, 46)?, name_len)?).into_owned();
+        position = offset(position, 46 + name_len + extra_len + comment_len)?;
+
+        if name.ends_with('/') {
+            continue;
+        }
+        if flags & 1 != 0 {
+            return Err(invalid(format!("{name} is encrypted")));
+        }
+
+        // Sizes are taken from the central directory, since the local header may defer them
+        if slice(bytes, local_offset, 4)? != LOCAL_SIGNATURE {
+            return Err(invalid(format!("corrupt zip entry {name}")));
+        }
+        let data_offset = offset(
+            local_offset,
+            30 + u16_at(bytes, local_offset, 26)? + u16_at(bytes, local_offset, 28)?,
+        )?;
+        let raw = slice(bytes, data_offset, compressed_size)?;
+
+        // The declared size is checked before decompressing, then checked again against the data
+        if size > budget {
+            return Err(invalid(format!(
+                "{name} is larger than {MAX_EXTRACTED_SIZE} bytes once decompressed"
+            )));
+        }
+        budget -= size;
+
+        let data = match method {
+            0 => raw.to_vec(),
+            8 => decompress(flate2::read::DeflateDecoder::new(raw), size, &name)?,
+            method => {
+                return Err(invalid(format!(
+                    "{name} uses unsupported compression method {method}"
+                )));
+            }
+        };
+        if data.len() != size {
+            return Err(invalid(format!(
+                "{name} does not match its declared size of {size} bytes"
+            )));
+        }
+
+        entries.push((name, data));
+    }
+
+    Ok(entries)
+}
+
+fn read_tar(bytes: &[u8]) -> Result<Vec<(String, Vec<u8>)>, Error> {
+    const BLOCK: usize = 512;
+
+    let mut entries = vec![];
+    let mut long_name = None;
+    let mut position = 0;
+    while let Ok(header) = slice(bytes, position, BLOCK) {
+        if header.iter().all(|b| *b == 0) {
+            break;
+        }
+
+        let size_field = header_str(&header[124..136]);
+        let size = usize::from_str_radix(size_field.trim(), 8)
+            .map_err(|_| invalid(format!("bad tar entry size '{size_field}'")))?;
+        let data_offset = offset(position, BLOCK)?;
+        let data = slice(bytes, data_offset, size)?;
+
+        // The data is padded out to a whole number of blocks
+        position = offset(data_offset, size.div_ceil(BLOCK) * BLOCK)?;
+
+        let mut name = header_str(&header[0..100]);
+        if &header[257..262] == b"ustar" {
+            let prefix = header_str(&header[345..500]);
+            if !prefix.is_empty() {
+                name = format!("{prefix}/{name}");
+            }
+        }
+
+        match header[156] {
+            // Regular files
+            0 | b'0' | b'7' => {
+                let name = long_name.take().unwrap_or(name);
+                entries.push((name, data.to_vec()));
+            }
+
+            // GNU long names apply to the entry that follows
+            b'L' => long_name = Some(header_str(data)),
+
+            // PAX extended headers - only the path is used
+            b'x' => long_name = pax_path(data).or(long_name),
+
+            // Directories, links, devices and global headers have no module source
+            _ => long_name = None,
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Finds the `path` record in a PAX extended header, made of `<length> <key>=<value>\n` records
+fn pax_path(data: &[u8]) -> Option<String> {
+    let data = String::from_utf8_lossy(data);
+    data.lines().find_map(|record| {
+        let (_, pair) = record.split_once(' ')?;
+        pair.strip_prefix("path=").map(str::to_string)
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::cast_possible_truncation)]
+pub(crate) mod test {
+    use super::*;
+    use std::io::Write;
+
+    pub(crate) fn tar(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut tar = vec![];
+        for (name, contents) in files {
+            let mut header = [0u8; 512];
+            header[..name.len()].copy_from_slice(name.as_bytes());
+            let size = format!("{:011o}\0", contents.len());
+            header[124..136].copy_from_slice(size.as_bytes());
+            header[156] = b'0';
+            header[257..263].copy_from_slice(b"ustar\0");
+            tar.extend_from_slice(&header);
+            tar.extend_from_slice(contents.as_bytes());
+            tar.resize(tar.len().div_ceil(512) * 512, 0);
+        }
+        tar.resize(tar.len() + 1024, 0);
+        tar
+    }
+
+    fn zip(files: &[(&str, &str)], deflate: bool) -> Vec<u8> {
+        let mut zip = vec![];
+        let mut central = vec![];
+        for (name, contents) in files {
+            let data = if deflate {
+                let mut encoder = flate2::write::DeflateEncoder::new(vec![], Default::default());
+                encoder.write_all(contents.as_bytes()).unwrap();
+                encoder.finish().unwrap()
+            } else {
+                contents.as_bytes().to_vec()
+            };
+            let method: u16 = if deflate { 8 } else { 0 };
+
+            let local_offset = zip.len() as u32;
+            zip.extend_from_slice(b"PK\x03\x04");
+            zip.extend_from_slice(&[20, 0, 0, 0]);
+            zip.extend_from_slice(&method.to_le_bytes());
+            zip.extend_from_slice(&[0; 8]);
+            zip.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            zip.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+            zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            zip.extend_from_slice(&[0, 0]);
+            zip.extend_from_slice(name.as_bytes());
+            zip.extend_from_slice(&data);
+
+            central.extend_from_slice(b"PK\x01\x02");
+            central.extend_from_slice(&[20, 0, 20, 0, 0, 0]);
+            central.extend_from_slice(&method.to_le_bytes());
+            central.extend_from_slice(&[0; 8]);
+            central.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            central.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+            central.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            central.extend_from_slice(&[0; 12]);
+            central.extend_from_slice(&local_offset.to_le_bytes());
+            central.extend_from_slice(name.as_bytes());
+        }
+
+        let central_offset = zip.len() as u32;
+        zip.extend_from_slice(&central);
+        zip.extend_from_slice(b"PK\x05\x06");
+        zip.extend_from_slice(&[0; 4]);
+        zip.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&(central.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&central_offset.to_le_bytes());
+        zip.extend_from_slice(&[0, 0]);
+        zip
+    }
+
+    const FILES: &[(&str, &str)] = &[
+        ("./main.js", "export default 1;"),
+        ("lib/util.ts", "export const x: number = 2;"),
+        ("readme.md", "# not a module"),
+    ];
+
+    fn expected() -> Vec<(String, String)> {
+        vec![
+            ("main.js".to_string(), "export default 1;".to_string()),
+            (
+                "lib/util.ts".to_string(),
+                "export const x: number = 2;".to_string(),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_read_tar() {
+        assert_eq!(read_archive(&tar(FILES)).unwrap(), expected());
+
+        let mut gz = flate2::write::GzEncoder::new(vec![], Default::default());
+        gz.write_all(&tar(FILES)).unwrap();
+        assert_eq!(read_archive(&gz.finish().unwrap()).unwrap(), expected());
+    }
+
+    #[test]
+    fn test_read_zip() {
+        assert_eq!(read_archive(&zip(FILES, false)).unwrap(), expected());
+        assert_eq!(read_archive(&zip(FILES, true)).unwrap(), expected());
+
+        let truncated = zip(FILES, false);
+        read_archive(&truncated[..truncated.len() - 30]).unwrap_err();
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(
+            normalize_path("./a/./b.js").unwrap(),
+            Some("a/b.js".to_string())
+        );
+        assert_eq!(
+            normalize_path("a\\b.js").unwrap(),
+            Some("a/b.js".to_string())
+        );
+        assert_eq!(normalize_path("dir/").unwrap(), None);
+        assert_eq!(
+            normalize_path("a/../b.js").unwrap(),
+            Some("b.js".to_string())
+        );
+        normalize_path("../escape.js").unwrap_err();
+        normalize_path("a/../../escape.js").unwrap_err();
+    }
+
+    #[test]
+    fn test_size_limits() {
+        // Start of the only central directory entry - 46 bytes, then the name, then the end record
+        let central = |archive: &[u8]| archive.len() - 22 - "main.js".len() - 46;
+
+        // A zip entry whose data does not match its declared size
+        let mut archive = zip(&[("main.js", "export default 1;")], true);
+        let entry = central(&archive);
+        archive[entry + 24..entry + 28].copy_from_slice(&100u32.to_le_bytes());
+        let e = read_archive(&archive).unwrap_err();
+        assert!(e.to_string().contains("declared size"), "{e}");
+
+        // A zip entry that expands past its declared size is cut off
+        let mut archive = zip(&[("main.js", "export default 1;")], true);
+        let entry = central(&archive);
+        archive[entry + 24..entry + 28].copy_from_slice(&4u32.to_le_bytes());
+        let e = read_archive(&archive).unwrap_err();
+        assert!(e.to_string().contains("once decompressed"), "{e}");
+
+        // A stream that expands past the limit
+        let mut gz = flate2::write::GzEncoder::new(vec![], Default::default());
+        gz.write_all(&[0; 4096]).unwrap();
+        let gz = gz.finish().unwrap();
+        decompress(flate2::read::GzDecoder::new(gz.as_slice()), 1024, "archive").unwrap_err();
+        assert_eq!(
+            decompress(flate2::read::GzDecoder::new(gz.as_slice()), 4096, "archive")
+                .unwrap()
+                .len(),
+            4096
+        );
+
+        // Offsets past the end of the archive fail instead of overflowing
+        let mut archive = zip(&[("main.js", "export default 1;")], false);
+        let entry = central(&archive);
+        archive[entry + 42..entry + 46].copy_from_slice(&u32::MAX.to_le_bytes());
+        read_archive(&archive).unwrap_err();
+    }
+}
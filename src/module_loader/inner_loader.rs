@@ -3,7 +3,10 @@
 #![allow(dead_code)]
 use crate::module_loader::{ClonableSource, ModuleCacheProvider};
 use crate::traits::ToModuleSpecifier;
-use crate::transpiler::{transpile, transpile_extension, ExtensionTranspilation};
+use crate::transpiler::{
+    transpile, transpile_extension, transpile_if, ExtensionTranspilation, TranspileOptions,
+};
+use crate::Module;
 use deno_core::anyhow::{anyhow, Error};
 use deno_core::error::AnyError;
 use deno_core::futures::FutureExt;
@@ -11,6 +14,7 @@ use deno_core::{
     FastString, ModuleLoadResponse, ModuleSource, ModuleSourceCode, ModuleSpecifier, ModuleType,
 };
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::{Arc, RwLock};
@@ -30,14 +34,62 @@ use node_resolver::InNpmPackageChecker;
 #[cfg(feature = "node_experimental")]
 use node_resolver::{NodeResolutionKind, ResolutionMode};
 
-use super::ImportProvider;
+use super::{
+    DynamicImportEvent, DynamicImportHook, ImportMap, ImportProvider, ModuleLoadEvent,
+    ModuleLoadObserver, ModuleResolver, SourceTransform,
+};
+use import_map::ImportMapError;
+use std::borrow::Cow;
 
 /// Stores the source code and source ma#![allow(deprecated)]p for loaded modules
 type SourceMapCache = HashMap<String, (String, Option<Vec<u8>>)>;
 
-/// Options for the `RustyLoader` struct
-/// Not for public use
+/// Tracks when each entry of the source map cache was last used, so the least recently used can be evicted
 #[derive(Default)]
+struct SourceLru {
+    /// Incremented on every use
+    clock: u64,
+
+    /// The last use of each file
+    last_used: HashMap<String, u64>,
+
+    /// Each file by its last use, oldest first
+    by_use: BTreeMap<u64, String>,
+}
+
+impl SourceLru {
+    /// Marks a file as the most recently used
+    fn touch(&mut self, filename: &str) {
+        self.clock += 1;
+        let filename = match self.last_used.get_mut(filename) {
+            Some(used) => {
+                let previous = std::mem::replace(used, self.clock);
+                self.by_use
+                    .remove(&previous)
+                    .unwrap_or_else(|| filename.to_string())
+            }
+            None => {
+                self.last_used.insert(filename.to_string(), self.clock);
+                filename.to_string()
+            }
+        };
+        self.by_use.insert(self.clock, filename);
+    }
+
+    /// Removes and returns the least recently used file
+    fn pop_oldest(&mut self) -> Option<String> {
+        let (_, filename) = self.by_use.pop_first()?;
+        self.last_used.remove(&filename);
+        Some(filename)
+    }
+}
+
+/// Options for the [`crate::module_loader::RustyLoader`] struct
+///
+/// Runtimes build these from [`crate::RuntimeOptions`] - construct them directly only
+/// to use a loader on its own, starting from [`LoaderOptions::default`], since more options may be added
+#[derive(Default)]
+#[non_exhaustive]
 pub struct LoaderOptions {
     /// An optional cache provider to manage module code caching
     pub cache_provider: Option<Box<dyn ModuleCacheProvider>>,
@@ -49,6 +101,9 @@ pub struct LoaderOptions {
     /// Used for error message generation
     pub source_map_cache: SourceMapCache,
 
+    /// Maximum number of entries in the source map cache - the least recently used are evicted
+    pub source_cache_limit: Option<usize>,
+
     /// A resolver for node modules
     #[cfg(feature = "node_experimental")]
     pub node_resolver: Arc<RustyResolver>,
@@ -57,10 +112,52 @@ pub struct LoaderOptions {
     pub import_provider: Option<Box<dyn ImportProvider>>,
 
     /// A whitelist of custom schema prefixes that are allowed to be loaded
-    pub schema_whlist: HashSet<String>,
+    pub schema_whitelist: HashSet<String>,
 
     /// The current working directory for the loader
     pub cwd: PathBuf,
+
+    /// Import aliases, in the style of tsconfig `paths` - `@app/*` -> `src/*`
+    pub path_aliases: HashMap<String, PathBuf>,
+
+    /// If true, file paths that differ only by case refer to the same module
+    pub case_insensitive_paths: bool,
+
+    /// An optional import map, consulted before the default resolution
+    pub import_map: Option<ImportMap>,
+
+    /// An optional callback fired for each module loaded
+    pub load_observer: Option<ModuleLoadObserver>,
+
+    /// An optional callback allowing or denying each dynamic import
+    pub dynamic_import_hook: Option<DynamicImportHook>,
+
+    /// An optional callback rewriting module source before it is transpiled
+    pub source_transform: Option<SourceTransform>,
+
+    /// Headers sent with every module fetched over HTTP
+    #[cfg(feature = "url_import")]
+    pub url_import_headers: HashMap<String, String>,
+
+    /// The user agent sent with every module fetched over HTTP
+    #[cfg(feature = "url_import")]
+    pub url_import_user_agent: Option<String>,
+
+    /// Root certificate store used to verify TLS connections when fetching modules over HTTP
+    /// Defaults to the bundled webpki roots
+    #[cfg(feature = "url_import")]
+    pub url_import_root_cert_store_provider: Option<Arc<dyn deno_tls::RootCertStoreProvider>>,
+
+    /// Domain names or IP addresses for which TLS errors are ignored when fetching modules over HTTP
+    /// An empty list ignores them for every host
+    #[cfg(feature = "url_import")]
+    pub url_import_unsafely_ignore_certificate_errors: Option<Vec<String>>,
+
+    /// If true, modules that need transpiling fail to load instead of being transpiled
+    pub disable_transpile: bool,
+
+    /// Options for the transpiler, for modules that need transpiling
+    pub transpile_options: TranspileOptions,
 }
 
 #[cfg(feature = "node_experimental")]
@@ -91,9 +188,40 @@ pub struct InnerRustyLoader {
     cache_provider: Option<Box<dyn ModuleCacheProvider>>,
     fs_whlist: HashSet<String>,
     source_map_cache: SourceMapCache,
+    source_cache_limit: Option<usize>,
+    source_cache_lru: RefCell<SourceLru>,
     import_provider: Option<Box<dyn ImportProvider>>,
-    schema_whlist: HashSet<String>,
+    schema_whitelist: HashSet<String>,
     cwd: PathBuf,
+    path_aliases: HashMap<String, PathBuf>,
+    case_insensitive_paths: bool,
+    canonical_paths: HashMap<String, ModuleSpecifier>,
+    import_map: Option<ImportMap>,
+    load_observer: Option<ModuleLoadObserver>,
+    dynamic_import_hook: Option<DynamicImportHook>,
+    source_transform: Option<SourceTransform>,
+    #[cfg(feature = "url_import")]
+    url_import_headers: HashMap<String, String>,
+    #[cfg(feature = "url_import")]
+    url_import_user_agent: Option<String>,
+    #[cfg(feature = "url_import")]
+    url_import_root_cert_store_provider: Option<Arc<dyn deno_tls::RootCertStoreProvider>>,
+    #[cfg(feature = "url_import")]
+    url_import_unsafely_ignore_certificate_errors: Option<Vec<String>>,
+    #[cfg(feature = "url_import")]
+    http_client: Option<reqwest::Client>,
+    disable_transpile: bool,
+    transpile_options: TranspileOptions,
+
+    module_resolver: Option<ModuleResolver>,
+    resolved_modules: HashMap<ModuleSpecifier, Module>,
+    static_modules: HashMap<ModuleSpecifier, Module>,
+
+    /// Directories holding the modules of an archive - modules under one may only import from within it
+    archive_roots: Vec<ModuleSpecifier>,
+
+    /// A module that may only be found if it is already loaded, and whether a load of it was refused
+    loaded_only: Option<(ModuleSpecifier, bool)>,
 
     #[cfg(feature = "node_experimental")]
     node: NodeProvider,
@@ -106,10 +234,44 @@ impl InnerRustyLoader {
         Self {
             cache_provider: options.cache_provider,
             fs_whlist: options.fs_whitelist,
+            source_cache_lru: {
+                let mut lru = SourceLru::default();
+                for filename in options.source_map_cache.keys() {
+                    lru.touch(filename);
+                }
+                RefCell::new(lru)
+            },
             source_map_cache: options.source_map_cache,
+            source_cache_limit: options.source_cache_limit,
             import_provider: options.import_provider,
-            schema_whlist: options.schema_whlist,
+            schema_whitelist: options.schema_whitelist,
             cwd: options.cwd,
+            path_aliases: options.path_aliases,
+            case_insensitive_paths: options.case_insensitive_paths,
+            canonical_paths: HashMap::new(),
+            import_map: options.import_map,
+            load_observer: options.load_observer,
+            dynamic_import_hook: options.dynamic_import_hook,
+            source_transform: options.source_transform,
+            #[cfg(feature = "url_import")]
+            url_import_headers: options.url_import_headers,
+            #[cfg(feature = "url_import")]
+            url_import_user_agent: options.url_import_user_agent,
+            #[cfg(feature = "url_import")]
+            url_import_root_cert_store_provider: options.url_import_root_cert_store_provider,
+            #[cfg(feature = "url_import")]
+            url_import_unsafely_ignore_certificate_errors: options
+                .url_import_unsafely_ignore_certificate_errors,
+            #[cfg(feature = "url_import")]
+            http_client: None,
+            disable_transpile: options.disable_transpile,
+            transpile_options: options.transpile_options,
+
+            module_resolver: None,
+            resolved_modules: HashMap::new(),
+            static_modules: HashMap::new(),
+            archive_roots: Vec::new(),
+            loaded_only: None,
 
             #[cfg(feature = "node_experimental")]
             node: NodeProvider::new(options.node_resolver),
@@ -121,6 +283,249 @@ impl InnerRustyLoader {
         self.cwd = cwd;
     }
 
+    /// Refuses to load `specifier` until the returned flag is taken with [`InnerRustyLoader::end_loaded_only`]
+    pub fn begin_loaded_only(&mut self, specifier: ModuleSpecifier) {
+        self.loaded_only = Some((specifier, false));
+    }
+
+    /// Stops refusing loads, returning true if a load was refused
+    pub fn end_loaded_only(&mut self) -> bool {
+        self.loaded_only.take().is_some_and(|(_, refused)| refused)
+    }
+
+    /// Sets the custom module resolver, consulted before the filesystem and URL loaders
+    /// Clears any modules previously returned by the old resolver
+    pub fn set_module_resolver(&mut self, resolver: ModuleResolver) {
+        self.module_resolver = Some(resolver);
+        self.resolved_modules.clear();
+    }
+
+    /// Makes a module available to imports under the given specifier, without loading it
+    /// Unlike modules from the custom resolver, these are kept when the resolver changes
+    pub fn register_static_module(&mut self, specifier: ModuleSpecifier, module: Module) {
+        self.remember_casing(&specifier);
+        self.static_modules.insert(specifier, module);
+    }
+
+    /// Confines imports made by modules under `root` to that directory
+    #[cfg(feature = "archive")]
+    pub fn add_archive_root(&mut self, root: ModuleSpecifier) {
+        self.archive_roots.push(root);
+    }
+
+    /// Forgets the static modules registered under `root` - modules already loaded are unaffected
+    #[cfg(feature = "archive")]
+    pub fn remove_static_modules_under(&mut self, root: &ModuleSpecifier) {
+        self.static_modules
+            .retain(|url, _| !url.as_str().starts_with(root.as_str()));
+    }
+
+    /// Fails if a module from an archive imports anything from outside of that archive
+    fn check_archive_import(&self, url: &ModuleSpecifier, referrer: &str) -> Result<(), Error> {
+        let within = |url: &str, root: &ModuleSpecifier| {
+            if self.case_insensitive_paths {
+                url.to_lowercase()
+                    .starts_with(&root.as_str().to_lowercase())
+            } else {
+                url.starts_with(root.as_str())
+            }
+        };
+
+        match self
+            .archive_roots
+            .iter()
+            .find(|root| within(referrer, root))
+        {
+            Some(root) if !within(url.as_str(), root) => Err(anyhow!(
+                "{url} is outside of the archive imported from by {referrer}"
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the module registered for a specifier, by the custom resolver or as a static module
+    fn registered_module(&self, url: &ModuleSpecifier) -> Option<&Module> {
+        self.resolved_modules
+            .get(url)
+            .or_else(|| self.static_modules.get(url))
+    }
+
+    /// Consults the custom module resolver for a specifier
+    /// Results are cached, so the resolver is called at most once per specifier
+    fn resolve_custom(&mut self, url: &ModuleSpecifier) -> bool {
+        if self.registered_module(url).is_some() {
+            return true;
+        }
+
+        let Some(resolver) = &self.module_resolver else {
+            return false;
+        };
+
+        match resolver(url.as_str()) {
+            Some(module) => {
+                self.resolved_modules.insert(url.clone(), module);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Maps an aliased import (`@app/utils`) to the path it refers to, if an alias matches
+    ///
+    /// Exact aliases take priority, then the longest matching wildcard prefix
+    /// Relative targets are resolved against the current working directory  
+    /// Extensionless targets are probed for a matching `.ts`/`.js` file, or directory index
+    fn resolve_path_alias(&self, specifier: &str) -> Option<PathBuf> {
+        const CANDIDATES: [&str; 6] = [".ts", ".tsx", ".mts", ".js", ".mjs", ".jsx"];
+        let target = match self.path_aliases.get(specifier) {
+            Some(target) => target.clone(),
+            None => {
+                let (prefix, target) = self
+                    .path_aliases
+                    .iter()
+                    .filter_map(|(alias, target)| Some((alias.strip_suffix('*')?, target)))
+                    .filter(|(prefix, _)| specifier.starts_with(prefix))
+                    .max_by_key(|(prefix, _)| prefix.len())?;
+
+                let rest = &specifier[prefix.len()..];
+                let target = target.to_string_lossy();
+                PathBuf::from(match target.strip_suffix('*') {
+                    Some(target) => format!("{target}{rest}"),
+                    None => target.to_string(),
+                })
+            }
+        };
+
+        let target = self.cwd.join(target);
+        if target.extension().is_some() || target.is_file() {
+            return Some(target);
+        }
+
+        let file = CANDIDATES.iter().map(|ext| {
+            let mut path = target.clone().into_os_string();
+            path.push(ext);
+            PathBuf::from(path)
+        });
+        let index = CANDIDATES
+            .iter()
+            .map(|ext| target.join(format!("index{ext}")));
+
+        file.chain(index)
+            .find(|path| path.is_file())
+            .or(Some(target))
+    }
+
+    /// Normalizes a file specifier, so that every spelling of a path refers to the same module
+    ///
+    /// On windows, backslashes are treated as path separators - elsewhere they are valid in file names.  
+    /// If paths are case-insensitive, the casing of the file on disk is used. A path that is not on disk keeps
+    /// the casing of the first spelling that loaded, so a misspelled import cannot pin a casing that fails to load
+    pub fn normalize_specifier(&mut self, mut url: ModuleSpecifier) -> ModuleSpecifier {
+        if url.scheme() != "file" {
+            return url;
+        }
+
+        if cfg!(windows) && url.path().contains("%5C") {
+            let path = url.path().replace("%5C", "/");
+            url.set_path(&path);
+        }
+
+        if !self.case_insensitive_paths {
+            return url;
+        }
+
+        let key = url.as_str().to_lowercase();
+        if let Some(canonical) = self.canonical_paths.get(&key) {
+            return canonical.clone();
+        }
+
+        let on_disk = url
+            .to_file_path()
+            .ok()
+            .and_then(|path| Self::disk_casing(&path))
+            .and_then(|path| ModuleSpecifier::from_file_path(path).ok());
+        match on_disk {
+            Some(mut canonical) => {
+                canonical.set_query(url.query());
+                canonical.set_fragment(url.fragment());
+                self.canonical_paths.insert(key, canonical.clone());
+                canonical
+            }
+            None => url,
+        }
+    }
+
+    /// Records the casing of a module that loaded, for paths that are not on disk - see [`Self::normalize_specifier`]
+    fn remember_casing(&mut self, url: &ModuleSpecifier) {
+        if self.case_insensitive_paths && url.scheme() == "file" {
+            self.canonical_paths
+                .entry(url.as_str().to_lowercase())
+                .or_insert_with(|| url.clone());
+        }
+    }
+
+    /// Returns a path as it is spelled on disk, matching each component regardless of case
+    /// An exact match is preferred, in case the filesystem holds several casings. Returns `None` if the path does not exist
+    fn disk_casing(path: &Path) -> Option<PathBuf> {
+        let mut resolved = PathBuf::new();
+        for component in path.components() {
+            let std::path::Component::Normal(name) = component else {
+                resolved.push(component);
+                continue;
+            };
+
+            let names: Vec<_> = std::fs::read_dir(&resolved)
+                .ok()?
+                .filter_map(|entry| entry.ok().map(|entry| entry.file_name()))
+                .collect();
+            let wanted = name.to_string_lossy().to_lowercase();
+            let found = names.iter().find(|n| n.as_os_str() == name).or_else(|| {
+                names
+                    .iter()
+                    .find(|n| n.to_string_lossy().to_lowercase() == wanted)
+            })?;
+            resolved.push(found);
+        }
+        Some(resolved)
+    }
+
+    /// Resolves a path or URL the way a module's filename is, relative to the current working directory
+    pub fn resolve_from_cwd(&mut self, specifier: &str) -> Result<ModuleSpecifier, Error> {
+        let url = if deno_core::specifier_has_uri_scheme(specifier) {
+            deno_core::resolve_url(specifier)?
+        } else {
+            specifier.to_module_specifier(&self.cwd)?
+        };
+        Ok(self.normalize_specifier(url))
+    }
+
+    /// Resolves a specifier using the import map, if one is set
+    /// Returns `None` if there is no import map, or if it has no mapping for a bare specifier
+    ///
+    /// Any other failure, such as an import blocked by a `null` entry, is an error
+    fn resolve_import_map(
+        &self,
+        specifier: &str,
+        referrer: &str,
+    ) -> Result<Option<ModuleSpecifier>, Error> {
+        let Some(import_map) = &self.import_map else {
+            return Ok(None);
+        };
+
+        let referrer = if deno_core::specifier_has_uri_scheme(referrer) {
+            deno_core::resolve_url(referrer)?
+        } else {
+            referrer.to_module_specifier(&self.cwd)?
+        };
+
+        // Unmapped bare specifiers fall through to the path aliases and default resolution
+        match import_map.resolve(specifier, &referrer) {
+            Ok(url) => Ok(Some(url)),
+            Err(ImportMapError::UnmappedBareSpecifier(..)) => Ok(None),
+            Err(e) => Err(anyhow!("{e}")),
+        }
+    }
+
     /// Adds a module specifier to the whitelist
     /// This allows the module to be loaded from the filesystem
     /// If they are included from rust first
@@ -151,6 +556,18 @@ impl InnerRustyLoader {
         specifier: &str,
         referrer: &str,
         kind: deno_core::ResolutionKind,
+    ) -> Result<ModuleSpecifier, Error> {
+        let url = self.resolve_unconfined(specifier, referrer, kind)?;
+        self.check_archive_import(&url, referrer)?;
+        Ok(url)
+    }
+
+    /// Resolves a specifier, without checking that imports from an archive stay within it
+    fn resolve_unconfined(
+        &mut self,
+        specifier: &str,
+        referrer: &str,
+        kind: deno_core::ResolutionKind,
     ) -> Result<ModuleSpecifier, Error> {
         //
         // Handle import aliasing for node imports
@@ -176,12 +593,24 @@ impl InnerRustyLoader {
                     node_resolver::REQUIRE_CONDITIONS,
                     NodeResolutionKind::Execution,
                 )?;
-                return Ok(url);
+                return Ok(self.normalize_specifier(url));
             }
         }
 
+        // Apply the import map, if there is one, then any matching path alias
+        // The mapped URL replaces the specifier, so that the scheme checks below apply to the target
+        let mapped = match self.resolve_import_map(specifier, referrer)? {
+            Some(url) => Some(url.to_string()),
+            None => match self.resolve_path_alias(specifier) {
+                Some(path) => Some(path.to_module_specifier(&self.cwd)?.to_string()),
+                None => None,
+            },
+        };
+        let specifier = mapped.as_deref().unwrap_or(specifier);
+
         // Resolve the module specifier to an absolute URL
         let url = deno_core::resolve_import(specifier, referrer)?;
+        let url = self.normalize_specifier(url);
 
         // Check if the module is in the cache
         if self
@@ -192,6 +621,12 @@ impl InnerRustyLoader {
             return Ok(url);
         }
 
+        // Check if the custom resolver can provide the module
+        // Each specifier is only resolved once, so import cycles are handled by the module map
+        if self.resolve_custom(&url) {
+            return Ok(url);
+        }
+
         // Check if the import provider allows the import
         if let Some(import_provider) = &mut self.import_provider {
             let resolve_result = import_provider.resolve(&url, referrer, kind);
@@ -253,7 +688,11 @@ impl InnerRustyLoader {
                 return Ok(url.into_url());
             }
 
-            _ if self.schema_whlist.iter().any(|s| specifier.starts_with(s)) => {
+            _ if self
+                .schema_whitelist
+                .iter()
+                .any(|s| specifier.starts_with(s)) =>
+            {
                 // Custom schema whitelist import - allow
             }
 
@@ -266,6 +705,37 @@ impl InnerRustyLoader {
         Ok(url)
     }
 
+    /// Applies the source transform, if any, to a module's source
+    pub fn transform_source<'a>(&self, specifier: &ModuleSpecifier, code: &'a str) -> Cow<'a, str> {
+        match &self.source_transform {
+            Some(transform) => Cow::Owned(transform(specifier.as_str(), code)),
+            None => Cow::Borrowed(code),
+        }
+    }
+
+    /// Consults the dynamic import hook, if any, for a resolved dynamic import
+    pub fn check_dynamic_import(
+        &self,
+        specifier: &ModuleSpecifier,
+        referrer: &str,
+    ) -> Result<(), Error> {
+        let Some(hook) = &self.dynamic_import_hook else {
+            return Ok(());
+        };
+
+        let event = DynamicImportEvent {
+            specifier: specifier.clone(),
+            referrer: referrer.to_string(),
+        };
+        if hook(&event) {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Dynamic import of {specifier} from {referrer} was denied"
+            ))
+        }
+    }
+
     pub fn load(
         inner: Rc<RefCell<Self>>,
         module_specifier: &ModuleSpecifier,
@@ -276,13 +746,47 @@ impl InnerRustyLoader {
         let module_specifier = module_specifier.clone();
         let maybe_referrer = maybe_referrer.cloned();
 
-        // Check if the module is in the cache first
-        if let Some(cache) = &inner.borrow().cache_provider {
-            if let Some(source) = cache.get(&module_specifier) {
-                return deno_core::ModuleLoadResponse::Sync(Ok(source));
+        // A module that must already be in the runtime is never fetched
+        if let Some((specifier, refused)) = inner.borrow_mut().loaded_only.as_mut() {
+            if *specifier == module_specifier {
+                *refused = true;
+                return ModuleLoadResponse::Sync(Err(anyhow!(
+                    "requested module is not loaded: {module_specifier}"
+                )));
             }
         }
 
+        // Check if the module is in the cache first
+        let start = std::time::Instant::now();
+        let cached = inner
+            .borrow()
+            .cache_provider
+            .as_ref()
+            .and_then(|cache| cache.get(&module_specifier));
+        if let Some(source) = cached {
+            inner.borrow().report_load(
+                &module_specifier,
+                start,
+                source.code.as_bytes().len(),
+                true,
+            );
+            return deno_core::ModuleLoadResponse::Sync(Ok(source));
+        }
+
+        // Next check for modules returned by the custom resolver, or registered as static modules
+        let resolved = inner
+            .borrow()
+            .registered_module(&module_specifier)
+            .map(|m| m.contents().to_string());
+        if let Some(code) = resolved {
+            return ModuleLoadResponse::Async(
+                async move {
+                    Self::handle_load(inner, module_specifier, |_, _| async move { Ok(code) }).await
+                }
+                .boxed_local(),
+            );
+        }
+
         // Next check the import provider
         let provider_result = inner.borrow_mut().import_provider.as_mut().and_then(|p| {
             p.import(
@@ -375,15 +879,87 @@ impl InnerRustyLoader {
         Ok(content)
     }
 
+    /// Fetches a module over HTTP, with the configured headers and user agent
+    ///
+    /// Redirects are followed using reqwest's defaults, which drop the `Authorization`
+    /// header (and other sensitive headers) when redirected to a different host
     #[cfg(feature = "url_import")]
     async fn load_remote(
-        _: Rc<RefCell<Self>>,
+        inner: Rc<RefCell<Self>>,
         module_specifier: ModuleSpecifier,
     ) -> Result<String, Error> {
-        let response = reqwest::get(module_specifier).await?;
+        let mut request = inner
+            .borrow_mut()
+            .http_client()?
+            .get(module_specifier.clone());
+        {
+            let inner = inner.borrow();
+            if let Some(user_agent) = &inner.url_import_user_agent {
+                request = request.header(reqwest::header::USER_AGENT, user_agent);
+            }
+            for (name, value) in &inner.url_import_headers {
+                request = request.header(name, value);
+            }
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(crate::Error::ImportHttpStatus(
+                module_specifier.to_string(),
+                status.as_u16(),
+            )
+            .into());
+        }
+
         Ok(response.text().await?)
     }
 
+    /// Returns the client used to fetch modules over HTTP, building it on first use
+    ///
+    /// The client is shared by every fetch, so that connections are pooled and TLS is only set up once
+    #[cfg(feature = "url_import")]
+    fn http_client(&mut self) -> Result<reqwest::Client, Error> {
+        if let Some(client) = &self.http_client {
+            return Ok(client.clone());
+        }
+
+        let client = self.build_http_client()?;
+        self.http_client = Some(client.clone());
+        Ok(client)
+    }
+
+    /// Builds the client used to fetch modules over HTTP
+    ///
+    /// The default client is used unless a root certificate store, or hosts for which to ignore
+    /// certificate errors, were configured - in which case TLS is set up the same way as for `fetch`
+    #[cfg(feature = "url_import")]
+    fn build_http_client(&self) -> Result<reqwest::Client, Error> {
+        if self.url_import_root_cert_store_provider.is_none()
+            && self.url_import_unsafely_ignore_certificate_errors.is_none()
+        {
+            return Ok(reqwest::Client::new());
+        }
+
+        let root_cert_store = match &self.url_import_root_cert_store_provider {
+            Some(provider) => Some(provider.get_or_try_init()?.clone()),
+            None => None,
+        };
+
+        // reqwest is built without HTTP/2, so only HTTP/1.1 may be negotiated
+        let tls_config = deno_tls::create_client_config(
+            root_cert_store,
+            vec![],
+            self.url_import_unsafely_ignore_certificate_errors.clone(),
+            deno_tls::TlsKeys::Null,
+            deno_tls::SocketUse::Http1Only,
+        )?;
+
+        Ok(reqwest::Client::builder()
+            .use_preconfigured_tls(tls_config)
+            .build()?)
+    }
+
     /// Loads a module's source code from the cache or from the provided handler
     async fn handle_load<F, Fut>(
         inner: Rc<RefCell<Self>>,
@@ -395,12 +971,19 @@ impl InnerRustyLoader {
         Fut: std::future::Future<Output = Result<String, deno_core::error::AnyError>>,
     {
         // Check if the module is in the cache first
+        let start = std::time::Instant::now();
         if let Some(Some(source)) = inner
             .borrow()
             .cache_provider
             .as_ref()
             .map(|p| p.get(&module_specifier))
         {
+            inner.borrow().report_load(
+                &module_specifier,
+                start,
+                source.code.as_bytes().len(),
+                true,
+            );
             return Ok(source);
         }
 
@@ -419,8 +1002,23 @@ impl InnerRustyLoader {
         };
 
         // Load the module code, and transpile it if necessary
+        // Modules from the custom resolver may carry an explicit language hint
         let code = handler(inner.clone(), module_specifier.clone()).await?;
-        let (tcode, source_map) = transpile(&module_specifier, &code)?;
+        inner.borrow_mut().remember_casing(&module_specifier);
+        let code = inner
+            .borrow()
+            .transform_source(&module_specifier, &code)
+            .into_owned();
+        let language = inner
+            .borrow()
+            .registered_module(&module_specifier)
+            .and_then(Module::language);
+        let (enabled, options) = {
+            let inner = inner.borrow();
+            (!inner.disable_transpile, inner.transpile_options.clone())
+        };
+        let (tcode, source_map) =
+            transpile_if(&module_specifier, &code, language, enabled, &options)?;
 
         // Create the module source
         let mut source = ModuleSource::new(
@@ -448,17 +1046,73 @@ impl InnerRustyLoader {
             source = import_provider.post_process(&module_specifier, source)?;
         }
 
+        inner.borrow().report_load(
+            &module_specifier,
+            start,
+            source.code.as_bytes().len(),
+            false,
+        );
         Ok(source)
     }
 
+    /// Passes a completed module load to the load observer, if there is one
+    pub fn report_load(
+        &self,
+        specifier: &ModuleSpecifier,
+        start: std::time::Instant,
+        bytes: usize,
+        cache_hit: bool,
+    ) {
+        if let Some(observer) = &self.load_observer {
+            observer(&ModuleLoadEvent {
+                specifier: specifier.clone(),
+                duration: start.elapsed(),
+                bytes,
+                cache_hit,
+            });
+        }
+    }
+
     /// Returns a reference to a file in the source map cache
     pub fn get_source_map(&self, filename: &str) -> Option<&(String, Option<Vec<u8>>)> {
-        self.source_map_cache.get(filename)
+        let entry = self.source_map_cache.get(filename)?;
+        self.touch_source_map(filename);
+        Some(entry)
+    }
+
+    /// Marks a file in the source map cache as the most recently used
+    fn touch_source_map(&self, filename: &str) {
+        if self.source_cache_limit.is_none() {
+            return;
+        }
+
+        self.source_cache_lru.borrow_mut().touch(filename);
     }
 
     /// Adds a source map to the cache
     pub fn add_source_map(&mut self, filename: &str, source: String, source_map: Option<Vec<u8>>) {
+        // Code compiled ahead of time is loaded without a map - keep the one registered for it
+        if source_map.is_none() {
+            if let Some((existing, Some(_))) = self.source_map_cache.get(filename) {
+                if *existing == source {
+                    return;
+                }
+            }
+        }
+
         self.source_map_cache
             .insert(filename.to_string(), (source, source_map));
+        self.touch_source_map(filename);
+
+        // Evict the least recently used sources beyond the limit
+        if let Some(limit) = self.source_cache_limit {
+            let mut lru = self.source_cache_lru.borrow_mut();
+            while self.source_map_cache.len() > limit {
+                let Some(evicted) = lru.pop_oldest() else {
+                    break;
+                };
+                self.source_map_cache.remove(&evicted);
+            }
+        }
     }
 }
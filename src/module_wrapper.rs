@@ -6,6 +6,8 @@ use deno_core::{serde_json, v8::GetPropertyNamesArgs};
 /// Exactly equivalent to [`Runtime::new`] followed by [`Runtime::load_module`]
 ///
 /// Can also be created using the [`crate::import`] function
+///
+/// The wrapper owns its runtime, so it is not `Send` - it must be used on the thread that created it
 pub struct ModuleWrapper {
     module_context: ModuleHandle,
     runtime: Runtime,
@@ -48,6 +50,85 @@ impl ModuleWrapper {
         Self::new_from_module(&module, options)
     }
 
+    /// Creates a new `ModuleWrapper` from a module's source code and runtime options.
+    ///
+    /// The wrapper owns a new runtime, with the module loaded into it
+    ///
+    /// # Arguments
+    /// * `name` - The filename of the module, used to resolve imports and infer the language.
+    /// * `source` - The source code of the module.
+    /// * `options` - The runtime options for the module.
+    ///
+    /// # Returns
+    /// A `Result` containing `Self` on success or an `Error` on failure.
+    ///
+    /// # Errors
+    /// Will return an error if module execution fails
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ json_args, ModuleWrapper, RuntimeOptions };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut module = ModuleWrapper::from_source(
+    ///     "add.js",
+    ///     "export function add(a, b) { return a + b; }",
+    ///     RuntimeOptions::default(),
+    /// )?;
+    ///
+    /// let value: i64 = module.call("add", json_args!(1, 2))?;
+    /// assert_eq!(value, 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_source(name: &str, source: &str, options: RuntimeOptions) -> Result<Self, Error> {
+        let module = Module::new(name, source);
+        Self::new_from_module(&module, options)
+    }
+
+    /// Creates a new `ModuleWrapper` from a given module and runtime options.
+    ///
+    /// See [`ModuleWrapper::new_from_module`]
+    ///
+    /// # Arguments
+    /// * `module` - A reference to the module to load.
+    /// * `options` - The runtime options for the module.
+    ///
+    /// # Returns
+    /// A `Result` containing `Self` on success or an `Error` on failure.
+    ///
+    /// # Errors
+    /// Will return an error if module execution fails
+    pub async fn new_from_module_async(
+        module: &Module,
+        options: RuntimeOptions,
+    ) -> Result<Self, Error> {
+        let mut runtime = Runtime::new(options)?;
+        let module_context = runtime.load_module_async(module).await?;
+        Ok(Self {
+            module_context,
+            runtime,
+        })
+    }
+
+    /// Creates a new `ModuleWrapper` from a file path and runtime options.
+    ///
+    /// See [`ModuleWrapper::new_from_file`]
+    ///
+    /// # Arguments
+    /// * `path` - The path to the module file.
+    /// * `options` - The runtime options for the module.
+    ///
+    /// # Returns
+    /// A `Result` containing `Self` on success or an `Error` on failure.
+    ///
+    /// # Errors
+    /// Will return an error if the file cannot be loaded, or if module execution fails
+    pub async fn new_from_file_async(path: &str, options: RuntimeOptions) -> Result<Self, Error> {
+        let module = Module::load(path)?;
+        Self::new_from_module_async(&module, options).await
+    }
+
     /// Returns a reference to the module context.
     #[must_use]
     pub fn get_module_context(&self) -> &ModuleHandle {
@@ -155,6 +236,26 @@ impl ModuleWrapper {
             .call_function(Some(&self.module_context), name, args)
     }
 
+    /// Calls the module's default export with the given arguments and deserializes the result.
+    ///
+    /// See [`Runtime::call_default`]
+    ///
+    /// # Arguments
+    /// * `args` - The arguments to pass to the function.
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of type `T` on success or an `Error` on failure.
+    ///
+    /// # Errors
+    /// Will return an error if the default export is missing or not a function, if the function returns an error,
+    /// or if the function returns a value that cannot be deserialized into the given type
+    pub fn call_default<T>(&mut self, args: &impl serde::ser::Serialize) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.runtime.call_default(&self.module_context, args)
+    }
+
     /// Calls a function in the module with the given name and arguments and deserializes the result.
     ///
     /// See [`Runtime::call_function_async`]
@@ -334,6 +435,41 @@ mod test_runtime {
         assert_eq!(4, value);
     }
 
+    #[test]
+    fn test_from_source() {
+        let mut module = ModuleWrapper::from_source(
+            "test.js",
+            "export function func(a) { return a * 2; }",
+            RuntimeOptions::default(),
+        )
+        .expect("Could not create wrapper");
+
+        let value: usize = module
+            .call("func", json_args!(2))
+            .expect("Could not call function");
+        assert_eq!(4, value);
+
+        let value: usize = module
+            .call("func", json_args!(5))
+            .expect("Could not call function");
+        assert_eq!(10, value);
+    }
+
+    #[test]
+    fn test_call_default() {
+        let mut module = ModuleWrapper::from_source(
+            "test.js",
+            "export default function(a) { return a * 2; }",
+            RuntimeOptions::default(),
+        )
+        .expect("Could not create wrapper");
+
+        let value: usize = module
+            .call_default(json_args!(3))
+            .expect("Could not call default export");
+        assert_eq!(6, value);
+    }
+
     #[test]
     fn test_get() {
         let module = Module::new(
@@ -90,6 +90,17 @@ export const whitelist = {
     "call_registered_function": "Rustyscript builtin",
     "call_registered_function_async": "Rustyscript builtin",
     "op_panic2": "Panic stub to replace op_panic",
+    "op_stream_open": "Rustyscript builtin",
+    "op_stream_next": "Rustyscript builtin",
+    "op_stream_close": "Rustyscript builtin",
+    "op_fake_clock_now": "Rustyscript builtin",
+    "op_fake_clock_wait": "Rustyscript builtin",
+    "op_record_console_error": "Rustyscript builtin",
+    "op_microtask_limit_exceeded": "Rustyscript builtin",
+    "op_require_module": "Rustyscript builtin",
+    "op_encoding_encode": "Rustyscript encoding - UTF-8 only, no I/O",
+    "op_encoding_encode_into": "Rustyscript encoding - UTF-8 only, no I/O",
+    "op_performance_now": "Rustyscript web stub - monotonic clock, no I/O",
 
     //
     // v8 ops
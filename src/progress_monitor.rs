@@ -0,0 +1,184 @@
+use crate::{
+    inner_runtime::{ProgressCallback, ProgressSignal},
+    Error,
+};
+use deno_core::v8;
+use std::{
+    ffi::c_void,
+    panic::AssertUnwindSafe,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+/// State shared between the monitor thread and the isolate's interrupt callback
+struct Progress {
+    callback: ProgressCallback,
+
+    /// Start of the call being monitored
+    started: Mutex<Instant>,
+
+    /// True while an interrupt request has not been serviced
+    pending: AtomicBool,
+
+    /// True while a call is being monitored - requests serviced outside of a call are ignored
+    active: AtomicBool,
+
+    /// Set once the callback has asked for the current call to be aborted
+    aborted: AtomicBool,
+}
+
+impl Progress {
+    fn elapsed(&self) -> Duration {
+        self.started
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .elapsed()
+    }
+}
+
+extern "C" fn on_interrupt(isolate: &mut v8::Isolate, data: *mut c_void) {
+    // Safety: `data` is an Arc leaked by the monitor for this request
+    let progress = unsafe { Arc::from_raw(data.cast::<Progress>()) };
+    progress.pending.store(false, Ordering::SeqCst);
+    if !progress.active.load(Ordering::SeqCst) || progress.aborted.load(Ordering::SeqCst) {
+        return;
+    }
+
+    // Unwinding out of the interrupt is not possible, so a panicking callback aborts the call instead
+    let elapsed = progress.elapsed();
+    let signal = std::panic::catch_unwind(AssertUnwindSafe(|| (progress.callback)(elapsed)))
+        .unwrap_or(ProgressSignal::Abort);
+    if signal == ProgressSignal::Abort {
+        progress.aborted.store(true, Ordering::SeqCst);
+        isolate.terminate_execution();
+    }
+}
+
+/// Invokes [`crate::RuntimeOptions::progress_callback`] for each call made through a runtime
+///
+/// A single background thread, living as long as the runtime, requests an interrupt every interval while a call
+/// is being monitored, and the callback runs inside it - interrupts are only serviced while javascript is executing,
+/// so idle time does not trigger the callback. If the callback returns [`ProgressSignal::Abort`], the isolate's
+/// execution is terminated
+pub(crate) struct ProgressMonitor {
+    isolate: v8::IsolateHandle,
+    progress: Arc<Progress>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ProgressMonitor {
+    /// Start the monitor thread for an isolate - it stays idle until a call is monitored
+    pub fn new(isolate: v8::IsolateHandle, interval: Duration, callback: ProgressCallback) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let progress = Arc::new(Progress {
+            callback,
+            started: Mutex::new(Instant::now()),
+            pending: AtomicBool::new(false),
+            active: AtomicBool::new(false),
+            aborted: AtomicBool::new(false),
+        });
+
+        let thread = {
+            let isolate = isolate.clone();
+            let stop = stop.clone();
+            let progress = progress.clone();
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    if !progress.active.load(Ordering::SeqCst)
+                        || progress.aborted.load(Ordering::SeqCst)
+                    {
+                        // Sleep until the next call is monitored
+                        std::thread::park();
+                        continue;
+                    }
+
+                    std::thread::park_timeout(interval);
+                    if !progress.active.load(Ordering::SeqCst) {
+                        continue;
+                    }
+
+                    // Only one request is in flight at a time
+                    if !progress.pending.swap(true, Ordering::SeqCst) {
+                        let data = Arc::into_raw(progress.clone()).cast_mut().cast::<c_void>();
+                        if !isolate.request_interrupt(on_interrupt, data) {
+                            // The isolate is gone - reclaim the reference, and stop
+                            drop(unsafe { Arc::from_raw(data.cast::<Progress>()) });
+                            break;
+                        }
+                    }
+                }
+            })
+        };
+
+        Self {
+            isolate,
+            progress,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// Start monitoring a call, until the returned guard is finished or dropped
+    pub fn watch(&self) -> MonitoredCall<'_> {
+        *self
+            .progress
+            .started
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Instant::now();
+        self.progress.aborted.store(false, Ordering::SeqCst);
+        self.progress.active.store(true, Ordering::SeqCst);
+        if let Some(thread) = &self.thread {
+            thread.thread().unpark();
+        }
+
+        MonitoredCall(self)
+    }
+
+    /// Stop monitoring the current call - returns true if the callback aborted it,
+    /// in which case the termination is cleared so the runtime can be used again
+    fn unwatch(&self) -> bool {
+        self.progress.active.store(false, Ordering::SeqCst);
+        let aborted = self.progress.aborted.swap(false, Ordering::SeqCst);
+        if aborted {
+            self.isolate.cancel_terminate_execution();
+        }
+        aborted
+    }
+}
+
+impl Drop for ProgressMonitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            thread.join().ok();
+        }
+    }
+}
+
+/// A call being monitored by a [`ProgressMonitor`] - monitoring stops when it is dropped
+pub(crate) struct MonitoredCall<'a>(&'a ProgressMonitor);
+
+impl MonitoredCall<'_> {
+    /// Stop monitoring, and return the result of the call
+    ///
+    /// A call that failed after being aborted by the callback reports [`Error::Cancelled`] instead of the
+    /// termination error. One that succeeded anyway - the abort came as it returned - keeps its result
+    pub fn finish<T>(self, result: Result<T, Error>) -> Result<T, Error> {
+        match result {
+            Err(_) if self.0.unwatch() => Err(Error::Cancelled),
+            result => result,
+        }
+    }
+}
+
+impl Drop for MonitoredCall<'_> {
+    fn drop(&mut self) {
+        self.0.unwatch();
+    }
+}
@@ -1,23 +1,135 @@
 use crate::{
-    async_bridge::{AsyncBridge, AsyncBridgeExt},
-    inner_runtime::{InnerRuntime, RsAsyncFunction, RsFunction},
+    async_bridge::{AsyncBridge, AsyncBridgeExt, BridgeSettings},
+    inner_runtime::{InnerRuntime, RsAsyncFunction, RsFunction, RsStreamFunction},
     js_value::Function,
-    Error, Module, ModuleHandle,
+    CallSlot, Error, Module, ModuleHandle, RsModuleFunction,
 };
-use deno_core::PollEventLoopOptions;
+use deno_core::{v8, PollEventLoopOptions};
 use std::{path::Path, rc::Rc, time::Duration};
 use tokio_util::sync::CancellationToken;
 
 /// Represents the set of options accepted by the runtime constructor
-pub use crate::inner_runtime::RuntimeOptions;
+pub use crate::inner_runtime::{RuntimeInfo, RuntimeOptions, RuntimeProfile};
 
 /// For functions returning nothing. Acts as a placeholder for the return type  
 /// Should accept any type of value from javascript
 ///
 /// It is in fact an alias for [`crate::js_value::Value`]  
 /// Note: This used to be an alias for `serde_json::Value`, but was changed for performance reasons
+///
+/// Use [`crate::js_value::Value::is_undefined`] to check if a function really returned nothing - as opposed to `null`.  
+/// See [`crate::js_value::Value`] for how `null` and `undefined` map to rust types
 pub type Undefined = crate::js_value::Value;
 
+/// Heap usage of a runtime's v8 isolate, as returned by [`Runtime::heap_stats`]  
+/// All sizes are in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct HeapStats {
+    /// Memory currently used by javascript objects
+    pub used_heap_size: usize,
+
+    /// Memory reserved for the heap - including space not yet in use
+    pub total_heap_size: usize,
+
+    /// The size the heap may grow to before the isolate runs out of memory
+    pub heap_size_limit: usize,
+
+    /// Memory held outside the heap, such as the backing stores of `ArrayBuffer`s
+    pub external_memory: usize,
+
+    /// Memory allocated by v8 through `malloc`
+    pub malloced_memory: usize,
+
+    /// Number of live contexts in the isolate
+    pub native_contexts: usize,
+
+    /// Number of contexts that were detached but not yet garbage collected  
+    /// A count that keeps growing usually indicates a leak
+    pub detached_contexts: usize,
+}
+
+/// Iterator over the values yielded by a javascript generator, as returned by [`Runtime::call_generator`]
+///
+/// Each call to `next` runs the generator (and the event loop, for async generators) until it yields,
+/// returns, or throws - each step is subject to the runtime's timeout.  
+/// Dropping the iterator before the generator is done calls its `return()` method, running any `finally` blocks;
+/// use [`GeneratorIter::close`] to see errors thrown during that cleanup
+pub struct GeneratorIter<'a, T> {
+    runtime: &'a mut Runtime,
+    generator: v8::Global<v8::Value>,
+    done: bool,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T> GeneratorIter<'_, T> {
+    /// Stops the generator early by calling its `return()` method, running its cleanup
+    ///
+    /// Does nothing if the generator is already done
+    ///
+    /// # Errors
+    /// Will return an error if the generator throws while cleaning up
+    pub fn close(mut self) -> Result<(), Error> {
+        self.finish()
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        if self.done {
+            return Ok(());
+        }
+        self.done = true;
+
+        let generator = &self.generator;
+        self.runtime.block_on(|runtime| async move {
+            let result = runtime.inner.call_method(generator, "return")?;
+            runtime.inner.resolve_with_event_loop(result).await?;
+            Ok(())
+        })
+    }
+}
+
+impl<T> Iterator for GeneratorIter<'_, T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let generator = &self.generator;
+        let result = self.runtime.block_on(|runtime| async move {
+            let result = runtime.inner.call_method(generator, "next")?;
+            let result = runtime.inner.resolve_with_event_loop(result).await?;
+            match runtime.inner.iterator_result(result)? {
+                Some(value) => runtime.inner.decode_value(value).map(Some),
+                None => Ok(None),
+            }
+        });
+
+        // A generator that throws is finished
+        match result {
+            Ok(Some(value)) => Some(Ok(value)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl<T> Drop for GeneratorIter<'_, T> {
+    fn drop(&mut self) {
+        // Errors during cleanup cannot be reported here - see `close`
+        let _ = self.finish();
+    }
+}
+
 /// A runtime instance that can be used to execute JavaScript code and interact with it.  
 /// Most runtime functions have 3 variants - blocking, async, and immediate
 ///
@@ -75,8 +187,10 @@ impl Runtime {
     /// Or if the deno runtime initialization fails (usually issues with extensions)
     ///
     pub fn new(options: RuntimeOptions) -> Result<Self, Error> {
-        let tokio = AsyncBridge::new(options.timeout)?;
-        let inner = InnerRuntime::new(options, tokio.heap_exhausted_token())?;
+        let settings = BridgeSettings::new(&options);
+        let mut tokio = AsyncBridge::for_options(settings.timeout(), &options)?;
+        let mut inner = InnerRuntime::new(options, tokio.heap_exhausted_token())?;
+        tokio.configure(settings, &mut inner);
         Ok(Self { inner, tokio })
     }
 
@@ -89,20 +203,133 @@ impl Runtime {
         options: RuntimeOptions,
         tokio: Rc<tokio::runtime::Runtime>,
     ) -> Result<Self, Error> {
-        let tokio = AsyncBridge::with_tokio_runtime(options.timeout, tokio);
-        let inner = InnerRuntime::new(options, tokio.heap_exhausted_token())?;
+        let settings = BridgeSettings::new(&options);
+        let mut tokio = AsyncBridge::with_tokio_runtime(settings.timeout(), tokio)
+            .with_local_set(options.tokio_local_set.clone());
+        let mut inner = InnerRuntime::new(options, tokio.heap_exhausted_token())?;
+        tokio.configure(settings, &mut inner);
         Ok(Self { inner, tokio })
     }
 
+    /// Creates a new instance of the runtime from a snapshot file written by [`crate::SnapshotBuilder::finish_to_file`]
+    ///
+    /// The snapshot replaces [`RuntimeOptions::startup_snapshot`] - the other options must provide
+    /// the same extensions and ops as the runtime that created it.  
+    /// The snapshot is kept in memory for the rest of the process, since the runtime requires a `'static` slice -
+    /// loading the same snapshot again reuses it
+    ///
+    /// # Errors
+    /// Will return [`Error::IncompatibleSnapshot`] if the file is not a snapshot, or was created by a different
+    /// version of rustyscript or v8, on another platform, with different extension features enabled,
+    /// or with different extensions or ops in its options.  
+    /// Can also fail if the file cannot be read, or if the runtime cannot be created
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ Runtime, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # #[cfg(feature = "snapshot_builder")] {
+    /// # use rustyscript::SnapshotBuilder;
+    /// let path = std::env::temp_dir().join("rustyscript_doc_example.snap");
+    /// SnapshotBuilder::new(Default::default())?
+    ///     .with_expression("globalThis.answer = 42")?
+    ///     .finish_to_file(&path)?;
+    ///
+    /// // Later - usually in another run of the program
+    /// let mut runtime = Runtime::from_snapshot_file(&path, Default::default())?;
+    /// let answer: usize = runtime.eval("globalThis.answer")?;
+    /// assert_eq!(answer, 42);
+    /// # std::fs::remove_file(&path).ok();
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_snapshot_file(
+        path: impl AsRef<Path>,
+        options: RuntimeOptions,
+    ) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| {
+            Error::Runtime(format!("Could not read snapshot {}: {e}", path.display()))
+        })?;
+        let extensions = crate::snapshot_file::extension_names(&options);
+        let snapshot = crate::snapshot_file::decode(&bytes, &extensions)?;
+        let snapshot = crate::snapshot_file::intern(snapshot);
+
+        Self::new(RuntimeOptions {
+            startup_snapshot: Some(snapshot),
+            ..options
+        })
+    }
+
     /// Access the underlying deno runtime instance directly
     pub fn deno_runtime(&mut self) -> &mut deno_core::JsRuntime {
         self.inner.deno_runtime()
     }
 
+    /// Returns the unique id of this runtime, assigned in order of creation
+    ///
+    /// Ids are unique within the process, and never reused
+    #[must_use]
+    pub fn id(&self) -> u64 {
+        self.inner.info().id
+    }
+
+    /// Returns the name of this runtime, set with [`RuntimeOptions::name`]
+    #[must_use]
+    pub fn name(&self) -> Option<&str> {
+        self.inner.info().name.as_deref()
+    }
+
+    /// Returns the id and name of this runtime, for tagging log lines
+    ///
+    /// The same value is available to ops and extensions through the op state
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, RuntimeOptions };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let runtime = Runtime::new(RuntimeOptions {
+    ///     name: Some("worker-1".to_string()),
+    ///     ..Default::default()
+    /// })?;
+    /// println!("[{}] ready", runtime.info()); // [runtime 1 (worker-1)] ready
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn info(&self) -> &RuntimeInfo {
+        self.inner.info()
+    }
+
     /// Access the underlying tokio runtime used for blocking operations
+    ///
+    /// Only meaningful for a runtime that owns its tokio runtime - see [`Self::try_tokio_runtime`]
+    ///
+    /// # Panics
+    /// If the runtime was created with [`crate::RuntimeOptions::tokio_handle`], since it does not own a tokio runtime
     #[must_use]
     pub fn tokio_runtime(&self) -> std::rc::Rc<tokio::runtime::Runtime> {
-        self.tokio.tokio_runtime()
+        match self.tokio.try_tokio_runtime() {
+            Ok(tokio) => tokio,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    /// Access the underlying tokio runtime used for blocking operations, if the runtime owns one
+    ///
+    /// # Errors
+    /// If the runtime was created with [`crate::RuntimeOptions::tokio_handle`], since it does not own a tokio runtime  
+    /// Use [`Self::tokio_handle`] instead
+    pub fn try_tokio_runtime(&self) -> Result<std::rc::Rc<tokio::runtime::Runtime>, Error> {
+        self.tokio.try_tokio_runtime()
+    }
+
+    /// Returns a handle to the tokio runtime used for blocking operations
+    #[must_use]
+    pub fn tokio_handle(&self) -> tokio::runtime::Handle {
+        self.tokio.tokio_handle()
     }
 
     /// Returns the timeout for the runtime
@@ -111,6 +338,35 @@ impl Runtime {
         self.tokio.timeout()
     }
 
+    /// Returns a handle that can cancel the runtime's running call from another thread
+    ///
+    /// Cancelling terminates the executing javascript, and the blocking `call_function`, `call_stored_function`,
+    /// or `call_entrypoint` that was running returns [`Error::Cancelled`].  
+    /// Cancelling while nothing is running has no effect, and the handle can be reused for later calls.
+    ///
+    /// ```rust
+    /// use rustyscript::{ json_args, Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export function spin() { while (true) {} }");
+    /// let module = runtime.load_module(&module)?;
+    ///
+    /// let handle = runtime.cancellation_handle();
+    /// std::thread::spawn(move || {
+    ///     std::thread::sleep(std::time::Duration::from_millis(50));
+    ///     handle.cancel();
+    /// });
+    ///
+    /// let result = runtime.call_function::<()>(Some(&module), "spin", json_args!());
+    /// assert!(matches!(result, Err(Error::Cancelled)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn cancellation_handle(&mut self) -> crate::CancellationHandle {
+        self.inner.cancellation_handle()
+    }
+
     /// Returns the heap exhausted token for the runtime  
     /// Used to detect when the runtime has run out of memory
     #[must_use]
@@ -120,9 +376,26 @@ impl Runtime {
 
     /// Destroy the v8 runtime, releasing all resources  
     /// Then the internal tokio runtime will be returned
+    ///
+    /// Only meaningful for a runtime that owns its tokio runtime - see [`Self::try_into_tokio_runtime`]
+    ///
+    /// # Panics
+    /// If the runtime was created with [`crate::RuntimeOptions::tokio_handle`], since it does not own a tokio runtime
     #[must_use]
     pub fn into_tokio_runtime(self) -> Rc<tokio::runtime::Runtime> {
-        self.tokio.into_tokio_runtime()
+        match self.try_into_tokio_runtime() {
+            Ok(tokio) => tokio,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    /// Destroy the v8 runtime, releasing all resources  
+    /// Then the internal tokio runtime will be returned, if the runtime owns one
+    ///
+    /// # Errors
+    /// If the runtime was created with [`crate::RuntimeOptions::tokio_handle`], since it does not own a tokio runtime
+    pub fn try_into_tokio_runtime(self) -> Result<Rc<tokio::runtime::Runtime>, Error> {
+        self.tokio.try_into_tokio_runtime()
     }
 
     /// Set the current working directory for the runtime  
@@ -136,6 +409,243 @@ impl Runtime {
         self.inner.set_current_dir(path)
     }
 
+    /// Set a custom module resolver, used to load modules from sources other than the filesystem or network
+    ///
+    /// The resolver is consulted before the filesystem and URL loaders, and receives the fully resolved specifier.  
+    /// Returning `None` falls back to the default loaders.
+    ///
+    /// - Modules are requested lazily, the first time they are imported
+    /// - Results are cached, so each specifier is only requested once - import cycles
+    ///   between custom and filesystem modules are therefore resolved normally
+    /// - Setting a new resolver clears the cache of the previous one
+    ///
+    /// ```rust
+    /// use rustyscript::{ json_args, Runtime, Module };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.set_module_resolver(Box::new(|specifier| match specifier {
+    ///     "db:module/foo.js" => Some(Module::new("foo.js", "export const foo = 5;")),
+    ///     _ => None,
+    /// }));
+    ///
+    /// let module = Module::new("test.js", "import { foo } from 'db:module/foo.js'; export const value = foo;");
+    /// let module = runtime.load_module(&module)?;
+    /// let value: usize = runtime.get_value(Some(&module), "value")?;
+    /// assert_eq!(value, 5);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_module_resolver(&mut self, resolver: crate::module_loader::ModuleResolver) {
+        self.inner.module_loader.set_module_resolver(resolver);
+    }
+
+    /// Make a set of modules, such as a bundled standard library, available for import
+    ///
+    /// The modules are not executed here - each is loaded the first time it is imported,
+    /// using its filename, resolved against the current working directory, as its specifier.  
+    /// Unlike modules from [`Runtime::set_module_resolver`], they are kept if the resolver changes.  
+    /// See [`crate::static_modules`] to build the slice
+    ///
+    /// # Errors
+    /// Will return an error if a module's filename cannot be resolved to a specifier
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ static_modules, Module, Runtime };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// const LIBRARY: &[Module] = static_modules![
+    ///     ("lib/math.js", "export const double = (x) => x * 2;"),
+    ///     ("lib/strings.ts", "export const shout = (s: string): string => s.toUpperCase();"),
+    /// ];
+    ///
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.load_static_modules(LIBRARY)?;
+    ///
+    /// let module = Module::new("main.js", "
+    ///     import { double } from './lib/math.js';
+    ///     export const value = double(21);
+    /// ");
+    /// let module = runtime.load_module(&module)?;
+    /// let value: usize = runtime.get_value(Some(&module), "value")?;
+    /// assert_eq!(value, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_static_modules(&mut self, modules: &[Module]) -> Result<(), Error> {
+        self.inner.register_static_modules(modules)
+    }
+
+    /// Make a single module available for import, without loading or executing it
+    ///
+    /// The module is only transpiled, instantiated and run the first time an `import` references it -
+    /// modules that are never imported cost nothing beyond keeping their source.  
+    /// Since nothing is parsed here, syntax errors and exceptions thrown by top-level code are
+    /// reported by the first load or import that uses the module, not by this call.  
+    /// See [`Runtime::load_static_modules`] to register several at once
+    ///
+    /// # Errors
+    /// Will return an error if the module's filename cannot be resolved to a specifier
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ Module, Runtime };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.register_module_lazy(&Module::new(
+    ///     "plugins/greet.js",
+    ///     "globalThis.greetLoaded = true; export const greet = (n) => `Hello ${n}`;",
+    /// ))?;
+    ///
+    /// // Not executed yet
+    /// let loaded: bool = runtime.eval("globalThis.greetLoaded === true")?;
+    /// assert!(!loaded);
+    ///
+    /// let module = Module::new("main.js", "
+    ///     import { greet } from './plugins/greet.js';
+    ///     export const value = greet('World');
+    /// ");
+    /// let module = runtime.load_module(&module)?;
+    /// let value: String = runtime.get_value(Some(&module), "value")?;
+    /// assert_eq!(value, "Hello World");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_module_lazy(&mut self, module: &Module) -> Result<(), Error> {
+        self.inner
+            .register_static_modules(std::slice::from_ref(module))
+    }
+
+    /// Loads modules from an in-memory zip, tar or gzipped tar archive, then executes its entrypoint
+    ///
+    /// Every module in the archive is registered under a unique virtual directory, so imports between
+    /// them - including relative imports across nested directories - resolve within the archive.  
+    /// Nothing is extracted to disk, and only the modules actually imported are executed.  
+    /// Modules from the archive cannot import anything outside of it
+    ///
+    /// The sources are released once the entrypoint is loaded - modules it does not import
+    /// cannot be imported dynamically later on
+    ///
+    /// Entries with extensions other than `js`, `mjs`, `cjs`, `jsx`, `ts`, `mts`, `cts`, `tsx` or `json` are ignored
+    ///
+    /// # Arguments
+    /// * `archive` - The bytes of the archive - the format is detected automatically
+    /// * `entrypoint` - Path of the module to execute, relative to the root of the archive
+    ///
+    /// # Returns
+    /// A `Result` containing a handle for the entrypoint module
+    ///
+    /// # Errors
+    /// Will return an error if the archive is malformed, uses an unsupported zip compression method,
+    /// or decompresses to more than 256MB, if the entrypoint is not in the archive,
+    /// or if loading or executing the module fails
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use rustyscript::{ Runtime, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let archive = std::fs::read("plugin.zip").unwrap();
+    /// let module = runtime.load_archive(&archive, "src/main.js")?;
+    /// let name: String = runtime.get_value(Some(&module), "name")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "archive")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "archive")))]
+    pub fn load_archive(
+        &mut self,
+        archive: &[u8],
+        entrypoint: &str,
+    ) -> Result<ModuleHandle, Error> {
+        use crate::module_loader::archive;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static NEXT_ARCHIVE: AtomicUsize = AtomicUsize::new(0);
+
+        let entrypoint = archive::normalize_path(entrypoint)?.unwrap_or_default();
+        let root = self.inner.current_dir().join(format!(
+            ".rustyscript-archive-{}",
+            NEXT_ARCHIVE.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let mut main = None;
+        let mut modules = vec![];
+        for (path, source) in archive::read_archive(archive)? {
+            let module = Module::new(root.join(&path), source);
+            if path == entrypoint {
+                main = Some(module.clone());
+            }
+            modules.push(module);
+        }
+
+        let main = main.ok_or_else(|| {
+            Error::ModuleNotFound(format!("{entrypoint} could not be found in the archive"))
+        })?;
+
+        let root = deno_core::ModuleSpecifier::from_directory_path(&root)
+            .map_err(|()| Error::Runtime(format!("invalid archive path {}", root.display())))?;
+        let root = self.inner.module_loader.normalize_specifier(root);
+        self.inner.module_loader.add_archive_root(root.clone());
+        self.inner.register_static_modules(&modules)?;
+
+        let result = self.load_module(&main);
+        self.inner.module_loader.remove_static_modules_under(&root);
+        result
+    }
+
+    /// Checks a module and everything it imports, without instantiating or running any of it
+    ///
+    /// Imports are resolved and loaded through the runtime's module loader, following the same rules
+    /// as a real load - aliases, import maps, allowed schemes and registered modules all apply.  
+    /// No top-level code or entrypoint is run, and the modules are not added to the runtime.
+    ///
+    /// Only the module's own syntax errors fail the call - unresolvable or invalid imports are listed
+    /// in [`DryRunReport::unresolved_imports`], and its exports in [`DryRunReport::exports`]
+    ///
+    /// Note that the loader still fetches remote imports, and fires the module load observer and cache provider if set
+    ///
+    /// # Errors
+    /// Will return an error if the module cannot be parsed
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ Module, Runtime };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("upload.js", "
+    ///     import { helper } from './missing.js';
+    ///     throw new Error('never runs');
+    ///     export default () => helper();
+    /// ");
+    ///
+    /// # #[cfg(feature = "typescript")] {
+    /// let report = runtime.dry_run(&module)?;
+    /// assert!(!report.is_ok());
+    /// assert_eq!(report.unresolved_imports[0].specifier, "./missing.js");
+    /// assert!(report.has_entrypoint());
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn dry_run(&mut self, module: &Module) -> Result<crate::DryRunReport, Error> {
+        self.block_on(|runtime| async move { runtime.dry_run_async(module).await })
+    }
+
+    /// Checks a module and everything it imports, without instantiating or running any of it
+    ///
+    /// Returns a future that resolves once the import graph has been walked  
+    /// See [`Runtime::dry_run`] for details
+    ///
+    /// # Errors
+    /// Will return an error if the module cannot be parsed
+    pub async fn dry_run_async(&mut self, module: &Module) -> Result<crate::DryRunReport, Error> {
+        crate::dry_run::dry_run(&self.inner.module_loader, &self.inner.cwd, module).await
+    }
+
     /// Get the current working directory for the runtime  
     /// This is used to resolve relative paths in the module loader
     ///
@@ -145,6 +655,12 @@ impl Runtime {
         self.inner.current_dir()
     }
 
+    /// Returns the options used to transpile modules, set with [`RuntimeOptions::transpile_options`]
+    #[must_use]
+    pub fn transpile_options(&self) -> &crate::TranspileOptions {
+        self.inner.transpile_options()
+    }
+
     /// Advance the JS event loop by a single tick  
     /// See [`Runtime::await_event_loop`] for fully running the event loop
     ///
@@ -159,6 +675,62 @@ impl Runtime {
         self.block_on(|runtime| async move { runtime.inner.advance_event_loop(options).await })
     }
 
+    /// Advance the JS event loop by a single tick, with the default options  
+    /// Equivalent to [`Runtime::advance_event_loop`] with [`deno_core::PollEventLoopOptions::default`]
+    ///
+    /// Returns true if the event loop has pending work, or false if it has completed
+    ///
+    /// # Errors
+    /// Can fail if a runtime error occurs during the event loop's execution
+    pub fn advance_one_tick(&mut self) -> Result<bool, Error> {
+        self.advance_event_loop(PollEventLoopOptions::default())
+    }
+
+    /// Run all work in the JS event loop that is ready, without waiting on timers or async operations
+    ///
+    /// Unlike [`Runtime::advance_one_tick`], the loop keeps being polled for as long as it has work ready;
+    /// unlike [`Runtime::block_on_event_loop`], it never sleeps. This makes it useful for tests, which can
+    /// step through async code and assert on the intermediate state, without depending on wall-clock timing
+    ///
+    /// Returns true if the event loop still has pending work (such as a timer), or false if it has completed
+    ///
+    /// # Arguments
+    /// * `wait_for_inspector` - See [`deno_core::PollEventLoopOptions::wait_for_inspector`]
+    ///
+    /// # Errors
+    /// Can fail if a runtime error occurs during the event loop's execution
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ json_args, Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "
+    ///     export const steps = [];
+    ///     export function start() {
+    ///         Promise.resolve().then(() => steps.push('resolved'));
+    ///         setTimeout(() => steps.push('timer'), 10);
+    ///     }
+    /// ");
+    /// let module = runtime.load_module(&module)?;
+    /// runtime.call_function_immediate::<()>(Some(&module), "start", json_args!())?;
+    ///
+    /// // The promise has resolved, but the timer is still pending
+    /// assert!(runtime.run_event_loop(false)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn run_event_loop(&mut self, wait_for_inspector: bool) -> Result<bool, Error> {
+        let options = PollEventLoopOptions {
+            wait_for_inspector,
+            ..Default::default()
+        };
+        self.block_on(
+            |runtime| async move { runtime.inner.run_event_loop_until_idle(options).await },
+        )
+    }
+
     /// Run the JS event loop to completion, or until a timeout is reached  
     /// Required when using the `_immediate` variants of functions
     ///
@@ -173,7 +745,8 @@ impl Runtime {
         options: PollEventLoopOptions,
         timeout: Option<Duration>,
     ) -> Result<(), Error> {
-        self.inner.await_event_loop(options, timeout).await
+        let result = self.inner.await_event_loop(options, timeout).await;
+        self.inner.settle_microtask_budget(result)
     }
 
     /// Run the JS event loop to completion, or until a timeout is reached  
@@ -195,17 +768,298 @@ impl Runtime {
         self.block_on(|runtime| async move { runtime.await_event_loop(options, timeout).await })
     }
 
-    /// Remove and return a value from the state, if one exists
-    /// ```rust
-    /// use rustyscript::{ Runtime };
+    /// Run everything the scripts have scheduled - pending promises, timers and async operations - until the event loop is idle
     ///
-    /// # fn main() -> Result<(), rustyscript::Error> {
-    /// let mut runtime = Runtime::new(Default::default())?;
-    /// runtime.put("test".to_string())?;
-    /// let value: String = runtime.take().unwrap();
-    /// assert_eq!(value, "test");
-    /// # Ok(())
-    /// # }
+    /// Useful after firing off async work with the `_immediate` variants of functions, or from registered functions,
+    /// to settle all of it at once instead of pumping the event loop call by call.  
+    /// Unlike [`Runtime::block_on_event_loop`], reaching the deadline is an error rather than a silent return
+    ///
+    /// The runtime's [`crate::RuntimeOptions::timeout`] still applies, so a deadline longer than it has no effect
+    ///
+    /// # Arguments
+    /// * `deadline` - The maximum time to wait for the event loop to become idle
+    ///
+    /// # Errors
+    /// Fails with [`Error::Timeout`] if the event loop still has pending work once the deadline passes  
+    /// Or if a runtime error occurs during the event loop's execution, such as an uncaught error in a timer
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ json_args, Runtime, Module, Error };
+    /// use std::time::Duration;
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "
+    ///     export const done = [];
+    ///     export function start() {
+    ///         setTimeout(() => done.push('timer'), 10);
+    ///         fetchLater().then(() => done.push('promise'));
+    ///     }
+    ///     const fetchLater = () => new Promise((r) => setTimeout(r, 20));
+    /// ");
+    /// let module = runtime.load_module(&module)?;
+    /// runtime.call_function_immediate::<()>(Some(&module), "start", json_args!())?;
+    ///
+    /// runtime.drain(Duration::from_secs(1))?;
+    /// let done: Vec<String> = runtime.get_value(Some(&module), "done")?;
+    /// assert_eq!(done, vec!["timer", "promise"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn drain(&mut self, deadline: Duration) -> Result<(), Error> {
+        self.block_on(|runtime| async move { runtime.drain_async(deadline).await })
+    }
+
+    /// Run everything the scripts have scheduled until the event loop is idle
+    ///
+    /// Returns a future that resolves once the event loop is idle  
+    /// See [`Runtime::drain`] for details
+    ///
+    /// # Arguments
+    /// * `deadline` - The maximum time to wait for the event loop to become idle
+    ///
+    /// # Errors
+    /// Fails with [`Error::Timeout`] if the event loop still has pending work once the deadline passes  
+    /// Or if a runtime error occurs during the event loop's execution
+    pub async fn drain_async(&mut self, deadline: Duration) -> Result<(), Error> {
+        let result = self.inner.drain(deadline).await;
+        self.inner.settle_microtask_budget(result)
+    }
+
+    /// Checks if a value returned from the runtime is a promise, without resolving it
+    ///
+    /// Use the `_immediate` variants of functions to get a value without awaiting it,
+    /// and convert it to a [`crate::js_value::Promise`] to collect the result later:
+    /// - [`crate::js_value::Promise::is_pending`] checks if the promise has settled
+    /// - [`Runtime::advance_event_loop`] lets the background work progress while you do other work
+    /// - [`crate::js_value::Promise::poll_promise`] or [`crate::js_value::Promise::into_future`] collect the result
+    ///
+    /// ```rust
+    /// use rustyscript::{ json_args, Runtime, Module, Error, js_value::{ Promise, Value } };
+    /// use std::task::Poll;
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export const f = async () => 2;");
+    /// let module = runtime.load_module(&module)?;
+    ///
+    /// let value: Value = runtime.call_function_immediate(Some(&module), "f", json_args!())?;
+    /// assert!(runtime.is_promise(&value)?);
+    ///
+    /// let promise: Promise<usize> = runtime.call_function_immediate(Some(&module), "f", json_args!())?;
+    /// while promise.is_pending(&mut runtime) {
+    ///     runtime.advance_event_loop(Default::default())?;
+    /// }
+    /// assert!(matches!(promise.poll_promise(&mut runtime), Poll::Ready(Ok(2))));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// Will return an error if the value belongs to a different runtime
+    pub fn is_promise(&mut self, value: &crate::js_value::Value) -> Result<bool, Error> {
+        value.check_runtime(self.inner.info())?;
+        let mut scope = self.deno_runtime().handle_scope();
+        let value = v8::Local::new(&mut scope, value.as_v8());
+        Ok(value.is_promise())
+    }
+
+    /// Write a snapshot of the v8 heap to the given writer, in the `.heapsnapshot` format
+    /// used by the memory tab in Chrome `DevTools`
+    ///
+    /// This is a debugging tool - taking a snapshot is slow, pauses execution while the heap is walked,
+    /// and the output can be many times larger than the heap itself.
+    ///
+    /// # Errors
+    /// Can fail if writing to the writer fails
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let mut snapshot = Vec::new();
+    /// runtime.write_heap_snapshot(&mut snapshot)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_heap_snapshot(&mut self, mut writer: impl std::io::Write) -> Result<(), Error> {
+        self.inner.write_heap_snapshot(&mut writer)
+    }
+
+    /// Returns the current heap usage of the runtime
+    ///
+    /// Cheap enough to call regularly - it reads counters kept by v8, and does not trigger garbage collection.  
+    /// Comparing `used_heap_size` against `heap_size_limit` can warn of an approaching out-of-memory condition
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let stats = runtime.heap_stats();
+    /// println!("{} of {} bytes used", stats.used_heap_size, stats.heap_size_limit);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn heap_stats(&mut self) -> HeapStats {
+        let mut stats = v8::HeapStatistics::default();
+        self.deno_runtime()
+            .v8_isolate()
+            .get_heap_statistics(&mut stats);
+
+        HeapStats {
+            used_heap_size: stats.used_heap_size(),
+            total_heap_size: stats.total_heap_size(),
+            heap_size_limit: stats.heap_size_limit(),
+            external_memory: stats.external_memory(),
+            malloced_memory: stats.malloced_memory(),
+            native_contexts: stats.number_of_native_contexts(),
+            detached_contexts: stats.number_of_detached_contexts(),
+        }
+    }
+
+    /// Serialize the state held by an object exported from a module, to be restored later
+    /// with [`Runtime::restore_module_state`] - for example, to implement save and load
+    ///
+    /// Closures and module-level variables cannot be captured, so the state must live in a
+    /// designated exported object. Its contents are serialized with v8's structured clone algorithm;
+    /// primitives, plain objects, arrays, `Map`, `Set`, `Date`, typed arrays and the like are supported,
+    /// but functions and symbols are not, and class instances are restored as plain objects.
+    ///
+    /// The format is v8's, and is only guaranteed to be readable by the same version of this crate
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle to a loaded module
+    /// * `name` - The name of the exported object holding the state
+    ///
+    /// # Returns
+    /// A `Result` containing the serialized state
+    ///
+    /// # Errors
+    /// Will return an error if the export does not exist, is not an object, or contains values that cannot be serialized
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, json_args };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let module = Module::new("test.js", "
+    ///     export const state = { score: 0 };
+    ///     export function addPoints(n) { state.score += n; return state.score; }
+    /// ");
+    ///
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let handle = runtime.load_module(&module)?;
+    /// runtime.call_function::<u32>(Some(&handle), "addPoints", json_args!(10))?;
+    ///
+    /// let saved = runtime.serialize_module_state(&handle, "state")?;
+    /// runtime.call_function::<u32>(Some(&handle), "addPoints", json_args!(5))?;
+    ///
+    /// runtime.restore_module_state(&handle, "state", &saved)?;
+    /// let score: u32 = runtime.call_function(Some(&handle), "addPoints", json_args!(0))?;
+    /// assert_eq!(score, 10);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn serialize_module_state(
+        &mut self,
+        module_context: &ModuleHandle,
+        name: &str,
+    ) -> Result<Vec<u8>, Error> {
+        self.inner.serialize_export(module_context, name)
+    }
+
+    /// Restore the state of an object exported from a module, from the output of [`Runtime::serialize_module_state`]
+    ///
+    /// The object is updated in place - its own properties are removed, and replaced with the saved ones,
+    /// or for a `Map` or `Set` its entries - so functions in the module that refer to it will see the restored state.  
+    /// If the object cannot be fully updated, it is left as it was.  
+    /// The state can be restored into a different runtime, as long as the same module is loaded
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle to a loaded module
+    /// * `name` - The name of the exported object holding the state
+    /// * `state` - The serialized state
+    ///
+    /// # Errors
+    /// Will return an error if the export does not exist or is not an object, if the state is invalid,
+    /// or if the object cannot be modified (for example, if it is frozen)
+    pub fn restore_module_state(
+        &mut self,
+        module_context: &ModuleHandle,
+        name: &str,
+        state: &[u8],
+    ) -> Result<(), Error> {
+        self.inner.restore_export(module_context, name, state)
+    }
+
+    /// Serialize a javascript value using v8's structured clone format, so that it can be
+    /// passed to another runtime with [`Runtime::import_value`]
+    ///
+    /// Unlike JSON, this preserves types such as `Date`, `Map`, `Set`, and typed arrays
+    ///
+    /// # Arguments
+    /// * `value` - The value to serialize, such as one returned by [`Runtime::eval`] or [`Runtime::get_value`]
+    ///
+    /// # Errors
+    /// Will return an error if the value cannot be cloned (for example, if it contains a function),
+    /// or if it came from a different runtime
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ Runtime, js_value::Value };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut trusted = Runtime::new(Default::default())?;
+    /// let mut untrusted = Runtime::new(Default::default())?;
+    ///
+    /// let value: Value = untrusted.eval("new Map([['when', new Date(0)]])")?;
+    /// let value = untrusted.export_value(&value)?;
+    ///
+    /// let value: Value = trusted.import_value(&value)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export_value(
+        &mut self,
+        value: &crate::js_value::Value,
+    ) -> Result<crate::js_value::SerializedValue, Error> {
+        value.check_runtime(self.inner.info())?;
+        let bytes = self.inner.serialize_value(value.as_v8())?;
+        Ok(crate::js_value::SerializedValue::from_bytes(bytes))
+    }
+
+    /// Deserialize a value produced by [`Runtime::export_value`], which may come from a different runtime
+    ///
+    /// Use [`crate::js_value::Value`] as the generic T to keep the value in javascript,
+    /// preserving types that cannot be represented in rust
+    ///
+    /// # Arguments
+    /// * `value` - The serialized value
+    ///
+    /// # Errors
+    /// Will return an error if the value is invalid, or cannot be deserialized into the requested type
+    pub fn import_value<T>(&mut self, value: &crate::js_value::SerializedValue) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let value = self.inner.deserialize_value(value.as_bytes())?;
+        self.inner.decode_value(value)
+    }
+
+    /// Remove and return a value from the state, if one exists
+    /// ```rust
+    /// use rustyscript::{ Runtime };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.put("test".to_string())?;
+    /// let value: String = runtime.take().unwrap();
+    /// assert_eq!(value, "test");
+    /// # Ok(())
+    /// # }
     /// ```
     pub fn take<T>(&mut self) -> Option<T>
     where
@@ -238,11 +1092,66 @@ impl Runtime {
         self.inner.put(value)
     }
 
+    /// Set an immutable context object, shared with registered functions without copying it
+    ///
+    /// Functions registered with [`Runtime::register_module_function`] can read it through
+    /// [`crate::ModuleGetter::shared_context`], and extension ops can borrow the `Arc<T>` from their `OpState`.  
+    /// Each access only clones the `Arc`, so large configuration is never re-serialized per call.
+    ///
+    /// - One context is stored per type - setting another of the same type replaces it
+    /// - The context must be `Send + Sync`, since the `Arc` may be cloned out to other threads by the functions using it
+    /// - It is read-only: javascript has no direct access to it, and can only observe it through registered functions
+    ///
+    /// # Errors
+    /// Can fail if the inner state cannot be borrowed mutably
+    ///
+    /// ```rust
+    /// use rustyscript::{ json_args, Runtime, Module, serde_json::Value };
+    /// use std::sync::Arc;
+    ///
+    /// struct TenantConfig { name: String }
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.set_shared_context(Arc::new(TenantConfig { name: "acme".to_string() }))?;
+    /// runtime.register_module_function("tenant", |_, getter| {
+    ///     let config = getter.shared_context::<TenantConfig>().unwrap();
+    ///     Ok(Value::String(config.name.clone()))
+    /// })?;
+    ///
+    /// let name: String = runtime.eval("rustyscript.functions.tenant()")?;
+    /// assert_eq!(name, "acme");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_shared_context<T>(&mut self, ctx: std::sync::Arc<T>) -> Result<(), Error>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.inner.put(ctx)
+    }
+
+    /// Get the shared context of the given type, set with [`Runtime::set_shared_context`]
+    /// Returns `None` if no context of that type has been set
+    pub fn shared_context<T>(&mut self) -> Option<std::sync::Arc<T>>
+    where
+        T: Send + Sync + 'static,
+    {
+        let state = self.deno_runtime().op_state();
+        let state = state.try_borrow().ok()?;
+        state.try_borrow::<std::sync::Arc<T>>().cloned()
+    }
+
     /// Register a rust function to be callable from JS
     /// - The [`crate::sync_callback`] macro can be used to simplify this process
+    /// - Returning `Err` throws a JS `Error` carrying the error's message, which can be caught with `try/catch`
+    /// - Returning [`Error::js_class`] throws an instance of a class registered with [`Runtime::register_error_class`]
+    /// - A dotted name, such as `myapi.db.query`, also places the function at that path on the global object,
+    ///   or under [`crate::RuntimeOptions::function_namespace_root`] if set
     ///
     /// # Errors
-    /// Since this function borrows the state, it can fail if the state cannot be borrowed mutably
+    /// Since this function borrows the state, it can fail if the state cannot be borrowed mutably  
+    /// Will also fail if a dotted name collides with an existing function, namespace or global along its path
     ///
     /// ```rust
     /// use rustyscript::{ Runtime, Module, serde_json::Value };
@@ -269,9 +1178,13 @@ impl Runtime {
 
     /// Register a non-blocking rust function to be callable from JS
     /// - The [`crate::async_callback`] macro can be used to simplify this process
+    /// - Returning `Err` rejects the promise with a JS `Error` carrying the error's message
+    /// - Returning [`Error::js_class`] rejects it with an instance of a class registered with [`Runtime::register_error_class`]
+    /// - A dotted name is placed at that path, as with [`Runtime::register_function`]
     ///
     /// # Errors
-    /// Since this function borrows the state, it can fail if the state cannot be borrowed mutably
+    /// Since this function borrows the state, it can fail if the state cannot be borrowed mutably  
+    /// Will also fail if a dotted name collides with an existing function, namespace or global along its path
     ///
     /// ```rust
     /// use rustyscript::{ Runtime, Module, serde_json::Value, async_callback, Error };
@@ -295,6 +1208,152 @@ impl Runtime {
         self.inner.register_async_function(name, callback)
     }
 
+    /// Register a rust function returning a stream of values, consumable from JS as an async iterable
+    /// - The function is called through `rustyscript.stream_functions`, and the result used with `for await`
+    /// - Values are pulled from the stream one at a time, as JS asks for them - the stream is never read ahead
+    /// - The stream is dropped once it ends, fails, or JS breaks out of the loop early  
+    ///   An iterable that is abandoned without being exhausted is only dropped with the runtime
+    /// - An `Err` from the stream is thrown into the loop
+    ///
+    /// Iterators can be adapted with [`deno_core::futures::stream::iter`]
+    ///
+    /// # Errors
+    /// Since this function borrows the state, it can fail if the state cannot be borrowed mutably
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, RsValueStream, serde_json::Value };
+    /// use rustyscript::deno_core::futures::stream;
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.register_stream_function("count", |args| {
+    ///     let n = args.first().and_then(Value::as_u64).unwrap_or(0);
+    ///     let stream: RsValueStream = Box::pin(stream::iter((0..n).map(|i| Ok(Value::from(i)))));
+    ///     Ok(stream)
+    /// })?;
+    ///
+    /// let module = Module::new("test.js", "
+    ///     let total = 0;
+    ///     for await (const i of rustyscript.stream_functions.count(4)) {
+    ///         total += i;
+    ///     }
+    ///     export { total };
+    /// ");
+    /// let module = runtime.load_module(&module)?;
+    /// let total: u64 = runtime.get_value(Some(&module), "total")?;
+    /// assert_eq!(total, 6);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_stream_function<F>(&mut self, name: &str, callback: F) -> Result<(), Error>
+    where
+        F: RsStreamFunction,
+    {
+        self.inner.register_stream_function(name, callback)
+    }
+
+    /// Register a rust function to be callable from JS, with synchronous access to the exports of loaded modules
+    /// - The function is called like any other, through `rustyscript.functions`
+    /// - Modules are looked up by the filename they were loaded with
+    ///
+    /// The [`crate::ModuleGetter`] only exists for the duration of the call, on the runtime's thread
+    ///
+    /// # Errors
+    /// Since this function borrows the state, it can fail if the state cannot be borrowed mutably
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, serde_json::Value };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.register_module_function("rate", |args, modules| {
+    ///     let rate: f64 = modules.get("config.js", "rate")?;
+    ///     let amount = args.first().and_then(Value::as_f64).unwrap_or_default();
+    ///     Ok(Value::from(amount * rate))
+    /// })?;
+    ///
+    /// runtime.load_module(&Module::new("config.js", "export const rate = 0.5;"))?;
+    /// let module = Module::new("test.js", "export const value = rustyscript.functions.rate(4);");
+    /// let module = runtime.load_module(&module)?;
+    /// let value: f64 = runtime.get_value(Some(&module), "value")?;
+    /// assert_eq!(value, 2.0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_module_function<F>(&mut self, name: &str, callback: F) -> Result<(), Error>
+    where
+        F: RsModuleFunction,
+    {
+        self.inner.register_module_function(name, callback)
+    }
+
+    /// Define a custom error class in javascript, so that registered functions can throw instances of it
+    ///
+    /// The class extends `Error`, and is available as a global - so scripts can use `instanceof` to tell errors apart.  
+    /// Registered functions throw it by returning [`Error::js_class`] with the same name.  
+    /// If a global constructor with that name already exists, such as `TypeError`, it is used instead
+    ///
+    /// # Errors
+    /// Will return an error if the name is not a valid javascript identifier, or the class cannot be defined
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ Runtime, Error, serde_json::Value };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.register_error_class("NetworkError")?;
+    /// runtime.register_function("fetch", |_| {
+    ///     Err::<Value, _>(Error::js_class("NetworkError", "connection refused"))
+    /// })?;
+    ///
+    /// let message: String = runtime.eval("
+    ///     try { rustyscript.functions.fetch() }
+    ///     catch (e) { if (e instanceof NetworkError) e.message; else throw e; }
+    /// ")?;
+    /// assert_eq!(message, "connection refused");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_error_class(&mut self, name: &str) -> Result<(), Error> {
+        self.inner.register_error_class(name)
+    }
+
+    /// Set the typescript signature of a registered function, for use by [`Runtime::generate_host_dts`]
+    ///
+    /// The signature is written after the function's name, such as `(a: number, b: number): number`  
+    /// Async functions should declare a `Promise` return type
+    pub fn set_function_type_hint(&mut self, name: &str, signature: &str) {
+        self.inner.set_function_type_hint(name, signature);
+    }
+
+    /// Generate a typescript declaration file (`.d.ts`) for the `rustyscript` global,
+    /// describing every function registered with the runtime
+    ///
+    /// Functions with a type hint (see [`Runtime::set_function_type_hint`]) use that signature,
+    /// while the rest are declared as taking and returning `any`
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, serde_json::Value };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.register_function("add", |args| {
+    ///     let sum: i64 = args.iter().filter_map(Value::as_i64).sum();
+    ///     Ok(Value::from(sum))
+    /// })?;
+    /// runtime.set_function_type_hint("add", "(a: number, b: number): number");
+    ///
+    /// let dts = runtime.generate_host_dts();
+    /// assert!(dts.contains("add(a: number, b: number): number;"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn generate_host_dts(&mut self) -> String {
+        self.inner.generate_host_dts()
+    }
+
     /// Evaluate a piece of non-ECMAScript-module JavaScript code  
     /// The expression is evaluated in the global context, so changes persist
     ///
@@ -346,12 +1405,106 @@ impl Runtime {
         self.block_on(|runtime| async move { runtime.eval_async(expr).await })
     }
 
-    /// Evaluate a piece of non-ECMAScript-module JavaScript code  
-    /// The expression is evaluated in the global context, so changes persist
+    /// Evaluate a piece of non-ECMAScript-module JavaScript code, and report the type of the result
+    /// without deserializing it
     ///
-    /// Awaits promise resolution, and runs the event loop to completion
+    /// Promises are resolved first, as with [`Runtime::eval`].  
+    /// To inspect a value you already have, use [`crate::js_value::Value::type_of`]
     ///
-    /// Asynchronous code is supported, partially
+    /// # Arguments
+    /// * `expr` - A string representing the JavaScript expression to evaluate
+    ///
+    /// # Errors
+    /// Can fail if the expression cannot be evaluated
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ Runtime, js_value::ValueType };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// assert_eq!(runtime.value_type_of("2n ** 64n")?, ValueType::BigInt);
+    /// assert_eq!(runtime.value_type_of("[1, 2, 3]")?, ValueType::Array);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn value_type_of(
+        &mut self,
+        expr: impl ToString,
+    ) -> Result<crate::js_value::ValueType, Error> {
+        let value: crate::js_value::Value = self.eval(expr)?;
+        value.type_of(self)
+    }
+
+    /// Returns the value thrown by the most recent failed call, and clears it
+    ///
+    /// Useful for recovering structured error payloads, such as custom fields on a thrown error,
+    /// that do not survive conversion to an [`Error`].  
+    /// `name` and `message` are included for `Error` objects, alongside their own enumerable properties.
+    ///
+    /// Recorded for exceptions thrown by evaluated code, called functions and loaded modules, and for
+    /// rejected promises they return. It is cleared as each of those calls starts, so it only ever describes
+    /// the latest one - `None` is returned if that call did not throw, or the value was already taken
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ json_args, Module, Runtime };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = runtime.load_module(&Module::new("test.js", "
+    ///     export function f() {
+    ///         throw Object.assign(new Error('Invalid input'), { code: 'E_INPUT' });
+    ///     }
+    /// "))?;
+    ///
+    /// let e = runtime.call_function::<()>(Some(&module), "f", json_args!()).unwrap_err();
+    /// let thrown = runtime.take_last_exception().unwrap();
+    /// assert_eq!(thrown["code"], "E_INPUT");
+    /// assert_eq!(thrown["message"], "Invalid input");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn take_last_exception(&mut self) -> Option<crate::serde_json::Value> {
+        let exception = self.inner.take_last_exception()?;
+        let mut scope = self.deno_runtime().handle_scope();
+        let exception = v8::Local::new(&mut scope, exception);
+        let mut value =
+            deno_core::serde_v8::from_v8::<crate::serde_json::Value>(&mut scope, exception)
+                .unwrap_or_else(|_| exception.to_rust_string_lossy(&mut scope).into());
+
+        // `name` and `message` are not enumerable on errors
+        if exception.is_native_error() {
+            if let (Some(fields), Ok(error)) = (
+                value.as_object_mut(),
+                v8::Local::<v8::Object>::try_from(exception),
+            ) {
+                for name in ["name", "message"] {
+                    if fields.contains_key(name) {
+                        continue;
+                    }
+
+                    let Some(key) = v8::String::new(&mut scope, name) else {
+                        continue;
+                    };
+                    if let Some(field) = error.get(&mut scope, key.into()).filter(|v| v.is_string())
+                    {
+                        let field = field.to_rust_string_lossy(&mut scope);
+                        fields.insert(name.to_string(), field.into());
+                    }
+                }
+            }
+        }
+
+        Some(value)
+    }
+
+    /// Evaluate a piece of non-ECMAScript-module JavaScript code  
+    /// The expression is evaluated in the global context, so changes persist
+    ///
+    /// Awaits promise resolution, and runs the event loop to completion
+    ///
+    /// Asynchronous code is supported, partially
     /// - Top-level await is not supported
     /// - The event loop will be run to completion after the expression is evaluated
     ///
@@ -380,9 +1533,13 @@ impl Runtime {
     where
         T: serde::de::DeserializeOwned,
     {
-        let result = self.inner.eval(expr.to_string()).await?;
-        let result = self.inner.resolve_with_event_loop(result).await?;
-        self.inner.decode_value(result)
+        let result = async {
+            let result = self.inner.eval(expr.to_string()).await?;
+            let result = self.inner.resolve_with_event_loop(result).await?;
+            self.inner.decode_value(result)
+        }
+        .await;
+        self.inner.settle_microtask_budget(result)
     }
 
     /// Evaluate a piece of non-ECMAScript-module JavaScript code  
@@ -422,8 +1579,12 @@ impl Runtime {
     where
         T: serde::de::DeserializeOwned,
     {
-        let result = self.inner.eval(expr.to_string()).await?;
-        self.inner.decode_value(result)
+        let result = async {
+            let result = self.inner.eval(expr.to_string()).await?;
+            self.inner.decode_value(result)
+        }
+        .await;
+        self.inner.settle_microtask_budget(result)
     }
 
     /// Calls a stored javascript function and deserializes its return value.
@@ -457,12 +1618,16 @@ impl Runtime {
     where
         T: serde::de::DeserializeOwned,
     {
-        let function = function.as_global(&mut self.deno_runtime().handle_scope());
-        let result = self
-            .inner
-            .call_function_by_ref(module_context, &function, args)?;
-        let result = self.inner.resolve_with_event_loop(result).await?;
-        self.inner.decode_value(result)
+        let result = async {
+            let function = function.as_global(&mut self.deno_runtime().handle_scope());
+            let result = self
+                .inner
+                .call_function_by_ref(module_context, &function, args)?;
+            let result = self.inner.resolve_with_event_loop(result).await?;
+            self.inner.decode_value(result)
+        }
+        .await;
+        self.inner.settle_microtask_budget(result)
     }
 
     /// Calls a stored javascript function and deserializes its return value.
@@ -494,10 +1659,12 @@ impl Runtime {
     where
         T: deno_core::serde::de::DeserializeOwned,
     {
-        self.block_on(|runtime| async move {
-            runtime
-                .call_stored_function_async(module_context, function, args)
-                .await
+        self.with_auto_recover(module_context, |runtime, module_context| {
+            runtime.block_on(|runtime| async move {
+                runtime
+                    .call_stored_function_async(module_context, function, args)
+                    .await
+            })
         })
     }
 
@@ -569,12 +1736,16 @@ impl Runtime {
     where
         T: deno_core::serde::de::DeserializeOwned,
     {
-        let function = self.inner.get_function_by_name(module_context, name)?;
-        let result = self
-            .inner
-            .call_function_by_ref(module_context, &function, args)?;
-        let result = self.inner.resolve_with_event_loop(result).await?;
-        self.inner.decode_value(result)
+        let result = async {
+            let function = self.inner.get_function_by_name(module_context, name)?;
+            let result = self
+                .inner
+                .call_function_by_ref(module_context, &function, args)?;
+            let result = self.inner.resolve_with_event_loop(result).await?;
+            self.inner.decode_value(result)
+        }
+        .await;
+        self.inner.settle_microtask_budget(result)
     }
 
     /// Calls a javascript function within the Deno runtime by its name and deserializes its return value.
@@ -586,7 +1757,17 @@ impl Runtime {
     /// # Arguments
     /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
     /// * `name` - A string representing the name of the javascript function to call.
-    /// * `args` - The arguments to pass to the function
+    /// * `args` - The arguments to pass to the function - see below
+    ///
+    /// # Arguments to the function
+    /// `args` can be any serializable value; a tuple or slice is spread into positional arguments.  
+    /// A tuple of typed values is checked at compile time, with no conversion to `serde_json::Value` -
+    /// `&("test", 5u32, vec![1, 2, 3])` calls `f("test", 5, [1, 2, 3])`.  
+    /// [`crate::json_args`] builds such a tuple. To pass a single array, wrap it in a 1-tuple: `&(vec![1, 2],)`
+    ///
+    /// Argument lists built at runtime, such as a `Vec<serde_json::Value>`, can be passed as `&args` or `&args[..]`.  
+    /// Arguments are converted to javascript straight from the reference, so there is no need to clone
+    /// or take ownership of a large list - and no need for [`crate::big_json_args`]
     ///
     /// # Returns
     /// A `Result` containing the deserialized result of the function call (`T`)  
@@ -604,9 +1785,16 @@ impl Runtime {
     ///
     /// # fn main() -> Result<(), Error> {
     /// let mut runtime = Runtime::new(Default::default())?;
-    /// let module = Module::new("/path/to/module.js", "export function f() { return 2; };");
+    /// let module = Module::new("/path/to/module.js", "
+    ///     export function f() { return 2; };
+    ///     export function g(s, n, list) { return s.length + n + list.length; };
+    /// ");
     /// let module = runtime.load_module(&module)?;
     /// let value: usize = runtime.call_function(Some(&module), "f", json_args!())?;
+    ///
+    /// // Typed positional arguments
+    /// let value: usize = runtime.call_function(Some(&module), "g", &("test", 5u32, vec![1, 2, 3]))?;
+    /// assert_eq!(value, 12);
     /// # Ok(())
     /// # }
     /// ```
@@ -619,13 +1807,142 @@ impl Runtime {
     where
         T: deno_core::serde::de::DeserializeOwned,
     {
-        self.block_on(|runtime| async move {
-            runtime
-                .call_function_async(module_context, name, args)
-                .await
+        self.with_auto_recover(module_context, |runtime, module_context| {
+            runtime.block_on(|runtime| async move {
+                runtime
+                    .call_function_async(module_context, name, args)
+                    .await
+            })
+        })
+    }
+
+    /// Calls the default export of a module, and deserializes its return value
+    ///
+    /// Useful for the common `export default function() {...}` pattern.  
+    /// Unlike [`Runtime::call_entrypoint`], this ignores any registered or configured entrypoint,
+    /// and always calls the module's `default` export, with the module namespace as `this`
+    ///
+    /// Blocks until:
+    /// - The event loop is resolved, and
+    /// - If the value is a promise, the promise is resolved
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by loading a module into the runtime
+    /// * `args` - The arguments to pass to the function - see [`Runtime::call_function`]
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function call (`T`)  
+    /// or an error (`Error`) if the default export is not callable, if the call fails,
+    /// or if the result cannot be deserialized.
+    ///
+    /// # Errors
+    /// Fails with [`Error::DefaultExportNotCallable`] if the module has no default export, or if it is not a function  
+    /// Or if the call fails, or the result cannot be deserialized into the requested type
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ json_args, Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export default function(a, b) { return a + b; }");
+    /// let module = runtime.load_module(&module)?;
+    ///
+    /// let value: usize = runtime.call_default(&module, json_args!(2, 3))?;
+    /// assert_eq!(value, 5);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_default<T>(
+        &mut self,
+        module_context: &ModuleHandle,
+        args: &impl serde::ser::Serialize,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        self.with_auto_recover(Some(module_context), |runtime, recovered| {
+            let module_context = recovered.unwrap_or(module_context);
+            runtime.block_on(|runtime| async move {
+                runtime.call_default_async(module_context, args).await
+            })
         })
     }
 
+    /// Calls the default export of a module, and deserializes its return value
+    ///
+    /// Returns a future that resolves when:
+    /// - The event loop is resolved, and
+    /// - If the value is a promise, the promise is resolved
+    ///
+    /// See [`Runtime::call_default`] for an example
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by loading a module into the runtime
+    /// * `args` - The arguments to pass to the function
+    ///
+    /// # Errors
+    /// Fails with [`Error::DefaultExportNotCallable`] if the module has no default export, or if it is not a function  
+    /// Or if the call fails, or the result cannot be deserialized into the requested type
+    pub async fn call_default_async<T>(
+        &mut self,
+        module_context: &ModuleHandle,
+        args: &impl serde::ser::Serialize,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        let result = async {
+            let function = self.inner.get_default_function(module_context)?;
+            let result = self
+                .inner
+                .call_function_by_ref(Some(module_context), &function, args)?;
+            let result = self.inner.resolve_with_event_loop(result).await?;
+            self.inner.decode_value(result)
+        }
+        .await;
+        self.inner.settle_microtask_budget(result)
+    }
+
+    /// Calls a javascript function by its name, and returns its result as an opaque handle, without deserializing it
+    ///
+    /// Equivalent to `call_function::<js_value::Value>` - promises are resolved the same way.  
+    /// The handle can be passed as an argument to other calls on this runtime, and arrives as the original
+    /// javascript value - useful for composing javascript functions from rust, including values such as
+    /// class instances, closures or cyclic objects that cannot be deserialized.
+    ///
+    /// Handles are only valid for the runtime that returned them
+    ///
+    /// # Errors
+    /// Fails if the function cannot be found, or if there are issues with calling the function
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ json_args, Module, Runtime };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = runtime.load_module(&Module::new("test.js", "
+    ///     export const counter = (start) => ({ value: start, increment() { return ++this.value; } });
+    ///     export const bump = (counter) => counter.increment();
+    /// "))?;
+    ///
+    /// let counter = runtime.call_function_raw(Some(&module), "counter", json_args!(5))?;
+    /// let value: usize = runtime.call_function(Some(&module), "bump", json_args!(counter))?;
+    /// assert_eq!(value, 6);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_function_raw(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        args: &impl serde::ser::Serialize,
+    ) -> Result<crate::js_value::Value, Error> {
+        self.call_function(module_context, name, args)
+    }
+
     /// Calls a javascript function within the Deno runtime by its name and deserializes its return value.
     ///
     /// Will not attempt to resolve promises, or run the event loop  
@@ -675,737 +1992,4927 @@ impl Runtime {
         self.inner.decode_value(result)
     }
 
-    /// Get a value from a runtime instance
+    /// Calls a javascript generator function - sync or async - and returns an iterator over the values it yields
     ///
-    /// Blocks until:
-    /// - The event loop is resolved, and
-    /// - If the value is a promise, the promise is resolved
+    /// Values are produced incrementally, as the generator yields them, which suits scripts that tail logs or
+    /// fetch paginated data. See [`GeneratorIter`] for how each step is run, and how early termination is handled
     ///
     /// # Arguments
     /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
-    /// * `name` - A string representing the name of the value to find
+    /// * `name` - A string representing the name of the generator function to call
+    /// * `args` - The arguments to pass to the function
     ///
     /// # Returns
-    /// A `Result` containing the deserialized result or an error (`Error`) if the value cannot be found,
-    /// Or if the result cannot be deserialized into the requested type
+    /// An iterator over the deserialized values, or an error if the function cannot be called
     ///
     /// # Errors
-    /// Can fail if the value cannot be found, or if the result cannot be deserialized.
+    /// Fails if the function cannot be found, or throws when called.  
+    /// The iterator yields an error if the result is not an iterator, if the generator throws,
+    /// or if a value cannot be deserialized - after which it stops
     ///
     /// # Example
     ///
     /// ```rust
-    /// use rustyscript::{ Runtime, Module, Error };
+    /// use rustyscript::{ json_args, Runtime, Module, Error };
     ///
     /// # fn main() -> Result<(), Error> {
     /// let mut runtime = Runtime::new(Default::default())?;
-    /// let module = Module::new("/path/to/module.js", "globalThis.my_value = 2;");
+    /// let module = Module::new("test.js", "
+    ///     export async function* pages(count) {
+    ///         for (let i = 1; i <= count; i++) {
+    ///             await new Promise(r => setTimeout(r, 1));
+    ///             yield `page ${i}`;
+    ///         }
+    ///     }
+    /// ");
     /// let module = runtime.load_module(&module)?;
-    /// let value: usize = runtime.get_value(Some(&module), "my_value")?;
+    ///
+    /// for page in runtime.call_generator::<String>(Some(&module), "pages", json_args!(3))? {
+    ///     println!("{}", page?);
+    /// }
     /// # Ok(())
     /// # }
     /// ```
-    pub fn get_value<T>(
+    pub fn call_generator<T>(
         &mut self,
         module_context: Option<&ModuleHandle>,
         name: &str,
-    ) -> Result<T, Error>
+        args: &impl serde::ser::Serialize,
+    ) -> Result<GeneratorIter<'_, T>, Error>
     where
-        T: serde::de::DeserializeOwned,
+        T: deno_core::serde::de::DeserializeOwned,
     {
-        self.block_on(|runtime| async move { runtime.get_value_async(module_context, name).await })
+        let function = self.inner.get_function_by_name(module_context, name)?;
+        let generator = self
+            .inner
+            .call_function_by_ref(module_context, &function, args)?;
+        Ok(GeneratorIter {
+            runtime: self,
+            generator,
+            done: false,
+            _marker: std::marker::PhantomData,
+        })
     }
 
-    /// Get a value from a runtime instance
+    /// Calls a set of javascript functions, and deserializes their return values
     ///
-    /// Returns a future that resolves when:
-    /// - The event loop is resolved, and
-    /// - If the value is a promise, the promise is resolved
+    /// All calls are made first, then the event loop is run once until every result has resolved.  
+    /// This avoids paying the event loop overhead on each call, for bulk operations over many small calls
     ///
-    /// See [`Runtime::get_value`] for an example
+    /// Returns a future that resolves once every call has resolved
+    ///
+    /// See [`Runtime::call_batch`] for an example
     ///
     /// # Arguments
-    /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
-    /// * `name` - A string representing the name of the value to find
+    /// * `calls` - The calls to make, as a list of `(module, function name, arguments)`
     ///
     /// # Returns
-    /// A `Result` containing the deserialized result or an error (`Error`) if the value cannot be found,  
-    /// Or if the result cannot be deserialized into the requested type
-    ///
-    /// # Errors
-    /// Can fail if the value cannot be found, or if the result cannot be deserialized.
-    pub async fn get_value_async<T>(
+    /// The result of each call, in the same order as `calls`  
+    /// A failing call does not abort the batch - its error is returned in its place
+    pub async fn call_batch_async<T>(
         &mut self,
-        module_context: Option<&ModuleHandle>,
-        name: &str,
-    ) -> Result<T, Error>
+        calls: &[(ModuleHandle, String, Vec<crate::serde_json::Value>)],
+    ) -> Vec<Result<T, Error>>
     where
-        T: serde::de::DeserializeOwned,
+        T: deno_core::serde::de::DeserializeOwned,
     {
-        let result = self.inner.get_value_ref(module_context, name)?;
-        let result = self.inner.resolve_with_event_loop(result).await?;
-        self.inner.decode_value(result)
+        let results = calls
+            .iter()
+            .map(|(module, name, args)| {
+                let function = self.inner.get_function_by_name(Some(module), name)?;
+                self.inner
+                    .call_function_by_ref(Some(module), &function, args)
+            })
+            .collect();
+
+        let results = self.inner.resolve_all_with_event_loop(results).await;
+
+        // Calls cut short by an overrun of the microtask budget report it instead of the termination error
+        let overrun = self.inner.settle_microtask_budget(Ok(())).err();
+        results
+            .into_iter()
+            .map(|result| match (&overrun, result) {
+                (Some(e), Err(_)) => Err(e.clone()),
+                (_, result) => result.and_then(|value| self.inner.decode_value(value)),
+            })
+            .collect()
     }
 
-    /// Get a value from a runtime instance
+    /// Calls a set of javascript functions, and deserializes their return values
     ///
-    /// Will not attempt to resolve promises, or run the event loop  
-    /// Promises can be returned by specifying the return type as [`crate::js_value::Promise`]  
-    /// The event loop should be run using [`Runtime::await_event_loop`]
+    /// All calls are made first, then the event loop is run once until every result has resolved.  
+    /// This avoids paying the event loop overhead on each call, for bulk operations over many small calls
+    ///
+    /// Blocks until every call has resolved
     ///
     /// # Arguments
-    /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
-    /// * `name` - A string representing the name of the value to find
+    /// * `calls` - The calls to make, as a list of `(module, function name, arguments)`
     ///
     /// # Returns
-    /// A `Result` containing the deserialized result or an error (`Error`) if the value cannot be found,
-    /// Or if the result cannot be deserialized into the requested type
-    ///
-    /// # Errors
-    /// Can fail if the value cannot be found, or if the result cannot be deserialized.
+    /// The result of each call, in the same order as `calls`  
+    /// A failing call does not abort the batch - its error is returned in its place
     ///
     /// # Example
     ///
     /// ```rust
-    /// use rustyscript::{ Runtime, Module, Error };
+    /// use rustyscript::{ json_args, Runtime, Module, Error };
     ///
     /// # fn main() -> Result<(), Error> {
     /// let mut runtime = Runtime::new(Default::default())?;
-    /// let module = Module::new("/path/to/module.js", "globalThis.my_value = 2;");
+    /// let module = Module::new("/path/to/module.js", "export function double(n) { return n * 2; };");
     /// let module = runtime.load_module(&module)?;
-    /// let value: usize = runtime.get_value_immediate(Some(&module), "my_value")?;
+    ///
+    /// let calls: Vec<_> = (0..100)
+    ///     .map(|i| (module.clone(), "double".to_string(), vec![i.into()]))
+    ///     .collect();
+    /// let results = runtime.call_batch::<usize>(&calls);
+    /// assert_eq!(results[10].as_ref().ok(), Some(&20));
     /// # Ok(())
     /// # }
     /// ```
-    pub fn get_value_immediate<T>(
+    pub fn call_batch<T>(
+        &mut self,
+        calls: &[(ModuleHandle, String, Vec<crate::serde_json::Value>)],
+    ) -> Vec<Result<T, Error>>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        let results =
+            self.block_on(|runtime| async move { Ok(runtime.call_batch_async(calls).await) });
+        match results {
+            Ok(results) => results,
+            Err(e) => calls.iter().map(|_| Err(e.clone())).collect(),
+        }
+    }
+
+    /// Calls a javascript function once for each item, and collects the deserialized results
+    ///
+    /// Each item is passed as the function's only argument. The function is looked up once,
+    /// every call is made, then the event loop is run once until every result has resolved - as with [`Runtime::call_batch`]
+    ///
+    /// Returns a future that resolves once every call has resolved
+    ///
+    /// See [`Runtime::map_values`] for an example
+    ///
+    /// # Errors
+    /// Fails if the function cannot be found, or on the first item whose call fails -
+    /// calls for the items after one that throws are not made
+    pub async fn map_values_async<T, U>(
         &mut self,
         module_context: Option<&ModuleHandle>,
         name: &str,
-    ) -> Result<T, Error>
+        items: impl IntoIterator<Item = T>,
+    ) -> Result<Vec<U>, Error>
     where
-        T: serde::de::DeserializeOwned,
+        T: serde::Serialize,
+        U: deno_core::serde::de::DeserializeOwned,
     {
-        let result = self.inner.get_value_ref(module_context, name)?;
-        self.inner.decode_value(result)
+        let results = self
+            .map_values_inner(module_context, name, items, true)
+            .await;
+        self.inner
+            .settle_microtask_budget(results)?
+            .into_iter()
+            .collect()
     }
 
-    /// Executes the given module, and returns a handle allowing you to extract values
-    /// and call functions
+    /// Calls a javascript function once for each item, and collects the deserialized results
     ///
-    /// Blocks until the module has been executed AND the event loop has fully resolved  
-    /// See [`Runtime::load_module_async`] for a non-blocking variant, or use with async
-    /// background tasks
+    /// Each item is passed as the function's only argument. The function is looked up once,
+    /// every call is made, then the event loop is run once until every result has resolved - as with [`Runtime::call_batch`]
+    ///
+    /// Blocks until every call has resolved  
+    /// See [`Runtime::map_values_each`] to collect the result of every item, instead of stopping at the first error
     ///
     /// # Arguments
-    /// * `module` - A `Module` object containing the module's filename and contents.
+    /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
+    /// * `name` - The name of the function to call
+    /// * `items` - The values to pass to the function, one call at a time
     ///
     /// # Returns
-    /// A `Result` containing a handle for the loaded module
-    /// or an error (`Error`) if there are issues with loading or executing the module
+    /// The result of each call, in the same order as `items`
     ///
     /// # Errors
-    /// Can fail if the module cannot be loaded, or execution fails
+    /// Fails if the function cannot be found, or on the first item whose call fails -
+    /// calls for the items after one that throws are not made
     ///
     /// # Example
     ///
     /// ```rust
-    /// // Create a module with filename and contents
-    /// use rustyscript::{Runtime, Module, Error};
+    /// use rustyscript::{ Runtime, Module, Error };
     ///
     /// # fn main() -> Result<(), Error> {
     /// let mut runtime = Runtime::new(Default::default())?;
-    /// let module = Module::new("test.js", "export default () => 'test'");
-    /// runtime.load_module(&module);
+    /// let module = Module::new("transform.js", "export const label = (p) => `${p.name}: ${p.age}`;");
+    /// let module = runtime.load_module(&module)?;
+    ///
+    /// #[derive(serde::Serialize)]
+    /// struct Person { name: &'static str, age: u32 }
+    /// let people = [Person { name: "Ann", age: 31 }, Person { name: "Bob", age: 42 }];
+    ///
+    /// let labels: Vec<String> = runtime.map_values(Some(&module), "label", &people)?;
+    /// assert_eq!(labels, ["Ann: 31", "Bob: 42"]);
     /// # Ok(())
     /// # }
     /// ```
-    pub fn load_module(&mut self, module: &Module) -> Result<ModuleHandle, Error> {
+    pub fn map_values<T, U>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        items: impl IntoIterator<Item = T>,
+    ) -> Result<Vec<U>, Error>
+    where
+        T: serde::Serialize,
+        U: deno_core::serde::de::DeserializeOwned,
+    {
         self.block_on(|runtime| async move {
-            let handle = runtime.load_module_async(module).await;
-            runtime
-                .await_event_loop(PollEventLoopOptions::default(), None)
-                .await?;
-            handle
+            runtime.map_values_async(module_context, name, items).await
         })
     }
 
-    /// Executes the given module, and returns a handle allowing you to extract values
-    /// and call functions
-    ///
-    /// Returns a future that resolves to the handle for the loaded module  
-    /// Makes no attempt to fully resolve the event loop - call [`Runtime::await_event_loop`]
-    /// to resolve background tasks and async listeners
+    /// Calls a javascript function once for each item, and collects the result of every call
     ///
-    /// # Arguments
-    /// * `module` - A `Module` object containing the module's filename and contents.
+    /// Like [`Runtime::map_values_async`], but a failing item does not stop the others - its error is returned in its place
     ///
-    /// # Returns
-    /// A `Result` containing a handle for the loaded module
-    /// or an error (`Error`) if there are issues with loading or executing the module
+    /// Returns a future that resolves once every call has resolved
     ///
     /// # Errors
-    /// Can fail if the module cannot be loaded, or execution fails
-    ///
-    /// See [`Runtime::load_module`] for an example
-    pub async fn load_module_async(&mut self, module: &Module) -> Result<ModuleHandle, Error> {
-        self.inner.load_modules(None, vec![module]).await
+    /// Fails if the function cannot be found
+    pub async fn map_values_each_async<T, U>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        items: impl IntoIterator<Item = T>,
+    ) -> Result<Vec<Result<U, Error>>, Error>
+    where
+        T: serde::Serialize,
+        U: deno_core::serde::de::DeserializeOwned,
+    {
+        let results = self
+            .map_values_inner(module_context, name, items, false)
+            .await;
+        self.inner.settle_microtask_budget(results)
     }
 
-    /// Executes the given module, and returns a handle allowing you to extract values
-    /// and call functions.
+    /// Calls a javascript function once for each item, and collects the result of every call
     ///
-    /// Blocks until all modules have been executed AND the event loop has fully resolved  
-    /// See [`Runtime::load_module_async`] for a non-blocking variant, or use with async
-    /// background tasks
+    /// Like [`Runtime::map_values`], but a failing item does not stop the others - its error is returned in its place
     ///
-    /// This will load 'module' as the main module, and the others as side-modules.  
-    /// Only one main module can be loaded per runtime
+    /// Blocks until every call has resolved
+    ///
+    /// # Errors
+    /// Fails if the function cannot be found
+    pub fn map_values_each<T, U>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        items: impl IntoIterator<Item = T>,
+    ) -> Result<Vec<Result<U, Error>>, Error>
+    where
+        T: serde::Serialize,
+        U: deno_core::serde::de::DeserializeOwned,
+    {
+        self.block_on(|runtime| async move {
+            runtime
+                .map_values_each_async(module_context, name, items)
+                .await
+        })
+    }
+
+    /// Makes one call per item, then resolves them all with a single run of the event loop
+    /// With `stop_on_error`, no further calls are made once one throws
+    async fn map_values_inner<T, U>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        items: impl IntoIterator<Item = T>,
+        stop_on_error: bool,
+    ) -> Result<Vec<Result<U, Error>>, Error>
+    where
+        T: serde::Serialize,
+        U: deno_core::serde::de::DeserializeOwned,
+    {
+        let function = self.inner.get_function_by_name(module_context, name)?;
+
+        let mut calls = vec![];
+        for item in items {
+            let result = self
+                .inner
+                .call_function_by_ref(module_context, &function, &(item,));
+            let failed = result.is_err();
+            calls.push(result);
+            if failed && stop_on_error {
+                break;
+            }
+        }
+
+        let results = self.inner.resolve_all_with_event_loop(calls).await;
+        Ok(results
+            .into_iter()
+            .map(|result| result.and_then(|value| self.inner.decode_value(value)))
+            .collect())
+    }
+
+    /// Resolves a javascript function by name once, returning a [`CallSlot`] that can be called repeatedly  
+    /// Use this instead of [`Runtime::call_function`] in hot loops, to avoid repeating the function lookup on every call,
+    /// and to reuse the storage for decoded arguments between calls
     ///
     /// # Arguments
-    /// * `module` - A `Module` object containing the module's filename and contents.
-    /// * `side_modules` - A set of additional modules to be loaded into memory for use
+    /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
+    /// * `name` - A string representing the name of the javascript function to prepare
     ///
     /// # Returns
-    /// A `Result` containing a handle for the loaded module
-    /// or an error (`Error`) if there are issues with loading or executing the module
+    /// A `Result` containing the prepared call slot, or an error (`Error`) if the function cannot be found
     ///
     /// # Errors
-    /// Can fail if the module cannot be loaded, or execution fails
+    /// Fails if the function cannot be found, or is not callable
     ///
     /// # Example
     ///
     /// ```rust
-    /// // Create a module with filename and contents
-    /// use rustyscript::{Runtime, Module, Error};
+    /// use rustyscript::{ json_args, Runtime, Module, Error };
     ///
     /// # fn main() -> Result<(), Error> {
     /// let mut runtime = Runtime::new(Default::default())?;
-    /// let module = Module::new("test.js", "export default () => 'test'");
-    /// runtime.load_modules(&module, vec![]);
+    /// let module = Module::new("/path/to/module.js", "export function add(a, b) { return a + b; };");
+    /// let module = runtime.load_module(&module)?;
+    ///
+    /// let slot = runtime.prepare_call(Some(&module), "add")?;
+    /// for i in 0..100 {
+    ///     let value: usize = runtime.call_slot(&slot, json_args!(i, 1))?;
+    ///     assert_eq!(i + 1, value);
+    /// }
     /// # Ok(())
     /// # }
     /// ```
-    pub fn load_modules(
-        &mut self,
-        module: &Module,
-        side_modules: Vec<&Module>,
-    ) -> Result<ModuleHandle, Error> {
-        self.block_on(move |runtime| async move {
-            let handle = runtime.load_modules_async(module, side_modules).await;
-            runtime
-                .await_event_loop(PollEventLoopOptions::default(), None)
-                .await?;
-            handle
-        })
-    }
-
-    /// Executes the given module, and returns a handle allowing you to extract values
-    /// and call functions.
-    ///
-    /// Returns a future that resolves to the handle for the loaded module  
-    /// Makes no attempt to resolve the event loop - call [`Runtime::await_event_loop`] to
-    /// resolve background tasks and async listeners
-    ///
-    /// This will load 'module' as the main module, and the others as side-modules.  
-    /// Only one main module can be loaded per runtime
-    ///
-    /// See [`Runtime::load_modules`] for an example
-    ///
-    /// # Arguments
-    /// * `module` - A `Module` object containing the module's filename and contents.
-    /// * `side_modules` - A set of additional modules to be loaded into memory for use
-    ///
-    /// # Returns
-    /// A `Result` containing a handle for the loaded main module, or the last side-module
-    /// or an error (`Error`) if there are issues with loading or executing the modules
-    ///
-    /// # Errors
-    /// Can fail if the modules cannot be loaded, or execution fails
-    pub async fn load_modules_async(
+    pub fn prepare_call(
         &mut self,
-        module: &Module,
-        side_modules: Vec<&Module>,
-    ) -> Result<ModuleHandle, Error> {
-        self.inner.load_modules(Some(module), side_modules).await
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+    ) -> Result<CallSlot, Error> {
+        self.inner.prepare_call(module_context, name)
     }
 
-    /// Executes the entrypoint function of a module within the Deno runtime.
+    /// Calls a function prepared with [`Runtime::prepare_call`] and deserializes its return value.
     ///
     /// Blocks until:
     /// - The event loop is resolved, and
     /// - If the value is a promise, the promise is resolved
     ///
+    /// See [`Runtime::prepare_call`] for an example
+    ///
     /// # Arguments
-    /// * `module_context` - A handle returned by loading a module into the runtime
+    /// * `slot` - A call slot prepared by this runtime
+    /// * `args` - The arguments to pass to the function
     ///
     /// # Returns
-    /// A `Result` containing the deserialized result of the entrypoint execution (`T`)  
-    /// if successful, or an error (`Error`) if the entrypoint is missing, the execution fails,
-    /// or the result cannot be deserialized.
+    /// A `Result` containing the deserialized result of the function call (`T`)  
+    /// or an error (`Error`) if there are issues with calling the function,
+    /// or if the result cannot be deserialized.
     ///
     /// # Errors
-    /// Can fail if the module cannot be loaded, if the entrypoint is missing, if the execution fails,  
+    /// Fails if the slot is not valid for this runtime, if there are issues with calling the function,  
     /// Or if the result cannot be deserialized into the requested type
-    ///
-    /// # Example
-    ///
-    /// ```rust
-    /// use rustyscript::{json_args, Runtime, Module, Error};
-    ///
-    /// # fn main() -> Result<(), Error> {
-    /// let mut runtime = Runtime::new(Default::default())?;
-    /// let module = Module::new("test.js", "export default () => 'test'");
-    /// let module = runtime.load_module(&module)?;
-    ///
-    /// // Run the entrypoint and handle the result
-    /// let value: String = runtime.call_entrypoint(&module, json_args!())?;
-    /// # Ok(())
-    /// # }
-    /// ```
-    pub fn call_entrypoint<T>(
+    pub fn call_slot<T>(
         &mut self,
-        module_context: &ModuleHandle,
+        slot: &CallSlot,
         args: &impl serde::ser::Serialize,
     ) -> Result<T, Error>
     where
         T: deno_core::serde::de::DeserializeOwned,
     {
-        self.block_on(
-            |runtime| async move { runtime.call_entrypoint_async(module_context, args).await },
-        )
+        self.block_on(|runtime| async move { runtime.call_slot_async(slot, args).await })
     }
 
-    /// Executes the entrypoint function of a module within the Deno runtime.
+    /// Calls a function prepared with [`Runtime::prepare_call`] and deserializes its return value.
     ///
     /// Returns a future that resolves when:
     /// - The event loop is resolved, and
     /// - If the value is a promise, the promise is resolved
     ///
-    /// Note that synchronous functions are run synchronously. Returned promises will be run asynchronously, however.
-    ///
-    /// See [`Runtime::call_entrypoint`] for an example
+    /// See [`Runtime::prepare_call`] for an example
     ///
     /// # Arguments
-    /// * `module_context` - A handle returned by loading a module into the runtime
+    /// * `slot` - A call slot prepared by this runtime
+    /// * `args` - The arguments to pass to the function
     ///
     /// # Returns
-    /// A `Result` containing the deserialized result of the entrypoint execution (`T`)  
-    /// if successful, or an error (`Error`) if the entrypoint is missing, the execution fails,
-    /// or the result cannot be deserialized.
+    /// A `Result` containing the deserialized result of the function call (`T`)  
+    /// or an error (`Error`) if there are issues with calling the function,
+    /// or if the result cannot be deserialized.
     ///
     /// # Errors
-    /// Can fail if the module cannot be loaded, if the entrypoint is missing, if the execution fails,  
+    /// Fails if the slot is not valid for this runtime, if there are issues with calling the function,  
     /// Or if the result cannot be deserialized into the requested type
-    pub async fn call_entrypoint_async<T>(
+    pub async fn call_slot_async<T>(
         &mut self,
-        module_context: &ModuleHandle,
+        slot: &CallSlot,
         args: &impl serde::ser::Serialize,
     ) -> Result<T, Error>
     where
         T: deno_core::serde::de::DeserializeOwned,
     {
-        if let Some(entrypoint) = module_context.entrypoint() {
-            let result = self
-                .inner
-                .call_function_by_ref(Some(module_context), entrypoint, args)?;
+        let result = async {
+            let result = self.inner.call_slot(slot, args)?;
             let result = self.inner.resolve_with_event_loop(result).await?;
             self.inner.decode_value(result)
-        } else {
-            Err(Error::MissingEntrypoint(module_context.module().clone()))
         }
+        .await;
+        self.inner.settle_microtask_budget(result)
     }
 
-    /// Executes the entrypoint function of a module within the Deno runtime.
+    /// Calls a function prepared with [`Runtime::prepare_call`] and deserializes its return value.
     ///
     /// Will not attempt to resolve promises, or run the event loop  
     /// Promises can be returned by specifying the return type as [`crate::js_value::Promise`]  
     /// The event loop should be run using [`Runtime::await_event_loop`]
     ///
     /// # Arguments
-    /// * `module_context` - A handle returned by loading a module into the runtime
+    /// * `slot` - A call slot prepared by this runtime
+    /// * `args` - The arguments to pass to the function
     ///
     /// # Returns
-    /// A `Result` containing the deserialized result of the entrypoint execution (`T`)
-    /// if successful, or an error (`Error`) if the entrypoint is missing, the execution fails,
-    /// or the result cannot be deserialized.
+    /// A `Result` containing the deserialized result of the function call (`T`)  
+    /// or an error (`Error`) if there are issues with calling the function,
+    /// or if the result cannot be deserialized.
     ///
     /// # Errors
-    /// Can fail if the module cannot be loaded, if the entrypoint is missing, if the execution fails,
+    /// Fails if the slot is not valid for this runtime, if there are issues with calling the function,  
     /// Or if the result cannot be deserialized into the requested type
+    pub fn call_slot_immediate<T>(
+        &mut self,
+        slot: &CallSlot,
+        args: &impl serde::ser::Serialize,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        let result = self.inner.call_slot(slot, args)?;
+        self.inner.decode_value(result)
+    }
+
+    /// Check if a function can be called by name, without calling it
+    ///
+    /// Uses the same lookup as [`Runtime::call_function`] - the module's exports first, then the global context  
+    /// Useful for optional hooks, which would otherwise need a failed call to be caught
+    ///
+    /// # Arguments
+    /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
+    /// * `name` - A string representing the name of the function to find
+    ///
+    /// # Returns
+    /// True if a value with that name exists, and is a function
     ///
     /// # Example
     ///
     /// ```rust
-    /// use rustyscript::{json_args, Runtime, Module, Error};
+    /// use rustyscript::{ json_args, Runtime, Module, Error, Undefined };
     ///
     /// # fn main() -> Result<(), Error> {
     /// let mut runtime = Runtime::new(Default::default())?;
-    /// let module = Module::new("test.js", "export default () => 'test'");
+    /// let module = Module::new("plugin.js", "export function onLoad() {}");
     /// let module = runtime.load_module(&module)?;
     ///
-    /// // Run the entrypoint and handle the result
-    /// let value: String = runtime.call_entrypoint_immediate(&module, json_args!())?;
+    /// for hook in ["onLoad", "onUnload"] {
+    ///     if runtime.has_function(Some(&module), hook) {
+    ///         runtime.call_function::<Undefined>(Some(&module), hook, json_args!())?;
+    ///     }
+    /// }
     /// # Ok(())
     /// # }
     /// ```
-    pub fn call_entrypoint_immediate<T>(
-        &mut self,
-        module_context: &ModuleHandle,
-        args: &impl serde::ser::Serialize,
-    ) -> Result<T, Error>
-    where
-        T: deno_core::serde::de::DeserializeOwned,
-    {
-        if let Some(entrypoint) = module_context.entrypoint() {
-            let result = self.block_on(|runtime| async move {
-                runtime
-                    .inner
-                    .call_function_by_ref(Some(module_context), entrypoint, args)
-            })?;
-            self.inner.decode_value(result)
-        } else {
-            Err(Error::MissingEntrypoint(module_context.module().clone()))
-        }
+    pub fn has_function(&mut self, module_context: Option<&ModuleHandle>, name: &str) -> bool {
+        self.inner
+            .get_function_by_name(module_context, name)
+            .is_ok()
     }
 
-    /// Loads a module into a new runtime, executes the entry function and returns the
-    /// result of the module's execution, deserialized into the specified Rust type (`T`).
+    /// Check if a module exports the given name, without reading or calling the export
+    ///
+    /// Unlike [`Runtime::get_value`], the global context is not searched,
+    /// and an export whose value is `undefined` still counts
     ///
     /// # Arguments
-    /// * `module` - A `Module` object containing the module's filename and contents.
-    /// * `side_modules` - A set of additional modules to be loaded into memory for use
-    /// * `runtime_options` - Options for the creation of the runtime
-    /// * `entrypoint_args` - Arguments to pass to the entrypoint function
+    /// * `module_context` - A handle to the module to search
+    /// * `name` - The name of the export
     ///
     /// # Returns
-    /// A `Result` containing the deserialized result of the entrypoint execution (`T`)  
-    /// if successful, or an error (`Error`) if the entrypoint is missing, the execution fails,
-    /// or the result cannot be deserialized.
-    ///
-    /// # Errors
-    /// Can fail if the module cannot be loaded, if the entrypoint is missing, if the execution fails,  
-    /// Or if the result cannot be deserialized into the requested type
+    /// True if the module's namespace contains the export
     ///
     /// # Example
     ///
     /// ```rust
-    /// // Create a module with filename and contents
-    /// use rustyscript::{json_args, Runtime, Module, Error};
+    /// use rustyscript::{ Runtime, Module, Error };
     ///
     /// # fn main() -> Result<(), Error> {
-    /// let module = Module::new("test.js", "export default () => 2");
-    /// let value: usize = Runtime::execute_module(&module, vec![], Default::default(), json_args!())?;
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export const version = 2;");
+    /// let module = runtime.load_module(&module)?;
+    ///
+    /// assert!(runtime.has_export(&module, "version"));
+    /// assert!(!runtime.has_export(&module, "missing"));
     /// # Ok(())
     /// # }
     /// ```
-    pub fn execute_module<T>(
-        module: &Module,
-        side_modules: Vec<&Module>,
-        runtime_options: RuntimeOptions,
-        entrypoint_args: &impl serde::ser::Serialize,
+    pub fn has_export(&mut self, module_context: &ModuleHandle, name: &str) -> bool {
+        self.inner.has_export(module_context, name)
+    }
+
+    /// Get a value from a runtime instance
+    ///
+    /// Blocks until:
+    /// - The event loop is resolved, and
+    /// - If the value is a promise, the promise is resolved
+    ///
+    /// # Arguments
+    /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
+    /// * `name` - A string representing the name of the value to find
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result or an error (`Error`) if the value cannot be found,
+    /// Or if the result cannot be deserialized into the requested type
+    ///
+    /// # Errors
+    /// Can fail if the value cannot be found, or if the result cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("/path/to/module.js", "globalThis.my_value = 2;");
+    /// let module = runtime.load_module(&module)?;
+    /// let value: usize = runtime.get_value(Some(&module), "my_value")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_value<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
     ) -> Result<T, Error>
     where
-        T: deno_core::serde::de::DeserializeOwned,
+        T: serde::de::DeserializeOwned,
     {
-        let mut runtime = Runtime::new(runtime_options)?;
-        let module = runtime.load_modules(module, side_modules)?;
-        let value: T = runtime.call_entrypoint(&module, entrypoint_args)?;
-        Ok(value)
+        self.block_on(|runtime| async move { runtime.get_value_async(module_context, name).await })
+    }
+
+    /// Get a value from a runtime instance
+    ///
+    /// Returns a future that resolves when:
+    /// - The event loop is resolved, and
+    /// - If the value is a promise, the promise is resolved
+    ///
+    /// See [`Runtime::get_value`] for an example
+    ///
+    /// # Arguments
+    /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
+    /// * `name` - A string representing the name of the value to find
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result or an error (`Error`) if the value cannot be found,  
+    /// Or if the result cannot be deserialized into the requested type
+    ///
+    /// # Errors
+    /// Can fail if the value cannot be found, or if the result cannot be deserialized.
+    pub async fn get_value_async<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let result = async {
+            let result = self.inner.get_value_ref(module_context, name)?;
+            let result = self.inner.resolve_with_event_loop(result).await?;
+            self.inner.decode_value(result)
+        }
+        .await;
+        self.inner.settle_microtask_budget(result)
+    }
+
+    /// Get a value from a runtime instance
+    ///
+    /// Will not attempt to resolve promises, or run the event loop  
+    /// Promises can be returned by specifying the return type as [`crate::js_value::Promise`]  
+    /// The event loop should be run using [`Runtime::await_event_loop`]
+    ///
+    /// # Arguments
+    /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
+    /// * `name` - A string representing the name of the value to find
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result or an error (`Error`) if the value cannot be found,
+    /// Or if the result cannot be deserialized into the requested type
+    ///
+    /// # Errors
+    /// Can fail if the value cannot be found, or if the result cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("/path/to/module.js", "globalThis.my_value = 2;");
+    /// let module = runtime.load_module(&module)?;
+    /// let value: usize = runtime.get_value_immediate(Some(&module), "my_value")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_value_immediate<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let result = self.inner.get_value_ref(module_context, name)?;
+        self.inner.decode_value(result)
+    }
+
+    /// Executes the given module, and returns a handle allowing you to extract values
+    /// and call functions
+    ///
+    /// Blocks until the module has been executed AND the event loop has fully resolved  
+    /// See [`Runtime::load_module_async`] for a non-blocking variant, or use with async
+    /// background tasks
+    ///
+    /// Top-level `await` is driven to completion, within the runtime's timeout, before the handle is returned
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object containing the module's filename and contents.
+    ///
+    /// # Returns
+    /// A `Result` containing a handle for the loaded module
+    /// or an error (`Error`) if there are issues with loading or executing the module
+    ///
+    /// # Errors
+    /// Can fail if the module cannot be loaded, or execution fails - including a rejected top-level `await`
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Create a module with filename and contents
+    /// use rustyscript::{Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export default () => 'test'");
+    /// runtime.load_module(&module);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_module(&mut self, module: &Module) -> Result<ModuleHandle, Error> {
+        self.block_on(|runtime| async move {
+            let handle = runtime.load_module_async(module).await;
+            runtime
+                .await_event_loop(PollEventLoopOptions::default(), None)
+                .await?;
+            handle
+        })
+    }
+
+    /// Executes the given module, and returns a handle to it along with the value its evaluation completed with
+    ///
+    /// This is mainly useful for script-mode modules - those without any `import` or `export` statements -
+    /// which are evaluated as a classic script, completing with the value of their last expression.  
+    /// The script runs in strict mode, so its top-level declarations stay local to it instead of becoming globals.  
+    /// ECMAScript modules always complete with `undefined`, so use exports to get values out of them
+    ///
+    /// A completion value that is a promise is resolved first, as with [`Runtime::eval`]  
+    /// Blocks until the module has been executed AND the event loop has fully resolved
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object containing the module's filename and contents.
+    ///
+    /// # Returns
+    /// A `Result` containing a handle for the loaded module, and its completion value
+    ///
+    /// # Errors
+    /// Can fail if the module cannot be loaded, if execution fails, or if the completion value cannot be deserialized
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("compute.js", "const base = 40; base + 2;");
+    /// let (_handle, value): (_, i32) = runtime.load_module_with_completion(&module)?;
+    /// assert_eq!(value, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_module_with_completion<T>(
+        &mut self,
+        module: &Module,
+    ) -> Result<(ModuleHandle, T), Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.block_on(|runtime| async move {
+            let (handle, completion) = runtime.inner.load_module_with_completion(module).await?;
+            runtime
+                .await_event_loop(PollEventLoopOptions::default(), None)
+                .await?;
+            let completion = runtime.inner.resolve_with_event_loop(completion).await?;
+            Ok((handle, runtime.inner.decode_value(completion)?))
+        })
+    }
+
+    /// Executes the given module, and returns a handle allowing you to extract values
+    /// and call functions
+    ///
+    /// Returns a future that resolves to the handle for the loaded module  
+    /// Makes no attempt to fully resolve the event loop - call [`Runtime::await_event_loop`]
+    /// to resolve background tasks and async listeners
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object containing the module's filename and contents.
+    ///
+    /// # Returns
+    /// A `Result` containing a handle for the loaded module
+    /// or an error (`Error`) if there are issues with loading or executing the module
+    ///
+    /// # Errors
+    /// Can fail if the module cannot be loaded, or execution fails
+    ///
+    /// See [`Runtime::load_module`] for an example
+    pub async fn load_module_async(&mut self, module: &Module) -> Result<ModuleHandle, Error> {
+        let result = self.inner.load_modules(None, vec![module]).await;
+        self.inner.settle_microtask_budget(result)
+    }
+
+    /// Executes a module transpiled ahead of time with [`Module::compile`], skipping transpilation
+    ///
+    /// Blocks until the module has been executed AND the event loop has fully resolved  
+    /// Errors still point at the original source, using the source map captured by [`Module::compile`]
+    ///
+    /// # Arguments
+    /// * `compiled` - The compiled module to load
+    ///
+    /// # Returns
+    /// A `Result` containing a handle for the loaded module
+    /// or an error (`Error`) if there are issues with loading or executing the module
+    ///
+    /// # Errors
+    /// Can fail if the module cannot be loaded, or execution fails
+    ///
+    /// See [`Module::compile`] for an example
+    pub fn load_compiled(
+        &mut self,
+        compiled: &crate::CompiledModule,
+    ) -> Result<ModuleHandle, Error> {
+        self.block_on(|runtime| async move {
+            let handle = runtime.inner.load_compiled(compiled).await;
+            runtime
+                .await_event_loop(PollEventLoopOptions::default(), None)
+                .await?;
+            handle
+        })
+    }
+
+    /// Executes the given module, and returns a handle allowing you to extract values
+    /// and call functions.
+    ///
+    /// Blocks until all modules have been executed AND the event loop has fully resolved  
+    /// See [`Runtime::load_module_async`] for a non-blocking variant, or use with async
+    /// background tasks
+    ///
+    /// This will load 'module' as the main module, and the others as side-modules.  
+    /// Only one main module can be loaded per runtime
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object containing the module's filename and contents.
+    /// * `side_modules` - A set of additional modules to be loaded into memory for use
+    ///
+    /// # Returns
+    /// A `Result` containing a handle for the loaded module
+    /// or an error (`Error`) if there are issues with loading or executing the module
+    ///
+    /// # Errors
+    /// Can fail if the module cannot be loaded, or execution fails
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Create a module with filename and contents
+    /// use rustyscript::{Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export default () => 'test'");
+    /// runtime.load_modules(&module, vec![]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_modules(
+        &mut self,
+        module: &Module,
+        side_modules: Vec<&Module>,
+    ) -> Result<ModuleHandle, Error> {
+        self.block_on(move |runtime| async move {
+            let handle = runtime.load_modules_async(module, side_modules).await;
+            runtime
+                .await_event_loop(PollEventLoopOptions::default(), None)
+                .await?;
+            handle
+        })
+    }
+
+    /// Executes the given module, and returns a handle allowing you to extract values
+    /// and call functions.
+    ///
+    /// Returns a future that resolves to the handle for the loaded module  
+    /// Makes no attempt to resolve the event loop - call [`Runtime::await_event_loop`] to
+    /// resolve background tasks and async listeners
+    ///
+    /// This will load 'module' as the main module, and the others as side-modules.  
+    /// Only one main module can be loaded per runtime
+    ///
+    /// See [`Runtime::load_modules`] for an example
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object containing the module's filename and contents.
+    /// * `side_modules` - A set of additional modules to be loaded into memory for use
+    ///
+    /// # Returns
+    /// A `Result` containing a handle for the loaded main module, or the last side-module
+    /// or an error (`Error`) if there are issues with loading or executing the modules
+    ///
+    /// # Errors
+    /// Can fail if the modules cannot be loaded, or execution fails
+    pub async fn load_modules_async(
+        &mut self,
+        module: &Module,
+        side_modules: Vec<&Module>,
+    ) -> Result<ModuleHandle, Error> {
+        let result = self.inner.load_modules(Some(module), side_modules).await;
+        self.inner.settle_microtask_budget(result)
+    }
+
+    /// Loads a set of modules - such as the result of [`Module::load_dir`] - executing one of them as the main module,
+    /// and loading the others as side-modules available for import
+    ///
+    /// Blocks until all modules have been executed AND the event loop has fully resolved  
+    /// See [`Runtime::load_module_set_async`] for a non-blocking variant
+    ///
+    /// The main module is chosen as follows:
+    /// - If `entrypoint` is given, the module whose filename is, or ends with, that path
+    /// - Otherwise, the only module in the set, or the only module calling `rustyscript.register_entrypoint`
+    ///
+    /// Detecting `register_entrypoint` is a simple search of the module's source, so calls made
+    /// indirectly (such as from an imported module) are not seen - use `entrypoint` for those cases
+    ///
+    /// # Arguments
+    /// * `modules` - The set of modules to load
+    /// * `entrypoint` - The filename of the main module, if it should not be detected automatically
+    ///
+    /// # Returns
+    /// A `Result` containing a handle for the main module
+    ///
+    /// # Errors
+    /// Will return [`Error::ModuleNotFound`] if no module matches `entrypoint`, and [`Error::AmbiguousEntrypoint`]
+    /// (listing the candidates) if the main module cannot be determined.  
+    /// Can also fail if the modules cannot be loaded, or execution fails
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{Runtime, Module, json_args};
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let modules = vec![
+    ///     Module::new("lib.js", "export const value = 2;"),
+    ///     Module::new("main.js", "
+    ///         import { value } from './lib.js';
+    ///         rustyscript.register_entrypoint(() => value);
+    ///     "),
+    /// ];
+    ///
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let handle = runtime.load_module_set(&modules, None)?;
+    /// let value: usize = runtime.call_entrypoint(&handle, json_args!())?;
+    /// assert_eq!(value, 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_module_set(
+        &mut self,
+        modules: &[Module],
+        entrypoint: Option<&str>,
+    ) -> Result<ModuleHandle, Error> {
+        self.block_on(move |runtime| async move {
+            let handle = runtime.load_module_set_async(modules, entrypoint).await;
+            runtime
+                .await_event_loop(PollEventLoopOptions::default(), None)
+                .await?;
+            handle
+        })
+    }
+
+    /// Loads a set of modules, executing one of them as the main module,
+    /// and loading the others as side-modules available for import
+    ///
+    /// Returns a future that resolves to the handle for the main module  
+    /// Makes no attempt to resolve the event loop - call [`Runtime::await_event_loop`] to
+    /// resolve background tasks and async listeners
+    ///
+    /// See [`Runtime::load_module_set`] for details on how the main module is chosen, and an example
+    ///
+    /// # Arguments
+    /// * `modules` - The set of modules to load
+    /// * `entrypoint` - The filename of the main module, if it should not be detected automatically
+    ///
+    /// # Returns
+    /// A `Result` containing a handle for the main module
+    ///
+    /// # Errors
+    /// Will return an error if the main module cannot be determined, the modules cannot be loaded, or execution fails
+    pub async fn load_module_set_async(
+        &mut self,
+        modules: &[Module],
+        entrypoint: Option<&str>,
+    ) -> Result<ModuleHandle, Error> {
+        let main = find_entrypoint(modules, entrypoint)?;
+        let side_modules = modules
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != main)
+            .map(|(_, m)| m)
+            .collect();
+        self.load_modules_async(&modules[main], side_modules).await
+    }
+
+    /// Returns a handle to a module that is already loaded in the runtime - such as one included in
+    /// the startup snapshot by [`crate::SnapshotBuilder::with_module`]
+    ///
+    /// Modules that are already loaded are not executed again.  
+    /// A module that is not yet loaded is not imported - [`Error::ModuleNotFound`] is returned instead
+    ///
+    /// Module source is not kept in the snapshot, so the returned handle's [`Module`] has empty contents
+    ///
+    /// Blocks until the module has been executed AND the event loop has fully resolved
+    ///
+    /// # Arguments
+    /// * `filename` - The filename the module was loaded with
+    ///
+    /// # Errors
+    /// Fails with [`Error::ModuleNotFound`] if the module is not loaded
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ json_args, Runtime, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # #[cfg(feature = "snapshot_builder")] {
+    /// # use rustyscript::{ Module, RuntimeOptions, SnapshotBuilder };
+    /// # let module = Module::new("my_module.js", "export function f() { return 42; }");
+    /// # let snapshot = SnapshotBuilder::new(Default::default())?.with_module(&module)?.finish();
+    /// # let snapshot: &'static [u8] = Box::leak(snapshot);
+    /// let mut runtime = Runtime::new(RuntimeOptions {
+    ///     startup_snapshot: Some(snapshot),
+    ///     ..Default::default()
+    /// })?;
+    ///
+    /// let module = runtime.get_module_handle("my_module.js")?;
+    /// let value: usize = runtime.call_function(Some(&module), "f", json_args!())?;
+    /// assert_eq!(value, 42);
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_module_handle(&mut self, filename: impl AsRef<Path>) -> Result<ModuleHandle, Error> {
+        let filename = filename.as_ref();
+        self.block_on(|runtime| async move {
+            let handle = runtime.get_module_handle_async(filename).await;
+            runtime
+                .await_event_loop(PollEventLoopOptions::default(), None)
+                .await?;
+            handle
+        })
+    }
+
+    /// Returns a handle to a module that is already loaded in the runtime - such as one included in
+    /// the startup snapshot by [`crate::SnapshotBuilder::with_module`]
+    ///
+    /// Returns a future that resolves to the handle for the module
+    /// Makes no attempt to fully resolve the event loop - call [`Runtime::await_event_loop`]
+    /// to resolve background tasks and async listeners
+    ///
+    /// See [`Runtime::get_module_handle`] for an example
+    ///
+    /// # Arguments
+    /// * `filename` - The filename the module was loaded with
+    ///
+    /// # Errors
+    /// Fails with [`Error::ModuleNotFound`] if the module is not loaded
+    pub async fn get_module_handle_async(
+        &mut self,
+        filename: impl AsRef<Path>,
+    ) -> Result<ModuleHandle, Error> {
+        let result = self.inner.get_module_handle(filename.as_ref()).await;
+        self.inner.settle_microtask_budget(result)
+    }
+
+    /// Executes the entrypoint function of a module within the Deno runtime.
+    ///
+    /// Blocks until:
+    /// - The event loop is resolved, and
+    /// - If the value is a promise, the promise is resolved
+    ///
+    /// Async entrypoints are driven to completion under the runtime's timeout,
+    /// and a rejected promise is returned as [`Error::JsError`]
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by loading a module into the runtime
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the entrypoint execution (`T`)  
+    /// if successful, or an error (`Error`) if the entrypoint is missing, the execution fails,
+    /// or the result cannot be deserialized.
+    ///
+    /// # Errors
+    /// Can fail if the module cannot be loaded, if the entrypoint is missing, if the execution fails,  
+    /// Or if the result cannot be deserialized into the requested type
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{json_args, Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export default () => 'test'");
+    /// let module = runtime.load_module(&module)?;
+    ///
+    /// // Run the entrypoint and handle the result
+    /// let value: String = runtime.call_entrypoint(&module, json_args!())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_entrypoint<T>(
+        &mut self,
+        module_context: &ModuleHandle,
+        args: &impl serde::ser::Serialize,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        self.with_auto_recover(Some(module_context), |runtime, recovered| {
+            let module_context = recovered.unwrap_or(module_context);
+            runtime.block_on(|runtime| async move {
+                runtime.call_entrypoint_async(module_context, args).await
+            })
+        })
+    }
+
+    /// Executes the entrypoint function of a module within the Deno runtime.
+    ///
+    /// Returns a future that resolves when:
+    /// - The event loop is resolved, and
+    /// - If the value is a promise, the promise is resolved
+    ///
+    /// Note that synchronous functions are run synchronously. Returned promises will be run asynchronously, however.
+    ///
+    /// See [`Runtime::call_entrypoint`] for an example
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by loading a module into the runtime
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the entrypoint execution (`T`)  
+    /// if successful, or an error (`Error`) if the entrypoint is missing, the execution fails,
+    /// or the result cannot be deserialized.
+    ///
+    /// # Errors
+    /// Can fail if the module cannot be loaded, if the entrypoint is missing, if the execution fails,  
+    /// Or if the result cannot be deserialized into the requested type
+    pub async fn call_entrypoint_async<T>(
+        &mut self,
+        module_context: &ModuleHandle,
+        args: &impl serde::ser::Serialize,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        let Some(entrypoint) = module_context.entrypoint() else {
+            return Err(Error::MissingEntrypoint(module_context.module().clone()));
+        };
+
+        let result = async {
+            let result = self
+                .inner
+                .call_function_by_ref(Some(module_context), entrypoint, args)?;
+            let result = self.inner.resolve_with_event_loop(result).await?;
+            self.inner.decode_value(result)
+        }
+        .await;
+        self.inner.settle_microtask_budget(result)
+    }
+
+    /// Executes the entrypoint function of a module within the Deno runtime.
+    ///
+    /// Will not attempt to resolve promises, or run the event loop  
+    /// Promises can be returned by specifying the return type as [`crate::js_value::Promise`]  
+    /// The event loop should be run using [`Runtime::await_event_loop`]
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by loading a module into the runtime
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the entrypoint execution (`T`)
+    /// if successful, or an error (`Error`) if the entrypoint is missing, the execution fails,
+    /// or the result cannot be deserialized.
+    ///
+    /// # Errors
+    /// Can fail if the module cannot be loaded, if the entrypoint is missing, if the execution fails,
+    /// Or if the result cannot be deserialized into the requested type
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{json_args, Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export default () => 'test'");
+    /// let module = runtime.load_module(&module)?;
+    ///
+    /// // Run the entrypoint and handle the result
+    /// let value: String = runtime.call_entrypoint_immediate(&module, json_args!())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_entrypoint_immediate<T>(
+        &mut self,
+        module_context: &ModuleHandle,
+        args: &impl serde::ser::Serialize,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        if let Some(entrypoint) = module_context.entrypoint() {
+            let result = self.block_on(|runtime| async move {
+                runtime
+                    .inner
+                    .call_function_by_ref(Some(module_context), entrypoint, args)
+            })?;
+            self.inner.decode_value(result)
+        } else {
+            Err(Error::MissingEntrypoint(module_context.module().clone()))
+        }
+    }
+
+    /// Loads a module into a new runtime, executes the entry function and returns the
+    /// result of the module's execution, deserialized into the specified Rust type (`T`).
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object containing the module's filename and contents.
+    /// * `side_modules` - A set of additional modules to be loaded into memory for use
+    /// * `runtime_options` - Options for the creation of the runtime
+    /// * `entrypoint_args` - Arguments to pass to the entrypoint function
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the entrypoint execution (`T`)  
+    /// if successful, or an error (`Error`) if the entrypoint is missing, the execution fails,
+    /// or the result cannot be deserialized.
+    ///
+    /// # Errors
+    /// Can fail if the module cannot be loaded, if the entrypoint is missing, if the execution fails,  
+    /// Or if the result cannot be deserialized into the requested type
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Create a module with filename and contents
+    /// use rustyscript::{json_args, Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let module = Module::new("test.js", "export default () => 2");
+    /// let value: usize = Runtime::execute_module(&module, vec![], Default::default(), json_args!())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn execute_module<T>(
+        module: &Module,
+        side_modules: Vec<&Module>,
+        runtime_options: RuntimeOptions,
+        entrypoint_args: &impl serde::ser::Serialize,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        let mut runtime = Runtime::new(runtime_options)?;
+        let module = runtime.load_modules(module, side_modules)?;
+        let value: T = runtime.call_entrypoint(&module, entrypoint_args)?;
+        Ok(value)
+    }
+
+    /// Like [`Runtime::execute_module`], but always calls the module's `teardown` export (if present)
+    /// after the entrypoint, before returning the result
+    ///
+    /// Teardown runs whether the entrypoint succeeded or not, so scripts can release anything they acquired.  
+    /// It is called with no arguments, and any value it returns is ignored
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object containing the module's filename and contents.
+    /// * `side_modules` - A set of additional modules to be loaded into memory for use
+    /// * `runtime_options` - Options for the creation of the runtime
+    /// * `entrypoint_args` - Arguments to pass to the entrypoint function
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the entrypoint execution (`T`)
+    ///
+    /// # Errors
+    /// Fails for the same reasons as [`Runtime::execute_module`], or if teardown fails.  
+    /// If both the entrypoint and teardown fail, returns [`Error::TeardownFailed`] containing both errors
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{json_args, Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let module = Module::new("test.js", "
+    ///     let handle = null;
+    ///     export default () => { handle = 'open'; return 2; };
+    ///     export function teardown() { handle = null; }
+    /// ");
+    /// let value: usize = Runtime::execute_module_with_teardown(&module, vec![], Default::default(), json_args!())?;
+    /// assert_eq!(value, 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn execute_module_with_teardown<T>(
+        module: &Module,
+        side_modules: Vec<&Module>,
+        runtime_options: RuntimeOptions,
+        entrypoint_args: &impl serde::ser::Serialize,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        let mut runtime = Runtime::new(runtime_options)?;
+        let module = runtime.load_modules(module, side_modules)?;
+        let result: Result<T, Error> = runtime.call_entrypoint(&module, entrypoint_args);
+
+        let teardown = if runtime.has_export(&module, "teardown") {
+            runtime
+                .call_function::<Undefined>(Some(&module), "teardown", &())
+                .map(|_| ())
+        } else {
+            Ok(())
+        };
+
+        match (result, teardown) {
+            (Ok(value), Ok(())) => Ok(value),
+            (Ok(_), Err(e)) | (Err(e), Ok(())) => Err(e),
+            (Err(e), Err(teardown_e)) => {
+                Err(Error::TeardownFailed(Box::new(e), Box::new(teardown_e)))
+            }
+        }
+    }
+}
+
+impl Runtime {
+    /// Runs a blocking call against `module_context`, or the module that replaced it after a recovery
+    ///
+    /// If the call failed because the isolate was terminated and `auto_recover` is enabled,
+    /// the module is reloaded and the call retried once against the new instance  
+    /// Calls interrupted by a [`CancellationHandle`] are not retried, and return [`Error::Cancelled`],
+    /// and neither are calls stopped by one of the runtime's limits
+    fn with_auto_recover<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        mut f: impl FnMut(&mut Self, Option<&ModuleHandle>) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        // Discard cancellations made while nothing was running
+        self.inner.take_cancellation();
+
+        let context = module_context.map(|m| self.inner.recovered_module(m).clone());
+        let e = match f(self, context.as_ref()) {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+
+        if self.inner.take_cancellation() {
+            return Err(Error::Cancelled);
+        }
+
+        // Limits terminate the isolate on purpose - the call would only be stopped again
+        if is_limit_error(&e) {
+            self.inner.recover_isolate();
+            return Err(e);
+        }
+
+        if !self.inner.auto_recover || !self.inner.recover_isolate() {
+            return Err(e);
+        }
+
+        let context =
+            match module_context {
+                Some(original) => Some(self.block_on(|runtime| async move {
+                    runtime.inner.reload_module(original).await
+                })?),
+                None => None,
+            };
+
+        let result = f(self, context.as_ref());
+        if result.is_err() && self.inner.take_cancellation() {
+            return Err(Error::Cancelled);
+        }
+        result
+    }
+}
+
+/// True for the errors of a call that was stopped on purpose by one of the runtime's limits
+fn is_limit_error(e: &Error) -> bool {
+    matches!(
+        e,
+        Error::Timeout(_)
+            | Error::CpuTimeExceeded(_)
+            | Error::MicrotaskLimitExceeded(_)
+            | Error::HeapExhausted
+            | Error::Cancelled
+    )
+}
+
+impl AsyncBridgeExt for Runtime {
+    fn bridge(&self) -> &AsyncBridge {
+        &self.tokio
+    }
+}
+
+/// Finds the index of the main module in a set - see [`Runtime::load_module_set`]
+fn find_entrypoint(modules: &[Module], entrypoint: Option<&str>) -> Result<usize, Error> {
+    let candidates: Vec<usize> = if let Some(entrypoint) = entrypoint {
+        let candidates: Vec<usize> = (0..modules.len())
+            .filter(|i| modules[*i].filename().ends_with(entrypoint))
+            .collect();
+        if candidates.is_empty() {
+            return Err(Error::ModuleNotFound(entrypoint.to_string()));
+        }
+        candidates
+    } else if modules.len() == 1 {
+        vec![0]
+    } else {
+        let candidates: Vec<usize> = (0..modules.len())
+            .filter(|i| modules[*i].contents().contains("register_entrypoint"))
+            .collect();
+        if candidates.is_empty() {
+            (0..modules.len()).collect()
+        } else {
+            candidates
+        }
+    };
+
+    match candidates.as_slice() {
+        [main] => Ok(*main),
+        _ => {
+            let list = candidates
+                .iter()
+                .map(|i| modules[*i].filename().display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            Err(Error::AmbiguousEntrypoint(list))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_runtime {
+    use crate::json_args;
+    use std::time::Duration;
+
+    use super::*;
+    use deno_core::{extension, op2, serde_json};
+
+    #[op2(fast)]
+    fn op_test_add(a: u32, b: u32) -> u32 {
+        a + b
+    }
+
+    #[op2]
+    #[string]
+    fn op_test_greet(#[string] name: String) -> String {
+        format!("Hello, {name}")
+    }
+
+    #[test]
+    fn test_new() {
+        Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+
+        extension!(test_extension);
+        Runtime::new(RuntimeOptions {
+            extensions: vec![test_extension::init_ops_and_esm()],
+            ..Default::default()
+        })
+        .expect("Could not create runtime with extensions");
+    }
+
+    #[test]
+    fn test_ops() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            ops: vec![op_test_add(), op_test_greet()],
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        let value: u32 = runtime.eval("Deno.core.ops.op_test_add(2, 3)").unwrap();
+        assert_eq!(5, value);
+
+        let value: String = runtime
+            .eval("Deno.core.ops.op_test_greet('rustyscript')")
+            .unwrap();
+        assert_eq!("Hello, rustyscript", value);
+    }
+
+    #[test]
+    fn test_get_value() {
+        let module = Module::new(
+            "test.js",
+            "
+            globalThis.a = 2;
+            export const b = 'test';
+            export const fnc = null;
+        ",
+        );
+
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+
+        assert_eq!(
+            2,
+            runtime
+                .get_value::<usize>(Some(&module), "a")
+                .expect("Could not find global")
+        );
+        assert_eq!(
+            "test",
+            runtime
+                .get_value::<String>(Some(&module), "b")
+                .expect("Could not find export")
+        );
+        runtime
+            .get_value::<Undefined>(Some(&module), "c")
+            .expect_err("Could not detect null");
+        runtime
+            .get_value::<Undefined>(Some(&module), "d")
+            .expect_err("Could not detect undeclared");
+    }
+
+    #[test]
+    fn test_load_module() {
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "
+            export default () => 2;
+        ",
+        );
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+        assert_ne!(0, module.id());
+
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let module1 = Module::new(
+            "importme.js",
+            "
+            export const value = 2;
+        ",
+        );
+        let module2 = Module::new(
+            "test.js",
+            "
+            import { value } from './importme.js';
+            rustyscript.register_entrypoint(() => value);
+        ",
+        );
+        runtime
+            .load_module(&module1)
+            .expect("Could not load modules");
+        let module = runtime
+            .load_module(&module2)
+            .expect("Could not load modules");
+        let value: usize = runtime
+            .call_entrypoint(&module, json_args!())
+            .expect("Could not call exported fn");
+        assert_eq!(2, value);
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            timeout: Duration::from_millis(50),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "
+            await new Promise(r => setTimeout(r, 2000));
+        ",
+        );
+        runtime
+            .load_modules(&module, vec![])
+            .expect_err("Did not interupt after timeout");
+    }
+
+    #[test]
+    fn test_load_module_toplevel_await() {
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+
+        // The handle is only returned once the top-level await has settled
+        let module = Module::new(
+            "config.js",
+            "
+            const sleep = (ms) => new Promise((r) => setTimeout(r, ms));
+            export let value = 0;
+            await sleep(50);
+            value = 2;
+        ",
+        );
+        let handle = runtime.load_module(&module).expect("Could not load module");
+        let value: usize = runtime.get_value(Some(&handle), "value").unwrap();
+        assert_eq!(2, value);
+
+        // Importers see the settled value too
+        let module = Module::new(
+            "test.js",
+            "
+            import { value } from './config.js';
+            export const doubled = value * 2;
+        ",
+        );
+        let handle = runtime.load_module(&module).expect("Could not load module");
+        let value: usize = runtime.get_value(Some(&handle), "doubled").unwrap();
+        assert_eq!(4, value);
+
+        // Rejections are returned as errors
+        let module = Module::new(
+            "reject.js",
+            "await Promise.reject(new Error('failed to start'));",
+        );
+        let e = runtime.load_module(&module).unwrap_err();
+        assert!(e.to_string().contains("failed to start"), "{e}");
+    }
+
+    #[test]
+    fn test_load_modules() {
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "
+            rustyscript.register_entrypoint(() => 2);
+        ",
+        );
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+        assert_ne!(0, module.id());
+
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let module1 = Module::new(
+            "importme.js",
+            "
+            export const value = 2;
+        ",
+        );
+        let module2 = Module::new(
+            "test.js",
+            "
+            import { value } from './importme.js';
+            rustyscript.register_entrypoint(() => value);
+        ",
+        );
+        let module = runtime
+            .load_modules(&module2, vec![&module1])
+            .expect("Could not load modules");
+        let value: usize = runtime
+            .call_entrypoint(&module, json_args!())
+            .expect("Could not call exported fn");
+        assert_eq!(2, value);
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            timeout: Duration::from_millis(50),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "
+            await new Promise(r => setTimeout(r, 5000));
+        ",
+        );
+        runtime
+            .load_modules(&module, vec![])
+            .expect_err("Did not interupt after timeout");
+    }
+
+    #[test]
+    fn test_case_insensitive_module_paths() {
+        let lib = Module::new(
+            "lib/Counter.js",
+            "export let count = 0; export const increment = () => ++count;",
+        );
+        let main = Module::new(
+            "main.js",
+            "
+            import { increment } from './lib/counter.js';
+            import { count } from './LIB/COUNTER.js';
+            increment();
+            export const value = count;
+        ",
+        );
+
+        // Every spelling of the path imports the same instance
+        let mut runtime = Runtime::new(RuntimeOptions {
+            case_insensitive_module_paths: true,
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let handle = runtime
+            .load_modules(&main, vec![&lib])
+            .expect("Could not load modules");
+        let value: usize = runtime
+            .get_value(Some(&handle), "value")
+            .expect("Could not get value");
+        assert_eq!(1, value);
+
+        // The casing the module was loaded with is kept
+        let handle = runtime
+            .get_module_handle("lib/COUNTER.js")
+            .expect("Could not get module handle");
+        assert!(handle.specifier().ends_with("/lib/Counter.js"));
+        let count: usize = runtime
+            .get_value(Some(&handle), "count")
+            .expect("Could not get value");
+        assert_eq!(1, count);
+
+        // Paths are case-sensitive by default, so the import is not found
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        runtime
+            .load_modules(&main, vec![&lib])
+            .expect_err("Imported a module with the wrong casing");
+    }
+
+    #[test]
+    fn test_module_handle_details() {
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create runtime");
+        let lib = Module::new("lib/util.js", "export const value = 2;");
+        let main = Module::new(
+            "main.js",
+            "
+            import { value } from './lib/util.js';
+            rustyscript.register_entrypoint(function start() { return value; });
+        ",
+        );
+        let handle = runtime
+            .load_modules(&main, vec![&lib])
+            .expect("Could not load modules");
+
+        assert!(handle.specifier().starts_with("file:///"));
+        assert!(handle.specifier().ends_with("/main.js"));
+        assert_eq!(Some("start"), handle.entrypoint_name());
+
+        // The specifier can be used to import the module
+        let lib_handle = runtime
+            .load_module(&Module::new("lib/other.js", "export default () => 1;"))
+            .expect("Could not load module");
+        assert!(lib_handle.specifier().ends_with("/lib/other.js"));
+        assert_eq!(Some("default"), lib_handle.entrypoint_name());
+
+        let importer = Module::new(
+            "importer.js",
+            &format!(
+                "export {{ default as f }} from '{}';",
+                lib_handle.specifier()
+            ),
+        );
+        let importer = runtime
+            .load_module(&importer)
+            .expect("Could not load module");
+        assert!(importer.entrypoint_name().is_none());
+        let value: usize = runtime
+            .call_function(Some(&importer), "f", json_args!())
+            .expect("Could not call function");
+        assert_eq!(1, value);
+    }
+
+    #[test]
+    fn test_transpile_disabled() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            transpile: false,
+            ..Default::default()
+        })
+        .expect("Could not create runtime");
+
+        let module = Module::new("test.js", "export const value = 5;");
+        let module = runtime.load_module(&module).expect("Could not load module");
+        let value: usize = runtime
+            .get_value(Some(&module), "value")
+            .expect("Could not get value");
+        assert_eq!(5, value);
+
+        let ts = Module::new("test.ts", "export const value: number = 5;");
+        let e = runtime
+            .load_module(&ts)
+            .expect_err("TypeScript should not load");
+        assert!(e.to_string().contains("transpilation is disabled"));
+
+        let ts = Module::new_typescript("typed.js", "export const value: number = 5;");
+        runtime
+            .load_module(&ts)
+            .expect_err("TypeScript should not load");
+
+        // Imports are checked too
+        let mut runtime = Runtime::new(RuntimeOptions {
+            transpile: false,
+            ..Default::default()
+        })
+        .expect("Could not create runtime");
+        runtime.set_module_resolver(Box::new(|specifier| {
+            specifier
+                .ends_with("types.ts")
+                .then(|| Module::new("types.ts", "export const x: number = 1;"))
+        }));
+        let importer = Module::new("importer.js", "import { x } from './types.ts';");
+        runtime
+            .load_module(&importer)
+            .expect_err("TypeScript import should fail");
+    }
+
+    #[test]
+    fn test_call_generator() {
+        let module = Module::new(
+            "test.js",
+            "
+            let cleaned = false;
+            export const wasCleaned = () => cleaned;
+            export async function* counter(n) {
+                cleaned = false;
+                try {
+                    for (let i = 0; i < n; i++) {
+                        await new Promise(r => setTimeout(r, 1));
+                        yield i;
+                    }
+                } finally {
+                    cleaned = true;
+                }
+            }
+            export function* letters() { yield 'a'; yield 'b'; }
+            export function* failing() { yield 1; throw new Error('boom'); }
+            export function notGenerator() { return 5; }
+        ",
+        );
+
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create runtime");
+        let module = runtime.load_module(&module).expect("Could not load module");
+
+        let values: Vec<usize> = runtime
+            .call_generator(Some(&module), "counter", json_args!(3))
+            .expect("Could not call generator")
+            .collect::<Result<_, _>>()
+            .expect("Generator failed");
+        assert_eq!(values, vec![0, 1, 2]);
+
+        let values: Vec<String> = runtime
+            .call_generator(Some(&module), "letters", json_args!())
+            .expect("Could not call generator")
+            .collect::<Result<_, _>>()
+            .expect("Generator failed");
+        assert_eq!(values, vec!["a", "b"]);
+
+        // Stopping early runs the generator's cleanup
+        let first: Vec<usize> = runtime
+            .call_generator(Some(&module), "counter", json_args!(100))
+            .expect("Could not call generator")
+            .take(2)
+            .collect::<Result<_, _>>()
+            .expect("Generator failed");
+        assert_eq!(first, vec![0, 1]);
+        let cleaned: bool = runtime
+            .call_function(Some(&module), "wasCleaned", json_args!())
+            .expect("Could not call function");
+        assert!(cleaned);
+
+        let mut failing = runtime
+            .call_generator::<usize>(Some(&module), "failing", json_args!())
+            .expect("Could not call generator");
+        assert_eq!(1, failing.next().unwrap().expect("Generator failed"));
+        failing
+            .next()
+            .unwrap()
+            .expect_err("Generator should have thrown");
+        assert!(failing.next().is_none());
+        drop(failing);
+
+        let mut not_generator = runtime
+            .call_generator::<usize>(Some(&module), "notGenerator", json_args!())
+            .expect("Could not call function");
+        not_generator.next().unwrap().expect_err("Not an iterator");
+    }
+
+    #[test]
+    fn test_drain() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const steps = [];
+            export function start() {
+                setTimeout(() => steps.push('timer'), 10);
+                new Promise((r) => setTimeout(r, 20)).then(() => steps.push('promise'));
+            }
+            export function forever() {
+                setInterval(() => {}, 5);
+            }
+            export function fail() {
+                setTimeout(() => { throw new Error('failed in a timer'); }, 5);
+            }
+        ",
+        );
+
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create runtime");
+        let module = runtime.load_module(&module).expect("Could not load module");
+
+        // Everything scheduled settles
+        runtime
+            .call_function_immediate::<()>(Some(&module), "start", json_args!())
+            .expect("Could not call function");
+        runtime
+            .drain(Duration::from_secs(2))
+            .expect("Could not drain the event loop");
+        let steps: Vec<String> = runtime
+            .get_value(Some(&module), "steps")
+            .expect("Could not get value");
+        assert_eq!(steps, vec!["timer", "promise"]);
+        assert!(!runtime.run_event_loop(false).expect("Event loop failed"));
+
+        // An idle event loop drains immediately
+        runtime
+            .drain(Duration::from_millis(10))
+            .expect("Could not drain the event loop");
+
+        // Errors thrown while draining are returned
+        runtime
+            .call_function_immediate::<()>(Some(&module), "fail", json_args!())
+            .expect("Could not call function");
+        let e = runtime.drain(Duration::from_secs(2)).unwrap_err();
+        assert!(e.to_string().contains("failed in a timer"), "{e}");
+
+        // Work that never finishes runs into the deadline
+        runtime
+            .call_function_immediate::<()>(Some(&module), "forever", json_args!())
+            .expect("Could not call function");
+        let e = runtime.drain(Duration::from_millis(50)).unwrap_err();
+        assert!(matches!(e, Error::Timeout(_)), "{e}");
+    }
+
+    #[test]
+    fn test_run_event_loop() {
+        let module = Module::new(
+            "test.js",
+            "
+            const steps = [];
+            export const getSteps = () => steps;
+            export function start() {
+                Promise.resolve()
+                    .then(() => steps.push('first'))
+                    .then(() => steps.push('second'));
+                setTimeout(() => steps.push('timer'), 50);
+            }
+        ",
+        );
+
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create runtime");
+        let module = runtime.load_module(&module).expect("Could not load module");
+        runtime
+            .call_function_immediate::<()>(Some(&module), "start", json_args!())
+            .expect("Could not call function");
+
+        // The promise chain runs, but the timer is still pending
+        let pending = runtime.run_event_loop(false).expect("Event loop failed");
+        assert!(pending);
+        let steps: Vec<String> = runtime
+            .call_function_immediate(Some(&module), "getSteps", json_args!())
+            .expect("Could not call function");
+        assert_eq!(steps, vec!["first", "second"]);
+
+        runtime
+            .block_on_event_loop(PollEventLoopOptions::default(), None)
+            .expect("Event loop failed");
+        let steps: Vec<String> = runtime
+            .call_function_immediate(Some(&module), "getSteps", json_args!())
+            .expect("Could not call function");
+        assert_eq!(steps, vec!["first", "second", "timer"]);
+
+        assert!(!runtime.run_event_loop(false).expect("Event loop failed"));
+        assert!(!runtime.advance_one_tick().expect("Event loop failed"));
+    }
+
+    #[test]
+    fn test_load_module_set() {
+        let lib = Module::new("scripts/lib.js", "export const value = 2;");
+        let main = Module::new(
+            "scripts/main.js",
+            "
+            import { value } from './lib.js';
+            rustyscript.register_entrypoint(() => value);
+        ",
+        );
+        let other = Module::new(
+            "scripts/other.js",
+            "rustyscript.register_entrypoint(() => 3);",
+        );
+
+        // Detected from the registered entrypoint
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let handle = runtime
+            .load_module_set(&[lib.clone(), main.clone()], None)
+            .expect("Could not load modules");
+        let value: usize = runtime
+            .call_entrypoint(&handle, json_args!())
+            .expect("Could not call entrypoint");
+        assert_eq!(2, value);
+
+        // Several candidates
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let modules = [lib, main, other];
+        let e = runtime
+            .load_module_set(&modules, None)
+            .expect_err("Entrypoint should be ambiguous");
+        let Error::AmbiguousEntrypoint(list) = e else {
+            panic!("Expected an ambiguous entrypoint error");
+        };
+        assert!(list.contains("main.js") && list.contains("other.js") && !list.contains("lib.js"));
+
+        // Chosen explicitly
+        let handle = runtime
+            .load_module_set(&modules, Some("other.js"))
+            .expect("Could not load modules");
+        let value: usize = runtime
+            .call_entrypoint(&handle, json_args!())
+            .expect("Could not call entrypoint");
+        assert_eq!(3, value);
+
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let e = runtime
+            .load_module_set(&modules, Some("missing.js"))
+            .expect_err("Entrypoint should not exist");
+        assert!(matches!(e, Error::ModuleNotFound(_)));
+    }
+
+    #[test]
+    fn test_call_function_tuple_args() {
+        let module = Module::new(
+            "test.js",
+            "
+            export function describe(name, count, list) {
+                return `${name}:${count}:${list.join(',')}`;
+            }
+            export function length(list) { return list.length; }
+        ",
+        );
+
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let module = runtime.load_module(&module).unwrap();
+
+        let value: String = runtime
+            .call_function(Some(&module), "describe", &("test", 5u32, vec![1, 2, 3]))
+            .unwrap();
+        assert_eq!(value, "test:5:1,2,3");
+
+        // A single array argument is wrapped in a 1-tuple
+        let value: usize = runtime
+            .call_function(Some(&module), "length", &(vec![1, 2, 3],))
+            .unwrap();
+        assert_eq!(value, 3);
+    }
+
+    #[test]
+    fn test_call_entrypoint() {
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "
+            rustyscript.register_entrypoint(() => 2);
+        ",
+        );
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+        let value: usize = runtime
+            .call_entrypoint(&module, json_args!())
+            .expect("Could not call registered fn");
+        assert_eq!(2, value);
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            default_entrypoint: Some("load".to_string()),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "
+            export const load = () => 2;
+        ",
+        );
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+        let value: usize = runtime
+            .call_entrypoint(&module, json_args!())
+            .expect("Could not call exported fn");
+        assert_eq!(2, value);
+
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "
+            export const load = () => 2;
+        ",
+        );
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+        runtime
+            .call_entrypoint::<Undefined>(&module, json_args!())
+            .expect_err("Did not detect no entrypoint");
+    }
+
+    #[test]
+    fn test_call_entrypoint_async() {
+        let module = Module::new(
+            "test.js",
+            "
+            const sleep = (ms) => new Promise((r) => setTimeout(r, ms));
+            export default async (fail) => {
+                await sleep(10);
+                if (fail) throw new Error('rejected');
+                return 2;
+            };
+        ",
+        );
+
+        // The blocking variant drives the promise to completion
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let handle = runtime.load_module(&module).expect("Could not load module");
+        let value: usize = runtime
+            .call_entrypoint(&handle, json_args!(false))
+            .expect("Could not call entrypoint");
+        assert_eq!(2, value);
+
+        let e = runtime
+            .call_entrypoint::<usize>(&handle, json_args!(true))
+            .unwrap_err();
+        assert!(matches!(e, Error::JsError(_)), "{e}");
+
+        // The async variant can be awaited directly
+        let tokio = runtime.tokio_runtime();
+        let value: usize = tokio
+            .block_on(runtime.call_entrypoint_async(&handle, json_args!(false)))
+            .expect("Could not call entrypoint");
+        assert_eq!(2, value);
+
+        // Async entrypoints are still subject to the timeout
+        let mut runtime = Runtime::new(RuntimeOptions {
+            timeout: Duration::from_millis(50),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "export default () => new Promise((r) => setTimeout(r, 2000));",
+        );
+        let handle = runtime.load_module(&module).expect("Could not load module");
+        runtime
+            .call_entrypoint::<Undefined>(&handle, json_args!())
+            .expect_err("Did not interupt after timeout");
+    }
+
+    #[test]
+    fn test_call_default() {
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+
+        let module = Module::new("test.js", "export default function(a, b) { return a * b; }");
+        let handle = runtime.load_module(&module).expect("Could not load module");
+        let value: usize = runtime
+            .call_default(&handle, json_args!(3, 4))
+            .expect("Could not call default export");
+        assert_eq!(12, value);
+
+        // Async defaults are resolved
+        let module = Module::new(
+            "async.js",
+            "export default async (n) => { await new Promise((r) => setTimeout(r, 5)); return n + 1; };",
+        );
+        let handle = runtime.load_module(&module).expect("Could not load module");
+        let value: usize = runtime
+            .call_default(&handle, json_args!(1))
+            .expect("Could not call default export");
+        assert_eq!(2, value);
+
+        // The default export is used even if an entrypoint is registered
+        let module = Module::new(
+            "entry.js",
+            "rustyscript.register_entrypoint(() => 'entrypoint'); export default () => 'default';",
+        );
+        let handle = runtime.load_module(&module).expect("Could not load module");
+        let value: String = runtime
+            .call_default(&handle, json_args!())
+            .expect("Could not call default export");
+        assert_eq!("default", value);
+
+        // Missing or non-callable defaults are reported clearly
+        let module = Module::new("value.js", "export default 5;");
+        let handle = runtime.load_module(&module).expect("Could not load module");
+        let e = runtime
+            .call_default::<Undefined>(&handle, json_args!())
+            .unwrap_err();
+        assert!(
+            matches!(&e, Error::DefaultExportNotCallable(_, t) if t == "number"),
+            "{e}"
+        );
+
+        let module = Module::new("none.js", "export const x = 5;");
+        let handle = runtime.load_module(&module).expect("Could not load module");
+        let e = runtime
+            .call_default::<Undefined>(&handle, json_args!())
+            .unwrap_err();
+        assert!(
+            matches!(&e, Error::DefaultExportNotCallable(_, t) if t == "undefined"),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn test_execute_module() {
+        let module = Module::new(
+            "test.js",
+            "
+            rustyscript.register_entrypoint(() => 2);
+        ",
+        );
+        let value: usize =
+            Runtime::execute_module(&module, vec![], RuntimeOptions::default(), json_args!())
+                .expect("Could not exec module");
+        assert_eq!(2, value);
+
+        let module = Module::new(
+            "test.js",
+            "
+            function load() { return 2; }
+        ",
+        );
+        Runtime::execute_module::<Undefined>(
+            &module,
+            vec![],
+            RuntimeOptions::default(),
+            json_args!(),
+        )
+        .expect_err("Could not detect no entrypoint");
+    }
+
+    #[test]
+    fn call_function() {
+        let module = Module::new(
+            "test.js",
+            "
+            globalThis.fna = (i) => i;
+            export function fnb() { return 'test'; }
+            export const fnc = 2;
+            export const fne = () => {};
+        ",
+        );
+
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+
+        let result: usize = runtime
+            .call_function(Some(&module), "fna", json_args!(2))
+            .expect("Could not call global");
+        assert_eq!(2, result);
+
+        let result: String = runtime
+            .call_function(Some(&module), "fnb", json_args!())
+            .expect("Could not call export");
+        assert_eq!("test", result);
+
+        runtime
+            .call_function::<Undefined>(Some(&module), "fnc", json_args!())
+            .expect_err("Did not detect non-function");
+        runtime
+            .call_function::<Undefined>(Some(&module), "fnd", json_args!())
+            .expect_err("Did not detect undefined");
+        runtime
+            .call_function::<Undefined>(Some(&module), "fne", json_args!())
+            .expect("Did not allow undefined return");
+    }
+
+    #[test]
+    fn test_call_slot() {
+        let module = Module::new(
+            "test.js",
+            "
+            export function add(a, b) { return a + b; }
+            export async function add_async(a, b) { return a + b; }
+        ",
+        );
+
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+
+        let slot = runtime
+            .prepare_call(Some(&module), "add")
+            .expect("Could not prepare call");
+        for i in 0..10 {
+            let result: usize = runtime
+                .call_slot(&slot, json_args!(i, 1))
+                .expect("Could not call slot");
+            assert_eq!(i + 1, result);
+        }
+
+        let slot = runtime
+            .prepare_call(Some(&module), "add_async")
+            .expect("Could not prepare call");
+        let result: usize = runtime
+            .call_slot(&slot, json_args!(2, 3))
+            .expect("Could not call async slot");
+        assert_eq!(5, result);
+
+        let mut other =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        other
+            .call_slot::<Undefined>(&slot, json_args!(2, 3))
+            .expect_err("Did not detect foreign slot");
+    }
+
+    #[test]
+    fn test_call_slot_after_recovery() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            auto_recover: true,
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        // Terminate the isolate once, when armed
+        let handle = runtime.deno_runtime().v8_isolate().thread_safe_handle();
+        let armed = std::rc::Rc::new(std::cell::Cell::new(false));
+        let trigger = armed.clone();
+        runtime
+            .register_function("kill", move |_| {
+                if trigger.replace(false) {
+                    handle.terminate_execution();
+                }
+                Ok(serde_json::Value::Null)
+            })
+            .expect("Could not register function");
+
+        let module = Module::new(
+            "test.js",
+            "
+            let count = 0;
+            export function f() {
+                count++;
+                rustyscript.functions.kill();
+                let n = 0;
+                for (let i = 0; i < 1000; i++) n += 1;
+                return count;
+            }
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+        let slot = runtime
+            .prepare_call(Some(&module), "f")
+            .expect("Could not prepare call");
+        let count: usize = runtime.call_slot(&slot, json_args!()).unwrap();
+        assert_eq!(1, count);
+
+        // The retry reloads the module, so the slot would still call the old instance
+        armed.set(true);
+        let count: usize = runtime
+            .call_function(Some(&module), "f", json_args!())
+            .expect("Did not recover from termination");
+        assert_eq!(1, count);
+        let e = runtime.call_slot::<usize>(&slot, json_args!()).unwrap_err();
+        assert!(e.to_string().contains("no longer valid"), "{e}");
+
+        // A slot prepared again calls the new instance
+        let slot = runtime
+            .prepare_call(Some(&module), "f")
+            .expect("Could not prepare call");
+        let count: usize = runtime.call_slot(&slot, json_args!()).unwrap();
+        assert_eq!(2, count);
+    }
+
+    #[test]
+    fn test_write_heap_snapshot() {
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        runtime
+            .eval::<Undefined>("globalThis.big = new Array(1000).fill('snapshot_marker')")
+            .expect("Could not eval");
+
+        let mut snapshot = Vec::new();
+        runtime
+            .write_heap_snapshot(&mut snapshot)
+            .expect("Could not write heap snapshot");
+
+        let snapshot: deno_core::serde_json::Value =
+            deno_core::serde_json::from_slice(&snapshot).expect("Snapshot was not valid JSON");
+        assert!(snapshot.get("snapshot").is_some());
+        assert!(snapshot.get("nodes").is_some());
+    }
+
+    #[test]
+    fn test_max_stack_size() {
+        // The thread needs a stack large enough for the limit
+        std::thread::Builder::new()
+            .stack_size(64 * 1024 * 1024)
+            .spawn(|| {
+                let depth = "
+                    function depth(n) { return n === 0 ? 0 : 1 + depth(n - 1); }
+                    depth(100000)
+                ";
+
+                let mut runtime =
+                    Runtime::new(RuntimeOptions::default()).expect("Could not create runtime");
+                runtime
+                    .eval::<usize>(depth)
+                    .expect_err("Default stack should have overflowed");
+
+                let mut runtime = Runtime::new(RuntimeOptions {
+                    max_stack_size: Some(32 * 1024 * 1024),
+                    ..Default::default()
+                })
+                .expect("Could not create runtime");
+                let result: usize = runtime
+                    .eval(depth)
+                    .expect("Stack should have been large enough");
+                assert_eq!(100_000, result);
+            })
+            .expect("Could not spawn thread")
+            .join()
+            .expect("Thread panicked");
+    }
+
+    #[test]
+    fn test_heap_stats() {
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let before = runtime.heap_stats();
+        assert!(before.used_heap_size > 0);
+        assert!(before.used_heap_size <= before.total_heap_size);
+        assert!(before.native_contexts >= 1);
+
+        runtime
+            .eval::<Undefined>("globalThis.big = new Array(100000).fill(0).map((_, i) => ({ i }))")
+            .expect("Could not eval");
+        let after = runtime.heap_stats();
+        assert!(after.used_heap_size > before.used_heap_size);
+    }
+
+    #[cfg(feature = "typescript")]
+    #[test]
+    fn test_load_compiled() {
+        let module = Module::new(
+            "compiled.ts",
+            "
+            type Value = number;
+            export const value: Value = 5;
+            export async function fail(): Promise<never> {
+                throw new Error('failed');
+            }
+        ",
+        );
+        let compiler = Runtime::new(RuntimeOptions::default()).unwrap();
+        let compiled = module.compile(&compiler).unwrap();
+        assert!(compiled.source_map().is_some());
+        assert!(!compiled.module().contents().contains("type Value"));
+
+        // Transpilation is not needed to load it
+        for _ in 0..2 {
+            let mut runtime = Runtime::new(RuntimeOptions {
+                transpile: false,
+                ..Default::default()
+            })
+            .unwrap();
+            let handle = runtime.load_compiled(&compiled).unwrap();
+            let value: usize = runtime.get_value(Some(&handle), "value").unwrap();
+            assert_eq!(value, 5);
+
+            // Errors point at the original source
+            let Err(Error::JsError(e)) =
+                runtime.call_function::<Undefined>(Some(&handle), "fail", json_args!())
+            else {
+                panic!("Expected a javascript error");
+            };
+            assert_eq!(e.frames[0].line_number, Some(5));
+        }
+    }
+
+    #[test]
+    fn test_runtime_info() {
+        let mut first = Runtime::new(RuntimeOptions {
+            name: Some("first".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        let second = Runtime::new(RuntimeOptions::default()).unwrap();
+
+        assert_ne!(first.id(), second.id());
+        assert_eq!(first.name(), Some("first"));
+        assert_eq!(second.name(), None);
+        assert_eq!(
+            first.info().to_string(),
+            format!("runtime {} (first)", first.id())
+        );
+        assert_eq!(
+            second.info().to_string(),
+            format!("runtime {}", second.id())
+        );
+
+        // Available to ops
+        let state = first.deno_runtime().op_state();
+        let info = state.borrow().borrow::<RuntimeInfo>().clone();
+        assert_eq!(info.name.as_deref(), Some("first"));
+
+        // And included in timeouts, to tell which runtime timed out
+        let mut slow = Runtime::new(RuntimeOptions {
+            name: Some("slow".to_string()),
+            timeout: Duration::from_millis(50),
+            ..Default::default()
+        })
+        .unwrap();
+        let e = slow
+            .eval::<usize>("new Promise((r) => setTimeout(() => r(1), 2000))")
+            .unwrap_err();
+        let Error::Timeout(message) = e else {
+            panic!("Expected a timeout, got {e}");
+        };
+        assert!(message.starts_with(&slow.info().to_string()), "{message}");
+    }
+
+    #[test]
+    fn test_register_stream_function() {
+        use deno_core::futures::stream;
+
+        /// Counts how many values were pulled, and whether the stream was dropped
+        struct Tracker(Rc<std::cell::Cell<usize>>, Rc<std::cell::Cell<bool>>);
+        impl Drop for Tracker {
+            fn drop(&mut self) {
+                self.1.set(true);
+            }
+        }
+
+        let pulled = Rc::new(std::cell::Cell::new(0));
+        let dropped = Rc::new(std::cell::Cell::new(false));
+
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let (pulled_, dropped_) = (pulled.clone(), dropped.clone());
+        runtime
+            .register_stream_function("naturals", move |_| {
+                dropped_.set(false);
+                let tracker = Tracker(pulled_.clone(), dropped_.clone());
+                let stream: crate::RsValueStream = Box::pin(stream::iter((0u64..).map(move |i| {
+                    tracker.0.set(tracker.0.get() + 1);
+                    Ok(serde_json::Value::from(i))
+                })));
+                Ok(stream)
+            })
+            .unwrap();
+        runtime
+            .register_stream_function("failing", |_| {
+                let stream: crate::RsValueStream = Box::pin(stream::iter(vec![
+                    Ok(serde_json::Value::from(1)),
+                    Err(Error::Runtime("stream failed".to_string())),
+                ]));
+                Ok(stream)
+            })
+            .unwrap();
+
+        let module = Module::new(
+            "test.js",
+            "
+            export async function take(n) {
+                const values = [];
+                for await (const i of rustyscript.stream_functions.naturals()) {
+                    if (values.length == n) break;
+                    values.push(i);
+                }
+                return values;
+            }
+
+            export async function failing() {
+                try {
+                    for await (const _ of rustyscript.stream_functions.failing()) {}
+                } catch (e) {
+                    return e.message;
+                }
+            }
+        ",
+        );
+        let module = runtime.load_module(&module).unwrap();
+
+        // Values are only pulled as they are needed, and the stream is dropped on break
+        let values: Vec<u64> = runtime
+            .call_function(Some(&module), "take", json_args!(3))
+            .unwrap();
+        assert_eq!(values, vec![0, 1, 2]);
+        assert_eq!(pulled.get(), 4);
+        assert!(dropped.get());
+
+        let message: String = runtime
+            .call_function(Some(&module), "failing", json_args!())
+            .unwrap();
+        assert!(message.contains("stream failed"), "{message}");
+    }
+
+    #[cfg(feature = "typescript")]
+    #[test]
+    fn test_load_static_modules() {
+        const LIBRARY: &[Module] = crate::static_modules![
+            (
+                "lib/a.js",
+                "import { b } from './b.ts'; export const a = () => b + 1;"
+            ),
+            ("lib/b.ts", "export const b: number = 1;"),
+            (
+                "lib/unused.js",
+                "throw new Error('Should not be executed');"
+            ),
+        ];
+
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        runtime.load_static_modules(LIBRARY).unwrap();
+
+        // Registered modules survive a change of resolver
+        runtime.set_module_resolver(Box::new(|_| None));
+
+        let module = Module::new(
+            "main.js",
+            "
+            import { a } from './lib/a.js';
+            export const value = a() + (await import('./lib/b.ts')).b;
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+        let value: usize = runtime.get_value(Some(&module), "value").unwrap();
+        assert_eq!(value, 3);
+    }
+
+    #[cfg(feature = "typescript")]
+    #[test]
+    fn test_source_transform() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            source_transform: Some(std::sync::Arc::new(|specifier: &str, source: &str| {
+                // Typescript is seen before it is transpiled
+                if specifier.ends_with(".ts") {
+                    assert!(source.contains(": number"));
+                }
+                source.replace("__SPECIFIER__", &format!("'{specifier}'"))
+            })),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        runtime.set_module_resolver(Box::new(|specifier| match specifier {
+            "db:module/dep.ts" => Some(Module::new(
+                "dep.ts",
+                "export const dep: number = 1; export const name = __SPECIFIER__;",
+            )),
+            _ => None,
+        }));
+
+        let module = Module::new(
+            "main.ts",
+            "
+            import { name as depName } from 'db:module/dep.ts';
+            export const name: string = __SPECIFIER__;
+            export { depName };
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+
+        let name: String = runtime.get_value(Some(&module), "name").unwrap();
+        assert!(name.ends_with("main.ts"), "{name}");
+        let dep_name: String = runtime.get_value(Some(&module), "depName").unwrap();
+        assert_eq!(dep_name, "db:module/dep.ts");
+    }
+
+    #[test]
+    fn test_dynamic_import_hook() {
+        let imports = Rc::new(std::cell::RefCell::new(Vec::new()));
+        let imports_ = imports.clone();
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            dynamic_import_hook: Some(Box::new(
+                move |event: &crate::module_loader::DynamicImportEvent| {
+                    imports_.borrow_mut().push(event.specifier.to_string());
+                    event.specifier.as_str() != "db:module/denied.js"
+                },
+            )),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        runtime.set_module_resolver(Box::new(|specifier| match specifier {
+            "db:module/allowed.js" => Some(Module::new("allowed.js", "export const value = 1;")),
+            "db:module/denied.js" => Some(Module::new("denied.js", "export const value = 2;")),
+            _ => None,
+        }));
+
+        let module = Module::new(
+            "test.js",
+            "
+            import 'db:module/denied.js';
+            export const allowed = () => import('db:module/allowed.js').then(m => m.value);
+            export const denied = () => import('db:module/denied.js')
+                .then(() => 'loaded', e => e.message);
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+
+        // Static imports are not checked
+        assert!(imports.borrow().is_empty());
+
+        let value: usize = runtime
+            .call_function(Some(&module), "allowed", json_args!())
+            .unwrap();
+        assert_eq!(value, 1);
+
+        let message: String = runtime
+            .call_function(Some(&module), "denied", json_args!())
+            .unwrap();
+        assert!(message.contains("was denied"), "{message}");
+
+        assert_eq!(
+            *imports.borrow(),
+            vec!["db:module/allowed.js", "db:module/denied.js"]
+        );
+    }
+
+    #[test]
+    fn test_module_resolver() {
+        let calls = Rc::new(std::cell::Cell::new(0));
+        let calls_ = calls.clone();
+
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        runtime.set_module_resolver(Box::new(move |specifier| {
+            calls_.set(calls_.get() + 1);
+            match specifier {
+                "db:module/a.js" => Some(Module::new(
+                    "a.js",
+                    "import { b } from 'db:module/b.js'; export const a = () => b + 1;",
+                )),
+                "db:module/b.js" => Some(Module::new(
+                    "b.js",
+                    "import { a } from 'db:module/a.js'; export const b = 1; export const call_a = () => a();",
+                )),
+                _ => None,
+            }
+        }));
+
+        let module = Module::new(
+            "test.js",
+            "
+            import { a } from 'db:module/a.js';
+            import { call_a } from 'db:module/b.js';
+            export const value = a() + call_a();
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+        let value: usize = runtime
+            .get_value(Some(&module), "value")
+            .expect("Could not get value");
+        assert_eq!(4, value);
+        assert_eq!(2, calls.get());
+
+        let module = Module::new("test2.js", "import 'db:module/missing.js';");
+        runtime
+            .load_module(&module)
+            .expect_err("Did not fall back to default loaders");
+    }
+
+    #[test]
+    fn test_generate_host_dts() {
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        runtime
+            .register_function("add", |_| Ok(serde_json::Value::Null))
+            .expect("Could not register function");
+        runtime
+            .register_function("untyped", |_| Ok(serde_json::Value::Null))
+            .expect("Could not register function");
+        runtime
+            .register_function("my-fn", |_| Ok(serde_json::Value::Null))
+            .expect("Could not register function");
+        runtime
+            .register_async_function("fetch", |_| Box::pin(async { Ok(serde_json::Value::Null) }))
+            .expect("Could not register function");
+        runtime.set_function_type_hint("add", "(a: number, b: number): number");
+
+        let dts = runtime.generate_host_dts();
+        assert!(dts.contains("declare namespace rustyscript {"));
+        assert!(dts.contains("        add(a: number, b: number): number;\n"));
+        assert!(dts.contains("        untyped(...args: any[]): any;\n"));
+        assert!(dts.contains("        \"my-fn\"(...args: any[]): any;\n"));
+        assert!(dts.contains("        fetch(...args: any[]): Promise<any>;\n"));
+    }
+
+    #[cfg(feature = "snapshot_builder")]
+    #[test]
+    fn test_snapshot_file() {
+        let path =
+            std::env::temp_dir().join(format!("rustyscript_test_{}.snap", std::process::id()));
+        crate::SnapshotBuilder::new(RuntimeOptions::default())
+            .expect("Could not create the snapshot builder")
+            .with_expression("globalThis.answer = 42")
+            .expect("Could not eval")
+            .finish_to_file(&path)
+            .expect("Could not write the snapshot");
+
+        let mut runtime = Runtime::from_snapshot_file(&path, RuntimeOptions::default())
+            .expect("Could not load the snapshot");
+        let answer: usize = runtime.eval("globalThis.answer").unwrap();
+        assert_eq!(answer, 42);
+
+        // Loading it again reuses the same copy
+        let bytes = std::fs::read(&path).unwrap();
+        let snapshot = crate::snapshot_file::decode(&bytes, &[]).unwrap();
+        assert!(std::ptr::eq(
+            crate::snapshot_file::intern(snapshot),
+            crate::snapshot_file::intern(&snapshot.to_vec())
+        ));
+
+        // A runtime with different extensions refuses it
+        let e = Runtime::from_snapshot_file(
+            &path,
+            RuntimeOptions {
+                extensions: vec![deno_core::Extension {
+                    name: "rustyscript_test_extension",
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        )
+        .err()
+        .expect("A snapshot with other extensions should be refused");
+        assert!(matches!(e, Error::IncompatibleSnapshot(_)), "{e}");
+
+        // A snapshot from another build is refused
+        let mut bytes = std::fs::read(&path).unwrap();
+        let version = env!("CARGO_PKG_VERSION").as_bytes();
+        let at = bytes
+            .windows(version.len())
+            .position(|w| w == version)
+            .unwrap();
+        bytes[at] = b'X';
+        std::fs::write(&path, &bytes).unwrap();
+        let e = Runtime::from_snapshot_file(&path, RuntimeOptions::default())
+            .err()
+            .expect("A stale snapshot should be refused");
+        assert!(matches!(e, Error::IncompatibleSnapshot(_)), "{e}");
+
+        // As is anything else
+        std::fs::write(&path, b"not a snapshot").unwrap();
+        let e = Runtime::from_snapshot_file(&path, RuntimeOptions::default())
+            .err()
+            .expect("A file that is not a snapshot should be refused");
+        assert!(matches!(e, Error::IncompatibleSnapshot(_)), "{e}");
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[cfg(feature = "snapshot_builder")]
+    #[test]
+    fn test_snapshot_modules() {
+        let module = Module::new(
+            "snapshot_module.js",
+            "
+            globalThis.loads = (globalThis.loads ?? 0) + 1;
+            export const f = () => 42;
+        ",
+        );
+        let snapshot = crate::SnapshotBuilder::new(RuntimeOptions::default())
+            .expect("Could not create the snapshot builder")
+            .with_module(&module)
+            .expect("Could not load module")
+            .finish();
+        let snapshot: &'static [u8] = Box::leak(snapshot);
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            startup_snapshot: Some(snapshot),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        let module = runtime
+            .get_module_handle("snapshot_module.js")
+            .expect("Could not find snapshot module");
+        let value: usize = runtime
+            .call_function(Some(&module), "f", json_args!())
+            .expect("Could not call function");
+        assert_eq!(42, value);
+
+        // The module was not executed a second time
+        let loads: usize = runtime.eval("globalThis.loads").expect("Could not eval");
+        assert_eq!(1, loads);
+
+        // Modules that are not loaded are not imported either
+        let e = runtime
+            .get_module_handle("src/ext/rustyscript/freeze_globals.js")
+            .unwrap_err();
+        assert!(matches!(e, Error::ModuleNotFound(_)), "{e}");
+    }
+
+    #[test]
+    fn test_is_promise() {
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "
+            let finish;
+            export const start = () => new Promise((resolve) => finish = resolve);
+            export const done = () => finish(5);
+            export const number = () => 5;
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+
+        let value: crate::js_value::Value = runtime
+            .call_function_immediate(Some(&module), "number", json_args!())
+            .expect("Could not call function");
+        assert!(!runtime.is_promise(&value).unwrap());
+
+        // Values from another runtime are refused instead of being opened in this one
+        let mut other =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let foreign: crate::js_value::Value = other.eval("Promise.resolve(1)").unwrap();
+        runtime.is_promise(&foreign).unwrap_err();
+
+        let value: crate::js_value::Value = runtime
+            .call_function_immediate(Some(&module), "start", json_args!())
+            .expect("Could not call function");
+        assert!(runtime.is_promise(&value).unwrap());
+
+        let promise = value
+            .try_into::<crate::js_value::Promise<usize>>(&mut runtime)
+            .expect("Could not convert to a promise");
+        assert!(promise.is_pending(&mut runtime));
+
+        runtime
+            .call_function_immediate::<Undefined>(Some(&module), "done", json_args!())
+            .expect("Could not call function");
+        runtime
+            .advance_event_loop(PollEventLoopOptions::default())
+            .expect("Could not advance event loop");
+
+        assert!(!promise.is_pending(&mut runtime));
+        let value = promise
+            .into_value(&mut runtime)
+            .expect("Could not resolve promise");
+        assert_eq!(5, value);
+    }
+
+    #[test]
+    fn test_call_batch() {
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "
+            export const double = (n) => n * 2;
+            export const later = async (n) => { await new Promise(r => setTimeout(r, 10)); return n; };
+            export const fail = () => { throw new Error('oops'); };
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+
+        let call = |name: &str, args: Vec<crate::serde_json::Value>| {
+            (module.clone(), name.to_string(), args)
+        };
+        let results = runtime.call_batch::<usize>(&[
+            call("double", vec![2.into()]),
+            call("fail", vec![]),
+            call("later", vec![7.into()]),
+            call("missing", vec![]),
+            call("double", vec![5.into()]),
+        ]);
+
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[0].as_ref().ok(), Some(&4));
+        assert!(matches!(results[1], Err(Error::JsError(_))));
+        assert_eq!(results[2].as_ref().ok(), Some(&7));
+        assert!(matches!(results[3], Err(Error::ValueNotFound(_))));
+        assert_eq!(results[4].as_ref().ok(), Some(&10));
+    }
+
+    #[test]
+    fn test_map_values() {
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "
+            export let calls = 0;
+            export const double = (n) => { calls++; return n * 2; };
+            export const later = async (n) => { await new Promise(r => setTimeout(r, 10)); return n + 1; };
+            export const check = (n) => { calls++; if (n < 0) throw new Error('negative'); return n; };
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+
+        let doubled: Vec<usize> = runtime.map_values(Some(&module), "double", 1..=4).unwrap();
+        assert_eq!(doubled, [2, 4, 6, 8]);
+
+        let later: Vec<usize> = runtime
+            .map_values(Some(&module), "later", vec![1, 2, 3])
+            .unwrap();
+        assert_eq!(later, [2, 3, 4]);
+
+        // Short-circuits on the first failing item
+        let before: usize = runtime.get_value(Some(&module), "calls").unwrap();
+        let e = runtime
+            .map_values::<_, usize>(Some(&module), "check", [1, -1, 2, 3])
+            .unwrap_err();
+        assert!(matches!(e, Error::JsError(_)), "{e}");
+        let after: usize = runtime.get_value(Some(&module), "calls").unwrap();
+        assert_eq!(after - before, 2);
+
+        // Or collects every result
+        let results = runtime
+            .map_values_each::<_, usize>(Some(&module), "check", [1, -1, 2])
+            .unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().ok(), Some(&1));
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().ok(), Some(&2));
+
+        runtime
+            .map_values::<_, usize>(Some(&module), "missing", [1])
+            .expect_err("Missing functions should fail");
+    }
+
+    #[test]
+    fn test_cancellation_handle() {
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "
+            export function spin() { while (true) {} }
+            export function value() { return 2; }
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+        let handle = runtime.cancellation_handle();
+
+        // Cancelling while idle does nothing
+        handle.cancel();
+        let value: usize = runtime
+            .call_function(Some(&module), "value", json_args!())
+            .expect("Idle cancellation affected the next call");
+        assert_eq!(2, value);
+
+        // The handle can be reused for several calls
+        for _ in 0..2 {
+            let canceller = handle.clone();
+            let thread = std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(50));
+                canceller.cancel();
+            });
+
+            let result = runtime.call_function::<Undefined>(Some(&module), "spin", json_args!());
+            assert!(matches!(result, Err(Error::Cancelled)));
+            thread.join().unwrap();
+        }
+
+        let value: usize = runtime
+            .call_function(Some(&module), "value", json_args!())
+            .expect("Runtime was not usable after cancellation");
+        assert_eq!(2, value);
+    }
+
+    #[test]
+    fn test_shared_context() {
+        struct Config {
+            values: Vec<usize>,
+        }
+
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let config = std::sync::Arc::new(Config {
+            values: vec![1, 2, 3],
+        });
+        runtime
+            .set_shared_context(config.clone())
+            .expect("Could not set context");
+        assert!(std::sync::Arc::ptr_eq(
+            &config,
+            &runtime.shared_context::<Config>().unwrap()
+        ));
+        assert!(runtime.shared_context::<String>().is_none());
+
+        runtime
+            .register_module_function("sum", |_, getter| {
+                let config = getter
+                    .shared_context::<Config>()
+                    .ok_or_else(|| Error::Runtime("No context".to_string()))?;
+                Ok(config.values.iter().sum::<usize>().into())
+            })
+            .expect("Could not register function");
+
+        let sum: usize = runtime
+            .eval("rustyscript.functions.sum()")
+            .expect("Could not call function");
+        assert_eq!(6, sum);
+    }
+
+    #[test]
+    fn test_module_state() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const state = { count: 0, items: [], seen: new Set() };
+            export function add(item) {
+                state.count++;
+                state.items.push(item);
+                state.seen.add(item);
+                return state.count;
+            }
+            export function seen() {
+                return state.seen.size;
+            }
+            export const frozen = Object.freeze({ a: 1 });
+            export const withFn = { f: () => 1 };
+            export const notObject = 5;
+
+            export const cache = new Map([['x', { n: 1 }]]);
+            export function cached() {
+                cache.set('y', { n: 2 });
+                return [...cache.keys()];
+            }
+
+            export const partial = { a: 1 };
+            Object.defineProperty(partial, 'fixed', { value: 2, enumerable: true });
+            export function partialA() { return partial.a; }
+        ",
+        );
+
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create runtime");
+        let handle = runtime.load_module(&module).expect("Could not load module");
+        runtime
+            .call_function::<usize>(Some(&handle), "add", json_args!("a"))
+            .expect("Could not call function");
+        let saved = runtime
+            .serialize_module_state(&handle, "state")
+            .expect("Could not serialize state");
+
+        runtime
+            .call_function::<usize>(Some(&handle), "add", json_args!("b"))
+            .expect("Could not call function");
+        runtime
+            .restore_module_state(&handle, "state", &saved)
+            .expect("Could not restore state");
+
+        // The module's own reference to the object sees the restored state
+        let count: usize = runtime
+            .call_function(Some(&handle), "add", json_args!("c"))
+            .expect("Could not call function");
+        assert_eq!(2, count);
+        let seen: usize = runtime
+            .call_function(Some(&handle), "seen", json_args!())
+            .expect("Could not call function");
+        assert_eq!(2, seen);
+
+        // Restore into a fresh runtime
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create runtime");
+        let handle = runtime.load_module(&module).expect("Could not load module");
+        runtime
+            .restore_module_state(&handle, "state", &saved)
+            .expect("Could not restore state");
+        let count: usize = runtime
+            .call_function(Some(&handle), "add", json_args!("b"))
+            .expect("Could not call function");
+        assert_eq!(2, count);
+
+        runtime
+            .serialize_module_state(&handle, "withFn")
+            .expect_err("Functions cannot be serialized");
+        runtime
+            .serialize_module_state(&handle, "notObject")
+            .expect_err("Only objects can be serialized");
+        runtime
+            .restore_module_state(&handle, "frozen", &saved)
+            .expect_err("Frozen objects cannot be restored");
+        runtime
+            .restore_module_state(&handle, "state", &[1, 2, 3])
+            .expect_err("Invalid state should be rejected");
+
+        // Maps are restored through their entries
+        let saved_cache = runtime
+            .serialize_module_state(&handle, "cache")
+            .expect("Could not serialize map");
+        runtime
+            .call_function::<Vec<String>>(Some(&handle), "cached", json_args!())
+            .expect("Could not call function");
+        runtime
+            .restore_module_state(&handle, "cache", &saved_cache)
+            .expect("Could not restore map");
+        let keys: Vec<String> = runtime
+            .call_function(Some(&handle), "cached", json_args!())
+            .expect("Could not call function");
+        assert_eq!(vec!["x", "y"], keys);
+        runtime
+            .restore_module_state(&handle, "state", &saved_cache)
+            .expect_err("A map cannot be restored into a plain object");
+
+        // A restore that fails part way leaves the object as it was
+        runtime
+            .restore_module_state(&handle, "partial", &saved)
+            .expect_err("Non-configurable properties cannot be replaced");
+        let a: usize = runtime
+            .call_function(Some(&handle), "partialA", json_args!())
+            .expect("Could not call function");
+        assert_eq!(1, a);
+    }
+
+    #[test]
+    fn test_export_value() {
+        let mut source =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let mut target =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+
+        let value: crate::js_value::Value = source
+            .eval("({ when: new Date(0), map: new Map([['a', 1]]), bytes: new Uint8Array([1, 2, 3]) })")
+            .unwrap();
+        let value = source.export_value(&value).unwrap();
+
+        let value: crate::js_value::Value = target.import_value(&value).unwrap();
+        {
+            let mut scope = target.deno_runtime().handle_scope();
+            let value = v8::Local::new(&mut scope, value.into_v8());
+            let global = scope.get_current_context().global(&mut scope);
+            let key = v8::String::new(&mut scope, "received").unwrap();
+            global.set(&mut scope, key.into(), value);
+        }
+
+        let types: Vec<String> = target
+            .eval("[received.when instanceof Date, received.map instanceof Map, received.bytes instanceof Uint8Array].map(String)")
+            .unwrap();
+        assert_eq!(types, vec!["true", "true", "true"]);
+
+        let sum: u8 = target
+            .eval("received.map.get('a') + received.bytes.reduce((a, b) => a + b)")
+            .unwrap();
+        assert_eq!(sum, 7);
+
+        // Functions cannot be cloned
+        let value: crate::js_value::Value = source.eval("({ f: () => 1 })").unwrap();
+        source
+            .export_value(&value)
+            .expect_err("Functions should not be serializable");
+
+        // Values can only be exported by the runtime they came from
+        let value: crate::js_value::Value = source.eval("({ a: 1 })").unwrap();
+        target
+            .export_value(&value)
+            .expect_err("Values from another runtime should be rejected");
+    }
+
+    #[test]
+    fn test_webassembly() {
+        // (module (func (export "add") (param i32 i32) (result i32) local.get 0 local.get 1 i32.add))
+        let wasm: Vec<u8> = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x07, 0x01, 0x60, 0x02, 0x7f,
+            0x7f, 0x01, 0x7f, 0x03, 0x02, 0x01, 0x00, 0x07, 0x07, 0x01, 0x03, 0x61, 0x64, 0x64,
+            0x00, 0x00, 0x0a, 0x09, 0x01, 0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6a, 0x0b,
+        ];
+
+        let module = Module::new(
+            "test.js",
+            "
+            export async function compileAndAdd(bytes, a, b) {
+                const compiled = await WebAssembly.compile(new Uint8Array(bytes));
+                const instance = await WebAssembly.instantiate(compiled);
+                return instance.exports.add(a, b);
+            }
+
+            export function syncAdd(bytes, a, b) {
+                const compiled = new WebAssembly.Module(new Uint8Array(bytes));
+                return new WebAssembly.Instance(compiled).exports.add(a, b);
+            }
+        ",
+        );
+
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create runtime");
+        let handle = runtime.load_module(&module).expect("Could not load module");
+
+        let result: i32 = runtime
+            .call_function(
+                Some(&handle),
+                "compileAndAdd",
+                json_args!(wasm.clone(), 2, 3),
+            )
+            .expect("Could not run wasm");
+        assert_eq!(5, result);
+
+        let result: i32 = runtime
+            .call_function(Some(&handle), "syncAdd", json_args!(wasm, 4, 5))
+            .expect("Could not run wasm");
+        assert_eq!(9, result);
+
+        // Invalid modules are reported as errors
+        runtime
+            .call_function::<i32>(
+                Some(&handle),
+                "compileAndAdd",
+                json_args!(vec![0u8, 1, 2], 1, 1),
+            )
+            .expect_err("Invalid wasm should fail to compile");
+    }
+
+    #[test]
+    fn test_max_call_depth() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            max_call_depth: Some(10),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        runtime
+            .load_module(&Module::new(
+                "test.js",
+                "export const obj = { get value() { return rustyscript.functions.recurse(); } };",
+            ))
+            .expect("Could not load module");
+
+        let calls = std::rc::Rc::new(std::cell::Cell::new(0));
+        let counter = calls.clone();
+        runtime
+            .register_module_function("recurse", move |_, getter| {
+                counter.set(counter.get() + 1);
+                getter.get::<crate::serde_json::Value>("test.js", "obj")?;
+                Ok(crate::serde_json::Value::Null)
+            })
+            .expect("Could not register function");
+
+        runtime
+            .eval::<crate::serde_json::Value>("rustyscript.functions.recurse()")
+            .expect_err("Recursion should have been stopped");
+        assert_eq!(10, calls.get());
+
+        // The depth is released once the calls unwind
+        calls.set(0);
+        runtime
+            .eval::<crate::serde_json::Value>("rustyscript.functions.recurse()")
+            .expect_err("Recursion should have been stopped");
+        assert_eq!(10, calls.get());
+    }
+
+    #[cfg(feature = "web")]
+    #[test]
+    fn test_streamed_response() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            timeout: Duration::from_millis(500),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let module = Module::new(
+            "test.js",
+            "
+            // Chunks arrive one at a time, only as the reader pulls them
+            function chunked(chunks, delay) {
+                let i = 0;
+                return new ReadableStream({
+                    async pull(controller) {
+                        await new Promise((resolve) => setTimeout(resolve, delay));
+                        if (i < chunks.length) {
+                            controller.enqueue(new TextEncoder().encode(chunks[i++]));
+                        } else {
+                            controller.close();
+                        }
+                    },
+                }, { highWaterMark: 0 });
+            }
+
+            export async function readChunks() {
+                const upper = new TransformStream({
+                    transform(chunk, controller) {
+                        controller.enqueue(new TextDecoder().decode(chunk).toUpperCase());
+                    },
+                });
+
+                const response = new Response(chunked(['a', 'b', 'c'], 1));
+                const reader = response.body.pipeThrough(upper).getReader();
+                const chunks = [];
+                for (let r = await reader.read(); !r.done; r = await reader.read()) {
+                    chunks.push(r.value);
+                }
+                return chunks;
+            }
+
+            export async function readStalled() {
+                const reader = chunked(['a'], 60_000).getReader();
+                return await reader.read();
+            }
+        ",
+        );
+        let handle = runtime.load_module(&module).unwrap();
+
+        let chunks: Vec<String> = runtime
+            .call_function(Some(&handle), "readChunks", json_args!())
+            .unwrap();
+        assert_eq!(chunks, vec!["A", "B", "C"]);
+
+        // A stream waiting on a slow source is still bound by the runtime's timeout
+        let e = runtime
+            .call_function::<Undefined>(Some(&handle), "readStalled", json_args!())
+            .unwrap_err();
+        assert!(matches!(e, Error::Timeout(_)), "{e}");
+    }
+
+    #[cfg(feature = "web")]
+    #[test]
+    fn test_permission_handler() {
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = calls.clone();
+        let mut runtime = crate::RuntimeBuilder::new()
+            .with_permission_handler(move |request| {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                !matches!(request, crate::PermissionRequest::Url(_))
+            })
+            .build()
+            .expect("Could not create the runtime");
+
+        let module = Module::new(
+            "test.js",
+            "
+            const attempt = () => fetch('http://127.0.0.1:1/').then(() => 'ok', (e) => e.message);
+            export const first = await attempt();
+            export const second = await attempt();
+        ",
+        );
+        let handle = runtime.load_module(&module).expect("Could not load module");
+
+        let first: String = runtime.get_value(Some(&handle), "first").unwrap();
+        let second: String = runtime.get_value(Some(&handle), "second").unwrap();
+        assert!(first.contains("Url("), "{first}");
+        assert_eq!(first, second);
+
+        // The decision is cached, so the handler is only asked once
+        assert_eq!(1, calls.load(std::sync::atomic::Ordering::SeqCst));
+    }
+
+    #[cfg(feature = "net")]
+    #[test]
+    fn test_net_permissions() {
+        let mut runtime = crate::RuntimeBuilder::new()
+            .with_web_permissions(std::sync::Arc::new(crate::AllowlistWebPermissions::new()))
+            .build()
+            .expect("Could not create the runtime");
+
+        let module = Module::new(
+            "test.js",
+            "
+            export const listen = typeof Deno.listen;
+            export const result = await Deno.connect({ hostname: '127.0.0.1', port: 1 })
+                .then(() => 'connected', (e) => e.message);
+        ",
+        );
+        let handle = runtime.load_module(&module).expect("Could not load module");
+
+        let listen: String = runtime.get_value(Some(&handle), "listen").unwrap();
+        assert_eq!(listen, "function");
+
+        // Nothing is allowed, so the connection never reaches the network
+        let result: String = runtime.get_value(Some(&handle), "result").unwrap();
+        assert_ne!(result, "connected");
+
+        // Without the web feature, fetch is not included
+        #[cfg(not(feature = "web"))]
+        {
+            let fetch: String = runtime.eval("typeof fetch").unwrap();
+            assert_eq!(fetch, "undefined");
+        }
+    }
+
+    #[test]
+    fn test_rng_seed() {
+        fn sample(seed: u64) -> Vec<f64> {
+            let mut runtime = Runtime::new(RuntimeOptions {
+                rng_seed: Some(seed),
+                ..Default::default()
+            })
+            .expect("Could not create the runtime");
+            runtime
+                .eval("Array.from({ length: 8 }, () => Math.random())")
+                .unwrap()
+        }
+
+        let first = sample(42);
+        assert_eq!(first, sample(42));
+        assert_ne!(first, sample(43));
+        assert!(first.iter().all(|n| (0.0..1.0).contains(n)));
+
+        #[cfg(feature = "crypto")]
+        {
+            let bytes = || -> Vec<u8> {
+                let mut runtime = Runtime::new(RuntimeOptions {
+                    rng_seed: Some(42),
+                    ..Default::default()
+                })
+                .expect("Could not create the runtime");
+                runtime
+                    .eval("Array.from(crypto.getRandomValues(new Uint8Array(16)))")
+                    .unwrap()
+            };
+            assert_eq!(bytes(), bytes());
+        }
+    }
+
+    #[test]
+    fn test_progress_callback() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let seen = calls.clone();
+        let mut runtime = crate::RuntimeBuilder::new()
+            .with_timeout(Duration::from_secs(10))
+            .with_progress_callback(Duration::from_millis(10), move |elapsed| {
+                seen.fetch_add(1, Ordering::SeqCst);
+                if elapsed > Duration::from_millis(200) {
+                    crate::ProgressSignal::Abort
+                } else {
+                    crate::ProgressSignal::Continue
+                }
+            })
+            .build()
+            .expect("Could not create the runtime");
+
+        // Short calls finish without being aborted
+        let value: usize = runtime.eval("1 + 1").unwrap();
+        assert_eq!(2, value);
+
+        // An uncooperative loop is interrupted, then aborted
+        let e = runtime.eval::<Undefined>("while (true) {}").unwrap_err();
+        assert!(matches!(e, Error::Cancelled), "{e}");
+        assert!(calls.load(Ordering::SeqCst) > 1);
+
+        // The runtime can be used again afterwards
+        let value: usize = runtime.eval("2 + 2").unwrap();
+        assert_eq!(4, value);
+
+        // Each call is timed from its own start
+        let value: usize = runtime
+            .eval("const start = Date.now(); while (Date.now() - start < 100) {} 3")
+            .unwrap();
+        assert_eq!(3, value);
+        let e = runtime.eval::<Undefined>("while (true) {}").unwrap_err();
+        assert!(matches!(e, Error::Cancelled), "{e}");
     }
-}
 
-impl AsyncBridgeExt for Runtime {
-    fn bridge(&self) -> &AsyncBridge {
-        &self.tokio
+    #[test]
+    fn test_cpu_time_limit() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            timeout: Duration::from_secs(10),
+            cpu_time_limit: Some(Duration::from_millis(100)),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        // Waiting does not use up the budget
+        let module = Module::new(
+            "test.js",
+            "
+            export const wait = () => new Promise((r) => setTimeout(() => r(1), 300));
+            export const spin = () => { while (true) {} };
+            export const busy = () => { const end = Date.now() + 60; while (Date.now() < end) {} return 1; };
+        ",
+        );
+        let handle = runtime.load_module(&module).expect("Could not load module");
+        let value: usize = runtime
+            .call_function(Some(&handle), "wait", json_args!())
+            .expect("Waiting should not count as CPU time");
+        assert_eq!(1, value);
+
+        // Spinning does
+        let e = runtime
+            .call_function::<Undefined>(Some(&handle), "spin", json_args!())
+            .unwrap_err();
+        assert!(matches!(e, Error::CpuTimeExceeded(_)), "{e}");
+
+        // The runtime can be used again afterwards
+        let value: usize = runtime.eval("1 + 1").unwrap();
+        assert_eq!(2, value);
+
+        // Each call gets a fresh budget
+        for _ in 0..3 {
+            let value: usize = runtime
+                .call_function(Some(&handle), "busy", json_args!())
+                .expect("The budget should not carry over between calls");
+            assert_eq!(1, value);
+        }
+        let e = runtime
+            .call_function::<Undefined>(Some(&handle), "spin", json_args!())
+            .unwrap_err();
+        assert!(matches!(e, Error::CpuTimeExceeded(_)), "{e}");
+
+        // The wall-clock timeout still applies when it trips first
+        let mut runtime = Runtime::new(RuntimeOptions {
+            timeout: Duration::from_millis(50),
+            cpu_time_limit: Some(Duration::from_secs(10)),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let e = runtime
+            .eval::<usize>("new Promise((r) => setTimeout(() => r(1), 2000))")
+            .unwrap_err();
+        assert!(matches!(e, Error::Timeout(_)), "{e}");
     }
-}
 
-#[cfg(test)]
-mod test_runtime {
-    use crate::json_args;
-    use std::time::Duration;
+    #[cfg(feature = "inspector")]
+    #[test]
+    fn test_inspector() {
+        let options = RuntimeOptions {
+            timeout: Duration::from_secs(5),
+            cpu_time_limit: Some(Duration::from_secs(1)),
+            inspector: Some(crate::InspectorOptions {
+                address: ([127, 0, 0, 1], 0).into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(options.time_limits(), (Duration::MAX, None));
 
-    use super::*;
-    use deno_core::extension;
+        let mut runtime = Runtime::new(options).expect("Could not create the runtime");
+        let value: usize = runtime.eval("1 + 2").unwrap();
+        assert_eq!(value, 3);
+    }
 
     #[test]
-    fn test_new() {
-        Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+    fn test_microtask_limit() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            microtask_limit: Some(1000),
+            timeout: Duration::from_secs(10),
+            ..Default::default()
+        })
+        .unwrap();
 
-        extension!(test_extension);
-        Runtime::new(RuntimeOptions {
-            extensions: vec![test_extension::init_ops_and_esm()],
+        // A promise chain that never yields to the event loop
+        let e = runtime
+            .eval::<Undefined>("(async () => { while (true) await null; })()")
+            .unwrap_err();
+        assert!(matches!(e, Error::MicrotaskLimitExceeded(1000)), "{e}");
+
+        // The runtime is still usable, and the budget is per turn - yielding to the event loop resets it
+        let value: usize = runtime
+            .eval(
+                "
+            (async () => {
+                let i = 0;
+                for (; i < 5000; i++) {
+                    if (i % 500 === 0) await new Promise(r => setTimeout(r, 1));
+                    await null;
+                }
+                return i;
+            })()
+        ",
+            )
+            .unwrap();
+        assert_eq!(value, 5000);
+
+        // An overrun by an async call is reported, and cleared, by that call
+        let tokio = runtime.tokio_runtime();
+        let e = tokio
+            .block_on(
+                runtime.eval_async::<Undefined>("(async () => { while (true) await null; })()"),
+            )
+            .unwrap_err();
+        assert!(matches!(e, Error::MicrotaskLimitExceeded(1000)), "{e}");
+
+        let value: usize = runtime.eval("1 + 1").unwrap();
+        assert_eq!(value, 2);
+    }
+
+    #[test]
+    fn test_env_vars() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            env_vars: [("LANG".to_string(), "en_US".to_string())].into(),
             ..Default::default()
         })
-        .expect("Could not create runtime with extensions");
+        .expect("Could not create the runtime");
+
+        let lang: String = runtime.eval("Deno.env.get('LANG')").unwrap();
+        assert_eq!(lang, "en_US");
+
+        let missing: bool = runtime.eval("Deno.env.get('PATH') === undefined").unwrap();
+        assert!(missing);
+
+        let vars: std::collections::HashMap<String, String> =
+            runtime.eval("Deno.env.toObject()").unwrap();
+        assert_eq!(vars.len(), 1);
+
+        runtime
+            .eval::<Undefined>("Deno.env.set('LANG', 'fr_FR')")
+            .expect_err("Environment should be read-only");
     }
 
     #[test]
-    fn test_get_value() {
+    fn test_call_function_raw() {
         let module = Module::new(
             "test.js",
             "
-            globalThis.a = 2;
-            export const b = 'test';
-            export const fnc = null;
+            class Account {
+                #balance = 0;
+                deposit(n) { this.#balance += n; return this; }
+                get balance() { return this.#balance; }
+            }
+            export const open = () => new Account();
+            export const deposit = (account, n) => account.deposit(n);
+            export const balance = (account) => account.balance;
+            export const same = (a, b) => a === b;
         ",
         );
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let handle = runtime.load_module(&module).unwrap();
 
-        let mut runtime =
-            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
-        let module = runtime
-            .load_modules(&module, vec![])
-            .expect("Could not load module");
+        let account = runtime
+            .call_function_raw(Some(&handle), "open", json_args!())
+            .unwrap();
+        let returned = runtime
+            .call_function_raw(Some(&handle), "deposit", json_args!(&account, 10))
+            .unwrap();
+        runtime
+            .call_function_raw(Some(&handle), "deposit", json_args!(&returned, 5))
+            .unwrap();
+
+        // The same object is passed through each call
+        let same: bool = runtime
+            .call_function(Some(&handle), "same", json_args!(&account, &returned))
+            .unwrap();
+        assert!(same);
+
+        let balance: usize = runtime
+            .call_function(Some(&handle), "balance", json_args!(&account))
+            .unwrap();
+        assert_eq!(balance, 15);
+
+        // Handles cannot be passed to another runtime, or serialized any other way
+        let mut other = Runtime::new(RuntimeOptions::default()).unwrap();
+        let other_handle = other.load_module(&module).unwrap();
+        other
+            .call_function::<usize>(Some(&other_handle), "balance", json_args!(&account))
+            .expect_err("Passed a value to the wrong runtime");
+        deno_core::serde_json::to_string(&account).expect_err("Serialized a value with serde_json");
+    }
+
+    #[test]
+    fn test_take_last_exception() {
+        let module = Module::new(
+            "test.js",
+            "
+            export function fail() {
+                throw Object.assign(new TypeError('bad input'), { code: 42, details: { field: 'x' } });
+            }
+            export async function reject() {
+                throw { code: 7 };
+            }
+            export function ok() {}
+        ",
+        );
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let handle = runtime.load_module(&module).unwrap();
+        assert!(runtime.take_last_exception().is_none());
+
+        runtime
+            .call_function::<Undefined>(Some(&handle), "fail", json_args!())
+            .unwrap_err();
+        let thrown = runtime.take_last_exception().unwrap();
+        assert_eq!(thrown["code"], 42);
+        assert_eq!(thrown["details"]["field"], "x");
+        assert_eq!(thrown["name"], "TypeError");
+        assert_eq!(thrown["message"], "bad input");
+
+        // Cleared once taken
+        assert!(runtime.take_last_exception().is_none());
+
+        runtime
+            .call_function::<Undefined>(Some(&handle), "reject", json_args!())
+            .unwrap_err();
+        let thrown = runtime.take_last_exception().unwrap();
+        assert_eq!(thrown, crate::serde_json::json!({ "code": 7 }));
+
+        runtime
+            .call_function::<Undefined>(Some(&handle), "ok", json_args!())
+            .unwrap();
+        assert!(runtime.take_last_exception().is_none());
+
+        // A call that succeeds clears the value left by the one before it
+        runtime
+            .call_function::<Undefined>(Some(&handle), "fail", json_args!())
+            .unwrap_err();
+        runtime
+            .call_function::<Undefined>(Some(&handle), "ok", json_args!())
+            .unwrap();
+        assert!(runtime.take_last_exception().is_none());
+
+        // Evaluated code and loaded modules are recorded too
+        runtime
+            .eval::<Undefined>("throw { from: 'eval' }")
+            .unwrap_err();
+        let thrown = runtime.take_last_exception().unwrap();
+        assert_eq!(thrown["from"], "eval");
+
+        let module = Module::new("broken.js", "throw { from: 'module' };");
+        runtime.load_module(&module).unwrap_err();
+        let thrown = runtime.take_last_exception().unwrap();
+        assert_eq!(thrown["from"], "module");
+    }
+
+    #[test]
+    fn test_function_paths() {
+        let mut runtime = Runtime::new(Default::default()).unwrap();
+        runtime
+            .register_function("myapi.db.query", |args| Ok(args[0].clone()))
+            .unwrap();
+        runtime
+            .register_async_function("myapi.db.fetch", |args| {
+                Box::pin(async move { Ok(args[0].clone()) })
+            })
+            .unwrap();
+        runtime
+            .register_function("myapi.version", |_| Ok(serde_json::json!(2)))
+            .unwrap();
+
+        let value: Vec<usize> = runtime
+            .eval(
+                "(async () => [myapi.db.query(1), await myapi.db.fetch(2), myapi.version(), rustyscript.functions['myapi.db.query'](4)])()",
+            )
+            .unwrap();
+        assert_eq!(value, vec![1, 2, 2, 4]);
+
+        let keys: Vec<String> = runtime.eval("Object.keys(myapi.db)").unwrap();
+        assert_eq!(keys, vec!["query", "fetch"]);
+
+        // Re-registering a function replaces it
+        runtime
+            .register_function("myapi.version", |_| Ok(serde_json::json!(3)))
+            .unwrap();
+        let value: usize = runtime.eval("myapi.version()").unwrap();
+        assert_eq!(value, 3);
+
+        // A function cannot be extended into a namespace, or the reverse
+        let e = runtime
+            .register_function("myapi.version.major", |_| Ok(serde_json::Value::Null))
+            .unwrap_err();
+        assert!(
+            e.to_string()
+                .contains("myapi.version is already a registered function"),
+            "{e}"
+        );
+        let e = runtime
+            .register_function("myapi.db", |_| Ok(serde_json::Value::Null))
+            .unwrap_err();
+        assert!(
+            e.to_string().contains("myapi.db is already a namespace"),
+            "{e}"
+        );
+
+        // Nor can existing globals be replaced
+        let e = runtime
+            .register_function("Math.square", |_| Ok(serde_json::Value::Null))
+            .unwrap_err();
+        assert!(e.to_string().contains("Math already exists"), "{e}");
+        runtime
+            .register_function("a..b", |_| Ok(serde_json::Value::Null))
+            .unwrap_err();
+
+        // Or under a configured root
+        let mut runtime = Runtime::new(RuntimeOptions {
+            function_namespace_root: Some("host".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        runtime
+            .register_function("myapi.db.query", |args| Ok(args[0].clone()))
+            .unwrap();
+        let value: usize = runtime
+            .eval("typeof myapi === 'undefined' ? host.myapi.db.query(5) : 0")
+            .unwrap();
+        assert_eq!(value, 5);
+    }
+
+    #[test]
+    fn test_register_error_class() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime.register_error_class("NetworkError").unwrap();
+        runtime
+            .register_error_class("not valid")
+            .expect_err("Class names must be identifiers");
+
+        runtime
+            .register_function("fail", |args| {
+                let class = args[0].as_str().unwrap_or_default().to_string();
+                Err(Error::js_class(class, "connection refused"))
+            })
+            .unwrap();
+        runtime
+            .register_async_function("fail_async", |_| {
+                Box::pin(async { Err(Error::js_class("NetworkError", "timed out")) })
+            })
+            .unwrap();
 
+        let caught: Vec<serde_json::Value> = runtime
+            .eval(
+                "
+            try { rustyscript.functions.fail('NetworkError') }
+            catch (e) { [e instanceof NetworkError, e instanceof Error, e.name, e.message] }
+        ",
+            )
+            .unwrap();
         assert_eq!(
-            2,
-            runtime
-                .get_value::<usize>(Some(&module), "a")
-                .expect("Could not find global")
+            caught,
+            vec![
+                true.into(),
+                true.into(),
+                "NetworkError".into(),
+                "connection refused".into()
+            ]
         );
+
+        let caught: Vec<serde_json::Value> = runtime
+            .eval(
+                "rustyscript.async_functions.fail_async().catch(e => [e instanceof NetworkError, e.message])",
+            )
+            .unwrap();
+        assert_eq!(caught, vec![true.into(), "timed out".into()]);
+
+        // Unregistered classes are thrown as plain errors
+        let caught: Vec<serde_json::Value> = runtime
+            .eval(
+                "
+            try { rustyscript.functions.fail('UnknownError') }
+            catch (e) { [e.constructor === Error, e.message] }
+        ",
+            )
+            .unwrap();
         assert_eq!(
-            "test",
-            runtime
-                .get_value::<String>(Some(&module), "b")
-                .expect("Could not find export")
+            caught,
+            vec![true.into(), "UnknownError: connection refused".into()]
         );
+
+        // Errors that escape to rust keep the class name
+        let e = runtime
+            .eval::<Undefined>("rustyscript.functions.fail('NetworkError')")
+            .unwrap_err();
+        let details = e.js_error_details().unwrap();
+        assert_eq!(details.name.as_deref(), Some("NetworkError"));
+    }
+
+    #[test]
+    fn test_v8_flags() {
+        // v8 is initialized by the first runtime, so flags cannot be changed afterwards
+        Runtime::new(RuntimeOptions::default()).unwrap();
+        let e = Runtime::new(RuntimeOptions {
+            v8_flags: vec!["--max-lazy".to_string()],
+            ..Default::default()
+        })
+        .err()
+        .expect("Flags should be refused once v8 is initialized");
+        assert!(e.to_string().contains("process-global"), "{e}");
+
+        // Runtimes without flags are unaffected
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let value: usize = runtime.eval("1 + 1").unwrap();
+        assert_eq!(value, 2);
+    }
+
+    #[test]
+    fn test_max_timers() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            max_timers: Some(2),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let thrown: usize = runtime
+            .eval(
+                "
+            globalThis.fired = 0;
+            globalThis.a = setInterval(() => {}, 1000);
+            setTimeout(() => { globalThis.fired++; }, 0);
+            let thrown = 0;
+            try { setTimeout(() => {}, 0); } catch (e) { if (e instanceof RangeError) thrown++; }
+            try { setInterval(() => {}, 0); } catch (e) { if (e instanceof RangeError) thrown++; }
+            thrown
+        ",
+            )
+            .unwrap();
+        assert_eq!(thrown, 2);
+
+        // Clearing a timer frees its slot
+        let id: usize = runtime
+            .eval("clearInterval(globalThis.a); setInterval(() => {}, 1000)")
+            .unwrap();
         runtime
-            .get_value::<Undefined>(Some(&module), "c")
-            .expect_err("Could not detect null");
+            .eval::<Undefined>(&format!("clearInterval({id})"))
+            .unwrap();
+
+        // So does a timeout firing
+        let fired: usize = runtime
+            .eval("new Promise(r => setTimeout(() => r(globalThis.fired), 10))")
+            .unwrap();
+        assert_eq!(fired, 1);
+
+        let ids: Vec<usize> = runtime
+            .eval("[setTimeout(() => {}, 10000), setTimeout(() => {}, 10000)]")
+            .unwrap();
         runtime
-            .get_value::<Undefined>(Some(&module), "d")
-            .expect_err("Could not detect undeclared");
+            .eval::<usize>("setTimeout(() => {}, 0)")
+            .expect_err("The limit should be reached again");
+
+        // Timers queued without going through the globals are counted as well
+        runtime
+            .eval::<usize>("Deno.core.queueUserTimer(0, false, 0, () => {})")
+            .expect_err("The limit applies to the core timer functions");
+        runtime
+            .eval::<usize>("Deno.core.ops.op_timer_queue(0, false, 0, () => {})")
+            .expect_err("The limit applies to the timer op");
+        runtime
+            .eval::<Undefined>(&format!("{ids:?}.forEach(clearTimeout)"))
+            .unwrap();
+        let id: usize = runtime
+            .eval("Deno.core.queueUserTimer(0, true, 10000, () => {})")
+            .unwrap();
+        runtime
+            .eval::<Undefined>(&format!("Deno.core.cancelTimer({id})"))
+            .unwrap();
     }
 
     #[test]
-    fn test_load_module() {
-        let mut runtime =
-            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+    fn test_max_timers_fake_time() {
+        let clock = crate::FakeClock::default();
+        let mut runtime = Runtime::new(RuntimeOptions {
+            max_timers: Some(1),
+            fake_time: Some(clock.clone()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        runtime.eval::<usize>("setTimeout(() => {}, 1000)").unwrap();
+        runtime
+            .eval::<usize>("setInterval(() => {}, 1000)")
+            .expect_err("Timers on the fake clock are counted");
+
+        // Firing frees the slot
+        clock.advance(Duration::from_secs(1));
+        runtime
+            .block_on_event_loop(Default::default(), None)
+            .unwrap();
+        runtime.eval::<usize>("setTimeout(() => {}, 1000)").unwrap();
+    }
+
+    #[test]
+    fn test_node_compat() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let defined: bool = runtime
+            .eval("typeof process !== 'undefined' || typeof Buffer !== 'undefined'")
+            .unwrap();
+        assert!(!defined);
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            env_vars: [("MODE".to_string(), "test".to_string())].into(),
+            node_compat: true,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let mode: String = runtime.eval("process.env.MODE").unwrap();
+        assert_eq!(mode, "test");
+        let missing: bool = runtime.eval("process.env.PATH === undefined").unwrap();
+        assert!(missing);
+
+        let is_global: bool = runtime.eval("global === globalThis").unwrap();
+        assert!(is_global);
+
+        let encoded: String = runtime
+            .eval("Buffer.from('héllo').toString('base64')")
+            .unwrap();
+        assert_eq!(encoded, "aMOpbGxv");
+
+        let decoded: String = runtime
+            .eval("Buffer.from('aMOpbGxv', 'base64').toString()")
+            .unwrap();
+        assert_eq!(decoded, "héllo");
+
+        let hex: String = runtime
+            .eval("Buffer.concat([Buffer.from([1, 2]), Buffer.from('ff', 'hex')]).toString('hex')")
+            .unwrap();
+        assert_eq!(hex, "0102ff");
+
+        runtime
+            .eval::<Undefined>("process.exit(1)")
+            .expect_err("process.exit should not be supported");
+    }
+
+    #[test]
+    fn test_require_shim() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let defined: bool = runtime.eval("typeof require !== 'undefined'").unwrap();
+        assert!(!defined);
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            require_shim: true,
+            ..Default::default()
+        })
+        .unwrap();
+        runtime
+            .load_module(&Module::new(
+                "lib/math.js",
+                "export const double = (n) => n * 2; export default 'math';",
+            ))
+            .unwrap();
+
+        // By filename, path, or with the extension left out
+        let value: usize = runtime.eval("require('lib/math.js').double(2)").unwrap();
+        assert_eq!(value, 4);
+        let value: usize = runtime.eval("require('./lib/math').double(3)").unwrap();
+        assert_eq!(value, 6);
+        let name: String = runtime.eval("require('./lib/math.js').default").unwrap();
+        assert_eq!(name, "math");
+
+        // Legacy scripts can use it as well
         let module = Module::new(
-            "test.js",
+            "legacy.js",
             "
-            export default () => 2;
+            const { double } = require('./lib/math');
+            export const value = double(5);
         ",
         );
-        let module = runtime
-            .load_modules(&module, vec![])
-            .expect("Could not load module");
-        assert_ne!(0, module.id());
+        let handle = runtime.load_module(&module).unwrap();
+        let value: usize = runtime.get_value(Some(&handle), "value").unwrap();
+        assert_eq!(value, 10);
 
-        let mut runtime =
-            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
-        let module1 = Module::new(
-            "importme.js",
+        // Modules that were not loaded ahead of time cannot be required
+        let e = runtime
+            .eval::<Undefined>("require('./lib/missing.js')")
+            .unwrap_err();
+        assert!(e.to_string().contains("already loaded"), "{e}");
+    }
+
+    #[test]
+    fn test_execute_module_with_teardown() {
+        let module = Module::new(
+            "test.js",
             "
-            export const value = 2;
+            export default (fail) => {
+                globalThis.opened = true;
+                if (fail) throw new Error('entry failed');
+                return 2;
+            };
+            export function teardown() {
+                if (!globalThis.opened) throw new Error('nothing to close');
+            }
         ",
         );
-        let module2 = Module::new(
-            "test.js",
+        let value: usize = Runtime::execute_module_with_teardown(
+            &module,
+            vec![],
+            Default::default(),
+            json_args!(false),
+        )
+        .unwrap();
+        assert_eq!(value, 2);
+
+        let e = Runtime::execute_module_with_teardown::<usize>(
+            &module,
+            vec![],
+            Default::default(),
+            json_args!(true),
+        )
+        .unwrap_err();
+        assert!(e.to_string().contains("entry failed"));
+
+        let failing = Module::new(
+            "failing.js",
             "
-            import { value } from './importme.js';
-            rustyscript.register_entrypoint(() => value);
+            export default () => { throw new Error('entry failed'); };
+            export function teardown() { throw new Error('teardown failed'); }
         ",
         );
-        runtime
-            .load_module(&module1)
-            .expect("Could not load modules");
-        let module = runtime
-            .load_module(&module2)
-            .expect("Could not load modules");
+        let e = Runtime::execute_module_with_teardown::<usize>(
+            &failing,
+            vec![],
+            Default::default(),
+            json_args!(),
+        )
+        .unwrap_err();
+        let Error::TeardownFailed(primary, teardown) = e else {
+            panic!("Expected a chained teardown error, got {e}");
+        };
+        assert!(primary.to_string().contains("entry failed"));
+        assert!(teardown.to_string().contains("teardown failed"));
+
+        // No teardown export
+        let plain = Module::new("plain.js", "export default () => 3;");
+        let value: usize =
+            Runtime::execute_module_with_teardown(&plain, vec![], Default::default(), json_args!())
+                .unwrap();
+        assert_eq!(value, 3);
+    }
+
+    #[test]
+    fn test_call_function_arg_list() {
+        let module = Module::new(
+            "test.js",
+            "export const sum = (...args) => args.reduce((a, b) => a + b, 0);",
+        );
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let handle = runtime.load_module(&module).unwrap();
+
+        let args: Vec<crate::serde_json::Value> =
+            (1..=100).map(crate::serde_json::Value::from).collect();
         let value: usize = runtime
-            .call_entrypoint(&module, json_args!())
-            .expect("Could not call exported fn");
-        assert_eq!(2, value);
+            .call_function(Some(&handle), "sum", &args[..])
+            .unwrap();
+        assert_eq!(value, 5050);
+
+        let value: usize = runtime.call_function(Some(&handle), "sum", &args).unwrap();
+        assert_eq!(value, 5050);
+
+        let empty: Vec<crate::serde_json::Value> = vec![];
+        let value: usize = runtime.call_function(Some(&handle), "sum", &empty).unwrap();
+        assert_eq!(value, 0);
+    }
+
+    #[cfg(feature = "typescript")]
+    #[test]
+    fn test_dry_run() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime
+            .register_module_lazy(&Module::new(
+                "market/lib.ts",
+                "
+                globalThis.libRan = true;
+                export * from './util.js';
+                export const lib: number = 1;
+            ",
+            ))
+            .unwrap();
+        runtime
+            .register_module_lazy(&Module::new(
+                "market/util.js",
+                "export function util() {} export default 5;",
+            ))
+            .unwrap();
+        runtime
+            .register_module_lazy(&Module::new("market/broken.js", "export const = ;"))
+            .unwrap();
 
-        let mut runtime = Runtime::new(RuntimeOptions {
-            timeout: Duration::from_millis(50),
-            ..Default::default()
-        })
-        .expect("Could not create the runtime");
         let module = Module::new(
-            "test.js",
+            "market/upload.js",
             "
-            await new Promise(r => setTimeout(r, 2000));
+            import { lib } from './lib.ts';
+            import './broken.js';
+            import './missing.js';
+            globalThis.uploadRan = true;
+            export * from './lib.ts';
+            export const { a, b: [c] } = { a: 1, b: [2] };
+            export default function main() { return lib; }
         ",
         );
+        let report = runtime.dry_run(&module).unwrap();
+
+        let unresolved: Vec<_> = report
+            .unresolved_imports
+            .iter()
+            .map(|i| i.specifier.as_str())
+            .collect();
+        assert_eq!(unresolved, vec!["./broken.js", "./missing.js"]);
+        assert_eq!(report.modules.len(), 2);
+
+        assert!(report.has_entrypoint());
+        for name in ["a", "c", "lib", "util"] {
+            assert!(report.exports.iter().any(|e| e == name), "{name}");
+        }
+        assert_eq!(report.missing_exports(&["lib", "setup"]), vec!["setup"]);
+
+        let ran: bool = runtime
+            .eval("globalThis.libRan === true || globalThis.uploadRan === true")
+            .unwrap();
+        assert!(!ran);
+
         runtime
-            .load_modules(&module, vec![])
-            .expect_err("Did not interupt after timeout");
+            .dry_run(&Module::new("bad.js", "export const = ;"))
+            .expect_err("The module's own syntax errors should fail");
     }
 
     #[test]
-    fn test_load_modules() {
-        let mut runtime =
-            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
-        let module = Module::new(
-            "test.js",
-            "
-            rustyscript.register_entrypoint(() => 2);
-        ",
-        );
-        let module = runtime
-            .load_modules(&module, vec![])
-            .expect("Could not load module");
-        assert_ne!(0, module.id());
+    fn test_register_module_lazy() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime
+            .register_module_lazy(&Module::new(
+                "lazy/used.js",
+                "globalThis.usedRan = true; export const x = 5;",
+            ))
+            .unwrap();
+        runtime
+            .register_module_lazy(&Module::new("lazy/broken.js", "export const = ;"))
+            .expect("Errors should only surface on first use");
 
-        let mut runtime =
-            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
-        let module1 = Module::new(
-            "importme.js",
-            "
-            export const value = 2;
-        ",
-        );
-        let module2 = Module::new(
-            "test.js",
-            "
-            import { value } from './importme.js';
-            rustyscript.register_entrypoint(() => value);
-        ",
-        );
-        let module = runtime
-            .load_modules(&module2, vec![&module1])
-            .expect("Could not load modules");
-        let value: usize = runtime
-            .call_entrypoint(&module, json_args!())
-            .expect("Could not call exported fn");
-        assert_eq!(2, value);
+        let ran: bool = runtime.eval("globalThis.usedRan === true").unwrap();
+        assert!(!ran);
+
+        let handle = runtime
+            .load_module(&Module::new(
+                "main.js",
+                "import { x } from './lazy/used.js'; export const y = x * 2;",
+            ))
+            .unwrap();
+        let y: usize = runtime.get_value(Some(&handle), "y").unwrap();
+        assert_eq!(y, 10);
+        let ran: bool = runtime.eval("globalThis.usedRan === true").unwrap();
+        assert!(ran);
+
+        runtime
+            .load_module(&Module::new("other.js", "import './lazy/broken.js';"))
+            .expect_err("The broken module should fail when first imported");
+    }
 
+    #[test]
+    fn test_module_load_observer() {
+        let loaded = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let observed = loaded.clone();
         let mut runtime = Runtime::new(RuntimeOptions {
-            timeout: Duration::from_millis(50),
+            module_load_observer: Some(Box::new(
+                move |event: &crate::module_loader::ModuleLoadEvent| {
+                    let name = event
+                        .specifier
+                        .path()
+                        .rsplit('/')
+                        .next()
+                        .unwrap_or_default();
+                    observed.borrow_mut().push(name.to_string());
+                },
+            )),
             ..Default::default()
         })
-        .expect("Could not create the runtime");
-        let module = Module::new(
-            "test.js",
-            "
-            await new Promise(r => setTimeout(r, 5000));
-        ",
-        );
+        .unwrap();
+
+        // Modules given from rust are reported as well as the modules they import
         runtime
-            .load_modules(&module, vec![])
-            .expect_err("Did not interupt after timeout");
+            .register_module_lazy(&Module::new("lib.js", "export const x = 5;"))
+            .unwrap();
+        runtime
+            .load_module(&Module::new("main.js", "import { x } from './lib.js';"))
+            .unwrap();
+        runtime
+            .load_modules(&Module::new("entry.js", "export const y = 1;"), vec![])
+            .unwrap();
+
+        let mut loaded = loaded.borrow().clone();
+        loaded.sort();
+        assert_eq!(loaded, vec!["entry.js", "lib.js", "main.js"]);
     }
 
     #[test]
-    fn test_call_entrypoint() {
-        let mut runtime =
-            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+    fn test_load_module_with_completion() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+
+        // Script-mode modules complete with their last expression, and run in strict mode
         let module = Module::new(
-            "test.js",
-            "
-            rustyscript.register_entrypoint(() => 2);
-        ",
+            "compute.js",
+            "var base = 40; function add(n) { return base + n; } add(2);",
         );
-        let module = runtime
-            .load_modules(&module, vec![])
-            .expect("Could not load module");
-        let value: usize = runtime
-            .call_entrypoint(&module, json_args!())
-            .expect("Could not call registered fn");
-        assert_eq!(2, value);
+        let (handle, value): (_, usize) = runtime.load_module_with_completion(&module).unwrap();
+        assert_eq!(value, 42);
+        assert_eq!(handle.module().contents(), module.contents());
+        let module = Module::new(
+            "strict.js",
+            "(function () { return this; })() === undefined",
+        );
+        let (_, strict): (_, bool) = runtime.load_module_with_completion(&module).unwrap();
+        assert!(strict);
+
+        // Their declarations do not leak into the globals
+        let leaked: bool = runtime
+            .eval("typeof base !== 'undefined' || typeof add !== 'undefined'")
+            .unwrap();
+        assert!(!leaked);
+
+        // Promises are resolved
+        let module = Module::new("async.js", "Promise.resolve('done')");
+        let (_, value): (_, String) = runtime.load_module_with_completion(&module).unwrap();
+        assert_eq!(value, "done");
+
+        // Typescript is transpiled first
+        #[cfg(feature = "typescript")]
+        {
+            let module = Module::new("typed.ts", "const n: number = 5; n * 2");
+            let (_, value): (_, usize) = runtime.load_module_with_completion(&module).unwrap();
+            assert_eq!(value, 10);
+        }
+
+        // ECMAScript modules complete with undefined, but still load normally
+        let module = Module::new("esm.js", "export const value = 3; value + 1;");
+        let (handle, value): (_, Option<usize>) =
+            runtime.load_module_with_completion(&module).unwrap();
+        assert_eq!(value, None);
+        let exported: usize = runtime.get_value(Some(&handle), "value").unwrap();
+        assert_eq!(exported, 3);
+
+        // Errors are reported as usual
+        let module = Module::new("fail.js", "throw new Error('nope')");
+        runtime
+            .load_module_with_completion::<Undefined>(&module)
+            .unwrap_err();
 
+        // The completion value does not go through the global object, so frozen globals are not a problem
         let mut runtime = Runtime::new(RuntimeOptions {
-            default_entrypoint: Some("load".to_string()),
+            freeze_globals: true,
             ..Default::default()
         })
-        .expect("Could not create the runtime");
+        .unwrap();
+        let module = Module::new("compute.js", "const base = 40; base + 2;");
+        let (_, value): (_, usize) = runtime.load_module_with_completion(&module).unwrap();
+        assert_eq!(value, 42);
+    }
+
+    #[cfg(all(feature = "archive", feature = "typescript"))]
+    #[test]
+    fn test_load_archive() {
+        let archive = crate::module_loader::archive::test::tar(&[
+            (
+                "plugin/src/main.js",
+                "import { helper } from './lib/helper.ts'; export const value = helper();",
+            ),
+            (
+                "plugin/src/lib/helper.ts",
+                "import data from '../../data/values.json' with { type: 'json' };
+                export const helper = (): number => data.base * 2;",
+            ),
+            ("plugin/data/values.json", r#"{ "base": 21 }"#),
+            ("plugin/notes.txt", "ignored"),
+        ]);
+
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        let handle = runtime
+            .load_archive(&archive, "plugin/src/main.js")
+            .unwrap();
+        let value: usize = runtime.get_value(Some(&handle), "value").unwrap();
+        assert_eq!(value, 42);
+
+        // A second copy of the same archive does not clash with the first
+        let handle = runtime
+            .load_archive(&archive, "./plugin/src/main.js")
+            .unwrap();
+        let value: usize = runtime.get_value(Some(&handle), "value").unwrap();
+        assert_eq!(value, 42);
+
+        let e = runtime
+            .load_archive(&archive, "plugin/missing.js")
+            .unwrap_err();
+        assert!(matches!(e, Error::ModuleNotFound(_)));
+
+        runtime
+            .load_archive(&archive, "plugin/notes.txt")
+            .expect_err("Non-module entries are not extracted");
+    }
+
+    #[cfg(all(feature = "archive", feature = "fs_import"))]
+    #[test]
+    fn test_load_archive_escape() {
+        // Imports cannot escape the archive, even to a file that exists
+        let dir = std::env::temp_dir().join("rustyscript_test_archive_escape");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("outside.js"), "export const value = 1;").unwrap();
+        let archive = crate::module_loader::archive::test::tar(&[(
+            "main.js",
+            "export { value } from '../outside.js';",
+        )]);
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+        runtime.set_current_dir(&dir).unwrap();
+        let e = runtime.load_archive(&archive, "main.js").unwrap_err();
+        assert!(e.to_string().contains("outside of the archive"), "{e}");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_fail_on_console_error() {
+        let mut runtime = crate::RuntimeBuilder::new()
+            .with_fail_on_console_error()
+            .build()
+            .expect("Could not create the runtime");
+
+        let value: usize = runtime.eval("console.log('fine'); 1 + 1").unwrap();
+        assert_eq!(value, 2);
+
+        let e = runtime
+            .eval::<usize>("console.error('bad', 1); console.warn({ a: 1 }); 5")
+            .unwrap_err();
+        let Error::ConsoleError(messages) = e else {
+            panic!("Expected a console error, got {e}");
+        };
+        let lines: Vec<&str> = messages.lines().collect();
+        assert_eq!(lines[0], "console.error: bad 1");
+        assert!(lines[1].starts_with("console.warn: "), "{messages}");
+        assert!(lines[1].contains("a: 1"), "{messages}");
+
+        // Messages are only reported once
+        let value: usize = runtime.eval("1 + 1").unwrap();
+        assert_eq!(value, 2);
+
+        // And only by the call that wrote them - not by the next blocking call
         let module = Module::new(
-            "test.js",
+            "logs.js",
             "
-            export const load = () => 2;
+            export function warn() { console.warn('later'); }
+            console.error('loading');
         ",
         );
-        let module = runtime
-            .load_modules(&module, vec![])
-            .expect("Could not load module");
-        let value: usize = runtime
-            .call_entrypoint(&module, json_args!())
-            .expect("Could not call exported fn");
-        assert_eq!(2, value);
+        let e = runtime.load_module(&module).unwrap_err();
+        assert!(matches!(e, Error::ConsoleError(_)), "{e}");
+        let handle = runtime
+            .get_module_handle("logs.js")
+            .expect("The module should stay loaded");
 
-        let mut runtime =
-            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
+        let tokio = runtime.tokio_runtime();
+        tokio
+            .block_on(runtime.call_function_async::<Undefined>(Some(&handle), "warn", json_args!()))
+            .unwrap();
+        let value: usize = runtime.eval("1 + 1").unwrap();
+        assert_eq!(value, 2);
+    }
+
+    #[test]
+    fn test_map_set_deserialize() {
+        use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+        #[derive(serde::Deserialize)]
+        struct Counted {
+            value: BTreeSet<u32>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct Sparse {
+            keep: BTreeSet<u32>,
+        }
+
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
         let module = Module::new(
             "test.js",
             "
-            export const load = () => 2;
+            export const scores = new Map([['a', new Map([[1, 'x'], [2, 'y']])], ['b', new Map()]]);
+            export const tags = new Set(['red', 'green', 'red']);
+            export const nested = { items: [new Set([3, 1, 2])], plain: { a: 1 } };
+            export const echo = (value) => value;
+            export const colliding = new Map([[1, 1], ['1', 2]]);
+
+            let readCount = 0;
+            export const counted = { get value() { readCount++; return new Set([1]); } };
+            export const reads = () => readCount;
+
+            const big = [];
+            big[4_000_000_000] = new Set([5]);
+            export const sparse = { keep: new Set([4]), big };
         ",
         );
-        let module = runtime
-            .load_modules(&module, vec![])
-            .expect("Could not load module");
-        runtime
-            .call_entrypoint::<Undefined>(&module, json_args!())
-            .expect_err("Did not detect no entrypoint");
+        let handle = runtime.load_module(&module).unwrap();
+
+        let mut get = |name: &str| -> crate::js_value::Value {
+            runtime.get_value(Some(&handle), name).unwrap()
+        };
+        let (scores, tags, nested) = (get("scores"), get("tags"), get("nested"));
+        let (colliding, counted, sparse) = (get("colliding"), get("counted"), get("sparse"));
+
+        let scores: HashMap<String, BTreeMap<u32, String>> =
+            scores.try_into_collections(&mut runtime).unwrap();
+        assert_eq!(scores["a"][&1], "x");
+        assert_eq!(scores["a"][&2], "y");
+        assert!(scores["b"].is_empty());
+
+        let tags: HashSet<String> = tags.try_into_collections(&mut runtime).unwrap();
+        assert_eq!(
+            tags,
+            HashSet::from(["red".to_string(), "green".to_string()])
+        );
+
+        let nested: crate::serde_json::Value = nested.try_into_collections(&mut runtime).unwrap();
+        let items: Vec<BTreeSet<u32>> =
+            crate::serde_json::from_value(nested["items"].clone()).unwrap();
+        assert_eq!(items[0], BTreeSet::from([1, 2, 3]));
+        assert_eq!(nested["plain"]["a"], 1);
+
+        // Keys that would become the same property are rejected instead of overwriting each other
+        colliding
+            .try_into_collections::<HashMap<String, u32>>(&mut runtime)
+            .unwrap_err();
+
+        // Getters run once, and sparse arrays are not filled in
+        let counted: Counted = counted.try_into_collections(&mut runtime).unwrap();
+        assert_eq!(counted.value, BTreeSet::from([1]));
+        let reads: u32 = runtime
+            .call_function(Some(&handle), "reads", json_args!())
+            .unwrap();
+        assert_eq!(reads, 1);
+
+        let sparse: Sparse = sparse.try_into_collections(&mut runtime).unwrap();
+        assert_eq!(sparse.keep, BTreeSet::from([4]));
+
+        // Rust maps and sets are passed back to javascript as plain objects and arrays
+        let echoed: HashMap<String, BTreeMap<u32, String>> = runtime
+            .call_function(Some(&handle), "echo", &(scores.clone(),))
+            .unwrap();
+        assert_eq!(echoed, scores);
+
+        // Values from another runtime are refused instead of being walked in this one
+        let mut other = Runtime::new(RuntimeOptions::default()).unwrap();
+        let foreign: crate::js_value::Value = other.eval("new Set([1])").unwrap();
+        foreign
+            .try_into_collections::<BTreeSet<u32>>(&mut runtime)
+            .unwrap_err();
     }
 
     #[test]
-    fn test_execute_module() {
+    fn test_on_timeout() {
+        let timed_out = std::rc::Rc::new(std::cell::RefCell::new(None));
+        let seen = timed_out.clone();
+        let mut runtime = crate::RuntimeBuilder::new()
+            .with_timeout(Duration::from_millis(50))
+            .with_name("slow")
+            .with_on_timeout(move |info| *seen.borrow_mut() = Some(info.to_string()))
+            .build()
+            .expect("Could not create the runtime");
+
+        let value: usize = runtime.eval("1 + 1").unwrap();
+        assert_eq!(value, 2);
+        assert!(timed_out.borrow().is_none());
+
+        let e = runtime
+            .eval::<usize>("new Promise((r) => setTimeout(() => r(1), 2000))")
+            .unwrap_err();
+        assert!(matches!(e, Error::Timeout(_)), "{e}");
+        assert_eq!(timed_out.borrow().clone(), Some(runtime.info().to_string()));
+    }
+
+    #[cfg(feature = "typescript")]
+    #[test]
+    fn test_transpile_options() {
+        let mut runtime = crate::RuntimeBuilder::new()
+            .with_transpile_options(crate::TranspileOptions {
+                experimental_decorators: true,
+                jsx: crate::JsxMode::Classic {
+                    factory: "h".to_string(),
+                    fragment_factory: "Fragment".to_string(),
+                },
+                ..Default::default()
+            })
+            .build()
+            .expect("Could not create the runtime");
+
         let module = Module::new(
-            "test.js",
+            "test.tsx",
             "
-            rustyscript.register_entrypoint(() => 2);
+            const h = (tag: string, props: any, ...children: any[]) => ({ tag, children });
+            const Fragment = 'fragment';
+
+            function sealed(target: any) { target.sealed = true; }
+
+            @sealed
+            class Widget {}
+
+            export const element = <div><span /></div>;
+            export const fragment = (<></>).tag;
+            export const isSealed = (Widget as any).sealed;
         ",
         );
-        let value: usize =
-            Runtime::execute_module(&module, vec![], RuntimeOptions::default(), json_args!())
-                .expect("Could not exec module");
-        assert_eq!(2, value);
+        let handle = runtime.load_module(&module).expect("Could not load module");
+
+        let element: crate::serde_json::Value =
+            runtime.get_value(Some(&handle), "element").unwrap();
+        assert_eq!(element["tag"], "div");
+        assert_eq!(element["children"][0]["tag"], "span");
 
+        let fragment: String = runtime.get_value(Some(&handle), "fragment").unwrap();
+        assert_eq!(fragment, "fragment");
+
+        let sealed: bool = runtime.get_value(Some(&handle), "isSealed").unwrap();
+        assert!(sealed);
+    }
+
+    #[test]
+    fn test_has_function() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
         let module = Module::new(
             "test.js",
             "
-            function load() { return 2; }
+            globalThis.global_fn = () => 1;
+            export function onLoad() {}
+            export const value = 5;
+            export let empty;
         ",
         );
-        Runtime::execute_module::<Undefined>(
-            &module,
-            vec![],
-            RuntimeOptions::default(),
-            json_args!(),
-        )
-        .expect_err("Could not detect no entrypoint");
+        let handle = runtime.load_module(&module).unwrap();
+
+        assert!(runtime.has_function(Some(&handle), "onLoad"));
+        assert!(runtime.has_function(Some(&handle), "global_fn"));
+        assert!(runtime.has_function(None, "global_fn"));
+        assert!(!runtime.has_function(Some(&handle), "value"));
+        assert!(!runtime.has_function(Some(&handle), "onUnload"));
+
+        assert!(runtime.has_export(&handle, "onLoad"));
+        assert!(runtime.has_export(&handle, "empty"));
+        assert!(!runtime.has_export(&handle, "global_fn"));
     }
 
     #[test]
-    fn call_function() {
-        let module = Module::new(
-            "test.js",
-            "
-            globalThis.fna = (i) => i;
-            export function fnb() { return 'test'; }
-            export const fnc = 2;
-            export const fne = () => {};
+    fn test_tokio_handle() {
+        // Blocking calls work from inside a multi-threaded runtime, driven on its handle
+        let tokio = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let value: i64 = tokio.block_on(async {
+            let mut runtime = crate::RuntimeBuilder::new()
+                .with_tokio_handle(tokio::runtime::Handle::current())
+                .build()
+                .expect("Could not create the runtime");
+
+            // There is no tokio runtime to give out - only the handle
+            runtime
+                .try_tokio_runtime()
+                .expect_err("The runtime is not owned");
+            runtime
+                .eval("new Promise((resolve) => resolve(2 + 3))")
+                .expect("Could not eval")
+        });
+        assert_eq!(value, 5);
+
+        // The timeout still applies on a single-threaded runtime that nothing is driving
+        let tokio = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let mut runtime = crate::RuntimeBuilder::new()
+            .with_tokio_handle(tokio.handle().clone())
+            .with_timeout(Duration::from_millis(50))
+            .build()
+            .expect("Could not create the runtime");
+        let e = runtime
+            .eval::<Undefined>("new Promise((resolve) => setTimeout(resolve, 60_000))")
+            .unwrap_err();
+        assert!(matches!(e, Error::Timeout(_)), "{e}");
+
+        // From inside a single-threaded runtime it is an error, instead of a panic
+        let tokio = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        tokio.block_on(async {
+            let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+            runtime
+                .eval::<i64>("1 + 1")
+                .expect_err("Blocking should have been refused");
+            assert_eq!(runtime.eval_async::<i64>("1 + 1").await.unwrap(), 2);
+        });
+    }
+
+    #[test]
+    fn test_performance() {
+        let mut runtime = Runtime::new(RuntimeOptions::default()).unwrap();
+
+        // Sub-millisecond resolution, as a float
+        let fractional: bool = runtime
+            .eval(
+                "
+            let fractional = false;
+            for (let i = 0; i < 10000 && !fractional; i++) {
+                fractional = performance.now() % 1 !== 0;
+            }
+            fractional
         ",
-        );
+            )
+            .unwrap();
+        assert!(fractional);
 
-        let mut runtime =
-            Runtime::new(RuntimeOptions::default()).expect("Could not create the runtime");
-        let module = runtime
-            .load_modules(&module, vec![])
-            .expect("Could not load module");
+        let (elapsed, entries): (f64, Vec<String>) = runtime
+            .eval(
+                "
+            performance.mark('start');
+            new Promise(r => setTimeout(r, 20)).then(() => {
+                performance.mark('end');
+                const measure = performance.measure('wait', 'start', 'end');
+                return [
+                    measure.duration,
+                    performance.getEntries().map(e => `${e.entryType}:${e.name}`),
+                ];
+            })
+        ",
+            )
+            .unwrap();
+        assert!(elapsed >= 15.0, "{elapsed}");
+        assert_eq!(entries, ["mark:start", "mark:end", "measure:wait"]);
 
-        let result: usize = runtime
-            .call_function(Some(&module), "fna", json_args!(2))
-            .expect("Could not call global");
-        assert_eq!(2, result);
+        let remaining: usize = runtime
+            .eval("performance.clearMarks(); performance.getEntries().length")
+            .unwrap();
+        assert_eq!(remaining, 1);
+        runtime
+            .eval::<Undefined>("performance.measure('bad', 'missing')")
+            .expect_err("Measuring from a missing mark should throw");
 
-        let result: String = runtime
-            .call_function(Some(&module), "fnb", json_args!())
-            .expect("Could not call export");
-        assert_eq!("test", result);
+        // Marks from the stub follow the fake clock - deno_web keeps its own clock for them
+        #[cfg(not(feature = "web"))]
+        {
+            let clock = crate::FakeClock::new(std::time::UNIX_EPOCH);
+            let mut runtime = Runtime::new(RuntimeOptions {
+                fake_time: Some(clock.clone()),
+                ..Default::default()
+            })
+            .unwrap();
+            runtime.eval::<Undefined>("performance.mark('a')").unwrap();
+            clock.advance(Duration::from_millis(250));
+            let duration: f64 = runtime
+                .eval("performance.mark('b'); performance.measure('ab', 'a', 'b').duration")
+                .unwrap();
+            assert!((duration - 250.0).abs() < f64::EPSILON, "{duration}");
+        }
+    }
+
+    #[test]
+    fn test_fake_time() {
+        let clock = crate::FakeClock::new(std::time::UNIX_EPOCH + Duration::from_secs(1000));
+        let mut runtime = Runtime::new(RuntimeOptions {
+            fake_time: Some(clock.clone()),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        let now: f64 = runtime.eval("Date.now()").unwrap();
+        assert_eq!(now, 1_000_000.0);
+        let now: f64 = runtime.eval("new Date().getTime()").unwrap();
+        assert_eq!(now, 1_000_000.0);
+        let is_date: bool = runtime.eval("new Date() instanceof Date").unwrap();
+        assert!(is_date);
 
         runtime
-            .call_function::<Undefined>(Some(&module), "fnc", json_args!())
-            .expect_err("Did not detect non-function");
+            .eval::<Undefined>(
+                "
+                globalThis.fired = [];
+                setTimeout(() => fired.push('timeout'), 1000);
+                globalThis.interval = setInterval(() => fired.push('interval'), 400);
+                const cancelled = setTimeout(() => fired.push('cancelled'), 500);
+                clearTimeout(cancelled);
+            ",
+            )
+            .unwrap();
+
+        // Nothing fires until the clock moves
         runtime
-            .call_function::<Undefined>(Some(&module), "fnd", json_args!())
-            .expect_err("Did not detect undefined");
+            .block_on_event_loop(Default::default(), None)
+            .unwrap();
+        let fired: Vec<String> = runtime.eval("fired").unwrap();
+        assert!(fired.is_empty());
+
+        clock.advance(Duration::from_millis(900));
         runtime
-            .call_function::<Undefined>(Some(&module), "fne", json_args!())
-            .expect("Did not allow undefined return");
+            .block_on_event_loop(Default::default(), None)
+            .unwrap();
+        let fired: Vec<String> = runtime.eval("fired").unwrap();
+        assert_eq!(fired, vec!["interval", "interval"]);
+
+        clock.advance(Duration::from_millis(100));
+        runtime
+            .block_on_event_loop(Default::default(), None)
+            .unwrap();
+        let fired: Vec<String> = runtime.eval("clearInterval(interval), fired").unwrap();
+        assert_eq!(fired, vec!["interval", "interval", "timeout"]);
+
+        // Cleared timers are removed from the clock
+        assert_eq!(0, clock.pending());
+        runtime
+            .eval::<Undefined>("clearTimeout(setTimeout(() => {}, 1_000_000))")
+            .unwrap();
+        assert_eq!(0, clock.pending());
+
+        let now: f64 = runtime.eval("Date.now()").unwrap();
+        assert_eq!(now, 1_001_000.0);
+    }
+
+    #[test]
+    fn test_auto_recover() {
+        fn run(auto_recover: bool) -> Result<(usize, usize), Error> {
+            let mut runtime = Runtime::new(RuntimeOptions {
+                auto_recover,
+                ..Default::default()
+            })
+            .expect("Could not create the runtime");
+
+            // Terminate the isolate on the first call only
+            let handle = runtime.deno_runtime().v8_isolate().thread_safe_handle();
+            let calls = std::rc::Rc::new(std::cell::Cell::new(0));
+            runtime
+                .register_function("kill", move |_| {
+                    calls.set(calls.get() + 1);
+                    if calls.get() == 1 {
+                        handle.terminate_execution();
+                    }
+                    Ok(serde_json::Value::Null)
+                })
+                .expect("Could not register function");
+
+            let module = Module::new(
+                "test.js",
+                "
+                let count = 0;
+                export function f() {
+                    count++;
+                    rustyscript.functions.kill();
+                    let n = 0;
+                    for (let i = 0; i < 1000; i++) n += 1;
+                    return count;
+                }
+            ",
+            );
+            let module = runtime.load_module(&module).expect("Could not load module");
+            let first = runtime.call_function(Some(&module), "f", json_args!())?;
+            let second = runtime.call_function(Some(&module), "f", json_args!())?;
+            Ok((first, second))
+        }
+
+        // The retry runs against a fresh instance of the module, which later calls keep using
+        assert_eq!((1, 2), run(true).expect("Did not recover from termination"));
+        run(false).expect_err("Recovered without auto_recover");
+
+        // Limits are not retried
+        let mut runtime = Runtime::new(RuntimeOptions {
+            auto_recover: true,
+            timeout: Duration::from_secs(10),
+            cpu_time_limit: Some(Duration::from_millis(50)),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "
+            export let calls = 0;
+            export function spin() { calls++; while (true) {} }
+            export const getCalls = () => calls;
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+        let e = runtime
+            .call_function::<Undefined>(Some(&module), "spin", json_args!())
+            .unwrap_err();
+        assert!(matches!(e, Error::CpuTimeExceeded(_)), "{e}");
+        let calls: usize = runtime
+            .call_function(Some(&module), "getCalls", json_args!())
+            .unwrap();
+        assert_eq!(1, calls);
     }
 
     #[test]
@@ -1,5 +1,8 @@
 use crate::module_loader::ImportProvider;
-use crate::{Error, RuntimeOptions};
+use crate::{Error, RsAsyncFunction, RsFunction, RuntimeOptions};
+
+/// A function registration deferred until the runtime is built
+type PendingRegistration = Box<dyn FnOnce(&mut crate::Runtime) -> Result<(), Error>>;
 
 /// A builder for creating a new runtime
 ///
@@ -9,19 +12,57 @@ use crate::{Error, RuntimeOptions};
 /// ```rust
 /// use rustyscript::RuntimeBuilder;
 ///
-/// let runtime = RuntimeBuilder::new()
+/// let mut runtime = RuntimeBuilder::new()
 ///     .with_timeout(std::time::Duration::from_secs(5))
 ///     .with_default_entrypoint("main".to_string())
 ///     .with_cryto_seed(42)
+///     .with_function("add", |args| {
+///         let a = args[0].as_i64().unwrap_or_default();
+///         let b = args[1].as_i64().unwrap_or_default();
+///         Ok((a + b).into())
+///     })
 ///     .build()
 ///     .expect("Failed to create runtime");
+///
+/// let sum: i64 = runtime.eval("rustyscript.functions.add(2, 3)").expect("Failed to call function");
+/// assert_eq!(sum, 5);
 /// ```
-pub struct RuntimeBuilder(RuntimeOptions);
+pub struct RuntimeBuilder(RuntimeOptions, Vec<PendingRegistration>);
 impl RuntimeBuilder {
     /// Create a new runtime builder with default options
     #[must_use]
     pub fn new() -> Self {
-        Self(RuntimeOptions::default())
+        Self(RuntimeOptions::default(), Vec::new())
+    }
+
+    /// Register a rust function to be callable from JS, once the runtime is built
+    ///
+    /// See [`crate::Runtime::register_function`]  
+    /// Functions are only registered by [`RuntimeBuilder::build`]
+    #[must_use]
+    pub fn with_function(mut self, name: impl ToString, callback: impl RsFunction) -> Self {
+        let name = name.to_string();
+        self.1.push(Box::new(move |runtime| {
+            runtime.register_function(&name, callback)
+        }));
+        self
+    }
+
+    /// Register a non-blocking rust function to be callable from JS, once the runtime is built
+    ///
+    /// See [`crate::Runtime::register_async_function`]  
+    /// Functions are only registered by [`RuntimeBuilder::build`]
+    #[must_use]
+    pub fn with_async_function(
+        mut self,
+        name: impl ToString,
+        callback: impl RsAsyncFunction,
+    ) -> Self {
+        let name = name.to_string();
+        self.1.push(Box::new(move |runtime| {
+            runtime.register_async_function(&name, callback)
+        }));
+        self
     }
 
     /// Add an extension to the runtime
@@ -46,6 +87,15 @@ impl RuntimeBuilder {
         self
     }
 
+    /// Add an individual `#[op2]` function to the runtime, such as `.with_op(op_double())`
+    ///
+    /// See [`crate::RuntimeOptions::ops`]
+    #[must_use]
+    pub fn with_op(mut self, op: deno_core::OpDecl) -> Self {
+        self.0.ops.push(op);
+        self
+    }
+
     /// Set the default entrypoint for the runtime
     ///
     /// This is the function to use as entrypoint if a module does not provide one
@@ -64,6 +114,38 @@ impl RuntimeBuilder {
         self
     }
 
+    /// Limit the CPU time each blocking call may spend executing javascript
+    ///
+    /// See [`crate::RuntimeOptions::cpu_time_limit`]
+    #[must_use]
+    pub fn with_cpu_time_limit(mut self, limit: std::time::Duration) -> Self {
+        self.0.cpu_time_limit = Some(limit);
+        self
+    }
+
+    /// Callback invoked when a call exceeds the timeout, before the call is abandoned
+    ///
+    /// See [`crate::RuntimeOptions::on_timeout`]
+    #[must_use]
+    pub fn with_on_timeout(mut self, hook: impl Fn(&crate::RuntimeInfo) + 'static) -> Self {
+        self.0.on_timeout = Some(std::sync::Arc::new(hook));
+        self
+    }
+
+    /// Callback invoked every `interval` while a blocking call executes javascript, which can abort the call
+    ///
+    /// See [`crate::RuntimeOptions::progress_callback`]
+    #[must_use]
+    pub fn with_progress_callback(
+        mut self,
+        interval: std::time::Duration,
+        callback: impl Fn(std::time::Duration) -> crate::ProgressSignal + Send + Sync + 'static,
+    ) -> Self {
+        self.0.progress_interval = interval;
+        self.0.progress_callback = Some(std::sync::Arc::new(callback));
+        self
+    }
+
     /// Optional maximum heap size for the runtime
     #[must_use]
     pub fn with_max_heap_size(mut self, max_heap_size: usize) -> Self {
@@ -71,6 +153,132 @@ impl RuntimeBuilder {
         self
     }
 
+    /// Optional maximum size, in bytes, of the stack used by javascript
+    ///
+    /// See [`crate::RuntimeOptions::max_stack_size`] - the thread running the runtime must have a large enough stack
+    #[must_use]
+    pub fn with_max_stack_size(mut self, max_stack_size: usize) -> Self {
+        self.0.max_stack_size = Some(max_stack_size);
+        self
+    }
+
+    /// Optional maximum size, in UTF-8 bytes, of strings returned from javascript
+    #[must_use]
+    pub fn with_max_string_bytes(mut self, max_string_bytes: usize) -> Self {
+        self.0.max_string_bytes = Some(max_string_bytes);
+        self
+    }
+
+    /// Optional maximum number of pending `setTimeout` and `setInterval` timers
+    ///
+    /// See [`crate::RuntimeOptions::max_timers`]
+    #[must_use]
+    pub fn with_max_timers(mut self, max_timers: usize) -> Self {
+        self.0.max_timers = Some(max_timers);
+        self
+    }
+
+    /// Optional maximum number of promise reactions run in a single turn of the event loop
+    ///
+    /// See [`crate::RuntimeOptions::microtask_limit`]
+    #[must_use]
+    pub fn with_microtask_limit(mut self, limit: usize) -> Self {
+        self.0.microtask_limit = Some(limit);
+        self
+    }
+
+    /// Dotted path under which functions registered with a dotted name, such as `myapi.db.query`, are placed
+    ///
+    /// See [`crate::RuntimeOptions::function_namespace_root`]
+    #[must_use]
+    pub fn with_function_namespace_root(mut self, root: &str) -> Self {
+        self.0.function_namespace_root = Some(root.to_string());
+        self
+    }
+
+    /// Name the runtime, to tell it apart from others in logs
+    ///
+    /// See [`crate::RuntimeOptions::name`]
+    #[must_use]
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.0.name = Some(name.to_string());
+        self
+    }
+
+    /// Seed `Math.random` and `crypto.getRandomValues`, making them deterministic
+    ///
+    /// **For testing only** - see [`crate::RuntimeOptions::rng_seed`]
+    #[must_use]
+    pub fn with_rng_seed(mut self, seed: u64) -> Self {
+        self.0.rng_seed = Some(seed);
+        self
+    }
+
+    /// Start an inspector server, allowing a debugger such as Chrome DevTools to attach to the runtime
+    ///
+    /// See [`crate::RuntimeOptions::inspector`]
+    #[cfg(feature = "inspector")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "inspector")))]
+    #[must_use]
+    pub fn with_inspector(mut self, options: crate::InspectorOptions) -> Self {
+        self.0.inspector = Some(options);
+        self
+    }
+
+    /// Expose an environment variable to javascript through `Deno.env`
+    ///
+    /// See [`crate::RuntimeOptions::env_vars`]
+    #[must_use]
+    pub fn with_env_var(mut self, key: &str, value: &str) -> Self {
+        self.0.env_vars.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Replace real time with a manually controlled clock
+    ///
+    /// See [`crate::RuntimeOptions::fake_time`]
+    #[must_use]
+    pub fn with_fake_time(mut self, clock: crate::FakeClock) -> Self {
+        self.0.fake_time = Some(clock);
+        self
+    }
+
+    /// Drive the runtime on an existing tokio runtime, instead of creating a new one
+    ///
+    /// See [`crate::RuntimeOptions::tokio_handle`]
+    #[must_use]
+    pub fn with_tokio_handle(mut self, handle: tokio::runtime::Handle) -> Self {
+        self.0.tokio_handle = Some(handle);
+        self
+    }
+
+    /// Run blocking calls inside the given local set, so ops can use `tokio::task::spawn_local`
+    ///
+    /// See [`crate::RuntimeOptions::tokio_local_set`]
+    #[must_use]
+    pub fn with_tokio_local_set(mut self, local_set: std::rc::Rc<tokio::task::LocalSet>) -> Self {
+        self.0.tokio_local_set = Some(local_set);
+        self
+    }
+
+    /// Optional maximum size, in bytes, of values returned from javascript
+    ///
+    /// See [`crate::RuntimeOptions::max_result_size`]
+    #[must_use]
+    pub fn with_max_result_size(mut self, max_result_size: usize) -> Self {
+        self.0.max_result_size = Some(max_result_size);
+        self
+    }
+
+    /// Optional maximum number of module sources kept by the module loader
+    ///
+    /// See [`crate::RuntimeOptions::source_cache_limit`]
+    #[must_use]
+    pub fn with_source_cache_limit(mut self, limit: usize) -> Self {
+        self.0.source_cache_limit = Some(limit);
+        self
+    }
+
     /// Optional import provider for the module loader
     #[must_use]
     pub fn with_import_provider(mut self, import_provider: Box<dyn ImportProvider>) -> Self {
@@ -123,6 +331,270 @@ impl RuntimeBuilder {
         self
     }
 
+    /// Add an import alias, in the style of tsconfig `paths`
+    ///
+    /// See [`RuntimeOptions::path_aliases`] for details on how aliases are matched
+    #[must_use]
+    pub fn with_path_alias(
+        mut self,
+        alias: impl ToString,
+        target: impl Into<std::path::PathBuf>,
+    ) -> Self {
+        self.0.path_aliases.insert(alias.to_string(), target.into());
+        self
+    }
+
+    /// Treat file paths that differ only by case as the same module
+    ///
+    /// See [`RuntimeOptions::case_insensitive_module_paths`]
+    #[must_use]
+    pub fn with_case_insensitive_module_paths(mut self) -> Self {
+        self.0.case_insensitive_module_paths = true;
+        self
+    }
+
+    /// Set a Deno-style import map, consulted before any other module resolution
+    ///
+    /// See [`crate::module_loader::parse_import_map`] to create one from JSON
+    #[must_use]
+    pub fn with_import_map(mut self, import_map: crate::module_loader::ImportMap) -> Self {
+        self.0.import_map = Some(import_map);
+        self
+    }
+
+    /// Add a header to send with every module imported over HTTP (`url_import` crate feature)
+    ///
+    /// Sensitive headers like `Authorization` are dropped on a redirect to a different host
+    #[cfg(feature = "url_import")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "url_import")))]
+    #[must_use]
+    pub fn with_url_import_header(mut self, name: impl ToString, value: impl ToString) -> Self {
+        self.0
+            .url_import_headers
+            .insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Set the user agent sent with every module imported over HTTP (`url_import` crate feature)
+    #[cfg(feature = "url_import")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "url_import")))]
+    #[must_use]
+    pub fn with_url_import_user_agent(mut self, user_agent: impl ToString) -> Self {
+        self.0.url_import_user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// Root certificate store used to verify modules imported over HTTPS
+    ///
+    /// See [`crate::RuntimeOptions::url_import_root_cert_store_provider`]
+    #[cfg(feature = "url_import")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "url_import")))]
+    #[must_use]
+    pub fn with_url_import_root_cert_store_provider(
+        mut self,
+        root_cert_store_provider: std::sync::Arc<dyn deno_tls::RootCertStoreProvider>,
+    ) -> Self {
+        self.0.url_import_root_cert_store_provider = Some(root_cert_store_provider);
+        self
+    }
+
+    /// Domain name or IP address for which TLS errors are ignored when importing modules over HTTPS
+    ///
+    /// This is useful for testing with self-signed certificates
+    ///
+    /// See [`crate::RuntimeOptions::url_import_unsafely_ignore_certificate_errors`]
+    #[cfg(feature = "url_import")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "url_import")))]
+    #[must_use]
+    pub fn with_url_import_unsafely_ignored_certificate_errors(
+        mut self,
+        domain: impl ToString,
+    ) -> Self {
+        self.0
+            .url_import_unsafely_ignore_certificate_errors
+            .get_or_insert_with(Vec::new)
+            .push(domain.to_string());
+        self
+    }
+
+    /// Root certificate store used to verify every TLS connection the runtime makes
+    ///
+    /// Applies to modules imported over HTTPS (`url_import` crate feature), as well as `fetch`, sockets
+    /// and websockets (`web` or `net` crate features) - equivalent to setting each individually
+    #[cfg(any(feature = "url_import", feature = "web", feature = "net"))]
+    #[cfg_attr(
+        docsrs,
+        doc(cfg(any(feature = "url_import", feature = "web", feature = "net")))
+    )]
+    #[must_use]
+    pub fn with_root_cert_store_provider(
+        mut self,
+        root_cert_store_provider: std::sync::Arc<dyn deno_tls::RootCertStoreProvider>,
+    ) -> Self {
+        #[cfg(feature = "url_import")]
+        {
+            self.0.url_import_root_cert_store_provider = Some(root_cert_store_provider.clone());
+        }
+
+        #[cfg(any(feature = "web", feature = "net"))]
+        {
+            self.0.extension_options.web.root_cert_store_provider = Some(root_cert_store_provider);
+        }
+
+        self
+    }
+
+    /// Domain name or IP address for which TLS errors are ignored by every TLS connection the runtime makes
+    ///
+    /// Applies to modules imported over HTTPS (`url_import` crate feature), as well as `fetch`, sockets
+    /// and websockets (`web` or `net` crate features)  
+    /// This is useful for testing with self-signed certificates
+    #[cfg(any(feature = "url_import", feature = "web", feature = "net"))]
+    #[cfg_attr(
+        docsrs,
+        doc(cfg(any(feature = "url_import", feature = "web", feature = "net")))
+    )]
+    #[must_use]
+    pub fn with_unsafely_ignored_certificate_errors(mut self, domain: impl ToString) -> Self {
+        let domain = domain.to_string();
+
+        #[cfg(feature = "url_import")]
+        {
+            self = self.with_url_import_unsafely_ignored_certificate_errors(domain.as_str());
+        }
+
+        #[cfg(any(feature = "web", feature = "net"))]
+        {
+            self = self.with_web_unsafely_ignored_certificate_errors(&domain);
+        }
+
+        self
+    }
+
+    /// Pass a flag to v8, such as `--harmony-temporal`
+    ///
+    /// **v8 flags are process-global** - see [`crate::RuntimeOptions::v8_flags`] for the implications
+    #[must_use]
+    pub fn with_v8_flag(mut self, flag: impl ToString) -> Self {
+        self.0.v8_flags.push(flag.to_string());
+        self
+    }
+
+    /// Set the maximum depth to which calls into registered functions may be nested
+    ///
+    /// See [`crate::RuntimeOptions::max_call_depth`]
+    #[must_use]
+    pub fn with_max_call_depth(mut self, depth: usize) -> Self {
+        self.0.max_call_depth = Some(depth);
+        self
+    }
+
+    /// Apply a saved [`crate::RuntimeProfile`], overwriting the plain-data options it covers
+    #[must_use]
+    pub fn with_profile(mut self, profile: crate::RuntimeProfile) -> Self {
+        self.0.apply_profile(profile);
+        self
+    }
+
+    /// Set a callback to observe each module load - its specifier, duration, size, and whether it was a cache hit
+    ///
+    /// Useful for progress reporting or profiling - it does not affect how modules are loaded
+    #[must_use]
+    pub fn with_module_load_observer(
+        mut self,
+        observer: impl Fn(&crate::module_loader::ModuleLoadEvent) + 'static,
+    ) -> Self {
+        self.0.module_load_observer = Some(Box::new(observer));
+        self
+    }
+
+    /// Set a callback observing, and allowing or denying, each dynamic `import()` made by a running script
+    ///
+    /// See [`crate::RuntimeOptions::dynamic_import_hook`]
+    #[must_use]
+    pub fn with_dynamic_import_hook(
+        mut self,
+        hook: impl Fn(&crate::module_loader::DynamicImportEvent) -> bool + 'static,
+    ) -> Self {
+        self.0.dynamic_import_hook = Some(Box::new(hook));
+        self
+    }
+
+    /// Rewrite the source of every module loaded, before it is transpiled
+    ///
+    /// See [`crate::RuntimeOptions::source_transform`]
+    #[must_use]
+    pub fn with_source_transform(
+        mut self,
+        transform: impl Fn(&str, &str) -> String + 'static,
+    ) -> Self {
+        self.0.source_transform = Some(std::sync::Arc::new(transform));
+        self
+    }
+
+    /// Set the options used to transpile TypeScript, JSX and TSX modules
+    ///
+    /// See [`crate::RuntimeOptions::transpile_options`]
+    #[must_use]
+    pub fn with_transpile_options(mut self, options: crate::TranspileOptions) -> Self {
+        self.0.transpile_options = options;
+        self
+    }
+
+    /// Reject modules that need transpiling, such as TypeScript, instead of transpiling them
+    ///
+    /// See [`crate::RuntimeOptions::transpile`] - plain javascript modules are not affected
+    #[must_use]
+    pub fn without_transpile(mut self) -> Self {
+        self.0.transpile = false;
+        self
+    }
+
+    /// Recursively freeze the intrinsics and `globalThis` once the first set of modules has been loaded
+    ///
+    /// See [`RuntimeOptions::freeze_globals`] for details on what is frozen
+    #[must_use]
+    pub fn with_frozen_globals(mut self) -> Self {
+        self.0.freeze_globals = true;
+        self
+    }
+
+    /// Retry blocking calls once if they fail because the isolate's execution was terminated
+    ///
+    /// See [`RuntimeOptions::auto_recover`] for details
+    #[must_use]
+    pub fn with_auto_recover(mut self) -> Self {
+        self.0.auto_recover = true;
+        self
+    }
+
+    /// Fail calls that write to `console.error` or `console.warn`
+    ///
+    /// See [`RuntimeOptions::fail_on_console_error`] for details
+    #[must_use]
+    pub fn with_fail_on_console_error(mut self) -> Self {
+        self.0.fail_on_console_error = true;
+        self
+    }
+
+    /// Define minimal versions of the node globals `process`, `Buffer` and `global`
+    ///
+    /// See [`RuntimeOptions::node_compat`] for details
+    #[must_use]
+    pub fn with_node_compat(mut self) -> Self {
+        self.0.node_compat = true;
+        self
+    }
+
+    /// Define a global `require` for CommonJS-style scripts, over the modules already loaded into the runtime
+    ///
+    /// See [`RuntimeOptions::require_shim`] for its limitations
+    #[must_use]
+    pub fn with_require_shim(mut self) -> Self {
+        self.0.require_shim = true;
+        self
+    }
+
     //
     // Extension options
     //
@@ -197,9 +669,18 @@ impl RuntimeBuilder {
     // Web options
     //
 
+    /// Replace all of the options for the web related extensions at once
+    #[cfg(any(feature = "web", feature = "net"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "web", feature = "net"))))]
+    #[must_use]
+    pub fn with_web_options(mut self, options: crate::WebOptions) -> Self {
+        self.0.extension_options.web = options;
+        self
+    }
+
     /// Base URL for some `deno_web` OPs
-    #[cfg(feature = "web")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+    #[cfg(any(feature = "web", feature = "net"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "web", feature = "net"))))]
     #[must_use]
     pub fn with_web_base_url(mut self, base_url: deno_core::ModuleSpecifier) -> Self {
         self.0.extension_options.web.base_url = Some(base_url);
@@ -207,8 +688,8 @@ impl RuntimeBuilder {
     }
 
     /// User agent to use for fetch
-    #[cfg(feature = "web")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+    #[cfg(any(feature = "web", feature = "net"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "web", feature = "net"))))]
     #[must_use]
     pub fn with_web_user_agent(mut self, user_agent: String) -> Self {
         self.0.extension_options.web.user_agent = user_agent;
@@ -216,8 +697,8 @@ impl RuntimeBuilder {
     }
 
     /// Root certificate store for TLS connections for fetches and network OPs
-    #[cfg(feature = "web")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+    #[cfg(any(feature = "web", feature = "net"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "web", feature = "net"))))]
     #[must_use]
     pub fn with_web_root_cert_store_provider(
         mut self,
@@ -228,8 +709,8 @@ impl RuntimeBuilder {
     }
 
     /// Proxy for fetch
-    #[cfg(feature = "web")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+    #[cfg(any(feature = "web", feature = "net"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "web", feature = "net"))))]
     #[must_use]
     pub fn with_web_proxy(mut self, proxy: deno_tls::Proxy) -> Self {
         self.0.extension_options.web.proxy = Some(proxy);
@@ -251,8 +732,8 @@ impl RuntimeBuilder {
     /// List of domain names or IP addresses for which fetches and network OPs will ignore SSL errors
     ///
     /// This is useful for testing with self-signed certificates
-    #[cfg(feature = "web")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+    #[cfg(any(feature = "web", feature = "net"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "web", feature = "net"))))]
     #[must_use]
     pub fn with_web_unsafely_ignored_certificate_errors(mut self, domain: impl ToString) -> Self {
         match &mut self
@@ -274,8 +755,8 @@ impl RuntimeBuilder {
     }
 
     /// Client certificate and key for fetch
-    #[cfg(feature = "web")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+    #[cfg(any(feature = "web", feature = "net"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "web", feature = "net"))))]
     #[must_use]
     pub fn with_web_client_cert_chain_and_key(mut self, keys: deno_tls::TlsKeys) -> Self {
         self.0.extension_options.web.client_cert_chain_and_key = keys;
@@ -295,8 +776,8 @@ impl RuntimeBuilder {
     }
 
     /// Permissions manager for sandbox-breaking extensions
-    #[cfg(feature = "web")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+    #[cfg(any(feature = "web", feature = "net"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "web", feature = "net"))))]
     #[must_use]
     pub fn with_web_permissions(
         mut self,
@@ -306,9 +787,24 @@ impl RuntimeBuilder {
         self
     }
 
+    /// Callback asked the first time a script accesses each net, fs, env or sys resource
+    ///
+    /// Return `true` to allow the access - decisions are cached per resource
+    /// Allowed accesses are still checked against the permissions manager set with [`Self::with_web_permissions`]
+    #[cfg(any(feature = "web", feature = "net"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "web", feature = "net"))))]
+    #[must_use]
+    pub fn with_permission_handler(
+        mut self,
+        handler: impl Fn(crate::ext::web::PermissionRequest) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.0.extension_options.web.permission_handler = Some(std::sync::Arc::new(handler));
+        self
+    }
+
     /// Blob store for the web related extensions
-    #[cfg(feature = "web")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+    #[cfg(any(feature = "web", feature = "net"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "web", feature = "net"))))]
     #[must_use]
     pub fn with_web_blob_store(mut self, blob_store: std::sync::Arc<deno_web::BlobStore>) -> Self {
         self.0.extension_options.web.blob_store = blob_store;
@@ -343,13 +839,20 @@ impl RuntimeBuilder {
     /// Consume the builder and create a new runtime with the given options
     ///
     /// # Errors
-    /// Will return an error if the runtime cannot be created (usually an issue with extensions)
+    /// Will return an error if the runtime cannot be created (usually an issue with extensions),
+    /// or if a function cannot be registered
     pub fn build(self) -> Result<crate::Runtime, Error> {
-        crate::Runtime::new(self.0)
+        let mut runtime = crate::Runtime::new(self.0)?;
+        for register in self.1 {
+            register(&mut runtime)?;
+        }
+        Ok(runtime)
     }
 
     /// Consume the builder and create a new snapshot runtime with the given options
     ///
+    /// Functions added with [`RuntimeBuilder::with_function`] are not registered in snapshots
+    ///
     /// # Errors
     /// Will return an error if the runtime cannot be created (usually an issue with extensions)
     #[cfg(feature = "snapshot_builder")]
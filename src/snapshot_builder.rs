@@ -1,5 +1,5 @@
 use crate::{
-    async_bridge::{AsyncBridge, AsyncBridgeExt},
+    async_bridge::{AsyncBridge, AsyncBridgeExt, BridgeSettings},
     inner_runtime::{InnerRuntime, RuntimeOptions},
     Error, Module, ModuleHandle,
 };
@@ -48,6 +48,9 @@ use tokio_util::sync::CancellationToken;
 pub struct SnapshotBuilder {
     inner: InnerRuntime<deno_core::JsRuntimeForSnapshot>,
     tokio: AsyncBridge,
+
+    /// Extensions and ops added by the options, recorded in snapshot files
+    extensions: Vec<&'static str>,
 }
 impl SnapshotBuilder {
     /// Creates a new instance of the runtime with the provided options.
@@ -90,9 +93,16 @@ impl SnapshotBuilder {
     /// Or if the deno runtime initialization fails (usually issues with extensions)
     ///
     pub fn new(options: RuntimeOptions) -> Result<Self, Error> {
-        let tokio = AsyncBridge::new(options.timeout)?;
-        let inner = InnerRuntime::new(options, tokio.heap_exhausted_token())?;
-        Ok(Self { inner, tokio })
+        let settings = BridgeSettings::new(&options);
+        let extensions = crate::snapshot_file::extension_names(&options);
+        let mut tokio = AsyncBridge::for_options(settings.timeout(), &options)?;
+        let mut inner = InnerRuntime::new(options, tokio.heap_exhausted_token())?;
+        tokio.configure(settings, &mut inner);
+        Ok(Self {
+            inner,
+            tokio,
+            extensions,
+        })
     }
 
     /// Creates a new instance of the runtime with the provided options and a pre-configured tokio runtime.
@@ -104,9 +114,17 @@ impl SnapshotBuilder {
         options: RuntimeOptions,
         tokio: Rc<tokio::runtime::Runtime>,
     ) -> Result<Self, Error> {
-        let tokio = AsyncBridge::with_tokio_runtime(options.timeout, tokio);
-        let inner = InnerRuntime::new(options, tokio.heap_exhausted_token())?;
-        Ok(Self { inner, tokio })
+        let settings = BridgeSettings::new(&options);
+        let extensions = crate::snapshot_file::extension_names(&options);
+        let mut tokio = AsyncBridge::with_tokio_runtime(settings.timeout(), tokio)
+            .with_local_set(options.tokio_local_set.clone());
+        let mut inner = InnerRuntime::new(options, tokio.heap_exhausted_token())?;
+        tokio.configure(settings, &mut inner);
+        Ok(Self {
+            inner,
+            tokio,
+            extensions,
+        })
     }
 
     /// Access the underlying deno runtime instance directly
@@ -115,9 +133,32 @@ impl SnapshotBuilder {
     }
 
     /// Access the underlying tokio runtime used for blocking operations
+    ///
+    /// Only meaningful for a runtime that owns its tokio runtime - see [`Self::try_tokio_runtime`]
+    ///
+    /// # Panics
+    /// If the runtime was created with [`crate::RuntimeOptions::tokio_handle`], since it does not own a tokio runtime
     #[must_use]
     pub fn tokio_runtime(&self) -> std::rc::Rc<tokio::runtime::Runtime> {
-        self.tokio.tokio_runtime()
+        match self.tokio.try_tokio_runtime() {
+            Ok(tokio) => tokio,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    /// Access the underlying tokio runtime used for blocking operations, if the runtime owns one
+    ///
+    /// # Errors
+    /// If the runtime was created with [`crate::RuntimeOptions::tokio_handle`], since it does not own a tokio runtime  
+    /// Use [`Self::tokio_handle`] instead
+    pub fn try_tokio_runtime(&self) -> Result<std::rc::Rc<tokio::runtime::Runtime>, Error> {
+        self.tokio.try_tokio_runtime()
+    }
+
+    /// Returns a handle to the tokio runtime used for blocking operations
+    #[must_use]
+    pub fn tokio_handle(&self) -> tokio::runtime::Handle {
+        self.tokio.tokio_handle()
     }
 
     /// Returns the timeout for the runtime
@@ -135,9 +176,26 @@ impl SnapshotBuilder {
 
     /// Destroy the v8 runtime, releasing all resources
     /// Then the internal tokio runtime will be returned
+    ///
+    /// Only meaningful for a runtime that owns its tokio runtime - see [`Self::try_into_tokio_runtime`]
+    ///
+    /// # Panics
+    /// If the runtime was created with [`crate::RuntimeOptions::tokio_handle`], since it does not own a tokio runtime
     #[must_use]
     pub fn into_tokio_runtime(self) -> Rc<tokio::runtime::Runtime> {
-        self.tokio.into_tokio_runtime()
+        match self.try_into_tokio_runtime() {
+            Ok(tokio) => tokio,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    /// Destroy the v8 runtime, releasing all resources
+    /// Then the internal tokio runtime will be returned, if the runtime owns one
+    ///
+    /// # Errors
+    /// If the runtime was created with [`crate::RuntimeOptions::tokio_handle`], since it does not own a tokio runtime
+    pub fn try_into_tokio_runtime(self) -> Result<Rc<tokio::runtime::Runtime>, Error> {
+        self.tokio.try_into_tokio_runtime()
     }
 
     /// Set the current working directory for the runtime
@@ -188,7 +246,8 @@ impl SnapshotBuilder {
         options: PollEventLoopOptions,
         timeout: Option<Duration>,
     ) -> Result<(), Error> {
-        self.inner.await_event_loop(options, timeout).await
+        let result = self.inner.await_event_loop(options, timeout).await;
+        self.inner.settle_microtask_budget(result)
     }
 
     /// Run the JS event loop to completion, or until a timeout is reached
@@ -324,9 +383,13 @@ impl SnapshotBuilder {
     where
         T: serde::de::DeserializeOwned,
     {
-        let result = self.inner.eval(expr.to_string()).await?;
-        let result = self.inner.resolve_with_event_loop(result).await?;
-        self.inner.decode_value(result)
+        let result = async {
+            let result = self.inner.eval(expr.to_string()).await?;
+            let result = self.inner.resolve_with_event_loop(result).await?;
+            self.inner.decode_value(result)
+        }
+        .await;
+        self.inner.settle_microtask_budget(result)
     }
 
     /// Evaluate a piece of non-ECMAScript-module JavaScript code  
@@ -366,8 +429,12 @@ impl SnapshotBuilder {
     where
         T: serde::de::DeserializeOwned,
     {
-        let result = self.inner.eval(expr.to_string()).await?;
-        self.inner.decode_value(result)
+        let result = async {
+            let result = self.inner.eval(expr.to_string()).await?;
+            self.inner.decode_value(result)
+        }
+        .await;
+        self.inner.settle_microtask_budget(result)
     }
 
     /// Calls a javascript function within the Deno runtime by its name and deserializes its return value.
@@ -401,12 +468,16 @@ impl SnapshotBuilder {
     where
         T: deno_core::serde::de::DeserializeOwned,
     {
-        let function = self.inner.get_function_by_name(module_context, name)?;
-        let result = self
-            .inner
-            .call_function_by_ref(module_context, &function, args)?;
-        let result = self.inner.resolve_with_event_loop(result).await?;
-        self.inner.decode_value(result)
+        let result = async {
+            let function = self.inner.get_function_by_name(module_context, name)?;
+            let result = self
+                .inner
+                .call_function_by_ref(module_context, &function, args)?;
+            let result = self.inner.resolve_with_event_loop(result).await?;
+            self.inner.decode_value(result)
+        }
+        .await;
+        self.inner.settle_microtask_budget(result)
     }
 
     /// Calls a javascript function within the Deno runtime by its name and deserializes its return value.
@@ -570,9 +641,13 @@ impl SnapshotBuilder {
     where
         T: serde::de::DeserializeOwned,
     {
-        let result = self.inner.get_value_ref(module_context, name)?;
-        let result = self.inner.resolve_with_event_loop(result).await?;
-        self.inner.decode_value(result)
+        let result = async {
+            let result = self.inner.get_value_ref(module_context, name)?;
+            let result = self.inner.resolve_with_event_loop(result).await?;
+            self.inner.decode_value(result)
+        }
+        .await;
+        self.inner.settle_microtask_budget(result)
     }
 
     /// Get a value from a runtime instance
@@ -675,7 +750,8 @@ impl SnapshotBuilder {
     ///
     /// See [`crate::Runtime::load_module`] for an example
     pub async fn load_module_async(&mut self, module: &Module) -> Result<ModuleHandle, Error> {
-        self.inner.load_modules(None, vec![module]).await
+        let result = self.inner.load_modules(None, vec![module]).await;
+        self.inner.settle_microtask_budget(result)
     }
 
     /// Executes the given module, and returns a handle allowing you to extract values
@@ -753,7 +829,8 @@ impl SnapshotBuilder {
         module: &Module,
         side_modules: Vec<&Module>,
     ) -> Result<ModuleHandle, Error> {
-        self.inner.load_modules(Some(module), side_modules).await
+        let result = self.inner.load_modules(Some(module), side_modules).await;
+        self.inner.settle_microtask_budget(result)
     }
 
     /// Executes the given module, on the runtime, making it available to be
@@ -763,6 +840,13 @@ impl SnapshotBuilder {
     /// This is a blocking operation, and will run the event loop to completion
     /// For a non-blocking variant, see [`SnapshotBuilder::load_module_async`]
     ///
+    /// The module is parsed, compiled and evaluated now, and its state is captured in the snapshot.  
+    /// Runtimes created from the snapshot can get a handle to it with [`crate::Runtime::get_module_handle`],
+    /// without loading it again
+    ///
+    /// WARNING: Any side effects in the module's code (including top-level code and `rustyscript.register_entrypoint` calls)
+    /// run once, at snapshot time - they do not run again when the snapshot is used
+    ///
     /// # Arguments
     /// * `module` - A `Module` object containing the module's filename and contents.
     ///
@@ -804,6 +888,24 @@ impl SnapshotBuilder {
         let deno_rt: JsRuntimeForSnapshot = self.inner.into_inner();
         deno_rt.snapshot()
     }
+
+    /// Consumes the runtime, and writes a snapshot of the runtime state to a file
+    ///
+    /// The file records the versions of rustyscript and v8 that created it, the extension features enabled,
+    /// and the extensions and ops in its options, so that [`crate::Runtime::from_snapshot_file`]
+    /// can refuse an incompatible snapshot instead of crashing.  
+    /// The same warnings as [`SnapshotBuilder::finish`] apply - the runtime loading it must be given the same extensions
+    ///
+    /// # Errors
+    /// Will return an error if the file cannot be written
+    pub fn finish_to_file(self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let extensions = self.extensions.clone();
+        let bytes = crate::snapshot_file::encode(&self.finish(), &extensions);
+        std::fs::write(path, bytes).map_err(|e| {
+            Error::Runtime(format!("Could not write snapshot {}: {e}", path.display()))
+        })
+    }
 }
 
 impl AsyncBridgeExt for SnapshotBuilder {
@@ -0,0 +1,128 @@
+//! The file format used by [`crate::SnapshotBuilder::finish_to_file`] and [`crate::Runtime::from_snapshot_file`]
+//!
+//! A snapshot only works with the exact build and extensions that created it - loading one from another version
+//! of rustyscript or v8, or into a runtime with different extensions, crashes the process.
+//! Snapshot files record the build and extensions they came from, so incompatible ones can be refused instead
+use crate::{Error, RuntimeOptions};
+use deno_core::v8;
+use std::{
+    collections::BTreeSet,
+    sync::{Mutex, PoisonError},
+};
+
+/// Identifies a snapshot file, and the version of the format
+const MAGIC: &[u8] = b"RSSNAP02";
+
+/// Cargo features that change the extensions built into a runtime
+const EXTENSION_FEATURES: &[(&str, bool)] = &[
+    ("node_experimental", cfg!(feature = "node_experimental")),
+    ("web_stub", cfg!(feature = "web_stub")),
+    ("encoding", cfg!(feature = "encoding")),
+    ("broadcast_channel", cfg!(feature = "broadcast_channel")),
+    ("cache", cfg!(feature = "cache")),
+    ("console", cfg!(feature = "console")),
+    ("cron", cfg!(feature = "cron")),
+    ("crypto", cfg!(feature = "crypto")),
+    ("ffi", cfg!(feature = "ffi")),
+    ("fs", cfg!(feature = "fs")),
+    ("http", cfg!(feature = "http")),
+    ("kv", cfg!(feature = "kv")),
+    ("io", cfg!(feature = "io")),
+    ("url", cfg!(feature = "url")),
+    ("web", cfg!(feature = "web")),
+    ("net", cfg!(feature = "net")),
+    ("webgpu", cfg!(feature = "webgpu")),
+    ("webidl", cfg!(feature = "webidl")),
+    ("webstorage", cfg!(feature = "webstorage")),
+    ("websocket", cfg!(feature = "websocket")),
+];
+
+/// Describes the current build, and the extensions added to the runtime - a snapshot can only be loaded by an identical one
+fn build_id(extensions: &[&str]) -> String {
+    let features: Vec<&str> = EXTENSION_FEATURES
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect();
+
+    format!(
+        "rustyscript {}, v8 {}, {}-{}, features [{}], extensions [{}]",
+        env!("CARGO_PKG_VERSION"),
+        v8::V8::get_version(),
+        std::env::consts::ARCH,
+        std::env::consts::OS,
+        features.join(", "),
+        extensions.join(", ")
+    )
+}
+
+/// Names the extensions and ops added to a runtime by its options
+/// The built-in extensions are covered by the enabled features
+pub(crate) fn extension_names(options: &RuntimeOptions) -> Vec<&'static str> {
+    options
+        .extensions
+        .iter()
+        .map(|extension| extension.name)
+        .chain(options.ops.iter().map(|op| op.name))
+        .collect()
+}
+
+/// Keeps a snapshot for the rest of the process, since the runtime requires a `'static` slice
+/// Each distinct snapshot is only kept once - loading the same one again reuses it
+pub(crate) fn intern(snapshot: &[u8]) -> &'static [u8] {
+    static SNAPSHOTS: Mutex<BTreeSet<&'static [u8]>> = Mutex::new(BTreeSet::new());
+    let mut snapshots = SNAPSHOTS.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(&snapshot) = snapshots.get(snapshot) {
+        return snapshot;
+    }
+
+    let snapshot: &'static [u8] = Box::leak(snapshot.into());
+    snapshots.insert(snapshot);
+    snapshot
+}
+
+fn incompatible(reason: impl ToString) -> Error {
+    Error::IncompatibleSnapshot(reason.to_string())
+}
+
+/// Prefixes a snapshot with a header recording the current build, and the extensions added to the runtime
+#[cfg(feature = "snapshot_builder")]
+pub(crate) fn encode(snapshot: &[u8], extensions: &[&str]) -> Vec<u8> {
+    let id = build_id(extensions);
+    let len = u32::try_from(id.len()).unwrap_or_default();
+
+    let mut bytes = Vec::with_capacity(MAGIC.len() + 4 + id.len() + snapshot.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&len.to_le_bytes());
+    bytes.extend_from_slice(id.as_bytes());
+    bytes.extend_from_slice(snapshot);
+    bytes
+}
+
+/// Checks the header of a snapshot file against the current build and the given extensions,
+/// and returns the snapshot it contains
+pub(crate) fn decode<'a>(bytes: &'a [u8], extensions: &[&str]) -> Result<&'a [u8], Error> {
+    let rest = bytes
+        .strip_prefix(MAGIC)
+        .ok_or_else(|| incompatible("not a rustyscript snapshot file"))?;
+
+    let len = rest
+        .get(..4)
+        .and_then(|len| len.try_into().ok())
+        .map(u32::from_le_bytes)
+        .ok_or_else(|| incompatible("the file is truncated"))?;
+    let (id, snapshot) = usize::try_from(len)
+        .ok()
+        .and_then(|len| Some((rest.get(4..4 + len)?, rest.get(4 + len..)?)))
+        .ok_or_else(|| incompatible("the file is truncated"))?;
+
+    let id = String::from_utf8_lossy(id);
+    let current = build_id(extensions);
+    if id != current {
+        return Err(incompatible(format!(
+            "it was created by {id}, but this is {current}"
+        )));
+    }
+
+    Ok(snapshot)
+}
@@ -3,20 +3,114 @@
 //! modules.
 //!
 //! It will only transpile, not typecheck (like Deno's `--no-check` flag).
+//!
+//! Without the `typescript` feature, javascript is passed through as-is, modules that need
+//! transpiling are an error, and the parser-based helpers are unavailable
 
-use deno_ast::MediaType;
-use deno_ast::ParseParams;
-use deno_ast::SourceTextInfo;
+#[cfg(feature = "typescript")]
+use deno_ast::swc::ast::{
+    CallExpr, Callee, Decl, ExportAll, ExportDecl, ExportDefaultDecl, ExportDefaultExpr,
+    ExportSpecifier, Expr, ImportDecl, Lit, ModuleExportName, NamedExport, ObjectPatProp, Pat,
+};
+#[cfg(feature = "typescript")]
+use deno_ast::swc::ecma_visit::{Visit, VisitWith};
+#[cfg(feature = "typescript")]
+use deno_ast::{MediaType, ParseDiagnostic, ParseParams, ParsedSource, SourceTextInfo};
 use deno_core::anyhow::Error;
 use deno_core::error::AnyError;
 use deno_core::FastString;
 use deno_core::ModuleSpecifier;
 use deno_core::SourceMapData;
+
+use crate::ModuleLanguage;
 use std::borrow::Cow;
 use std::rc::Rc;
 
 pub type ModuleContents = (String, Option<SourceMapData>);
 
+/// How JSX in `.jsx` and `.tsx` modules is transformed
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum JsxMode {
+    /// Each element becomes a call to the factory, such as `React.createElement("div", null)`
+    ///
+    /// The factory and fragment factory must be in scope in every module using JSX
+    Classic {
+        /// Function called for each element - defaults to `React.createElement`
+        factory: String,
+
+        /// Value used for `<></>` fragments - defaults to `React.Fragment`
+        fragment_factory: String,
+    },
+
+    /// Elements become calls to `jsx` functions imported automatically, from `{import_source}/jsx-runtime`
+    ///
+    /// The import source must be resolvable by the module loader, such as through [`crate::RuntimeOptions::import_map`]
+    Automatic {
+        /// Module the JSX runtime is imported from, such as `react` or `preact`
+        import_source: String,
+
+        /// Import from `jsx-dev-runtime` instead, which adds source locations to each element
+        development: bool,
+    },
+}
+
+impl Default for JsxMode {
+    fn default() -> Self {
+        Self::Classic {
+            factory: "React.createElement".to_string(),
+            fragment_factory: "React.Fragment".to_string(),
+        }
+    }
+}
+
+/// Options passed to the transpiler, for modules that need transpiling - see [`crate::RuntimeOptions::transpile_options`]
+#[derive(Debug, Clone, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TranspileOptions {
+    /// Use TypeScript's legacy `experimentalDecorators` semantics instead of leaving decorators as-is
+    pub experimental_decorators: bool,
+
+    /// Emit design-time type metadata for decorated declarations, as with TypeScript's `emitDecoratorMetadata`
+    ///
+    /// Only has an effect along with `experimental_decorators`
+    pub emit_decorator_metadata: bool,
+
+    /// How JSX is transformed - defaults to the classic `React.createElement` mode
+    pub jsx: JsxMode,
+}
+
+#[cfg(feature = "typescript")]
+impl TranspileOptions {
+    fn to_deno(&self) -> deno_ast::TranspileOptions {
+        let mut options = deno_ast::TranspileOptions {
+            use_ts_decorators: self.experimental_decorators,
+            emit_metadata: self.emit_decorator_metadata,
+            ..Default::default()
+        };
+
+        match &self.jsx {
+            JsxMode::Classic {
+                factory,
+                fragment_factory,
+            } => {
+                options.jsx_factory.clone_from(factory);
+                options.jsx_fragment_factory.clone_from(fragment_factory);
+            }
+            JsxMode::Automatic {
+                import_source,
+                development,
+            } => {
+                options.jsx_automatic = true;
+                options.jsx_development = *development;
+                options.jsx_import_source = Some(import_source.clone());
+            }
+        }
+
+        options
+    }
+}
+
+#[cfg(feature = "typescript")]
 fn should_transpile(media_type: MediaType) -> bool {
     matches!(
         media_type,
@@ -31,15 +125,91 @@ fn should_transpile(media_type: MediaType) -> bool {
     )
 }
 
+/// Determines the media type of a module, from its language hint or its specifier
+#[cfg(feature = "typescript")]
+fn media_type(module_specifier: &ModuleSpecifier, language: Option<ModuleLanguage>) -> MediaType {
+    let mut media_type = match language {
+        Some(ModuleLanguage::JavaScript) => MediaType::JavaScript,
+        Some(ModuleLanguage::TypeScript) => MediaType::TypeScript,
+        None => MediaType::from_specifier(module_specifier),
+    };
+
+    if media_type == MediaType::Unknown && module_specifier.as_str().contains("/node:") {
+        media_type = MediaType::TypeScript;
+    }
+
+    media_type
+}
+
 ///
 /// Transpiles source code from TS to JS without typechecking
 pub fn transpile(module_specifier: &ModuleSpecifier, code: &str) -> Result<ModuleContents, Error> {
-    let mut media_type = MediaType::from_specifier(module_specifier);
+    transpile_as(module_specifier, code, None, &TranspileOptions::default())
+}
 
-    if media_type == MediaType::Unknown && module_specifier.as_str().contains("/node:") {
-        media_type = MediaType::TypeScript;
+/// Returns true if a module needs transpiling before it can be loaded, from its language hint or its specifier
+#[cfg(feature = "typescript")]
+fn needs_transpiling(module_specifier: &ModuleSpecifier, language: Option<ModuleLanguage>) -> bool {
+    should_transpile(media_type(module_specifier, language))
+}
+
+/// Returns true if a module needs transpiling before it can be loaded, from its language hint or its specifier
+#[cfg(not(feature = "typescript"))]
+fn needs_transpiling(module_specifier: &ModuleSpecifier, language: Option<ModuleLanguage>) -> bool {
+    match language {
+        Some(ModuleLanguage::JavaScript) => false,
+        Some(ModuleLanguage::TypeScript) => true,
+        None => {
+            let path = module_specifier.path();
+            [".ts", ".tsx", ".jsx", ".mts", ".cts"]
+                .iter()
+                .any(|extension| path.ends_with(extension))
+        }
     }
+}
+
+/// Error for parser-based operations when the `typescript` feature is disabled
+#[cfg(not(feature = "typescript"))]
+fn parser_unavailable(what: &str) -> Error {
+    crate::Error::Runtime(format!(
+        "{what} requires the `typescript` crate feature, which is disabled"
+    ))
+    .into()
+}
+
+///
+/// Transpiles source code from TS to JS without typechecking
+/// If a language is given, it is used instead of the one inferred from the specifier
+///
+/// Without the `typescript` feature, javascript is returned unchanged and anything else is an error
+#[cfg(not(feature = "typescript"))]
+pub fn transpile_as(
+    module_specifier: &ModuleSpecifier,
+    code: &str,
+    language: Option<ModuleLanguage>,
+    _options: &TranspileOptions,
+) -> Result<ModuleContents, Error> {
+    if needs_transpiling(module_specifier, language) {
+        return Err(crate::Error::Runtime(format!(
+            "{module_specifier} must be transpiled, but rustyscript was built without the `typescript` feature"
+        ))
+        .into());
+    }
+
+    Ok((code.to_string(), None))
+}
 
+///
+/// Transpiles source code from TS to JS without typechecking
+/// If a language is given, it is used instead of the one inferred from the specifier
+#[cfg(feature = "typescript")]
+pub fn transpile_as(
+    module_specifier: &ModuleSpecifier,
+    code: &str,
+    language: Option<ModuleLanguage>,
+    options: &TranspileOptions,
+) -> Result<ModuleContents, Error> {
+    let media_type = media_type(module_specifier, language);
     let should_transpile = should_transpile(media_type);
 
     let code = if should_transpile {
@@ -54,9 +224,7 @@ pub fn transpile(module_specifier: &ModuleSpecifier, code: &str) -> Result<Modul
             maybe_syntax: None,
         })?;
 
-        let transpile_options = deno_ast::TranspileOptions {
-            ..Default::default()
-        };
+        let transpile_options = options.to_deno();
 
         let transpile_mod_options = deno_ast::TranspileModuleOptions {
             ..Default::default()
@@ -84,6 +252,271 @@ pub fn transpile(module_specifier: &ModuleSpecifier, code: &str) -> Result<Modul
     Ok(code)
 }
 
+///
+/// Transpiles source code from TS to JS if `enabled` is set, or passes it through unchanged if not
+/// When disabled, a module that would need transpiling is an error
+pub fn transpile_if(
+    module_specifier: &ModuleSpecifier,
+    code: &str,
+    language: Option<ModuleLanguage>,
+    enabled: bool,
+    options: &TranspileOptions,
+) -> Result<ModuleContents, Error> {
+    if enabled {
+        return transpile_as(module_specifier, code, language, options);
+    }
+
+    if needs_transpiling(module_specifier, language) {
+        return Err(crate::Error::Runtime(format!(
+            "{module_specifier} must be transpiled, but transpilation is disabled (see `RuntimeOptions::transpile`)"
+        ))
+        .into());
+    }
+
+    Ok((code.to_string(), None))
+}
+
+/// Parses a module for the helpers that inspect its syntax, treating a source of unknown type as javascript
+#[cfg(feature = "typescript")]
+fn parse(
+    module_specifier: &ModuleSpecifier,
+    code: &str,
+    language: Option<ModuleLanguage>,
+) -> Result<ParsedSource, ParseDiagnostic> {
+    let media_type = match media_type(module_specifier, language) {
+        MediaType::Unknown => MediaType::JavaScript,
+        media_type => media_type,
+    };
+
+    deno_ast::parse_module(ParseParams {
+        specifier: module_specifier.clone(),
+        text: code.into(),
+        media_type,
+        capture_tokens: false,
+        scope_analysis: false,
+        maybe_syntax: None,
+    })
+}
+
+///
+/// Parses a module, and lists the specifiers it imports, in order of appearance
+///
+/// Includes static imports, re-exports, and dynamic imports of string literals  
+/// Type-only imports and exports are skipped, since they are erased by transpilation
+#[cfg(feature = "typescript")]
+pub fn find_imports(
+    module_specifier: &ModuleSpecifier,
+    code: &str,
+    language: Option<ModuleLanguage>,
+) -> Result<Vec<String>, Error> {
+    let parsed = parse(module_specifier, code, language)?;
+
+    let mut collector = ImportCollector::default();
+    parsed.program().visit_with(&mut collector);
+    Ok(collector.0)
+}
+
+///
+/// Parses a module, and lists the names it exports, in order of appearance
+///
+/// Also returns the sources of any `export * from` re-exports, whose names can only be found by loading them  
+/// Type-only exports are skipped, since they are erased by transpilation
+#[cfg(feature = "typescript")]
+pub fn find_exports(
+    module_specifier: &ModuleSpecifier,
+    code: &str,
+    language: Option<ModuleLanguage>,
+) -> Result<(Vec<String>, Vec<String>), Error> {
+    let parsed = parse(module_specifier, code, language)?;
+
+    let mut collector = ExportCollector::default();
+    parsed.program().visit_with(&mut collector);
+    Ok((collector.names, collector.star_sources))
+}
+
+///
+/// Lists the specifiers a module imports - requires the `typescript` feature, and is an error without it
+#[cfg(not(feature = "typescript"))]
+pub fn find_imports(
+    _module_specifier: &ModuleSpecifier,
+    _code: &str,
+    _language: Option<ModuleLanguage>,
+) -> Result<Vec<String>, Error> {
+    Err(parser_unavailable("Listing imports"))
+}
+
+///
+/// Lists the names a module exports - requires the `typescript` feature, and is an error without it
+#[cfg(not(feature = "typescript"))]
+pub fn find_exports(
+    _module_specifier: &ModuleSpecifier,
+    _code: &str,
+    _language: Option<ModuleLanguage>,
+) -> Result<(Vec<String>, Vec<String>), Error> {
+    Err(parser_unavailable("Listing exports"))
+}
+
+///
+/// Parses a module, and returns every syntax error found, in order of appearance
+///
+/// The parser recovers from most errors, so several can be reported at once  
+/// If it cannot recover, only the error that stopped it is returned, marked as fatal
+#[cfg(feature = "typescript")]
+pub fn diagnose(
+    module_specifier: &ModuleSpecifier,
+    code: &str,
+    language: Option<ModuleLanguage>,
+) -> Vec<(ParseDiagnostic, bool)> {
+    match parse(module_specifier, code, language) {
+        Ok(parsed) => parsed
+            .diagnostics()
+            .iter()
+            .map(|d| (d.clone(), false))
+            .collect(),
+        Err(fatal) => vec![(fatal, true)],
+    }
+}
+
+/// Collects import specifiers while walking a module
+#[cfg(feature = "typescript")]
+#[derive(Default)]
+struct ImportCollector(Vec<String>);
+#[cfg(feature = "typescript")]
+impl ImportCollector {
+    fn add(&mut self, specifier: &str) {
+        if !self.0.iter().any(|s| s == specifier) {
+            self.0.push(specifier.to_string());
+        }
+    }
+}
+#[cfg(feature = "typescript")]
+impl Visit for ImportCollector {
+    fn visit_import_decl(&mut self, import: &ImportDecl) {
+        if !import.type_only {
+            self.add(&import.src.value);
+        }
+    }
+
+    fn visit_export_all(&mut self, export: &ExportAll) {
+        if !export.type_only {
+            self.add(&export.src.value);
+        }
+    }
+
+    fn visit_named_export(&mut self, export: &NamedExport) {
+        if let Some(src) = &export.src {
+            if !export.type_only {
+                self.add(&src.value);
+            }
+        }
+    }
+
+    fn visit_call_expr(&mut self, call: &CallExpr) {
+        if let Callee::Import(_) = call.callee {
+            if let Some(arg) = call.args.first() {
+                match &*arg.expr {
+                    Expr::Lit(Lit::Str(s)) => self.add(&s.value),
+                    Expr::Tpl(tpl) if tpl.exprs.is_empty() => {
+                        if let Some(cooked) = tpl.quasis.first().and_then(|q| q.cooked.as_ref()) {
+                            self.add(cooked);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        call.visit_children_with(self);
+    }
+}
+
+/// Collects exported names while walking a module
+#[cfg(feature = "typescript")]
+#[derive(Default)]
+struct ExportCollector {
+    names: Vec<String>,
+    star_sources: Vec<String>,
+}
+#[cfg(feature = "typescript")]
+impl ExportCollector {
+    fn add(&mut self, name: &str) {
+        if !self.names.iter().any(|s| s == name) {
+            self.names.push(name.to_string());
+        }
+    }
+
+    fn add_export_name(&mut self, name: &ModuleExportName) {
+        match name {
+            ModuleExportName::Ident(ident) => self.add(&ident.sym),
+            ModuleExportName::Str(s) => self.add(&s.value),
+        }
+    }
+
+    /// Adds every name bound by a declaration pattern, such as `export const { a, b: [c] } = ...`
+    fn add_pat(&mut self, pat: &Pat) {
+        match pat {
+            Pat::Ident(ident) => self.add(&ident.sym),
+            Pat::Array(array) => array.elems.iter().flatten().for_each(|p| self.add_pat(p)),
+            Pat::Rest(rest) => self.add_pat(&rest.arg),
+            Pat::Assign(assign) => self.add_pat(&assign.left),
+            Pat::Object(object) => {
+                for prop in &object.props {
+                    match prop {
+                        ObjectPatProp::KeyValue(kv) => self.add_pat(&kv.value),
+                        ObjectPatProp::Assign(assign) => self.add(&assign.key.sym),
+                        ObjectPatProp::Rest(rest) => self.add_pat(&rest.arg),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+#[cfg(feature = "typescript")]
+impl Visit for ExportCollector {
+    fn visit_export_decl(&mut self, export: &ExportDecl) {
+        match &export.decl {
+            Decl::Fn(f) => self.add(&f.ident.sym),
+            Decl::Class(c) => self.add(&c.ident.sym),
+            Decl::TsEnum(e) => self.add(&e.id.sym),
+            Decl::Var(var) => var.decls.iter().for_each(|d| self.add_pat(&d.name)),
+            _ => {}
+        }
+    }
+
+    fn visit_export_default_decl(&mut self, _: &ExportDefaultDecl) {
+        self.add("default");
+    }
+
+    fn visit_export_default_expr(&mut self, _: &ExportDefaultExpr) {
+        self.add("default");
+    }
+
+    fn visit_named_export(&mut self, export: &NamedExport) {
+        if export.type_only {
+            return;
+        }
+
+        for specifier in &export.specifiers {
+            match specifier {
+                ExportSpecifier::Named(named) if !named.is_type_only => {
+                    self.add_export_name(named.exported.as_ref().unwrap_or(&named.orig));
+                }
+                ExportSpecifier::Namespace(ns) => self.add_export_name(&ns.name),
+                ExportSpecifier::Default(default) => self.add(&default.exported.sym),
+                ExportSpecifier::Named(_) => {}
+            }
+        }
+    }
+
+    fn visit_export_all(&mut self, export: &ExportAll) {
+        let source = export.src.value.to_string();
+        if !export.type_only && !self.star_sources.contains(&source) {
+            self.star_sources.push(source);
+        }
+    }
+}
+
 ///
 /// Transpile an extension
 #[allow(clippy::type_complexity)]
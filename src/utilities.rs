@@ -33,6 +33,46 @@ where
     runtime.eval(javascript)
 }
 
+/// Evaluate a piece of non-ECMAScript-module JavaScript code, without blocking
+///
+/// Async version of [`evaluate`] - the runtime is created and driven on the current thread.  
+/// Since the runtime is `!Send`, so is the returned future; it must be run on a
+/// [`tokio::task::LocalSet`] (or awaited directly in a `block_on`), and cannot be `tokio::spawn`ed
+///
+/// # Arguments
+/// * `javascript` - A single javascript expression
+///
+/// # Returns
+/// A `Result` containing the deserialized result of the expression if successful,
+/// or an error if execution fails, or the result cannot be deserialized.
+///
+/// # Errors
+/// Will return an error if the runtime cannot be started (usually due to extension issues)  
+/// Or if the expression is invalid, or if the result cannot be deserialized into the given type
+///
+/// # Example
+///
+/// ```rust
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let tokio = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+/// let local = tokio::task::LocalSet::new();
+///
+/// let result: i64 = local.block_on(&tokio, async {
+///     // Other !Send tasks can run alongside with `tokio::task::spawn_local`
+///     rustyscript::evaluate_async("5 + 5").await
+/// })?;
+/// assert_eq!(10, result);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn evaluate_async<T>(javascript: &str) -> Result<T, Error>
+where
+    T: deno_core::serde::de::DeserializeOwned,
+{
+    let mut runtime = Runtime::new(RuntimeOptions::default())?;
+    runtime.eval_async(javascript).await
+}
+
 /// Validates the syntax of some JS
 ///
 /// # Arguments
@@ -60,6 +100,99 @@ pub fn validate(javascript: &str) -> Result<bool, Error> {
     }
 }
 
+/// How serious a problem reported by [`validate_all`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticSeverity {
+    /// A syntax error the parser recovered from - other problems after it are also reported
+    Error,
+
+    /// A syntax error the parser could not recover from - nothing after it was checked
+    Fatal,
+}
+
+/// A single problem found by [`validate_all`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Description of the problem
+    pub message: String,
+
+    /// 1-based line the problem starts on
+    pub line: usize,
+
+    /// 1-based column the problem starts at
+    pub column: usize,
+
+    /// How serious the problem is
+    pub severity: DiagnosticSeverity,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// Checks the syntax of some JS or TS, and returns every problem found
+///
+/// Unlike [`validate`], no runtime is started, and checking does not stop at the first error  
+/// The code is only parsed - it is not executed, and imports are not resolved
+///
+/// # Arguments
+/// * `source` - The module's source code
+/// * `filename` - Name of the module - its extension decides whether it is parsed as JS or TS
+///
+/// # Returns
+/// A `Result` containing the problems found, in order of appearance - empty if the code is valid
+///
+/// # Errors
+/// Will return an error if the filename cannot be resolved to a module specifier,
+/// or if the `typescript` crate feature, which provides the parser, is disabled
+///
+/// # Example
+///
+/// ```rust
+/// # #[cfg(feature = "typescript")] {
+/// let diagnostics = rustyscript::validate_all("let a = ;\nlet b = 5;", "test.js")
+///     .expect("Something went wrong!");
+/// assert_eq!(diagnostics[0].line, 1);
+///
+/// assert!(rustyscript::validate_all("5 + 5", "test.js").unwrap().is_empty());
+/// # }
+/// ```
+pub fn validate_all(source: &str, filename: &str) -> Result<Vec<Diagnostic>, Error> {
+    let specifier = resolve_path(filename, None)?;
+
+    #[cfg(not(feature = "typescript"))]
+    {
+        let _ = (source, specifier);
+        Err(Error::Runtime(
+            "validate_all requires the `typescript` crate feature, which is disabled".to_string(),
+        ))
+    }
+
+    #[cfg(feature = "typescript")]
+    {
+        let diagnostics = crate::transpiler::diagnose(&specifier, source, None)
+            .into_iter()
+            .map(|(diagnostic, fatal)| {
+                let position = diagnostic.display_position();
+                Diagnostic {
+                    message: diagnostic.message().to_string(),
+                    line: position.line_number,
+                    column: position.column_number,
+                    severity: if fatal {
+                        DiagnosticSeverity::Fatal
+                    } else {
+                        DiagnosticSeverity::Error
+                    },
+                }
+            })
+            .collect();
+
+        Ok(diagnostics)
+    }
+}
+
 /// Imports a JS module into a new runtime
 ///
 /// # Arguments
@@ -82,6 +215,42 @@ pub fn import(path: &str) -> Result<ModuleWrapper, Error> {
     ModuleWrapper::new_from_file(path, RuntimeOptions::default())
 }
 
+/// Imports a JS module into a new runtime, without blocking
+///
+/// Async version of [`import`] - the runtime is created and driven on the current thread.  
+/// Since the runtime is `!Send`, so are the returned future and the resulting [`ModuleWrapper`];
+/// they must stay on a [`tokio::task::LocalSet`] (or in a `block_on`), and cannot be `tokio::spawn`ed
+///
+/// # Arguments
+/// * `path` - Path to the JS module to import
+///
+/// # Returns
+/// A `Result` containing a handle to the imported module,
+/// or an error if something went wrong.
+///
+/// # Errors
+/// Will return an error if the file cannot be found, execution fails, or the runtime
+/// cannot be started (usually due to extension issues)
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let tokio = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+/// let local = tokio::task::LocalSet::new();
+///
+/// local.block_on(&tokio, async {
+///     let mut module = rustyscript::import_async("js/my_module.js").await?;
+///     let value: String = module.get_async("my_value").await?;
+///     Ok::<_, rustyscript::Error>(())
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn import_async(path: &str) -> Result<ModuleWrapper, Error> {
+    ModuleWrapper::new_from_file_async(path, RuntimeOptions::default()).await
+}
+
 /// Resolve a path to absolute path, relative to the current working directory
 /// or an optional base directory
 ///
@@ -262,6 +431,92 @@ mod runtime_macros {
             })
         }
     }
+
+    /// Wraps an `impl` block, adding a `register_functions` method that registers each of its methods
+    /// with [`crate::Runtime::register_function`], under the method's name
+    ///
+    /// Methods must take `&self`, and return a `Result` whose error converts into [`crate::Error`].  
+    /// Arguments are deserialized from JS positionally, and the result serialized automatically.
+    /// Missing arguments are treated as `null`, so trailing `Option` parameters can be left out in JS
+    ///
+    /// `register_functions` consumes the value, which is shared between the registered functions;
+    /// use interior mutability (such as a `Cell` or `RefCell`) for state that methods need to change
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ Error, Runtime, impl_functions };
+    /// use std::cell::Cell;
+    ///
+    /// struct Counter {
+    ///     count: Cell<i64>,
+    /// }
+    ///
+    /// impl_functions! {
+    ///     impl Counter {
+    ///         fn add(&self, amount: i64) -> Result<i64, Error> {
+    ///             self.count.set(self.count.get() + amount);
+    ///             Ok(self.count.get())
+    ///         }
+    ///
+    ///         fn reset(&self, to: Option<i64>) -> Result<(), Error> {
+    ///             self.count.set(to.unwrap_or_default());
+    ///             Ok(())
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// Counter { count: Cell::new(0) }.register_functions(&mut runtime)?;
+    ///
+    /// let count: i64 = runtime.eval("rustyscript.functions.add(2); rustyscript.functions.add(3)")?;
+    /// assert_eq!(count, 5);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[macro_export]
+    macro_rules! impl_functions {
+        (
+            impl $ty:ty {
+                $(
+                    $(#[$meta:meta])*
+                    $vis:vis fn $name:ident(&self $(, $arg:ident: $arg_ty:ty)* $(,)?) -> $ret:ty $body:block
+                )*
+            }
+        ) => {
+            impl $ty {
+                $(
+                    $(#[$meta])*
+                    $vis fn $name(&self $(, $arg: $arg_ty)*) -> $ret $body
+                )*
+
+                /// Registers each of this type's methods as a function callable from JS
+                ///
+                /// # Errors
+                /// Will return an error if a function cannot be registered
+                pub fn register_functions(self, runtime: &mut $crate::Runtime) -> Result<(), $crate::Error> {
+                    let instance = std::rc::Rc::new(self);
+                    $(
+                        let this = instance.clone();
+                        runtime.register_function(stringify!($name), move |args: &[$crate::serde_json::Value]| {
+                            #[allow(unused_mut, unused_variables)]
+                            let mut args = args.iter();
+                            $(
+                                let $arg: $arg_ty = $crate::serde_json::from_value(
+                                    args.next().cloned().unwrap_or_default()
+                                ).map_err(|e| $crate::Error::Runtime(
+                                    format!("Invalid argument `{}` for {}: {e}", stringify!($arg), stringify!($name))
+                                ))?;
+                            )*
+                            let result = this.$name($($arg),*)?;
+                            $crate::serde_json::to_value(result).map_err(|e| $crate::Error::Runtime(e.to_string()))
+                        })?;
+                    )*
+                    Ok(())
+                }
+            }
+        };
+    }
 }
 
 #[cfg(test)]
@@ -286,18 +541,129 @@ mod test_runtime {
         assert_eq!(serde_json::Value::Number(10.into()), result);
     }
 
+    #[test]
+    fn test_impl_functions() {
+        struct Api {
+            prefix: String,
+            calls: std::cell::Cell<usize>,
+        }
+
+        impl_functions! {
+            impl Api {
+                fn greet(&self, name: String) -> Result<String, Error> {
+                    self.calls.set(self.calls.get() + 1);
+                    Ok(format!("{}{name}", self.prefix))
+                }
+
+                fn sum(&self, a: i64, b: i64, c: Option<i64>) -> Result<i64, Error> {
+                    self.calls.set(self.calls.get() + 1);
+                    Ok(a + b + c.unwrap_or_default())
+                }
+
+                fn calls(&self) -> Result<usize, Error> {
+                    Ok(self.calls.get())
+                }
+            }
+        }
+
+        let mut runtime =
+            Runtime::new(RuntimeOptions::default()).expect("Could not create runtime");
+        Api {
+            prefix: "Hello, ".to_string(),
+            calls: std::cell::Cell::new(0),
+        }
+        .register_functions(&mut runtime)
+        .expect("Could not register functions");
+
+        let greeting: String = runtime
+            .eval("rustyscript.functions.greet('world')")
+            .expect("Could not call function");
+        assert_eq!("Hello, world", greeting);
+
+        let sum: i64 = runtime
+            .eval("rustyscript.functions.sum(1, 2) + rustyscript.functions.sum(1, 2, 3)")
+            .expect("Could not call function");
+        assert_eq!(9, sum);
+
+        let calls: usize = runtime
+            .eval("rustyscript.functions.calls()")
+            .expect("Could not call function");
+        assert_eq!(3, calls);
+
+        runtime
+            .eval::<String>("rustyscript.functions.greet(5)")
+            .expect_err("Invalid argument type");
+        runtime
+            .eval::<i64>("rustyscript.functions.sum(1)")
+            .expect_err("Missing argument");
+    }
+
     #[test]
     fn test_evaluate() {
         assert_eq!(5, evaluate::<i64>("3 + 2").expect("invalid expression"));
         evaluate::<i64>("a5; 3 + 2").expect_err("Expected an error");
     }
 
+    #[test]
+    fn test_async_utilities() {
+        let tokio = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("Could not create tokio runtime");
+        let local = tokio::task::LocalSet::new();
+
+        local.block_on(&tokio, async {
+            let value = evaluate_async::<i64>("new Promise(r => setTimeout(() => r(3 + 2), 10))")
+                .await
+                .expect("invalid expression");
+            assert_eq!(5, value);
+            evaluate_async::<i64>("a5; 3 + 2")
+                .await
+                .expect_err("Expected an error");
+
+            let mut module = import_async("examples/javascript/example_module.js")
+                .await
+                .expect("Could not import module");
+            let food: String = module
+                .get_async("MY_FAVOURITE_FOOD")
+                .await
+                .expect("Could not get value");
+            assert_eq!("saskatoonberries", food);
+        });
+    }
+
     #[test]
     fn test_validate() {
         assert!(validate("3 + 2").expect("invalid expression"));
         assert!(!validate("5;+-").expect("invalid expression"));
     }
 
+    #[cfg(feature = "typescript")]
+    #[test]
+    fn test_validate_all() {
+        // Strict mode violations are recoverable, so both are reported
+        let diagnostics =
+            validate_all("with (a) {}\ndelete b;\n", "test.js").expect("Could not validate");
+        assert_eq!(diagnostics.len(), 2, "{diagnostics:?}");
+        assert_eq!((diagnostics[0].line, diagnostics[1].line), (1, 2));
+        assert!(
+            diagnostics
+                .iter()
+                .all(|d| d.severity == DiagnosticSeverity::Error),
+            "{diagnostics:?}"
+        );
+
+        // Parsing stops at an unrecoverable error
+        let diagnostics = validate_all("let x = 1;\nlet y = ;", "test.ts").unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Fatal);
+        assert_eq!(diagnostics[0].line, 2);
+
+        assert!(validate_all("let x: number = 5;", "test.ts")
+            .unwrap()
+            .is_empty());
+    }
+
     #[test]
     fn test_resolve_path() {
         assert!(resolve_path("test.js", None)
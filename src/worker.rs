@@ -18,19 +18,36 @@
 
 use crate::{Error, RuntimeOptions};
 use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
 use std::rc::Rc;
 use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
 use std::thread::{spawn, JoinHandle};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
 
 /// A pool of worker threads that can be used to run javascript code in parallel
 /// Uses a round-robin strategy to distribute work between workers
 /// Each worker is an independent runtime instance
+///
+/// [`WorkerPool::submit`] instead dispatches to the least busy worker, with an optional bound on the number of
+/// pending queries per worker (see [`WorkerPool::with_queue_capacity`])
+///
+/// Workers that have stopped (such as after a panic) are replaced with a fresh runtime on the next dispatch
 pub struct WorkerPool<W>
 where
     W: InnerWorker,
 {
     workers: Vec<Rc<RefCell<Worker<W>>>>,
+
+    /// Ids of the requests submitted to each worker, in the order it will answer them
+    queues: Vec<VecDeque<usize>>,
+
+    /// Responses received while waiting for a different request
+    completed: HashMap<usize, Result<W::Response, Error>>,
+
+    next_request: usize,
     next_worker: usize,
+    queue_capacity: Option<usize>,
     options: W::RuntimeOptions,
 }
 
@@ -50,12 +67,24 @@ where
         }
 
         Ok(Self {
+            queues: vec![VecDeque::new(); workers.len()],
             workers,
+            completed: HashMap::new(),
+            next_request: 0,
             next_worker: 0,
+            queue_capacity: None,
             options,
         })
     }
 
+    /// Limit the number of queries that can be pending on each worker
+    /// Once every worker is at capacity, [`WorkerPool::submit`] fails with [`Error::WorkerQueueFull`]
+    #[must_use]
+    pub fn with_queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = Some(capacity);
+        self
+    }
+
     /// Returns the runtime options used by the workers in the pool
     #[must_use]
     pub fn options(&self) -> &W::RuntimeOptions {
@@ -85,25 +114,185 @@ where
     }
 
     /// Get a worker by its index in the pool
+    ///
+    /// A stopped worker is replaced first, unless requests made with [`WorkerPool::submit`] are still pending on it  
+    /// Sending to the worker directly bypasses the pool's bookkeeping, so it must not be mixed with
+    /// [`WorkerPool::submit`] and [`WorkerPool::receive`] - one caller would be handed the other's response
     #[must_use]
     pub fn worker_by_id(&self, id: usize) -> Option<Rc<RefCell<Worker<W>>>> {
-        Some(Rc::clone(self.workers.get(id)?))
+        let worker = self.workers.get(id)?;
+        if self.queues[id].is_empty() {
+            if let Ok(mut worker) = worker.try_borrow_mut() {
+                if !worker.is_alive() {
+                    if let Ok(replacement) = Worker::new(self.options.clone()) {
+                        *worker = replacement;
+                    }
+                }
+            }
+        }
+        Some(Rc::clone(worker))
     }
 
     /// Get the next worker in the pool
+    ///
+    /// A stopped worker is replaced first - if that fails, the stopped worker is returned,
+    /// and sending to it fails with [`Error::WorkerHasStopped`]  
+    /// Sending to the worker directly bypasses the pool's bookkeeping, so it must not be mixed with
+    /// [`WorkerPool::submit`] and [`WorkerPool::receive`] - one caller would be handed the other's response
     pub fn next_worker(&mut self) -> Rc<RefCell<Worker<W>>> {
-        let worker = &self.workers[self.next_worker];
+        let id = self.next_worker;
         self.next_worker = (self.next_worker + 1) % self.workers.len();
-        Rc::clone(worker)
+        self.respawn_if_stopped(id).ok();
+        Rc::clone(&self.workers[id])
     }
 
-    /// Send a request to the next worker in the pool
+    /// Send a request to the least busy worker in the pool
     /// This will block the current thread until the response is received
     ///
+    /// This is [`WorkerPool::submit`] followed by [`WorkerPool::receive`], so it can be mixed freely with both
+    ///
     /// # Errors
-    /// Will return an error if the worker has already been stopped, or if the worker thread panicked
+    /// Will return [`Error::WorkerQueueFull`] if every worker is at capacity,
+    /// or an error if the worker stopped before responding
     pub fn send_and_await(&mut self, query: W::Query) -> Result<W::Response, Error> {
-        self.next_worker().borrow().send_and_await(query)
+        let request = self.submit(query)?;
+        self.receive(request)
+    }
+
+    /// Send a request to the least busy worker in the pool, without waiting for the response
+    ///
+    /// Returns an id for the request - collect the response with [`WorkerPool::receive`] or
+    /// [`WorkerPool::receive_async`]. Responses can be collected in any order
+    ///
+    /// # Errors
+    /// Will return [`Error::WorkerQueueFull`] if every worker is at capacity,
+    /// or an error if a stopped worker could not be replaced
+    ///
+    /// ```rust
+    /// use rustyscript::{ Error, worker::{ WorkerPool, DefaultWorker, DefaultWorkerQuery, DefaultWorkerResponse } };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut pool = WorkerPool::<DefaultWorker>::new(Default::default(), 2)?.with_queue_capacity(8);
+    ///
+    /// let a = pool.submit(DefaultWorkerQuery::Eval("1 + 1".to_string()))?;
+    /// let b = pool.submit(DefaultWorkerQuery::Eval("2 + 2".to_string()))?;
+    ///
+    /// let DefaultWorkerResponse::Value(b) = pool.receive(b)? else { panic!() };
+    /// let DefaultWorkerResponse::Value(a) = pool.receive(a)? else { panic!() };
+    /// assert_eq!((a, b), (2.into(), 4.into()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn submit(&mut self, query: W::Query) -> Result<usize, Error> {
+        // Least pending work first, starting from the round-robin position to break ties
+        let n_workers = self.workers.len();
+        let id = (0..n_workers)
+            .map(|i| (self.next_worker + i) % n_workers)
+            .min_by_key(|&id| self.queues[id].len())
+            .ok_or_else(|| Error::Runtime("The worker pool has no workers".to_string()))?;
+
+        if let Some(capacity) = self.queue_capacity {
+            if self.queues[id].len() >= capacity {
+                return Err(Error::WorkerQueueFull(capacity));
+            }
+        }
+
+        self.respawn_if_stopped(id)?;
+        self.workers[id].borrow().send(query)?;
+
+        let request = self.next_request;
+        self.next_request += 1;
+        self.queues[id].push_back(request);
+        self.next_worker = (id + 1) % n_workers;
+        Ok(request)
+    }
+
+    /// Wait for the response to a request made with [`WorkerPool::submit`]
+    /// This will block the current thread until the response is received
+    ///
+    /// # Errors
+    /// Will return an error if there is no pending request with that id, or if its worker stopped before responding  
+    /// A stopped worker is replaced, and every other request pending on it fails with [`Error::WorkerHasStopped`]
+    pub fn receive(&mut self, request: usize) -> Result<W::Response, Error> {
+        loop {
+            if let Some(response) = self.completed.remove(&request) {
+                return response;
+            }
+
+            let id = self.worker_for(request)?;
+            let response = self.workers[id].borrow().receive();
+            self.record_response(id, response)?;
+        }
+    }
+
+    /// Wait for the response to a request made with [`WorkerPool::submit`]
+    /// Returns a future that resolves once the response is received
+    ///
+    /// # Errors
+    /// Will return an error if there is no pending request with that id, or if its worker stopped before responding  
+    /// A stopped worker is replaced, and every other request pending on it fails with [`Error::WorkerHasStopped`]
+    pub async fn receive_async(&mut self, request: usize) -> Result<W::Response, Error> {
+        loop {
+            if let Some(response) = self.completed.remove(&request) {
+                return response;
+            }
+
+            let id = self.worker_for(request)?;
+            let responses = self.workers[id].borrow().responses();
+            let response = Worker::<W>::receive_from(&responses).await;
+            self.record_response(id, response)?;
+        }
+    }
+
+    /// Get the worker a pending request was sent to
+    fn worker_for(&self, request: usize) -> Result<usize, Error> {
+        self.queues
+            .iter()
+            .position(|queue| queue.contains(&request))
+            .ok_or_else(|| Error::Runtime(format!("No pending request with id {request}")))
+    }
+
+    /// Store a response from a worker against the oldest request pending on it
+    ///
+    /// If the worker stopped instead, every request pending on it fails, and it is replaced
+    fn record_response(
+        &mut self,
+        id: usize,
+        response: Result<W::Response, Error>,
+    ) -> Result<(), Error> {
+        match response {
+            Err(Error::WorkerHasStopped) => self.replace_worker(id),
+            Err(e) => Err(e),
+            Ok(_) => {
+                if let Some(request) = self.queues[id].pop_front() {
+                    self.completed.insert(request, response);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Replace a worker with a new instance if it has stopped
+    fn respawn_if_stopped(&mut self, id: usize) -> Result<(), Error> {
+        if !self.workers[id].borrow().is_alive() {
+            self.replace_worker(id)?;
+        }
+        Ok(())
+    }
+
+    /// Replace a worker with a new instance
+    /// Responses the old one sent before stopping are kept, and any requests still pending on it fail with [`Error::WorkerHasStopped`]
+    fn replace_worker(&mut self, id: usize) -> Result<(), Error> {
+        let stopped = Rc::clone(&self.workers[id]);
+        for response in stopped.borrow().remaining_responses() {
+            self.record_response(id, Ok(response))?;
+        }
+
+        for request in std::mem::take(&mut self.queues[id]) {
+            self.completed.insert(request, Err(Error::WorkerHasStopped));
+        }
+        self.workers[id] = Rc::new(RefCell::new(Worker::new(self.options.clone())?));
+        Ok(())
     }
 
     /// Evaluate a string of non-ecma javascript code in a separate thread
@@ -135,7 +324,7 @@ where
 {
     handle: Option<JoinHandle<()>>,
     tx: Option<Sender<W::Query>>,
-    rx: Receiver<W::Response>,
+    rx: Arc<tokio::sync::Mutex<UnboundedReceiver<W::Response>>>,
 }
 
 impl<W> Worker<W>
@@ -148,9 +337,20 @@ where
     /// Can fail if the runtime cannot be initialized (usually due to extension issues)
     pub fn new(options: W::RuntimeOptions) -> Result<Self, Error> {
         let (qtx, qrx) = channel();
-        let (rtx, rrx) = channel();
+        let (rtx, rrx) = channel::<W::Response>();
         let (init_tx, init_rx) = channel::<Option<Error>>();
 
+        // Responses are forwarded to a channel that can also be awaited
+        // The relay ends once the worker thread drops its sender
+        let (atx, arx) = unbounded_channel();
+        spawn(move || {
+            while let Ok(response) = rrx.recv() {
+                if atx.send(response).is_err() {
+                    break;
+                }
+            }
+        });
+
         let handle = spawn(move || {
             let rx = qrx;
             let tx = rtx;
@@ -172,7 +372,7 @@ where
         let worker = Self {
             handle: Some(handle),
             tx: Some(qtx),
-            rx: rrx,
+            rx: Arc::new(tokio::sync::Mutex::new(arx)),
         };
 
         // Wait for initialization to complete
@@ -216,6 +416,12 @@ where
         }
     }
 
+    /// Returns true if the worker thread is still running, and accepting requests
+    #[must_use]
+    pub fn is_alive(&self) -> bool {
+        self.tx.is_some() && self.handle.as_ref().is_some_and(|h| !h.is_finished())
+    }
+
     /// Stop the worker and wait for it to finish
     /// Stops by destroying the sender, which will cause the thread to exit the loop and finish
     ///
@@ -247,27 +453,97 @@ where
     /// Receive a response from the worker
     /// This will block the current thread until a response is received
     ///
+    /// Blocking from a worker thread of a multi-threaded tokio runtime is allowed - from inside a
+    /// single-threaded runtime it is not, and [`Worker::receive_async`] must be used instead
+    ///
     /// # Errors
-    /// Will return an error if the worker has already been stopped, or if the worker thread panicked
+    /// Will return [`Error::WorkerHasStopped`] if the worker has stopped, or if the worker thread panicked
     pub fn receive(&self) -> Result<W::Response, Error> {
-        self.rx.recv().map_err(|e| Error::Runtime(e.to_string()))
+        let mut rx = self.rx.try_lock().map_err(|_| {
+            Error::Runtime("Another response from this worker is being awaited".to_string())
+        })?;
+
+        let response = match tokio::runtime::Handle::try_current() {
+            Err(_) => rx.blocking_recv(),
+            Ok(current)
+                if current.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread =>
+            {
+                tokio::task::block_in_place(|| rx.blocking_recv())
+            }
+            Ok(_) => {
+                return Err(Error::Runtime(
+                    "Cannot block from within a single-threaded tokio runtime - use `receive_async` instead".to_string(),
+                ))
+            }
+        };
+        response.ok_or(Error::WorkerHasStopped)
+    }
+
+    /// Receive a response from the worker
+    /// Returns a future that resolves once a response is received
+    ///
+    /// # Errors
+    /// Will return [`Error::WorkerHasStopped`] if the worker has stopped, or if the worker thread panicked
+    pub async fn receive_async(&self) -> Result<W::Response, Error> {
+        Self::receive_from(&self.rx).await
     }
 
     /// Try to receive a response from the worker without blocking
     /// This will return `Ok(None)` if no response is available
     ///
     /// # Errors
-    /// Will return an error if the worker has already been stopped, or if the worker thread panicked
+    /// Will return [`Error::WorkerHasStopped`] if the worker has stopped, or if the worker thread panicked
     pub fn try_receive(&self) -> Result<Option<W::Response>, Error> {
-        match self.rx.try_recv() {
+        let Ok(mut rx) = self.rx.try_lock() else {
+            return Ok(None);
+        };
+        match rx.try_recv() {
             Ok(v) => Ok(Some(v)),
-            Err(e) => match e {
-                std::sync::mpsc::TryRecvError::Empty => Ok(None),
-                std::sync::mpsc::TryRecvError::Disconnected => Err(Error::Runtime(e.to_string())),
-            },
+            Err(tokio::sync::mpsc::error::TryRecvError::Empty) => Ok(None),
+            Err(tokio::sync::mpsc::error::TryRecvError::Disconnected) => {
+                Err(Error::WorkerHasStopped)
+            }
         }
     }
 
+    /// Collect the responses a stopped worker sent before it stopped
+    ///
+    /// Once the worker thread has exited, the channel is drained until it disconnects, since the relay
+    /// may still be forwarding the last responses
+    fn remaining_responses(&self) -> Vec<W::Response> {
+        let mut responses = Vec::new();
+        let Ok(mut rx) = self.rx.try_lock() else {
+            return responses;
+        };
+        if self.handle.as_ref().is_some_and(|h| !h.is_finished()) {
+            // Still running, so there is no end to wait for - take what has arrived
+            while let Ok(response) = rx.try_recv() {
+                responses.push(response);
+            }
+            return responses;
+        }
+
+        loop {
+            match rx.try_recv() {
+                Ok(response) => responses.push(response),
+                Err(tokio::sync::mpsc::error::TryRecvError::Empty) => std::thread::yield_now(),
+                Err(tokio::sync::mpsc::error::TryRecvError::Disconnected) => break responses,
+            }
+        }
+    }
+
+    /// The channel the worker's responses arrive on, which can be awaited without borrowing the worker
+    fn responses(&self) -> Arc<tokio::sync::Mutex<UnboundedReceiver<W::Response>>> {
+        Arc::clone(&self.rx)
+    }
+
+    /// Await the next response on a worker's response channel
+    async fn receive_from(
+        rx: &tokio::sync::Mutex<UnboundedReceiver<W::Response>>,
+    ) -> Result<W::Response, Error> {
+        rx.lock().await.recv().await.ok_or(Error::WorkerHasStopped)
+    }
+
     /// Send a request to the worker and wait for a response
     /// This will block the current thread until a response is received
     /// Will return an error if the worker has stopped or panicked
@@ -361,28 +637,40 @@ where
 /// For a more performant worker, or to use extensions and/or loader caches, you'll need to implement your own worker
 pub struct DefaultWorker(Worker<DefaultWorker>);
 impl InnerWorker for DefaultWorker {
+    /// The runtime, the modules loaded into it by id, and the nonce identifying the worker's module tokens
     type Runtime = (
         crate::Runtime,
         std::collections::HashMap<deno_core::ModuleId, crate::ModuleHandle>,
+        u64,
     );
     type RuntimeOptions = DefaultWorkerOptions;
     type Query = DefaultWorkerQuery;
     type Response = DefaultWorkerResponse;
 
     fn init_runtime(options: Self::RuntimeOptions) -> Result<Self::Runtime, Error> {
-        let runtime = crate::Runtime::new(crate::RuntimeOptions {
+        let mut runtime = crate::Runtime::new(crate::RuntimeOptions {
             default_entrypoint: options.default_entrypoint,
             timeout: options.timeout,
             shared_array_buffer_store: options.shared_array_buffer_store,
             startup_snapshot: options.startup_snapshot,
             ..Default::default()
         })?;
-        let modules = std::collections::HashMap::new();
-        Ok((runtime, modules))
+
+        // Shared state is installed first, so preloaded modules can already use it
+        options.shared_state.install(&mut runtime)?;
+
+        // Preloaded modules are ready before the worker accepts any queries
+        let mut modules = std::collections::HashMap::new();
+        for module in &options.preload_modules {
+            let handle = runtime.load_module(module)?;
+            modules.insert(handle.id(), handle);
+        }
+
+        Ok((runtime, modules, token_nonce()))
     }
 
     fn handle_query(runtime: &mut Self::Runtime, query: Self::Query) -> Self::Response {
-        let (runtime, modules) = runtime;
+        let (runtime, modules, nonce) = runtime;
         match query {
             DefaultWorkerQuery::Eval(code) => match runtime.eval(&code) {
                 Ok(v) => Self::Response::Value(v),
@@ -437,6 +725,17 @@ impl InnerWorker for DefaultWorker {
                 }
             }
 
+            DefaultWorkerQuery::GetModuleId(filename) => {
+                let id = modules
+                    .values()
+                    .find(|handle| handle.module().filename() == std::path::Path::new(&filename))
+                    .map(crate::ModuleHandle::id);
+                match id {
+                    Some(id) => Self::Response::ModuleId(id),
+                    None => Self::Response::Error(Error::ModuleNotFound(filename)),
+                }
+            }
+
             DefaultWorkerQuery::GetValue(id, name) => {
                 let handle = if let Some(id) = id {
                     match modules.get(&id) {
@@ -456,6 +755,45 @@ impl InnerWorker for DefaultWorker {
                     Err(e) => Self::Response::Error(e),
                 }
             }
+
+            DefaultWorkerQuery::GetModuleToken(id) => {
+                if modules.contains_key(&id) {
+                    Self::Response::ModuleToken(WorkerModuleToken { worker: *nonce, id })
+                } else {
+                    Self::Response::Error(Error::Runtime("Module not found".to_string()))
+                }
+            }
+
+            DefaultWorkerQuery::ResolveModuleToken(token) => match token.resolve(*nonce) {
+                Ok(id) => Self::Response::ModuleId(id),
+                Err(e) => Self::Response::Error(e),
+            },
+
+            DefaultWorkerQuery::CallEntrypointWithToken(token, args) => {
+                let result = token
+                    .resolve(*nonce)
+                    .and_then(|id| find_module(modules, id))
+                    .and_then(|handle| runtime.call_entrypoint(handle, &args));
+                match result {
+                    Ok(v) => Self::Response::Value(v),
+                    Err(e) => Self::Response::Error(e),
+                }
+            }
+
+            DefaultWorkerQuery::CallFunctionWithToken(token, name, args) => {
+                let result = token
+                    .map(|token| {
+                        token
+                            .resolve(*nonce)
+                            .and_then(|id| find_module(modules, id))
+                    })
+                    .transpose()
+                    .and_then(|handle| runtime.call_function(handle, &name, &args));
+                match result {
+                    Ok(v) => Self::Response::Value(v),
+                    Err(e) => Self::Response::Error(e),
+                }
+            }
         }
     }
 }
@@ -528,6 +866,118 @@ impl DefaultWorker {
         }
     }
 
+    /// Load a module into the worker as a side module
+    /// Returns a token for the loaded module, which maps back to its handle inside the worker
+    ///
+    /// Unlike a bare module id, the token can be serialized and passed around. It is issued by the worker thread,
+    /// and rejected by any other worker - including one started after a restart
+    ///
+    /// # Errors
+    /// Can fail if execution of the module fails
+    ///
+    /// ```rust
+    /// use rustyscript::{Error, Module, worker::{DefaultWorker, DefaultWorkerOptions}};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let worker = DefaultWorker::new(DefaultWorkerOptions {
+    ///     default_entrypoint: Some("run".to_string()),
+    ///     ..Default::default()
+    /// })?;
+    ///
+    /// let module = Module::new("test.js", "export const run = (a, b) => ({ sum: a + b });");
+    /// let token = worker.load_module_token(module)?;
+    ///
+    /// let value: std::collections::HashMap<String, i32> = worker.call_entrypoint_with(&token, &(1, 2))?;
+    /// assert_eq!(value["sum"], 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_module_token(&self, module: crate::Module) -> Result<WorkerModuleToken, Error> {
+        let id = self.load_module(module)?;
+        self.module_token(id)
+    }
+
+    /// Load a module into the worker as the main module
+    /// Returns a token for the loaded module - see [`DefaultWorker::load_module_token`]
+    ///
+    /// # Errors
+    /// Can fail if execution of the module fails
+    pub fn load_main_module_token(
+        &self,
+        module: crate::Module,
+    ) -> Result<WorkerModuleToken, Error> {
+        let id = self.load_main_module(module)?;
+        self.module_token(id)
+    }
+
+    /// Get a token for a module id returned by this worker, such as from [`DefaultWorker::get_module_id`]
+    ///
+    /// # Errors
+    /// Will return an error if no module with that id has been loaded into the worker
+    pub fn module_token(&self, id: deno_core::ModuleId) -> Result<WorkerModuleToken, Error> {
+        match self
+            .0
+            .send_and_await(DefaultWorkerQuery::GetModuleToken(id))?
+        {
+            DefaultWorkerResponse::ModuleToken(token) => Ok(token),
+            DefaultWorkerResponse::Error(e) => Err(e),
+            _ => Err(Error::Runtime(
+                "Unexpected response from the worker".to_string(),
+            )),
+        }
+    }
+
+    /// Get the module id a token refers to
+    ///
+    /// # Errors
+    /// Will return an error if the token was issued by a different worker
+    pub fn resolve_token(&self, token: &WorkerModuleToken) -> Result<deno_core::ModuleId, Error> {
+        match self
+            .0
+            .send_and_await(DefaultWorkerQuery::ResolveModuleToken(*token))?
+        {
+            DefaultWorkerResponse::ModuleId(id) => Ok(id),
+            DefaultWorkerResponse::Error(e) => Err(e),
+            _ => Err(Error::Runtime(
+                "Unexpected response from the worker".to_string(),
+            )),
+        }
+    }
+
+    /// Get the id of a module already loaded into the worker, by the filename it was loaded with
+    /// Used to find modules loaded from [`DefaultWorkerOptions::preload_modules`]
+    ///
+    /// # Errors
+    /// Will return an error if no module with that filename has been loaded
+    ///
+    /// ```rust
+    /// use rustyscript::{Error, Module, worker::{DefaultWorker, DefaultWorkerOptions}};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let worker = DefaultWorker::new(DefaultWorkerOptions {
+    ///     preload_modules: vec![Module::new("math.js", "export const add = (a, b) => a + b;")],
+    ///     ..Default::default()
+    /// })?;
+    ///
+    /// let id = worker.get_module_id("math.js")?;
+    /// let result: i32 = worker.call_function(Some(id), "add".to_string(), vec![1.into(), 2.into()])?;
+    /// assert_eq!(result, 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_module_id(&self, filename: &str) -> Result<deno_core::ModuleId, Error> {
+        match self
+            .0
+            .send_and_await(DefaultWorkerQuery::GetModuleId(filename.to_string()))?
+        {
+            DefaultWorkerResponse::ModuleId(id) => Ok(id),
+            DefaultWorkerResponse::Error(e) => Err(e),
+            _ => Err(Error::Runtime(
+                "Unexpected response from the worker".to_string(),
+            )),
+        }
+    }
+
     /// Call the entrypoint function in a module
     /// Returns the result of the function call
     /// The module id must be the id of a module loaded with `load_main_module` or `load_module`
@@ -557,6 +1007,63 @@ impl DefaultWorker {
         }
     }
 
+    /// Call the entrypoint function in the module a token refers to
+    /// Arguments are given as a tuple - see [`WorkerArgs`] - and the result is deserialized into the requested type
+    ///
+    /// # Errors
+    /// Can fail if the token was issued by a different worker, if there is no entrypoint function,
+    /// if the entrypoint function returns an error, or if the arguments or return value cannot be converted
+    pub fn call_entrypoint_with<T>(
+        &self,
+        token: &WorkerModuleToken,
+        args: &impl WorkerArgs,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let query = DefaultWorkerQuery::CallEntrypointWithToken(*token, args.to_values()?);
+        match self.0.send_and_await(query)? {
+            DefaultWorkerResponse::Value(v) => {
+                crate::serde_json::from_value(v).map_err(Error::from)
+            }
+            DefaultWorkerResponse::Error(e) => Err(e),
+            _ => Err(Error::Runtime(
+                "Unexpected response from the worker".to_string(),
+            )),
+        }
+    }
+
+    /// Call a function in the module a token refers to, or in the global context if no token is given
+    /// Arguments are given as a tuple - see [`WorkerArgs`]
+    ///
+    /// # Errors
+    /// Can fail if the token was issued by a different worker, if the function is not found,
+    /// if the function returns an error, or if the arguments or return value cannot be converted
+    pub fn call_function_with<T>(
+        &self,
+        token: Option<&WorkerModuleToken>,
+        name: &str,
+        args: &impl WorkerArgs,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let query = DefaultWorkerQuery::CallFunctionWithToken(
+            token.copied(),
+            name.to_string(),
+            args.to_values()?,
+        );
+        match self.0.send_and_await(query)? {
+            DefaultWorkerResponse::Value(v) => {
+                crate::serde_json::from_value(v).map_err(Error::from)
+            }
+            DefaultWorkerResponse::Error(e) => Err(e),
+            _ => Err(Error::Runtime(
+                "Unexpected response from the worker".to_string(),
+            )),
+        }
+    }
+
     /// Call a function in a module
     /// Returns the result of the function call
     /// The module id must be the id of a module loaded with `load_main_module` or `load_module`
@@ -620,6 +1127,93 @@ impl AsRef<Worker<DefaultWorker>> for DefaultWorker {
     }
 }
 
+/// A serializable reference to a module loaded into a [`DefaultWorker`]
+///
+/// Issued by the worker thread, and carries a nonce unique to that worker - so it maps back to the module's handle
+/// inside that worker, and is rejected by any other, including workers started by another process  
+/// See [`DefaultWorker::load_module_token`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct WorkerModuleToken {
+    worker: u64,
+    id: deno_core::ModuleId,
+}
+impl WorkerModuleToken {
+    /// The id of the module inside the worker
+    #[must_use]
+    pub fn id(&self) -> deno_core::ModuleId {
+        self.id
+    }
+
+    /// Returns the module id, if the token was issued by the worker with the given nonce
+    fn resolve(&self, nonce: u64) -> Result<deno_core::ModuleId, Error> {
+        if self.worker == nonce {
+            Ok(self.id)
+        } else {
+            Err(Error::Runtime(
+                "Module token was issued by a different worker".to_string(),
+            ))
+        }
+    }
+}
+
+/// Returns a nonce identifying the module tokens of a new worker - called from the worker thread
+///
+/// The keys of a `RandomState` are seeded from the OS for each thread, so the nonce differs between workers,
+/// and between runs of the process
+fn token_nonce() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    hasher.finish()
+}
+
+/// Finds the handle of a module loaded into a [`DefaultWorker`], by its id
+fn find_module(
+    modules: &HashMap<deno_core::ModuleId, crate::ModuleHandle>,
+    id: deno_core::ModuleId,
+) -> Result<&crate::ModuleHandle, Error> {
+    modules
+        .get(&id)
+        .ok_or_else(|| Error::Runtime("Module not found".to_string()))
+}
+
+/// Arguments for a call made with [`DefaultWorker::call_function_with`] or [`DefaultWorker::call_entrypoint_with`]
+///
+/// Implemented for tuples of serializable values, each element being passed as one argument - `()` passes none.  
+/// A single argument is given as a one-element tuple, so a list is passed as one array: `(&vec![1, 2, 3],)`
+pub trait WorkerArgs {
+    /// Convert the arguments into the list of values sent to the worker
+    ///
+    /// # Errors
+    /// Will return an error if an argument cannot be serialized
+    fn to_values(&self) -> Result<Vec<crate::serde_json::Value>, Error>;
+}
+
+macro_rules! impl_worker_args {
+    ($($ty:ident $arg:ident),*) => {
+        impl<$($ty: serde::ser::Serialize),*> WorkerArgs for ($($ty,)*) {
+            fn to_values(&self) -> Result<Vec<crate::serde_json::Value>, Error> {
+                let ($($arg,)*) = self;
+                Ok(vec![$(crate::serde_json::to_value($arg)?),*])
+            }
+        }
+    };
+}
+impl WorkerArgs for () {
+    fn to_values(&self) -> Result<Vec<crate::serde_json::Value>, Error> {
+        Ok(vec![])
+    }
+}
+
+impl_worker_args!(A a0);
+impl_worker_args!(A a0, B a1);
+impl_worker_args!(A a0, B a1, C a2);
+impl_worker_args!(A a0, B a1, C a2, D a3);
+impl_worker_args!(A a0, B a1, C a2, D a3, E a4);
+impl_worker_args!(A a0, B a1, C a2, D a3, E a4, F a5);
+impl_worker_args!(A a0, B a1, C a2, D a3, E a4, F a5, G a6);
+impl_worker_args!(A a0, B a1, C a2, D a3, E a4, F a5, G a6, H a7);
+
 /// Options for the default worker
 #[derive(Default, Clone)]
 pub struct DefaultWorkerOptions {
@@ -638,6 +1232,113 @@ pub struct DefaultWorkerOptions {
     /// Optional shared array buffer store to use for the runtime
     /// Allows data-sharing between runtimes across threads
     pub shared_array_buffer_store: Option<deno_core::SharedArrayBufferStore>,
+
+    /// Modules to load into the runtime when the worker starts
+    /// They are loaded before the worker accepts any queries, and [`DefaultWorker::new`]
+    /// will return an error if any of them fail to load
+    ///
+    /// Use [`DefaultWorker::get_module_id`] to get their ids
+    pub preload_modules: Vec<crate::Module>,
+
+    /// State shared with the thread that created the worker, installed into the worker's `OpState` at startup
+    /// See [`SharedWorkerState`]
+    pub shared_state: SharedWorkerState,
+}
+
+type StateInstaller = Arc<dyn Fn(&mut crate::Runtime) -> Result<(), Error> + Send + Sync>;
+
+/// A set of `Arc`-wrapped values shared between a thread and the workers it creates
+///
+/// Each value is installed into the worker's `OpState` as an `Arc<T>` when the worker starts, exactly as
+/// [`crate::Runtime::set_shared_context`] would - extension ops can borrow the `Arc<T>` from their `OpState`.
+///
+/// Javascript has no direct access to the values. To expose them to scripts, register functions with
+/// [`SharedWorkerState::with_function`] - they are registered in each worker as it starts, and read the
+/// values through [`crate::ModuleGetter::shared_context`].
+///
+/// Only the `Arc` is cloned, so every worker and the original thread refer to the same value.  
+/// Nothing is synchronized for you: the values are accessed concurrently from several threads,
+/// so any mutable state inside them must use its own locking (`Mutex`, `RwLock`, atomics, or a thread-safe pool)
+///
+/// Custom [`InnerWorker`] implementations can call [`SharedWorkerState::install`] from `init_runtime`
+///
+/// ```rust
+/// use rustyscript::{ worker::{ DefaultWorker, DefaultWorkerOptions, SharedWorkerState }, serde_json::Value };
+/// use std::sync::{ Arc, atomic::{ AtomicUsize, Ordering } };
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let counter = Arc::new(AtomicUsize::new(0));
+/// let state = SharedWorkerState::new()
+///     .with(counter.clone())
+///     .with_function("hit", |_, getter| {
+///         let counter = getter.shared_context::<AtomicUsize>().unwrap();
+///         Ok(Value::from(counter.fetch_add(1, Ordering::SeqCst) + 1))
+///     });
+///
+/// let worker = DefaultWorker::new(DefaultWorkerOptions {
+///     shared_state: state,
+///     ..Default::default()
+/// })?;
+///
+/// let hits: usize = worker.eval("rustyscript.functions.hit()".to_string())?;
+/// assert_eq!(hits, 1);
+/// assert_eq!(counter.load(Ordering::SeqCst), 1);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct SharedWorkerState(Vec<StateInstaller>);
+impl SharedWorkerState {
+    /// Create an empty set of shared state
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a value to share with workers
+    /// One value is stored per type - adding another of the same type replaces it in the worker
+    #[must_use]
+    pub fn with<T>(mut self, value: Arc<T>) -> Self
+    where
+        T: Send + Sync + 'static,
+    {
+        self.0.push(Arc::new(move |runtime| {
+            runtime.set_shared_context(value.clone())
+        }));
+        self
+    }
+
+    /// Add a function for javascript to call, registered in each worker as it starts
+    /// It behaves like [`crate::Runtime::register_module_function`], and can read the shared values
+    /// through [`crate::ModuleGetter::shared_context`]
+    #[must_use]
+    pub fn with_function<F>(mut self, name: &str, callback: F) -> Self
+    where
+        F: crate::RsModuleFunction + Clone + Send + Sync,
+    {
+        let name = name.to_string();
+        self.0.push(Arc::new(move |runtime| {
+            runtime.register_module_function(&name, callback.clone())
+        }));
+        self
+    }
+
+    /// Install every shared value into the given runtime's `OpState`, and register the shared functions
+    ///
+    /// # Errors
+    /// Can fail if the runtime's state cannot be borrowed mutably
+    pub fn install(&self, runtime: &mut crate::Runtime) -> Result<(), Error> {
+        for install in &self.0 {
+            install(runtime)?;
+        }
+        Ok(())
+    }
+
+    /// Returns true if no values are shared
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
 }
 
 /// Query types for the default worker
@@ -664,6 +1365,25 @@ pub enum DefaultWorkerQuery {
 
     /// Gets a value from a module
     GetValue(Option<deno_core::ModuleId>, String),
+
+    /// Gets the id of a loaded module by its filename
+    GetModuleId(String),
+
+    /// Gets a token for a loaded module, by its id
+    GetModuleToken(deno_core::ModuleId),
+
+    /// Gets the id of the module a token refers to, if the token was issued by this worker
+    ResolveModuleToken(WorkerModuleToken),
+
+    /// Calls the entrypoint function in the module a token refers to
+    CallEntrypointWithToken(WorkerModuleToken, Vec<crate::serde_json::Value>),
+
+    /// Calls a function in the module a token refers to, or in the global context
+    CallFunctionWithToken(
+        Option<WorkerModuleToken>,
+        String,
+        Vec<crate::serde_json::Value>,
+    ),
 }
 
 /// Response types for the default worker
@@ -675,9 +1395,217 @@ pub enum DefaultWorkerResponse {
     /// A successful response with a module id
     ModuleId(deno_core::ModuleId),
 
+    /// A successful response with a module token
+    ModuleToken(WorkerModuleToken),
+
     /// A successful response with no value
     Ok(()),
 
     /// An error response
     Error(Error),
 }
+
+#[cfg(test)]
+mod test_worker {
+    use super::*;
+
+    /// Doubles each query, and panics on 0
+    struct Doubler;
+    impl InnerWorker for Doubler {
+        type Runtime = ();
+        type RuntimeOptions = ();
+        type Query = u32;
+        type Response = u32;
+
+        fn init_runtime((): Self::RuntimeOptions) -> Result<Self::Runtime, Error> {
+            Ok(())
+        }
+
+        fn handle_query((): &mut Self::Runtime, query: Self::Query) -> Self::Response {
+            assert_ne!(query, 0, "Stopping the worker");
+            query * 2
+        }
+    }
+
+    #[test]
+    fn test_pool_request_ids() {
+        let mut pool = WorkerPool::<Doubler>::new((), 2).expect("Could not create the pool");
+        let requests = (1..=6)
+            .map(|query| pool.submit(query).map(|id| (id, query)))
+            .collect::<Result<Vec<_>, _>>()
+            .expect("Could not submit");
+
+        // Responses match their requests, whatever order they are collected in
+        for (id, query) in requests.into_iter().rev() {
+            assert_eq!(query * 2, pool.receive(id).expect("Could not receive"));
+        }
+
+        pool.receive(0).expect_err("Received a response twice");
+    }
+
+    #[test]
+    fn test_pool_stopped_worker() {
+        let mut pool = WorkerPool::<Doubler>::new((), 1).expect("Could not create the pool");
+        let a = pool.submit(1).unwrap();
+        let b = pool.submit(0).unwrap();
+        let c = pool.submit(3).unwrap();
+
+        // Every request pending on the stopped worker fails, instead of being lost
+        let e = pool.receive(c).unwrap_err();
+        assert!(matches!(e, Error::WorkerHasStopped), "{e}");
+        assert_eq!(2, pool.receive(a).unwrap());
+        let e = pool.receive(b).unwrap_err();
+        assert!(matches!(e, Error::WorkerHasStopped), "{e}");
+
+        // And the worker is replaced
+        let d = pool.submit(5).unwrap();
+        assert_eq!(10, pool.receive(d).unwrap());
+    }
+
+    #[test]
+    fn test_pool_replaced_before_receive() {
+        let mut pool = WorkerPool::<Doubler>::new((), 1).expect("Could not create the pool");
+        let a = pool.submit(1).unwrap();
+        let b = pool.submit(0).unwrap();
+
+        let worker = pool.worker_by_id(0).unwrap();
+        while worker.borrow().is_alive() {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        // Submitting replaces the stopped worker, without losing the response it already sent
+        let c = pool.submit(3).unwrap();
+        assert_eq!(2, pool.receive(a).unwrap());
+        let e = pool.receive(b).unwrap_err();
+        assert!(matches!(e, Error::WorkerHasStopped), "{e}");
+        assert_eq!(6, pool.receive(c).unwrap());
+    }
+
+    #[test]
+    fn test_pool_send_and_await() {
+        let mut pool = WorkerPool::<Doubler>::new((), 1).expect("Could not create the pool");
+        let a = pool.submit(1).unwrap();
+
+        // Goes through the same queue as submitted requests, so neither is handed the other's response
+        assert_eq!(4, pool.send_and_await(2).unwrap());
+        assert_eq!(2, pool.receive(a).unwrap());
+
+        // And replaces a stopped worker
+        pool.send_and_await(0).unwrap_err();
+        assert_eq!(6, pool.send_and_await(3).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_pool_receive_async() {
+        let mut pool = WorkerPool::<Doubler>::new((), 2).expect("Could not create the pool");
+        let a = pool.submit(1).unwrap();
+        let b = pool.submit(2).unwrap();
+
+        assert_eq!(4, pool.receive_async(b).await.unwrap());
+        assert_eq!(2, pool.receive_async(a).await.unwrap());
+
+        // Blocking is refused from a single-threaded runtime, instead of stalling it
+        let c = pool.submit(3).unwrap();
+        pool.receive(c).unwrap_err();
+        assert_eq!(6, pool.receive_async(c).await.unwrap());
+    }
+
+    #[test]
+    fn test_shared_state() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let state = SharedWorkerState::new()
+            .with(counter.clone())
+            .with_function("hit", |_, getter| {
+                let counter = getter
+                    .shared_context::<AtomicUsize>()
+                    .ok_or_else(|| Error::Runtime("No shared counter".to_string()))?;
+                Ok(crate::serde_json::Value::from(
+                    counter.fetch_add(1, Ordering::SeqCst) + 1,
+                ))
+            });
+
+        // Every worker sees the same counter, shared with this thread
+        let options = DefaultWorkerOptions {
+            shared_state: state,
+            ..Default::default()
+        };
+        let a = DefaultWorker::new(options.clone()).expect("Could not create the worker");
+        let b = DefaultWorker::new(options).expect("Could not create the worker");
+
+        let hits: usize = a.eval("rustyscript.functions.hit()".to_string()).unwrap();
+        assert_eq!(hits, 1);
+        let hits: usize = b.eval("rustyscript.functions.hit()".to_string()).unwrap();
+        assert_eq!(hits, 2);
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_module_tokens() {
+        let options = DefaultWorkerOptions {
+            default_entrypoint: Some("run".to_string()),
+            ..Default::default()
+        };
+        let a = DefaultWorker::new(options.clone()).expect("Could not create the worker");
+        let b = DefaultWorker::new(options).expect("Could not create the worker");
+
+        let module = crate::Module::new("test.js", "export const run = (a, b) => a + b;");
+        let token = a.load_module_token(module.clone()).unwrap();
+        let value: i32 = a.call_entrypoint_with(&token, &(1, 2)).unwrap();
+        assert_eq!(value, 3);
+        assert_eq!(a.resolve_token(&token).unwrap(), token.id());
+
+        // The other worker rejects the token, even once it has loaded the same module
+        let id = b.load_module(module).unwrap();
+        b.call_entrypoint_with::<i32>(&token, &(1, 2))
+            .expect_err("Token accepted by another worker");
+        b.resolve_token(&token)
+            .expect_err("Token accepted by another worker");
+
+        // As does the worker itself, once the token is tampered with
+        let mut forged = crate::serde_json::to_value(token).unwrap();
+        forged["worker"] = crate::serde_json::Value::from(0);
+        let forged: WorkerModuleToken = crate::serde_json::from_value(forged).unwrap();
+        a.resolve_token(&forged).expect_err("Forged token accepted");
+
+        // Tokens are only issued for modules loaded into the worker
+        a.module_token(id + 1000)
+            .expect_err("Token issued for a missing module");
+    }
+
+    #[test]
+    fn test_call_function_with() {
+        let worker = DefaultWorker::new(DefaultWorkerOptions::default())
+            .expect("Could not create the worker");
+        let module = crate::Module::new(
+            "test.js",
+            "export const count = (...args) => args.length;
+            export const sum = (values) => values.reduce((a, b) => a + b, 0);",
+        );
+        let token = worker.load_module_token(module).unwrap();
+
+        // Each element of a tuple is one argument
+        let count: usize = worker
+            .call_function_with(Some(&token), "count", &(1, "two", [3]))
+            .unwrap();
+        assert_eq!(count, 3);
+        let count: usize = worker
+            .call_function_with(Some(&token), "count", &())
+            .unwrap();
+        assert_eq!(count, 0);
+
+        // A list is passed as a single argument
+        let sum: i32 = worker
+            .call_function_with(Some(&token), "sum", &(vec![1, 2, 3],))
+            .unwrap();
+        assert_eq!(sum, 6);
+
+        // Functions in the global context are called without a token
+        worker
+            .eval::<crate::serde_json::Value>("globalThis.twice = (x) => x * 2".to_string())
+            .unwrap();
+        let value: i32 = worker.call_function_with(None, "twice", &(21,)).unwrap();
+        assert_eq!(value, 42);
+    }
+}